@@ -159,7 +159,7 @@ export default function SubstrateLoginDocPage() {
               <div className="text-purple-400">const</div> <span className="text-slate-300">result = </span><div className="text-blue-400">await</div> <span className="text-slate-300">prover.generateProof(secretValue);</span>
               <br/>
               <div className="text-green-400">// Verify a proof</div>
-              <div className="text-purple-400">const</div> <span className="text-slate-300">isValid = </span><div className="text-blue-400">await</div> <span className="text-slate-300">prover.verifyProof(proofStr, publicInput);</span>
+              <div className="text-purple-400">const</div> <span className="text-slate-300">isValid = </span><div className="text-blue-400">await</div> <span className="text-slate-300">prover.verifyProof(result.proof, publicInput);</span>
             </div>
             
             <div className="flex justify-between mt-8 pt-6 border-t border-emerald-400/20">
@@ -83,7 +83,7 @@ const WasmZkpExamplePage = () => {
         revoked: false
       };
       
-      await zkProver.generateCredentialProof(credential);
+      await zkProver.generateCredentialProof(credential, { revoked: [] }, 'demo-holder-secret', window.location.origin, Math.floor(Date.now() / 1000));
       
     } catch (error) {
       console.error('Performance test error:', error);
@@ -213,20 +213,18 @@ export default function LoginPage() {
         throw new Error(proofResult.message || 'Failed to generate DID proof');
       }
       
-      // Verify the proof
+      // Verify the proof against the key in the resolved DID document
+      const didDocument = await zkProver.resolveDID(identity.did);
       const isVerified = await zkProver.verifyDIDProof(
-        identity.did,
+        didDocument,
         challenge,
-        JSON.stringify(proofResult)
+        proofResult.proof
       );
       
       if (!isVerified) {
         throw new Error('Identity verification failed');
       }
       
-      // If this was a production app, we would resolve the DID to get attributes
-      const didDocument = await zkProver.resolveDID(identity.did);
-      
       // Create a mock credential for demonstration
       const credential = {
         id: `credential-${Date.now()}`,
@@ -251,7 +249,7 @@ export default function LoginPage() {
       };
       
       // Generate a credential proof using WebAssembly for speed
-      const credentialProof = await zkProver.generateCredentialProof(credential);
+      const credentialProof = await zkProver.generateCredentialProof(credential, { revoked: [] }, 'demo-holder-secret', window.location.origin, Math.floor(Date.now() / 1000));
       
       if (!credentialProof.success) {
         throw new Error(credentialProof.message || 'Failed to generate credential proof');
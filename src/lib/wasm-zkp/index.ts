@@ -25,6 +25,7 @@ export interface CredentialAttribute {
   name: string;
   value: string;
   reveal: boolean;
+  salt?: string;
 }
 
 export interface Credential {
@@ -36,6 +37,28 @@ export interface Credential {
   issuedAt: string;
   expiresAt?: string;
   revoked: boolean;
+  // Set by `issue_bbs_credential_signature`; snake_case as the Rust `Credential` serializes it
+  bbs_signature?: string;
+}
+
+// Condition on one credential attribute (e.g. age >= 18), as in a proof request
+export interface Predicate {
+  field: string;
+  operator: '>' | '>=' | '=' | '<=' | '<' | '!=' | 'in' | 'contains';
+  value: any;
+}
+
+// Credential hashes an issuer has revoked; holders prove their credential is absent from it
+export interface RevocationRegistry {
+  revoked: string[];
+}
+
+// What a verifier asks of one credential in a conjunction proof
+export interface CredentialRequest {
+  issuerPublicKey: string;
+  // Issuer's latest revocation root, hex-encoded
+  revocationRoot: string;
+  predicates: Predicate[];
 }
 
 export interface ChainIdentity {
@@ -56,28 +79,58 @@ export interface CrossChainIdentity {
   updated: string;
 }
 
+// Proving progress: constraint synthesis is reported every 1024 constraints, multiexp at its start and end
+export interface ProvingProgress {
+  stage: 'synthesis' | 'multiexp';
+  done: number;
+  total: number;
+}
+
+export interface ProvingOptions {
+  onProgress?: (progress: ProvingProgress) => void;
+  signal?: AbortSignal;
+}
+
 // WASM ZKP Module Integration
 
 // ZK Prover interface
 export interface WasmZkModule {
   initialize: () => Promise<void>;
-  generateProof: (value: number) => Promise<ProofResult>;
-  verifyProof: (proof: string, publicInput: number) => Promise<boolean>;
-  generate_credential_proof: (credentialJson: string) => Promise<string>;
-  verify_credential_proof: (proofJson: string) => Promise<boolean>;
-  generate_did_proof: (did: string, privateKey: string, challenge: string) => Promise<string>;
-  verify_did_proof: (did: string, challenge: string, proofStr: string) => Promise<boolean>;
+  generateProof: (value: number, options?: ProvingOptions) => Promise<ProofResult>;
+  verifyProof: (proofJson: string, publicInput: number) => Promise<boolean>;
+  generate_credential_proof: (credentialJson: string, revocationRegistryJson: string, holderSecret: string, context: string, currentTime: number, options?: ProvingOptions) => Promise<string>;
+  verify_credential_proof: (presentationJson: string, issuerPublicKey: string, revocationRoot: string, context: string, currentTime: number) => Promise<boolean>;
+  generate_predicate_proof: (credentialJson: string, revocationRegistryJson: string, holderSecret: string, predicatesJson: string, context: string, currentTime: number, options?: ProvingOptions) => Promise<string>;
+  verify_predicate_proof: (presentationJson: string, issuerPublicKey: string, revocationRoot: string, predicatesJson: string, context: string, currentTime: number) => Promise<boolean>;
+  generate_conjunction_proof: (credentialsJson: string, holderSecret: string, context: string, currentTime: number, options?: ProvingOptions) => Promise<string>;
+  verify_conjunction_proof: (presentationJson: string, requestsJson: string, context: string, currentTime: number) => Promise<boolean>;
+  generate_bbs_presentation: (credentialJson: string, issuerPublicKey: string, nonce: string) => Promise<string>;
+  verify_bbs_presentation: (presentationJson: string, issuerPublicKey: string, nonce: string) => Promise<boolean>;
+  generate_did_proof: (did: string, privateKey: string, challenge: string, options?: ProvingOptions) => Promise<string>;
+  verify_did_proof: (didDocumentJson: string, challenge: string, proofJson: string) => Promise<boolean>;
   resolve_did: (did: string) => Promise<any>;
   resolve_multi_chain_did: (did: string) => Promise<any>;
   link_identities: (sourceDid: string, targetDid: string, signature: string, nonce: string) => Promise<any>;
   verify_identity_link: (sourceDid: string, targetDid: string) => Promise<any>;
 }
 
+// A proof and what it was made for, as every prove call returns and every verify call takes.
+// Verifiers reject an envelope for another circuit, key or context before checking the proof.
+export interface ProofEnvelope {
+  version: number;
+  proofSystem: 'groth16' | 'marlin';
+  proofType: string;
+  vkHash: string; // hex SHA-256 of the verifying key
+  publicInputs: string[]; // hex-encoded
+  proof: string; // base64
+  context?: string;
+  createdAt: number; // Unix seconds
+}
+
 // Proof result interface
 export interface ProofResult {
   success: boolean;
-  proof?: string;
-  public_inputs?: string[];
+  proof?: ProofEnvelope;
   publicInput?: number;
   message?: string;
   error?: string;
@@ -87,7 +140,79 @@ export interface ProofResult {
 // WebAssembly ZK Prover implementation
 export type WasmZkProver = WasmZkModule;
 
+// Client for prover.worker.ts. Proving runs in the worker so the page stays responsive;
+// aborting stops synthesis through a shared flag when SharedArrayBuffer is available, and
+// otherwise terminates the worker, which loses its keys until circuits are initialized again.
+// `threadsModuleUrl` points at the multi-threaded build, used when the page is cross-origin isolated.
+export class ProverWorker {
+  private worker: Worker;
+  private nextId = 0;
+
+  constructor(private workerUrl: URL, private moduleUrl: string, private threadsModuleUrl?: string) {
+    this.worker = this.spawn();
+  }
+
+  public call<T>(method: string, args: unknown[], options: ProvingOptions = {}): Promise<T> {
+    const id = this.nextId++;
+    const flag = typeof SharedArrayBuffer === 'undefined' ? undefined : new SharedArrayBuffer(4);
+
+    return new Promise<T>((resolve, reject) => {
+      const worker = this.worker;
+      const onAbort = () => {
+        if (flag) {
+          Atomics.store(new Int32Array(flag), 0, 1);
+        } else {
+          worker.terminate();
+          this.worker = this.spawn();
+          finish();
+          reject(new Error('Proving cancelled'));
+        }
+      };
+      const onMessage = (event: MessageEvent) => {
+        const reply = event.data;
+        if (reply.id !== id) return;
+        if (reply.type === 'progress') {
+          options.onProgress?.({ stage: reply.stage, done: reply.done, total: reply.total });
+          return;
+        }
+        finish();
+        if (reply.type === 'result') resolve(reply.value);
+        else reject(new Error(reply.message));
+      };
+      const finish = () => {
+        worker.removeEventListener('message', onMessage);
+        options.signal?.removeEventListener('abort', onAbort);
+      };
+
+      if (options.signal?.aborted) {
+        reject(new Error('Proving cancelled'));
+        return;
+      }
+      worker.addEventListener('message', onMessage);
+      options.signal?.addEventListener('abort', onAbort);
+      worker.postMessage({ type: 'call', id, method, args, flag });
+    });
+  }
+
+  private spawn(): Worker {
+    const worker = new Worker(this.workerUrl, { type: 'module' });
+    worker.postMessage({ type: 'init', moduleUrl: this.moduleUrl, threadsModuleUrl: this.threadsModuleUrl });
+    return worker;
+  }
+}
+
 // Mock implementation for development
+const mockEnvelope = (proofType: string, publicInputs: string[], context?: string, proofSystem = 'groth16'): ProofEnvelope => ({
+  version: 1,
+  proofSystem,
+  proofType,
+  vkHash: '00'.repeat(32),
+  publicInputs,
+  proof: 'bW9ja19wcm9vZg==',
+  context,
+  createdAt: Math.floor(Date.now() / 1000)
+});
+
 const mockProver: WasmZkProver = {
   initialize: async () => {
     console.log('Mock ZK Prover initialized');
@@ -98,30 +223,74 @@ const mockProver: WasmZkProver = {
     console.log(`Generating mock proof for value: ${value}`);
     return Promise.resolve({
       success: true,
-      proof: `mock-proof-${Date.now()}`,
+      proof: mockEnvelope('SquareRoot', [value.toString(16)]),
       publicInput: value,
       message: 'Mock proof generated successfully'
     });
   },
   
-  verifyProof: async (proof: string, publicInput: number) => {
-    console.log(`Verifying mock proof: ${proof} with input: ${publicInput}`);
+  verifyProof: async (proofJson: string, publicInput: number) => {
+    console.log(`Verifying mock proof: ${proofJson} with input: ${publicInput}`);
     return Promise.resolve(true);
   },
   
-  generate_credential_proof: async (credentialJson: string) => {
+  generate_credential_proof: async (credentialJson: string, _revocationRegistryJson: string, _holderSecret: string, context: string, _currentTime: number) => {
     console.log(`Generating mock credential proof for: ${credentialJson}`);
     return Promise.resolve(JSON.stringify({
-      success: true,
-      message: "Mock credential proof generated successfully",
-      credentialHash: "hash_123",
-      issuerHash: "hash_456",
-      attributeHash: "hash_789"
+      proof: mockEnvelope('CredentialVerification', [], context),
+      issuerPublicKey: "mock_issuer_key",
+      disclosed: (JSON.parse(credentialJson).attributes as CredentialAttribute[])
+        .map((attr, slot) => ({ slot, attr }))
+        .filter(({ attr }) => attr.reveal)
+        .map(({ slot, attr }) => ({ slot, name: attr.name, value: attr.value })),
+      nullifier: "mock_nullifier"
+    }));
+  },
+  
+  verify_credential_proof: async (presentationJson: string, _issuerPublicKey: string, _revocationRoot: string, _context: string, _currentTime: number) => {
+    console.log(`Verifying mock credential proof: ${presentationJson}`);
+    return Promise.resolve(true);
+  },
+  
+  generate_predicate_proof: async (_credentialJson: string, _revocationRegistryJson: string, _holderSecret: string, predicatesJson: string, context: string, _currentTime: number) => {
+    console.log(`Generating mock predicate proof for: ${predicatesJson}`);
+    return Promise.resolve(JSON.stringify({
+      proof: mockEnvelope('Predicates', [], context),
+      nullifier: "mock_nullifier"
+    }));
+  },
+  
+  verify_predicate_proof: async (presentationJson: string, _issuerPublicKey: string, _revocationRoot: string, _predicatesJson: string, _context: string, _currentTime: number) => {
+    console.log(`Verifying mock predicate proof: ${presentationJson}`);
+    return Promise.resolve(true);
+  },
+  
+  generate_conjunction_proof: async (credentialsJson: string, _holderSecret: string, context: string, _currentTime: number) => {
+    console.log(`Generating mock conjunction proof for: ${credentialsJson}`);
+    return Promise.resolve(JSON.stringify({
+      proof: mockEnvelope('Conjunction', [], context),
+      nullifier: "mock_nullifier"
     }));
   },
   
-  verify_credential_proof: async (proofJson: string) => {
-    console.log(`Verifying mock credential proof: ${proofJson}`);
+  verify_conjunction_proof: async (presentationJson: string, _requestsJson: string, _context: string, _currentTime: number) => {
+    console.log(`Verifying mock conjunction proof: ${presentationJson}`);
+    return Promise.resolve(true);
+  },
+  
+  generate_bbs_presentation: async (credentialJson: string, _issuerPublicKey: string, nonce: string) => {
+    console.log(`Generating mock BBS+ presentation for: ${credentialJson}`);
+    return Promise.resolve(JSON.stringify({
+      proof: mockEnvelope('CredentialVerification', [], nonce, 'bbs+'),
+      disclosed: (JSON.parse(credentialJson).attributes as CredentialAttribute[])
+        .map((attr, slot) => ({ slot, attr }))
+        .filter(({ attr }) => attr.reveal)
+        .map(({ slot, attr }) => ({ slot, name: attr.name, value: attr.value }))
+    }));
+  },
+  
+  verify_bbs_presentation: async (presentationJson: string, _issuerPublicKey: string, _nonce: string) => {
+    console.log(`Verifying mock BBS+ presentation: ${presentationJson}`);
     return Promise.resolve(true);
   },
   
@@ -130,14 +299,12 @@ const mockProver: WasmZkProver = {
     return Promise.resolve(JSON.stringify({
       success: true,
       message: "Mock DID ownership proof generated successfully",
-      did: did,
-      challenge: challenge,
-      response: "mock_response_hash"
+      proof: mockEnvelope('DIDOwnership', [], challenge)
     }));
   },
   
-  verify_did_proof: async (did: string, challenge: string, proofStr: string) => {
-    console.log(`Verifying mock DID proof for: ${did}`);
+  verify_did_proof: async (didDocumentJson: string, challenge: string, proofJson: string) => {
+    console.log(`Verifying mock DID proof for: ${JSON.parse(didDocumentJson).id}`);
     return Promise.resolve(true);
   },
   
@@ -228,9 +395,9 @@ export class ZkProver {
   }
   
   // Generate proof
-  public async generateProof(value: number): Promise<ProofResult> {
+  public async generateProof(value: number, options?: ProvingOptions): Promise<ProofResult> {
     try {
-      return await this.prover.generateProof(value);
+      return await this.prover.generateProof(value, options);
     } catch (error) {
       console.error('Error generating proof:', error);
       return {
@@ -241,9 +408,9 @@ export class ZkProver {
   }
   
   // Verify proof
-  public async verifyProof(proof: string, publicInput: number): Promise<boolean> {
+  public async verifyProof(proof: ProofEnvelope, publicInput: number): Promise<boolean> {
     try {
-      return await this.prover.verifyProof(proof, publicInput);
+      return await this.prover.verifyProof(JSON.stringify(proof), publicInput);
     } catch (error) {
       console.error('Error verifying proof:', error);
       return false;
@@ -252,7 +419,15 @@ export class ZkProver {
 
   // Credential operations
 
-  public async generateCredentialProof(credential: Credential): Promise<any> {
+  public async generateCredentialProof(
+    credential: Credential,
+    revocationRegistry: RevocationRegistry,
+    holderSecret: string,
+    context: string,
+    currentTime: number,
+    revealedAttributes?: string[],
+    options?: ProvingOptions
+  ): Promise<any> {
     await this.init();
     
     if (!this.prover) {
@@ -263,9 +438,24 @@ export class ZkProver {
     }
     
     try {
-      const credentialJson = JSON.stringify(credential);
-      const resultJson = await this.prover.generate_credential_proof(credentialJson);
-      return JSON.parse(resultJson);
+      // Attributes marked `reveal` are disclosed; an explicit list overrides the marks
+      const disclosing = revealedAttributes === undefined ? credential : {
+        ...credential,
+        attributes: credential.attributes.map(attr => ({ ...attr, reveal: revealedAttributes.includes(attr.name) }))
+      };
+      const credentialJson = JSON.stringify(disclosing);
+      const presentationJson = await this.prover.generate_credential_proof(
+        credentialJson,
+        JSON.stringify(revocationRegistry),
+        holderSecret,
+        context,
+        currentTime,
+        options
+      );
+      return {
+        success: true,
+        presentation: JSON.parse(presentationJson)
+      };
     } catch (error) {
       console.error('Error generating credential proof:', error);
       return {
@@ -275,7 +465,13 @@ export class ZkProver {
     }
   }
 
-  public async verifyCredentialProof(proofJson: string): Promise<boolean> {
+  public async verifyCredentialProof(
+    presentationJson: string,
+    issuerPublicKey: string,
+    revocationRoot: string,
+    context: string,
+    currentTime: number
+  ): Promise<boolean> {
     await this.init();
     
     if (!this.prover) {
@@ -283,16 +479,182 @@ export class ZkProver {
     }
     
     try {
-      return await this.prover.verify_credential_proof(proofJson);
+      return await this.prover.verify_credential_proof(presentationJson, issuerPublicKey, revocationRoot, context, currentTime);
     } catch (error) {
       console.error('Error verifying credential proof:', error);
       return false;
     }
   }
 
+  public async generatePredicateProof(
+    credential: Credential,
+    revocationRegistry: RevocationRegistry,
+    holderSecret: string,
+    predicates: Predicate[],
+    context: string,
+    currentTime: number,
+    options?: ProvingOptions
+  ): Promise<any> {
+    await this.init();
+    
+    if (!this.prover) {
+      return {
+        success: false,
+        message: 'ZK prover not initialized'
+      };
+    }
+    
+    try {
+      const presentationJson = await this.prover.generate_predicate_proof(
+        JSON.stringify(credential),
+        JSON.stringify(revocationRegistry),
+        holderSecret,
+        JSON.stringify(predicates),
+        context,
+        currentTime,
+        options
+      );
+      return {
+        success: true,
+        presentation: JSON.parse(presentationJson)
+      };
+    } catch (error) {
+      console.error('Error generating predicate proof:', error);
+      return {
+        success: false,
+        message: error instanceof Error ? error.message : 'Unknown error generating predicate proof'
+      };
+    }
+  }
+
+  public async verifyPredicateProof(
+    presentationJson: string,
+    issuerPublicKey: string,
+    revocationRoot: string,
+    predicates: Predicate[],
+    context: string,
+    currentTime: number
+  ): Promise<boolean> {
+    await this.init();
+    
+    if (!this.prover) {
+      return false;
+    }
+    
+    try {
+      return await this.prover.verify_predicate_proof(
+        presentationJson,
+        issuerPublicKey,
+        revocationRoot,
+        JSON.stringify(predicates),
+        context,
+        currentTime
+      );
+    } catch (error) {
+      console.error('Error verifying predicate proof:', error);
+      return false;
+    }
+  }
+
+  public async generateConjunctionProof(
+    credentials: { credential: Credential; predicates: Predicate[]; revocationRegistry: RevocationRegistry }[],
+    holderSecret: string,
+    context: string,
+    currentTime: number,
+    options?: ProvingOptions
+  ): Promise<any> {
+    await this.init();
+    
+    if (!this.prover) {
+      return {
+        success: false,
+        message: 'ZK prover not initialized'
+      };
+    }
+    
+    try {
+      const presentationJson = await this.prover.generate_conjunction_proof(JSON.stringify(credentials), holderSecret, context, currentTime, options);
+      return {
+        success: true,
+        presentation: JSON.parse(presentationJson)
+      };
+    } catch (error) {
+      console.error('Error generating conjunction proof:', error);
+      return {
+        success: false,
+        message: error instanceof Error ? error.message : 'Unknown error generating conjunction proof'
+      };
+    }
+  }
+
+  public async verifyConjunctionProof(
+    presentationJson: string,
+    requests: CredentialRequest[],
+    context: string,
+    currentTime: number
+  ): Promise<boolean> {
+    await this.init();
+    
+    if (!this.prover) {
+      return false;
+    }
+    
+    try {
+      return await this.prover.verify_conjunction_proof(presentationJson, JSON.stringify(requests), context, currentTime);
+    } catch (error) {
+      console.error('Error verifying conjunction proof:', error);
+      return false;
+    }
+  }
+
+  // Selective disclosure from a BBS+ signature: no proving key, unlinkable across presentations
+  public async generateBbsPresentation(credential: Credential, issuerPublicKey: string, nonce: string, revealedAttributes?: string[]): Promise<any> {
+    await this.init();
+    
+    if (!this.prover) {
+      return {
+        success: false,
+        message: 'ZK prover not initialized'
+      };
+    }
+    
+    try {
+      const disclosing = revealedAttributes === undefined ? credential : {
+        ...credential,
+        attributes: credential.attributes.map(attr => ({ ...attr, reveal: revealedAttributes.includes(attr.name) }))
+      };
+      const presentationJson = await this.prover.generate_bbs_presentation(JSON.stringify(disclosing), issuerPublicKey, nonce);
+      return {
+        success: true,
+        presentation: JSON.parse(presentationJson)
+      };
+    } catch (error) {
+      console.error('Error generating BBS+ presentation:', error);
+      return {
+        success: false,
+        message: error instanceof Error ? error.message : 'Unknown error generating BBS+ presentation'
+      };
+    }
+  }
+
+  public async verifyBbsPresentation(presentationJson: string, issuerPublicKey: string, nonce: string): Promise<boolean> {
+    await this.init();
+    
+    if (!this.prover) {
+      return false;
+    }
+    
+    try {
+      return await this.prover.verify_bbs_presentation(presentationJson, issuerPublicKey, nonce);
+    } catch (error) {
+      console.error('Error verifying BBS+ presentation:', error);
+      return false;
+    }
+  }
+
   // DID operations
 
-  public async generateDIDProof(did: string, privateKey: string, challenge: string): Promise<any> {
+  public async generateDIDProof(did: string, privateKey: string, challenge: string, options?: ProvingOptions): Promise<any> {
     await this.init();
     
     if (!this.prover) {
@@ -303,7 +665,7 @@ export class ZkProver {
     }
     
     try {
-      const resultJson = await this.prover.generate_did_proof(did, privateKey, challenge);
+      const resultJson = await this.prover.generate_did_proof(did, privateKey, challenge, options);
       return JSON.parse(resultJson);
     } catch (error) {
       console.error('Error generating DID proof:', error);
@@ -314,7 +676,7 @@ export class ZkProver {
     }
   }
 
-  public async verifyDIDProof(did: string, challenge: string, proofStr: string): Promise<boolean> {
+  public async verifyDIDProof(didDocument: any, challenge: string, proof: ProofEnvelope): Promise<boolean> {
     await this.init();
     
     if (!this.prover) {
@@ -322,7 +684,7 @@ export class ZkProver {
     }
     
     try {
-      return await this.prover.verify_did_proof(did, challenge, proofStr);
+      return await this.prover.verify_did_proof(JSON.stringify(didDocument), challenge, JSON.stringify(proof));
     } catch (error) {
       console.error('Error verifying DID proof:', error);
       return false;
@@ -0,0 +1,58 @@
+// Runs the wasm prover off the main thread. Proving holds its thread until done, so the
+// page posts calls here and receives progress, results and errors as messages.
+//
+//   { type: 'init', moduleUrl, threadsModuleUrl? }    load the wasm-bindgen glue once
+//   { type: 'call', id, method, args, flag? }         run `${method}_async(...args)`
+//
+// `flag` is a SharedArrayBuffer holding one Int32; the page cancels by storing 1 into it.
+// Without one (no cross-origin isolation) the page cancels by terminating this worker.
+// The same goes for the multi-threaded build (`threadsModuleUrl`, from `build.sh --threads`):
+// it needs shared memory, so the worker falls back to `moduleUrl` when it is unavailable.
+
+type Reply =
+  | { id: number; type: 'progress'; stage: 'synthesis' | 'multiexp'; done: number; total: number }
+  | { id: number; type: 'result'; value: unknown }
+  | { id: number; type: 'error'; message: string };
+
+const STAGES = ['synthesis', 'multiexp'] as const;
+
+let wasm: any = null;
+let prover: any = null;
+// Calls posted while the module is still loading wait for it
+let ready: Promise<void> | null = null;
+
+const reply = (message: Reply) => (self as any).postMessage(message);
+
+async function load(moduleUrl: string, threadsModuleUrl?: string) {
+  const threaded = threadsModuleUrl && self.crossOriginIsolated && typeof SharedArrayBuffer !== 'undefined';
+  wasm = await import(/* webpackIgnore: true */ threaded ? threadsModuleUrl : moduleUrl);
+  await wasm.default();
+  if (threaded) {
+    await wasm.initThreadPool(navigator.hardwareConcurrency);
+  }
+  prover = new wasm.ZkProver();
+}
+
+self.onmessage = async (event: MessageEvent) => {
+  const message = event.data;
+
+  if (message.type === 'init') {
+    ready = load(message.moduleUrl, message.threadsModuleUrl);
+    return;
+  }
+
+  const { id, method, args, flag } = message;
+  try {
+    await ready;
+    if (!prover) {
+      throw new Error('ZK prover worker not initialized');
+    }
+    const token = flag ? wasm.CancellationToken.from_shared_flag(new Int32Array(flag)) : undefined;
+    const onProgress = (stage: number, done: number, total: number) =>
+      reply({ id, type: 'progress', stage: STAGES[stage], done, total });
+    const value = await prover[`${method}_async`](...args, onProgress, token);
+    reply({ id, type: 'result', value });
+  } catch (error) {
+    reply({ id, type: 'error', message: error instanceof Error ? error.message : String(error) });
+  }
+};
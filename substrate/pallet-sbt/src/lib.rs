@@ -73,6 +73,25 @@ pub mod pallet {
         OptionQuery,
     >;
 
+    /// Latest root of each issuer's revocation registry (see the wasm-zkp `revocation` module),
+    /// keyed by the issuer's Jubjub public key, as verifiers know issuers, with the account
+    /// that publishes it. Zero-knowledge presentations prove non-revocation against the root
+    /// without revealing the credential id.
+    ///
+    /// The root is trusted as the issuer's own statement: the pallet checks neither the root
+    /// nor that the account controls the key. The first account to publish for a key is the
+    /// only one that can update it, so verifiers should check that account is the issuer's,
+    /// e.g. that its DID in `pallet_did` is the credential's issuer.
+    #[pallet::storage]
+    #[pallet::getter(fn revocation_root)]
+    pub type RevocationRoots<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        [u8; 32],
+        (T::AccountId, [u8; 32]),
+        OptionQuery,
+    >;
+
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
@@ -83,6 +102,10 @@ pub mod pallet {
         /// A credential was revoked
         /// [credential_id, issuer]
         CredentialRevoked(CredentialId, T::AccountId),
+
+        /// An issuer published a new revocation root
+        /// [issuer, issuer_key, revocation_root]
+        RevocationRootPublished(T::AccountId, [u8; 32], [u8; 32]),
     }
 
     #[pallet::error]
@@ -99,6 +122,8 @@ pub mod pallet {
         TooManyCredentials,
         /// Credential already revoked
         AlreadyRevoked,
+        /// Another account publishes the revocation root of this issuer key
+        IssuerKeyTaken,
     }
 
     #[pallet::call]
@@ -183,6 +208,31 @@ pub mod pallet {
             
             Ok(())
         }
+
+        /// Publish the latest root of the revocation registry of the issuer with Jubjub
+        /// public key `issuer_key`, for credentials issued off-chain. The root is stored
+        /// as the issuer's own statement, unchecked (see `RevocationRoots`).
+        #[pallet::call_index(2)]
+        #[pallet::weight(5_000)]
+        pub fn publish_revocation_root(
+            origin: OriginFor<T>,
+            issuer_key: [u8; 32],
+            revocation_root: [u8; 32],
+        ) -> DispatchResult {
+            let issuer = ensure_signed(origin)?;
+            
+            // Only the account that first published for the key can update its root
+            if let Some((publisher, _)) = RevocationRoots::<T>::get(issuer_key) {
+                ensure!(publisher == issuer, Error::<T>::IssuerKeyTaken);
+            }
+            
+            RevocationRoots::<T>::insert(issuer_key, (issuer.clone(), revocation_root));
+            
+            // Emit event
+            Self::deposit_event(Event::RevocationRootPublished(issuer, issuer_key, revocation_root));
+            
+            Ok(())
+        }
     }
 }
 
@@ -340,6 +390,28 @@ mod tests {
         });
     }
 
+    #[test]
+    fn publish_revocation_root_works() {
+        new_test_ext().execute_with(|| {
+            let issuer = 1;
+            let issuer_key = [3u8; 32];
+            
+            // Issuers of off-chain credentials publish roots without an on-chain credential
+            assert_ok!(PalletSbt::publish_revocation_root(RuntimeOrigin::signed(issuer), issuer_key, [7u8; 32]));
+            assert_eq!(PalletSbt::revocation_root(issuer_key), Some((issuer, [7u8; 32])));
+            
+            // The publisher updates its root
+            assert_ok!(PalletSbt::publish_revocation_root(RuntimeOrigin::signed(issuer), issuer_key, [8u8; 32]));
+            assert_eq!(PalletSbt::revocation_root(issuer_key), Some((issuer, [8u8; 32])));
+            
+            // Another account cannot overwrite it
+            assert_noop!(
+                PalletSbt::publish_revocation_root(RuntimeOrigin::signed(2), issuer_key, [9u8; 32]),
+                Error::<Test>::IssuerKeyTaken
+            );
+        });
+    }
+
     #[test]
     fn requires_did_first() {
         new_test_ext().execute_with(|| {
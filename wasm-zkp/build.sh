@@ -11,12 +11,25 @@ fi
 echo "Building WebAssembly module..."
 wasm-pack build --target web --out-dir pkg
 
+# `--threads` also builds the multi-threaded module for cross-origin isolated pages. Wasm
+# threads need a nightly toolchain that rebuilds std with atomics
+if [ "$1" == "--threads" ]; then
+    echo "Building multi-threaded WebAssembly module..."
+    RUSTFLAGS='-C target-feature=+atomics,+bulk-memory' \
+        rustup run nightly wasm-pack build --target web --out-dir pkg-threads \
+        -- --features threads -Z build-std=panic_abort,std
+fi
+
 # Create the output directory if it doesn't exist
 mkdir -p ../src/lib/wasm-zkp/pkg
 
 # Copy the build artifacts to the src directory
 echo "Copying build artifacts to src/lib/wasm-zkp/pkg..."
 cp pkg/* ../src/lib/wasm-zkp/pkg/
+if [ "$1" == "--threads" ]; then
+    mkdir -p ../src/lib/wasm-zkp/pkg-threads
+    cp -r pkg-threads/* ../src/lib/wasm-zkp/pkg-threads/
+fi
 
 echo "Build complete! 🎉"
 echo "WASM module is available at src/lib/wasm-zkp/pkg/" 
\ No newline at end of file
@@ -0,0 +1,268 @@
+//! Native command-line tool for the wasm-zkp circuits.
+//!
+//! Lets ops and CI generate keys, create proofs from JSON witness files and
+//! verify proof files without a browser:
+//!
+//! ```text
+//! zkp keygen --circuit square-root --params-out params.bin --vk-out vk.bin
+//! zkp prove --circuit square-root --params params.bin --witness witness.json --out proof.json
+//! zkp verify --vk vk.bin --proof proof.json --input 1900000000000000000000000000000000000000000000000000000000000000
+//! zkp verify-batch --vk vk.bin --proofs proofs.json
+//! ```
+//!
+//! Proof files are proof envelopes, JSON or (with `prove --cbor`) CBOR. `verify`
+//! rejects an envelope made under another verifying key, then checks the proof
+//! against the public inputs the verifier expects, hex-encoded as in the
+//! envelope's `publicInputs`; the inputs recorded in the envelope are never
+//! trusted.
+//!
+//! Production keys come from a phase-2 ceremony over imported phase-1 powers:
+//!
+//! ```text
+//! zkp import-powers --zcash response --accumulator response --size 65536 --out powers.bin
+//! zkp keygen --circuit range-proof --ceremony range.mpc --powers powers.bin --params-out params.bin --vk-out vk.bin
+//! ```
+//!
+//! Conjunction keys are made per number of credentials, e.g.
+//! `zkp keygen --circuit conjunction --credentials 3 ...`; `prove` takes the
+//! count from the witness file.
+
+use bellman::{groth16::Parameters, Circuit};
+use bls12_381::{Bls12, Scalar};
+use clap::{Parser, Subcommand};
+use std::{fs, io, path::PathBuf, process::ExitCode, str::FromStr};
+use wasm_zkp::crypto::{
+    proof_system::{Groth16, ProofSystem},
+    zk_proofs::{
+        check_satisfied, create_circuit_proof, decode_scalar, encode_scalar, deserialize_parameters,
+        deserialize_verifying_key, serialize_parameters, serialize_verifying_key,
+        verify_prepared_proof,
+    },
+    setup_circuit, verify_ceremony, verifying_key_hash, BatchEntry, CircuitId, ConjunctionWitness, CredentialWitness, DIDOwnershipWitness, MPCParameters,
+    PowersOfTau, ZcashAccumulator, ZCASH_TAU_POWERS_LENGTH,
+    MembershipWitness, PredicateWitness, ProofEnvelope, ProofType, RangeWitness, SquareWitness,
+    prepared_verifying_key, verify_batch_entries,
+};
+use wasm_zkp::error::ZkError;
+
+#[derive(Parser)]
+#[command(name = "zkp", version, about = "Generate keys, prove and verify wasm-zkp circuits")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate proving parameters and a verifying key for a circuit
+    Keygen {
+        /// Circuit to generate keys for (square-root, set-membership, credential-verification, did-ownership, range-proof, predicates, conjunction)
+        #[arg(long, value_parser = ProofType::from_str)]
+        circuit: ProofType,
+        /// Number of credentials a conjunction covers, each with its own keys (default 2)
+        #[arg(long)]
+        credentials: Option<usize>,
+        /// Take the parameters from a phase-2 ceremony instead of a single-party setup
+        #[arg(long, requires = "powers")]
+        ceremony: Option<PathBuf>,
+        /// Phase-1 powers of tau the ceremony was built on, used to verify its transcript
+        #[arg(long, requires = "ceremony")]
+        powers: Option<PathBuf>,
+        #[arg(long)]
+        params_out: PathBuf,
+        #[arg(long)]
+        vk_out: PathBuf,
+    },
+    /// Create a proof from a JSON witness file
+    Prove {
+        #[arg(long, value_parser = ProofType::from_str)]
+        circuit: ProofType,
+        #[arg(long)]
+        params: PathBuf,
+        #[arg(long)]
+        witness: PathBuf,
+        /// Where to write the proof envelope; printed to stdout when omitted
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Write the envelope as CBOR instead of JSON (needs `--out`)
+        #[arg(long, requires = "out")]
+        cbor: bool,
+    },
+    /// Convert a Zcash Powers of Tau accumulator into the phase-1 file `keygen --powers` reads
+    ImportPowers {
+        /// Accumulator file from the Zcash Powers of Tau ceremony
+        #[arg(long)]
+        zcash: PathBuf,
+        /// Kind of accumulator (challenge or response)
+        #[arg(long, value_parser = ZcashAccumulator::from_str)]
+        accumulator: ZcashAccumulator,
+        /// Number of powers to keep; must cover the largest circuit's domain
+        #[arg(long)]
+        size: usize,
+        /// Number of powers in the accumulator
+        #[arg(long, default_value_t = ZCASH_TAU_POWERS_LENGTH)]
+        ceremony_powers: usize,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Verify a proof file against a verifying key and the expected public inputs; exits non-zero if invalid
+    Verify {
+        #[arg(long)]
+        vk: PathBuf,
+        #[arg(long)]
+        proof: PathBuf,
+        /// Expected public input (hex-encoded scalar), once per input in circuit order
+        #[arg(long = "input", required = true)]
+        inputs: Vec<String>,
+    },
+    /// Verify many proofs under one verifying key; exits non-zero if any is invalid
+    VerifyBatch {
+        #[arg(long)]
+        vk: PathBuf,
+        /// JSON array of `{"proof": <envelope>, "publicInputs": [<hex>, ...]}` with the expected inputs
+        #[arg(long)]
+        proofs: PathBuf,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(command: Command) -> Result<ExitCode, ZkError> {
+    match command {
+        Command::Keygen { circuit, credentials, ceremony, powers, params_out, vk_out } => {
+            let ceremony = match (ceremony, powers) {
+                (Some(ceremony), Some(powers)) => Some((
+                    MPCParameters::read(&fs::read(ceremony)?[..])?,
+                    PowersOfTau::read(&fs::read(powers)?[..])?,
+                )),
+                _ => None,
+            };
+            let params = keygen(CircuitId::new(circuit, credentials)?, ceremony)?;
+            fs::write(&params_out, serialize_parameters(&params)?)?;
+            fs::write(&vk_out, serialize_verifying_key(&params.vk)?)?;
+            println!("wrote {} and {}", params_out.display(), vk_out.display());
+        }
+        Command::Prove { circuit, params, witness, out, cbor } => {
+            let params = deserialize_parameters(&fs::read(params)?)?;
+            let witness = fs::read_to_string(witness)?;
+            let envelope = prove(circuit, &params, &witness)?;
+            match out {
+                Some(path) if cbor => fs::write(path, envelope.to_cbor()?)?,
+                Some(path) => fs::write(path, serde_json::to_string_pretty(&envelope)?)?,
+                None => println!("{}", serde_json::to_string_pretty(&envelope)?),
+            }
+        }
+        Command::ImportPowers { zcash, accumulator, size, ceremony_powers, out } => {
+            let reader = io::BufReader::new(fs::File::open(zcash)?);
+            let powers = PowersOfTau::read_zcash(reader, accumulator, ceremony_powers, size)?;
+            powers.verify()?;
+
+            let mut bytes = Vec::new();
+            powers.write(&mut bytes)?;
+            fs::write(&out, bytes)?;
+            println!("wrote {} powers to {}", size, out.display());
+        }
+        Command::Verify { vk, proof, inputs } => {
+            let vk = deserialize_verifying_key(&fs::read(vk)?)?;
+            let envelope = ProofEnvelope::decode(&fs::read(proof)?)?;
+            envelope.check_key(Groth16.name(), &verifying_key_hash(&Groth16, &vk)?)?;
+            let proof = envelope.decode_proof(&Groth16)?;
+            println!("circuit: {}", envelope.proof_type.name());
+            let inputs = inputs.iter().map(|input| decode_scalar(input)).collect::<Result<Vec<_>, _>>()?;
+            for (i, input) in inputs.iter().enumerate() {
+                println!("input {}: {}", i, encode_scalar(input));
+            }
+
+            let pvk = bellman::groth16::prepare_verifying_key(&vk);
+            if !verify_prepared_proof(&pvk, &proof, &inputs)? {
+                println!("invalid");
+                return Ok(ExitCode::FAILURE);
+            }
+            println!("valid");
+        }
+        Command::VerifyBatch { vk, proofs } => {
+            let vk = deserialize_verifying_key(&fs::read(vk)?)?;
+            let entries: Vec<BatchEntry> = serde_json::from_str(&fs::read_to_string(proofs)?)?;
+
+            let pvk = prepared_verifying_key(&vk)?;
+            let result = verify_batch_entries(&vk, &verifying_key_hash(&Groth16, &vk)?, &pvk, &entries);
+            for i in &result.failed {
+                println!("invalid {}", i);
+            }
+            println!("{} of {} valid", result.valid, entries.len());
+            if !result.all_valid() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+// Single-party setup over the circuit shape, or the ceremony's keys once its
+// transcript verifies against this circuit and the phase-1 powers
+fn keygen(circuit: CircuitId, ceremony: Option<(MPCParameters, PowersOfTau)>) -> Result<Parameters<Bls12>, ZkError> {
+    match ceremony {
+        Some((mpc, powers)) => {
+            for hash in verify_ceremony(&mpc, circuit, &powers)? {
+                eprintln!("contribution {}", hex::encode(hash));
+            }
+            Ok(mpc.params().clone())
+        }
+        None => setup_circuit(&Groth16, circuit),
+    }
+}
+
+fn prove(circuit: ProofType, params: &Parameters<Bls12>, witness: &str) -> Result<ProofEnvelope, ZkError> {
+    match circuit {
+        ProofType::SquareRoot => {
+            prove_circuit(circuit, params, serde_json::from_str::<SquareWitness>(witness)?.build_circuit()?, None)
+        }
+        ProofType::CredentialVerification => {
+            let witness = serde_json::from_str::<CredentialWitness>(witness)?;
+            prove_circuit(circuit, params, witness.build_circuit()?, Some(&witness.context))
+        }
+        ProofType::DIDOwnership => {
+            let witness = serde_json::from_str::<DIDOwnershipWitness>(witness)?;
+            prove_circuit(circuit, params, witness.build_circuit()?, Some(&witness.challenge))
+        }
+        ProofType::RangeProof => {
+            let witness = serde_json::from_str::<RangeWitness>(witness)?;
+            prove_circuit(circuit, params, witness.build_circuit()?, Some(&witness.context))
+        }
+        ProofType::SetMembership => {
+            prove_circuit(circuit, params, serde_json::from_str::<MembershipWitness>(witness)?.build_circuit()?, None)
+        }
+        ProofType::Predicates => {
+            let witness = serde_json::from_str::<PredicateWitness>(witness)?;
+            prove_circuit(circuit, params, witness.build_circuit()?, Some(&witness.context))
+        }
+        ProofType::Conjunction => {
+            let witness = serde_json::from_str::<ConjunctionWitness>(witness)?;
+            prove_circuit(circuit, params, witness.build_circuit()?, Some(&witness.context))
+        }
+    }
+}
+
+// The envelope records the context the proof is bound to (the relying party or
+// DID challenge), so it matches what the wasm verifiers check
+fn prove_circuit<C: Circuit<Scalar> + Clone>(
+    proof_type: ProofType,
+    params: &Parameters<Bls12>,
+    (circuit, inputs): (C, Vec<Scalar>),
+    context: Option<&str>,
+) -> Result<ProofEnvelope, ZkError> {
+    // Fail with the offending constraint rather than emit a proof that cannot verify
+    check_satisfied(circuit.clone())?;
+
+    let proof = create_circuit_proof(params, circuit)?;
+    ProofEnvelope::seal(&Groth16, params, proof_type, &proof, &inputs, context)
+}
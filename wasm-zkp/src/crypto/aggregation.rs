@@ -0,0 +1,771 @@
+//! Aggregation of Groth16 proofs (SnarkPack).
+//!
+//! `n` proofs under one verifying key are folded into a single aggregate whose
+//! size and verification time grow with `log n`, following Gailly, Maller and
+//! Nitulescu, "SnarkPack: Practical SNARK Aggregation" (FC 2022):
+//!
+//! * The `A`, `B` and `C` elements of all proofs are committed to with pairing
+//!   commitments under keys from a structured reference string of two secrets
+//!   `a` and `b`.
+//! * A random challenge `r` combines the `n` Groth16 equations into one:
+//!   `∏ e(Aᵢ, Bᵢ)^(rⁱ) = e(α, β)^(Σ rⁱ) · e(Σ rⁱ·ICᵢ, γ) · e(Σ rⁱ·Cᵢ, δ)`.
+//! * A GIPA argument (`TIPP` for the pairing product, `MIPP` for the sum of the
+//!   `C`s) shows that the two aggregated values match the commitments, halving
+//!   the vectors each round, and KZG openings show the final commitment keys
+//!   were derived from the reference string.
+//!
+//! Aggregates travel in a `ProofEnvelope` of their own proof system,
+//! `AGGREGATE_PROOF_SYSTEM`, whose key hash covers both the aggregation key and
+//! the circuit's verifying key (see `aggregate_key_hash`).
+//!
+//! The reference string is independent of the circuits: it is either generated
+//! locally for development or built from two public powers-of-tau transcripts.
+//! The combined equation has a single `(α, β, γ, δ)`, so an aggregate only
+//! covers proofs under one verifying key: it does not merge a presentation of
+//! different credential circuits (say KYC, age and membership) into one proof.
+//! That case is served by `ConjunctionCircuit`, which proves predicates over
+//! several credentials in one Groth16 proof. Below a few dozen proofs an
+//! aggregate is also larger than the proofs it replaces; aggregation pays off
+//! for large batches of one circuit, such as a verifier settling many
+//! presentations at once.
+
+use crate::crypto::ceremony::{read_g1, read_g2, read_u32, PowersOfTau};
+use crate::crypto::envelope::{key_hash, ProofEnvelope};
+use crate::crypto::target_group::{TargetElement, TARGET_ELEMENT_BYTES};
+use crate::crypto::zk_proofs::{serialize_verifying_key, ProofType};
+use crate::error::ZkError;
+use bellman::groth16::{Proof, VerifyingKey};
+use blake2::{Blake2b512, Digest};
+use bls12_381::{multi_miller_loop, Bls12, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt, Scalar};
+use ff::Field;
+use group::Curve;
+use rand::RngCore;
+use std::io::{self, Read, Write};
+
+/// `ProofEnvelope::proof_system` of aggregates
+pub const AGGREGATE_PROOF_SYSTEM: &str = "groth16-snarkpack";
+
+/// Pairing commitment to a vector, one target element per reference-string secret
+pub type Commitment = [TargetElement; 2];
+
+/// Structured reference string for aggregation: powers of two independent
+/// secrets `a` and `b`, `2n` of them in G1 and `n` in G2, for up to `n` proofs
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregationSrs {
+    g1_a: Vec<G1Affine>,
+    g1_b: Vec<G1Affine>,
+    g2_a: Vec<G2Affine>,
+    g2_b: Vec<G2Affine>,
+}
+
+/// What an aggregate verifier needs from the reference string
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AggregationVerifyingKey {
+    pub g1_a: G1Affine,
+    pub g1_b: G1Affine,
+    pub g2_a: G2Affine,
+    pub g2_b: G2Affine,
+}
+
+/// One GIPA round: cross terms of the halved vectors for both arguments
+#[derive(Clone, Debug, PartialEq)]
+pub struct GipaRound {
+    pub ip_ab_l: TargetElement,
+    pub ip_ab_r: TargetElement,
+    pub com_ab_l: Commitment,
+    pub com_ab_r: Commitment,
+    pub agg_c_l: G1Affine,
+    pub agg_c_r: G1Affine,
+    pub com_c_l: Commitment,
+    pub com_c_r: Commitment,
+}
+
+/// An aggregate of Groth16 proofs under one verifying key
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregateProof {
+    pub com_ab: Commitment,
+    pub com_c: Commitment,
+    /// `∏ e(Aᵢ, Bᵢ)^(rⁱ)`
+    pub ip_ab: TargetElement,
+    /// `Σ rⁱ·Cᵢ`
+    pub agg_c: G1Affine,
+    pub rounds: Vec<GipaRound>,
+    pub final_a: G1Affine,
+    pub final_b: G2Affine,
+    pub final_c: G1Affine,
+    pub final_v: [G2Affine; 2],
+    pub final_w: [G1Affine; 2],
+    pub opening_v: [G2Affine; 2],
+    pub opening_w: [G1Affine; 2],
+}
+
+impl AggregationSrs {
+    /// Generate a reference string for up to `max_proofs` proofs from local secrets.
+    ///
+    /// Whoever runs this knows `a` and `b` and can forge aggregates; it exists for
+    /// tests and local development only.
+    pub fn generate_insecure<R: RngCore>(max_proofs: usize, rng: &mut R) -> Result<Self, ZkError> {
+        if max_proofs < 2 || !max_proofs.is_power_of_two() {
+            return Err(ZkError::InvalidInput("aggregation size must be a power of two of at least 2".into()));
+        }
+        let a = Scalar::random(&mut *rng);
+        let b = Scalar::random(&mut *rng);
+        let g1 = |secret: &Scalar| scale_g1(&vec![G1Affine::generator(); 2 * max_proofs], &powers(secret, 2 * max_proofs));
+        let g2 = |secret: &Scalar| scale_g2(&vec![G2Affine::generator(); max_proofs], &powers(secret, max_proofs));
+
+        Ok(Self { g1_a: g1(&a), g1_b: g1(&b), g2_a: g2(&a), g2_b: g2(&b) })
+    }
+
+    /// Build a reference string from two powers-of-tau transcripts with independent
+    /// secrets, such as two public ceremonies. It supports the largest power of two
+    /// of proofs both transcripts have enough powers for.
+    pub fn from_powers_of_tau(first: &PowersOfTau, second: &PowersOfTau) -> Result<Self, ZkError> {
+        if first.tau_g1.get(1) == second.tau_g1.get(1) {
+            return Err(ZkError::InvalidInput("aggregation needs two different powers of tau".into()));
+        }
+        let available = (first.tau_g1.len() / 2).min(second.tau_g1.len() / 2)
+            .min(first.tau_g2.len())
+            .min(second.tau_g2.len());
+        if available < 2 {
+            return Err(ZkError::InvalidInput("powers of tau are too small to aggregate proofs".into()));
+        }
+        let max_proofs = 1 << available.ilog2();
+
+        Ok(Self {
+            g1_a: first.tau_g1[..2 * max_proofs].to_vec(),
+            g1_b: second.tau_g1[..2 * max_proofs].to_vec(),
+            g2_a: first.tau_g2[..max_proofs].to_vec(),
+            g2_b: second.tau_g2[..max_proofs].to_vec(),
+        })
+    }
+
+    /// The largest number of proofs this reference string can aggregate
+    pub fn max_proofs(&self) -> usize {
+        self.g2_a.len()
+    }
+
+    pub fn verifying_key(&self) -> AggregationVerifyingKey {
+        AggregationVerifyingKey {
+            g1_a: self.g1_a[1],
+            g1_b: self.g1_b[1],
+            g2_a: self.g2_a[1],
+            g2_b: self.g2_b[1],
+        }
+    }
+
+    /// Write the reference string: a big-endian `u32` size followed by compressed points
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&(self.max_proofs() as u32).to_be_bytes())?;
+        for point in self.g1_a.iter().chain(&self.g1_b) {
+            writer.write_all(&point.to_compressed())?;
+        }
+        for point in self.g2_a.iter().chain(&self.g2_b) {
+            writer.write_all(&point.to_compressed())?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let max_proofs = usize::try_from(read_u32(&mut reader)?).unwrap_or(usize::MAX);
+        if max_proofs < 2 || !max_proofs.is_power_of_two() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported aggregation size"));
+        }
+        let mut g1 = || (0..2 * max_proofs).map(|_| read_g1(&mut reader)).collect::<io::Result<Vec<_>>>();
+        let (g1_a, g1_b) = (g1()?, g1()?);
+        let mut g2 = || (0..max_proofs).map(|_| read_g2(&mut reader)).collect::<io::Result<Vec<_>>>();
+        let (g2_a, g2_b) = (g2()?, g2()?);
+
+        Ok(Self { g1_a, g1_b, g2_a, g2_b })
+    }
+}
+
+impl AggregationVerifyingKey {
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.g1_a.to_compressed())?;
+        writer.write_all(&self.g1_b.to_compressed())?;
+        writer.write_all(&self.g2_a.to_compressed())?;
+        writer.write_all(&self.g2_b.to_compressed())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        Ok(Self {
+            g1_a: read_g1(&mut reader)?,
+            g1_b: read_g1(&mut reader)?,
+            g2_a: read_g2(&mut reader)?,
+            g2_b: read_g2(&mut reader)?,
+        })
+    }
+}
+
+/// Key hash in the envelope of an aggregate: `key_hash` over the aggregation key
+/// followed by the circuit's verifying key, as both are needed to verify it
+pub fn aggregate_key_hash(agg_vk: &AggregationVerifyingKey, vk: &VerifyingKey<Bls12>) -> Result<[u8; 32], ZkError> {
+    let mut bytes = Vec::new();
+    agg_vk.write(&mut bytes)?;
+    bytes.extend(serialize_verifying_key(vk)?);
+    Ok(key_hash(&bytes))
+}
+
+impl AggregateProof {
+    /// Wrap the aggregate of `proof_type` proofs under `vk` in an envelope. It records
+    /// no public inputs: the verifier supplies those of every aggregated proof.
+    pub fn seal(
+        &self,
+        agg_vk: &AggregationVerifyingKey,
+        vk: &VerifyingKey<Bls12>,
+        proof_type: ProofType,
+    ) -> Result<ProofEnvelope, ZkError> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes)?;
+        Ok(ProofEnvelope::new(AGGREGATE_PROOF_SYSTEM, proof_type, aggregate_key_hash(agg_vk, vk)?, &[], bytes, None))
+    }
+
+    /// The aggregate in `envelope`, which must hold one of `proof_type` proofs made
+    /// under `agg_vk` and `vk`
+    pub fn open(
+        envelope: &ProofEnvelope,
+        agg_vk: &AggregationVerifyingKey,
+        vk: &VerifyingKey<Bls12>,
+        proof_type: ProofType,
+    ) -> Result<Self, ZkError> {
+        envelope.check(AGGREGATE_PROOF_SYSTEM, proof_type, &aggregate_key_hash(agg_vk, vk)?, None)?;
+        Ok(Self::read(&envelope.proof[..])?)
+    }
+
+    /// Write the aggregate: commitments and group elements in field order, with
+    /// the number of GIPA rounds as one byte
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_commitment(&mut writer, &self.com_ab)?;
+        write_commitment(&mut writer, &self.com_c)?;
+        writer.write_all(&self.ip_ab.to_bytes())?;
+        writer.write_all(&self.agg_c.to_compressed())?;
+        writer.write_all(&[self.rounds.len() as u8])?;
+        for round in &self.rounds {
+            writer.write_all(&round.ip_ab_l.to_bytes())?;
+            writer.write_all(&round.ip_ab_r.to_bytes())?;
+            write_commitment(&mut writer, &round.com_ab_l)?;
+            write_commitment(&mut writer, &round.com_ab_r)?;
+            writer.write_all(&round.agg_c_l.to_compressed())?;
+            writer.write_all(&round.agg_c_r.to_compressed())?;
+            write_commitment(&mut writer, &round.com_c_l)?;
+            write_commitment(&mut writer, &round.com_c_r)?;
+        }
+        writer.write_all(&self.final_a.to_compressed())?;
+        writer.write_all(&self.final_b.to_compressed())?;
+        writer.write_all(&self.final_c.to_compressed())?;
+        for point in &self.final_v {
+            writer.write_all(&point.to_compressed())?;
+        }
+        for point in &self.final_w {
+            writer.write_all(&point.to_compressed())?;
+        }
+        for point in &self.opening_v {
+            writer.write_all(&point.to_compressed())?;
+        }
+        for point in &self.opening_w {
+            writer.write_all(&point.to_compressed())?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let com_ab = read_commitment(&mut reader)?;
+        let com_c = read_commitment(&mut reader)?;
+        let ip_ab = read_target(&mut reader)?;
+        let agg_c = read_g1(&mut reader)?;
+
+        let mut count = [0u8; 1];
+        reader.read_exact(&mut count)?;
+        if usize::from(count[0]) >= usize::BITS as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "too many aggregation rounds"));
+        }
+        let rounds = (0..count[0])
+            .map(|_| {
+                Ok(GipaRound {
+                    ip_ab_l: read_target(&mut reader)?,
+                    ip_ab_r: read_target(&mut reader)?,
+                    com_ab_l: read_commitment(&mut reader)?,
+                    com_ab_r: read_commitment(&mut reader)?,
+                    agg_c_l: read_g1(&mut reader)?,
+                    agg_c_r: read_g1(&mut reader)?,
+                    com_c_l: read_commitment(&mut reader)?,
+                    com_c_r: read_commitment(&mut reader)?,
+                })
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            com_ab,
+            com_c,
+            ip_ab,
+            agg_c,
+            rounds,
+            final_a: read_g1(&mut reader)?,
+            final_b: read_g2(&mut reader)?,
+            final_c: read_g1(&mut reader)?,
+            final_v: [read_g2(&mut reader)?, read_g2(&mut reader)?],
+            final_w: [read_g1(&mut reader)?, read_g1(&mut reader)?],
+            opening_v: [read_g2(&mut reader)?, read_g2(&mut reader)?],
+            opening_w: [read_g1(&mut reader)?, read_g1(&mut reader)?],
+        })
+    }
+}
+
+/// Aggregate proofs made under `vk`, each with the public inputs it was proven for
+pub fn aggregate_proofs(
+    srs: &AggregationSrs,
+    vk: &VerifyingKey<Bls12>,
+    proofs: &[(Proof<Bls12>, Vec<Scalar>)],
+) -> Result<AggregateProof, ZkError> {
+    let inputs: Vec<Vec<Scalar>> = proofs.iter().map(|(_, inputs)| inputs.clone()).collect();
+    let size = aggregation_size(vk, &inputs)?;
+    if size > srs.max_proofs() {
+        return Err(ZkError::InvalidInput(format!(
+            "{} proofs need an aggregation size of {}, the reference string supports {}",
+            proofs.len(), size, srs.max_proofs()
+        )));
+    }
+
+    // Pad to a power of two by repeating the last proof
+    let padded = |i: usize| &proofs[i.min(proofs.len() - 1)].0;
+    let mut a: Vec<G1Affine> = (0..size).map(|i| padded(i).a).collect();
+    let mut b: Vec<G2Affine> = (0..size).map(|i| padded(i).b).collect();
+    let mut c: Vec<G1Affine> = (0..size).map(|i| padded(i).c).collect();
+    let mut v = [srs.g2_a[..size].to_vec(), srs.g2_b[..size].to_vec()];
+    let mut w = [srs.g1_a[size..2 * size].to_vec(), srs.g1_b[size..2 * size].to_vec()];
+
+    let com_ab = commit_ab(&a, &b, [&v[0], &v[1]], [&w[0], &w[1]]);
+    let com_c = commit_c(&c, [&v[0], &v[1]]);
+
+    let mut transcript = Transcript::new(vk, &inputs)?;
+    transcript.absorb_commitment(&com_ab);
+    transcript.absorb_commitment(&com_c);
+    let r = transcript.challenge();
+    let r_inv = r.invert().unwrap();
+
+    // Rescale A and C by rⁱ and the key of A by r⁻ⁱ, which leaves the commitments unchanged
+    let r_powers = powers(&r, size);
+    a = scale_g1(&a, &r_powers);
+    c = scale_g1(&c, &r_powers);
+    v = v.map(|key| scale_g2(&key, &powers(&r_inv, size)));
+
+    let ip_ab = TargetElement::pairing_product(&a, &b);
+    let agg_c = sum_g1(&c).to_affine();
+    transcript.absorb_target(&ip_ab);
+    transcript.absorb_g1(&agg_c);
+
+    // The MIPP scalar vector starts as all ones and stays constant while folding
+    let mut s = Scalar::one();
+    let mut rounds = Vec::new();
+    let mut challenges = Vec::new();
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_l, a_r) = a.split_at(half);
+        let (b_l, b_r) = b.split_at(half);
+        let (c_l, c_r) = c.split_at(half);
+        let (v_l, v_r) = (v.each_ref().map(|key| &key[..half]), v.each_ref().map(|key| &key[half..]));
+        let (w_l, w_r) = (w.each_ref().map(|key| &key[..half]), w.each_ref().map(|key| &key[half..]));
+
+        let round = GipaRound {
+            ip_ab_l: TargetElement::pairing_product(a_r, b_l),
+            ip_ab_r: TargetElement::pairing_product(a_l, b_r),
+            com_ab_l: commit_ab(a_r, b_l, v_l, w_r),
+            com_ab_r: commit_ab(a_l, b_r, v_r, w_l),
+            agg_c_l: (sum_g1(c_r) * s).to_affine(),
+            agg_c_r: (sum_g1(c_l) * s).to_affine(),
+            com_c_l: commit_c(c_r, v_l),
+            com_c_r: commit_c(c_l, v_r),
+        };
+        transcript.absorb_round(&round);
+        let x = transcript.challenge();
+        let x_inv = x.invert().unwrap();
+
+        a = fold_g1(a_l, a_r, &x);
+        b = fold_g2(b_l, b_r, &x_inv);
+        c = fold_g1(c_l, c_r, &x);
+        v = [fold_g2(v_l[0], v_r[0], &x_inv), fold_g2(v_l[1], v_r[1], &x_inv)];
+        w = [fold_g1(w_l[0], w_r[0], &x), fold_g1(w_l[1], w_r[1], &x)];
+        s *= Scalar::one() + x_inv;
+
+        rounds.push(round);
+        challenges.push(x);
+    }
+
+    let final_v = [v[0][0], v[1][0]];
+    let final_w = [w[0][0], w[1][0]];
+    transcript.absorb_finals(&a[0], &b[0], &c[0], &final_v, &final_w);
+    let z = transcript.challenge();
+
+    // KZG openings of the final keys at z
+    let inverses: Vec<Scalar> = challenges.iter().map(|x| x.invert().unwrap()).collect();
+    let v_quotient = opening_quotient(&folding_polynomial(&inverses, &r_inv), &z);
+    let mut w_polynomial = vec![Scalar::zero(); size];
+    w_polynomial.extend(folding_polynomial(&challenges, &Scalar::one()));
+    let w_quotient = opening_quotient(&w_polynomial, &z);
+
+    Ok(AggregateProof {
+        com_ab,
+        com_c,
+        ip_ab,
+        agg_c,
+        rounds,
+        final_a: a[0],
+        final_b: b[0],
+        final_c: c[0],
+        final_v,
+        final_w,
+        opening_v: [msm_g2(&srs.g2_a, &v_quotient), msm_g2(&srs.g2_b, &v_quotient)],
+        opening_w: [msm_g1(&srs.g1_a, &w_quotient), msm_g1(&srs.g1_b, &w_quotient)],
+    })
+}
+
+/// Verify an aggregate of proofs under `vk` for the given public inputs, one
+/// vector per aggregated proof in aggregation order
+pub fn verify_aggregate_proof(
+    agg_vk: &AggregationVerifyingKey,
+    vk: &VerifyingKey<Bls12>,
+    public_inputs: &[Vec<Scalar>],
+    proof: &AggregateProof,
+) -> Result<bool, ZkError> {
+    let size = aggregation_size(vk, public_inputs)?;
+    if proof.rounds.len() != size.trailing_zeros() as usize {
+        return Ok(false);
+    }
+
+    let mut transcript = Transcript::new(vk, public_inputs)?;
+    transcript.absorb_commitment(&proof.com_ab);
+    transcript.absorb_commitment(&proof.com_c);
+    let r = transcript.challenge();
+    let r_inv = r.invert().unwrap();
+    transcript.absorb_target(&proof.ip_ab);
+    transcript.absorb_g1(&proof.agg_c);
+
+    let mut com_ab = proof.com_ab;
+    let mut com_c = proof.com_c;
+    let mut ip_ab = proof.ip_ab;
+    let mut agg_c = G1Projective::from(proof.agg_c);
+    let mut challenges = Vec::with_capacity(proof.rounds.len());
+    for round in &proof.rounds {
+        transcript.absorb_round(round);
+        let x = transcript.challenge();
+        let x_inv = x.invert().unwrap();
+
+        com_ab = fold_commitment(&round.com_ab_l, &com_ab, &round.com_ab_r, &x, &x_inv);
+        com_c = fold_commitment(&round.com_c_l, &com_c, &round.com_c_r, &x, &x_inv);
+        ip_ab = round.ip_ab_l.pow(&x) * ip_ab * round.ip_ab_r.pow(&x_inv);
+        agg_c = round.agg_c_l * x + agg_c + round.agg_c_r * x_inv;
+        challenges.push(x);
+    }
+    transcript.absorb_finals(&proof.final_a, &proof.final_b, &proof.final_c, &proof.final_v, &proof.final_w);
+    let z = transcript.challenge();
+
+    // The folded values must match the final vectors of length one
+    let final_v = [std::slice::from_ref(&proof.final_v[0]), std::slice::from_ref(&proof.final_v[1])];
+    let final_w = [std::slice::from_ref(&proof.final_w[0]), std::slice::from_ref(&proof.final_w[1])];
+    let inverses: Vec<Scalar> = challenges.iter().map(|x| x.invert().unwrap()).collect();
+    let s = inverses.iter().fold(Scalar::one(), |acc, x_inv| acc * (Scalar::one() + x_inv));
+    let tipp = com_ab == commit_ab(&[proof.final_a], &[proof.final_b], final_v, final_w)
+        && ip_ab == TargetElement::pairing_product(&[proof.final_a], &[proof.final_b]);
+    let mipp = com_c == commit_c(&[proof.final_c], final_v) && agg_c == proof.final_c * s;
+    if !(tipp && mipp) {
+        return Ok(false);
+    }
+
+    // The final keys must be the folding polynomials evaluated at the secrets
+    let v_eval = evaluate_folding_polynomial(&inverses, &r_inv, &z);
+    let w_eval = z.pow_vartime(&[size as u64, 0, 0, 0]) * evaluate_folding_polynomial(&challenges, &Scalar::one(), &z);
+    let keys = opens_in_g2(&agg_vk.g1_a, &proof.final_v[0], &proof.opening_v[0], &z, &v_eval)
+        && opens_in_g2(&agg_vk.g1_b, &proof.final_v[1], &proof.opening_v[1], &z, &v_eval)
+        && opens_in_g1(&agg_vk.g2_a, &proof.final_w[0], &proof.opening_w[0], &z, &w_eval)
+        && opens_in_g1(&agg_vk.g2_b, &proof.final_w[1], &proof.opening_w[1], &z, &w_eval);
+    if !keys {
+        return Ok(false);
+    }
+
+    // The combined Groth16 equation, with inputs padded like the proofs
+    let r_powers = powers(&r, size);
+    let padded = |i: usize| &public_inputs[i.min(public_inputs.len() - 1)];
+    let r_sum = r_powers.iter().fold(Scalar::zero(), |acc, r_i| acc + r_i);
+    let mut ic = vk.ic[0] * r_sum;
+    for (j, base) in vk.ic[1..].iter().enumerate() {
+        let weight = r_powers.iter().enumerate().fold(Scalar::zero(), |acc, (i, r_i)| acc + r_i * padded(i)[j]);
+        ic += base * weight;
+    }
+    let expected = TargetElement::pairing_product(
+        &[(vk.alpha_g1 * r_sum).to_affine(), ic.to_affine(), proof.agg_c],
+        &[vk.beta_g2, vk.gamma_g2, vk.delta_g2],
+    );
+
+    Ok(proof.ip_ab == expected)
+}
+
+// Padded number of proofs, after checking each proof's input count against `vk`
+fn aggregation_size(vk: &VerifyingKey<Bls12>, public_inputs: &[Vec<Scalar>]) -> Result<usize, ZkError> {
+    if public_inputs.is_empty() {
+        return Err(ZkError::InvalidInput("nothing to aggregate".into()));
+    }
+    if let Some(i) = public_inputs.iter().position(|inputs| inputs.len() + 1 != vk.ic.len()) {
+        return Err(ZkError::InvalidInput(format!(
+            "proof {} has {} public inputs, the verifying key expects {}",
+            i, public_inputs[i].len(), vk.ic.len() - 1
+        )));
+    }
+    Ok(public_inputs.len().next_power_of_two().max(2))
+}
+
+/// Fiat-Shamir transcript over BLAKE2b
+struct Transcript(Blake2b512);
+
+impl Transcript {
+    fn new(vk: &VerifyingKey<Bls12>, public_inputs: &[Vec<Scalar>]) -> Result<Self, ZkError> {
+        let mut hasher = Blake2b512::new();
+        hasher.update(b"wasm-zkp/snarkpack/v1");
+        hasher.update(serialize_verifying_key(vk)?);
+        hasher.update((public_inputs.len() as u64).to_be_bytes());
+        for input in public_inputs.iter().flatten() {
+            hasher.update(input.to_bytes());
+        }
+        Ok(Transcript(hasher))
+    }
+
+    fn absorb_target(&mut self, element: &TargetElement) {
+        self.0.update(element.to_bytes());
+    }
+
+    fn absorb_commitment(&mut self, commitment: &Commitment) {
+        commitment.iter().for_each(|element| self.absorb_target(element));
+    }
+
+    fn absorb_g1(&mut self, point: &G1Affine) {
+        self.0.update(point.to_compressed());
+    }
+
+    fn absorb_g2(&mut self, point: &G2Affine) {
+        self.0.update(point.to_compressed());
+    }
+
+    fn absorb_round(&mut self, round: &GipaRound) {
+        self.absorb_target(&round.ip_ab_l);
+        self.absorb_target(&round.ip_ab_r);
+        self.absorb_commitment(&round.com_ab_l);
+        self.absorb_commitment(&round.com_ab_r);
+        self.absorb_g1(&round.agg_c_l);
+        self.absorb_g1(&round.agg_c_r);
+        self.absorb_commitment(&round.com_c_l);
+        self.absorb_commitment(&round.com_c_r);
+    }
+
+    fn absorb_finals(&mut self, a: &G1Affine, b: &G2Affine, c: &G1Affine, v: &[G2Affine; 2], w: &[G1Affine; 2]) {
+        self.absorb_g1(a);
+        self.absorb_g2(b);
+        self.absorb_g1(c);
+        v.iter().for_each(|point| self.absorb_g2(point));
+        w.iter().for_each(|point| self.absorb_g1(point));
+    }
+
+    // A non-zero challenge, which is then absorbed itself
+    fn challenge(&mut self) -> Scalar {
+        loop {
+            let digest: [u8; 64] = self.0.clone().finalize().into();
+            self.0.update(digest);
+            let challenge = Scalar::from_bytes_wide(&digest);
+            if !bool::from(challenge.is_zero()) {
+                return challenge;
+            }
+        }
+    }
+}
+
+fn pairing_product(g1: &[G1Affine], g2: &[G2Affine]) -> Gt {
+    let prepared: Vec<G2Prepared> = g2.iter().map(|&point| point.into()).collect();
+    let terms: Vec<(&G1Affine, &G2Prepared)> = g1.iter().zip(prepared.iter()).collect();
+    multi_miller_loop(&terms).final_exponentiation()
+}
+
+// Commitment to (A, B) under keys (v, w): e(A, v)·e(w, B) for each secret
+fn commit_ab(a: &[G1Affine], b: &[G2Affine], v: [&[G2Affine]; 2], w: [&[G1Affine]; 2]) -> Commitment {
+    [0, 1].map(|k| TargetElement::pairing_product(&[a, w[k]].concat(), &[v[k], b].concat()))
+}
+
+// Commitment to C under key v: e(C, v) for each secret
+fn commit_c(c: &[G1Affine], v: [&[G2Affine]; 2]) -> Commitment {
+    [0, 1].map(|k| TargetElement::pairing_product(c, v[k]))
+}
+
+// Verifier side of a folding step: left^x · current · right^(1/x)
+fn fold_commitment(left: &Commitment, current: &Commitment, right: &Commitment, x: &Scalar, x_inv: &Scalar) -> Commitment {
+    [0, 1].map(|k| left[k].pow(x) * current[k] * right[k].pow(x_inv))
+}
+
+fn powers(base: &Scalar, count: usize) -> Vec<Scalar> {
+    std::iter::successors(Some(Scalar::one()), |power| Some(power * base)).take(count).collect()
+}
+
+fn scale_g1(points: &[G1Affine], scalars: &[Scalar]) -> Vec<G1Affine> {
+    let projective: Vec<G1Projective> = points.iter().zip(scalars).map(|(p, s)| p * s).collect();
+    let mut affine = vec![G1Affine::identity(); projective.len()];
+    G1Projective::batch_normalize(&projective, &mut affine);
+    affine
+}
+
+fn scale_g2(points: &[G2Affine], scalars: &[Scalar]) -> Vec<G2Affine> {
+    let projective: Vec<G2Projective> = points.iter().zip(scalars).map(|(p, s)| p * s).collect();
+    let mut affine = vec![G2Affine::identity(); projective.len()];
+    G2Projective::batch_normalize(&projective, &mut affine);
+    affine
+}
+
+// left + x·right, element-wise
+fn fold_g1(left: &[G1Affine], right: &[G1Affine], x: &Scalar) -> Vec<G1Affine> {
+    let projective: Vec<G1Projective> = left.iter().zip(right).map(|(l, r)| r * x + l).collect();
+    let mut affine = vec![G1Affine::identity(); projective.len()];
+    G1Projective::batch_normalize(&projective, &mut affine);
+    affine
+}
+
+fn fold_g2(left: &[G2Affine], right: &[G2Affine], x: &Scalar) -> Vec<G2Affine> {
+    let projective: Vec<G2Projective> = left.iter().zip(right).map(|(l, r)| r * x + l).collect();
+    let mut affine = vec![G2Affine::identity(); projective.len()];
+    G2Projective::batch_normalize(&projective, &mut affine);
+    affine
+}
+
+fn sum_g1(points: &[G1Affine]) -> G1Projective {
+    points.iter().fold(G1Projective::identity(), |acc, point| acc + point)
+}
+
+fn msm_g1(bases: &[G1Affine], scalars: &[Scalar]) -> G1Affine {
+    bases.iter().zip(scalars).fold(G1Projective::identity(), |acc, (base, s)| acc + base * s).to_affine()
+}
+
+fn msm_g2(bases: &[G2Affine], scalars: &[Scalar]) -> G2Affine {
+    bases.iter().zip(scalars).fold(G2Projective::identity(), |acc, (base, s)| acc + base * s).to_affine()
+}
+
+// Coefficients of ∏ⱼ (1 + cⱼ·(t·X)^(2^(k-1-j))): folding a key of powers
+// `(t·secret)ⁱ` with challenges `cⱼ` leaves this polynomial at the secret
+fn folding_polynomial(challenges: &[Scalar], t: &Scalar) -> Vec<Scalar> {
+    let mut coefficients = vec![Scalar::one()];
+    for c in challenges.iter().rev() {
+        let shift = t.pow_vartime(&[coefficients.len() as u64, 0, 0, 0]) * c;
+        let upper: Vec<Scalar> = coefficients.iter().map(|coefficient| coefficient * shift).collect();
+        coefficients.extend(upper);
+    }
+    coefficients
+}
+
+fn evaluate_folding_polynomial(challenges: &[Scalar], t: &Scalar, z: &Scalar) -> Scalar {
+    let mut power = t * z;
+    let mut value = Scalar::one();
+    for c in challenges.iter().rev() {
+        value *= Scalar::one() + c * power;
+        power = power.square();
+    }
+    value
+}
+
+// Coefficients of (p(X) - p(z)) / (X - z)
+fn opening_quotient(coefficients: &[Scalar], z: &Scalar) -> Vec<Scalar> {
+    let mut quotient = vec![Scalar::zero(); coefficients.len() - 1];
+    let mut carry = Scalar::zero();
+    for i in (1..coefficients.len()).rev() {
+        carry = coefficients[i] + carry * z;
+        quotient[i - 1] = carry;
+    }
+    quotient
+}
+
+// e(g^a / g^z, opening) == e(g, commitment / h^eval) for a commitment in G2
+fn opens_in_g2(g1_secret: &G1Affine, commitment: &G2Affine, opening: &G2Affine, z: &Scalar, eval: &Scalar) -> bool {
+    let shifted = (G1Projective::from(g1_secret) - G1Affine::generator() * z).to_affine();
+    let target = (G2Projective::from(commitment) - G2Affine::generator() * eval).to_affine();
+    pairing_product(&[shifted, -G1Affine::generator()], &[*opening, target]) == Gt::identity()
+}
+
+// e(opening, h^a / h^z) == e(commitment / g^eval, h) for a commitment in G1
+fn opens_in_g1(g2_secret: &G2Affine, commitment: &G1Affine, opening: &G1Affine, z: &Scalar, eval: &Scalar) -> bool {
+    let shifted = (G2Projective::from(g2_secret) - G2Affine::generator() * z).to_affine();
+    let target = (G1Projective::from(commitment) - G1Affine::generator() * eval).to_affine();
+    pairing_product(&[*opening, -target], &[shifted, G2Affine::generator()]) == Gt::identity()
+}
+
+fn write_commitment<W: Write>(writer: &mut W, commitment: &Commitment) -> io::Result<()> {
+    commitment.iter().try_for_each(|element| writer.write_all(&element.to_bytes()))
+}
+
+fn read_target<R: Read>(reader: &mut R) -> io::Result<TargetElement> {
+    let mut bytes = [0u8; TARGET_ELEMENT_BYTES];
+    reader.read_exact(&mut bytes)?;
+    TargetElement::from_bytes(&bytes)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid target group element"))
+}
+
+fn read_commitment<R: Read>(reader: &mut R) -> io::Result<Commitment> {
+    Ok([read_target(reader)?, read_target(reader)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::proof_system::Groth16;
+    use crate::crypto::zk_proofs::{create_square_proof, scalar_from_u64, setup_square_circuit};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn aggregates_verify_and_bind_their_inputs() {
+        let params = setup_square_circuit(4, 16).unwrap();
+        let srs = AggregationSrs::generate_insecure(8, &mut OsRng).unwrap();
+        let agg_vk = srs.verifying_key();
+
+        // Five proofs are padded to eight
+        let proofs: Vec<_> = (1..=5u64).map(|x| create_square_proof(&Groth16, &params, x, x * x).unwrap()).collect();
+        let inputs: Vec<Vec<Scalar>> = proofs.iter().map(|(_, inputs)| inputs.clone()).collect();
+        let aggregate = aggregate_proofs(&srs, &params.vk, &proofs).unwrap();
+        assert_eq!(aggregate.rounds.len(), 3);
+        assert!(verify_aggregate_proof(&agg_vk, &params.vk, &inputs, &aggregate).unwrap());
+
+        let envelope = aggregate.seal(&agg_vk, &params.vk, ProofType::SquareRoot).unwrap();
+        assert_eq!(envelope.proof_system, AGGREGATE_PROOF_SYSTEM);
+        let envelope = ProofEnvelope::from_cbor(&envelope.to_cbor().unwrap()).unwrap();
+        assert_eq!(AggregateProof::open(&envelope, &agg_vk, &params.vk, ProofType::SquareRoot).unwrap(), aggregate);
+        assert!(AggregateProof::open(&envelope, &agg_vk, &params.vk, ProofType::RangeProof).is_err());
+        let other_params = setup_square_circuit(4, 16).unwrap();
+        assert!(AggregateProof::open(&envelope, &agg_vk, &other_params.vk, ProofType::SquareRoot).is_err());
+
+        let mut wrong = inputs.clone();
+        wrong[2] = vec![scalar_from_u64(10)];
+        assert!(!verify_aggregate_proof(&agg_vk, &params.vk, &wrong, &aggregate).unwrap());
+        assert!(!verify_aggregate_proof(&agg_vk, &params.vk, &inputs[..4], &aggregate).unwrap());
+
+        let other_srs = AggregationSrs::generate_insecure(8, &mut OsRng).unwrap();
+        assert!(!verify_aggregate_proof(&other_srs.verifying_key(), &params.vk, &inputs, &aggregate).unwrap());
+    }
+
+    #[test]
+    fn invalid_proofs_do_not_aggregate() {
+        let params = setup_square_circuit(4, 16).unwrap();
+        let srs = AggregationSrs::generate_insecure(4, &mut OsRng).unwrap();
+
+        let mut proofs: Vec<_> = (1..=4u64).map(|x| create_square_proof(&Groth16, &params, x, x * x).unwrap()).collect();
+        proofs[3].1 = vec![scalar_from_u64(15)];
+        let inputs: Vec<Vec<Scalar>> = proofs.iter().map(|(_, inputs)| inputs.clone()).collect();
+        let aggregate = aggregate_proofs(&srs, &params.vk, &proofs).unwrap();
+        assert!(!verify_aggregate_proof(&srs.verifying_key(), &params.vk, &inputs, &aggregate).unwrap());
+
+        proofs.push(proofs[0].clone());
+        assert!(matches!(aggregate_proofs(&srs, &params.vk, &proofs), Err(ZkError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn reference_string_from_powers_of_tau() {
+        let first = PowersOfTau::generate_insecure(5, &mut OsRng).unwrap();
+        let second = PowersOfTau::generate_insecure(5, &mut OsRng).unwrap();
+        let srs = AggregationSrs::from_powers_of_tau(&first, &second).unwrap();
+        assert_eq!(srs.max_proofs(), 4);
+        assert!(AggregationSrs::from_powers_of_tau(&first, &first).is_err());
+
+        let mut bytes = Vec::new();
+        srs.write(&mut bytes).unwrap();
+        assert_eq!(AggregationSrs::read(&bytes[..]).unwrap(), srs);
+    }
+}
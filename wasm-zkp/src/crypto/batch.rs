@@ -0,0 +1,209 @@
+//! Batch verification of Groth16 proofs under one verifying key.
+//!
+//! A batch is checked with a single multi-Miller loop: each proof is weighted by
+//! a fresh random scalar, so all pairings against the fixed key elements are
+//! shared and a batch of `n` proofs costs `n + 3` Miller loops and one final
+//! exponentiation instead of `3n` pairings. If the batch fails, it is bisected
+//! to find the failing entries, so a few bad proofs do not cost a full
+//! one-by-one pass.
+//!
+//! Prepared verifying keys are cached by `verifying_key_hash`, the hash envelopes
+//! name keys by, so repeated verification under the same key does not redo the
+//! G2 precomputation.
+
+use crate::crypto::envelope::{verifying_key_hash, ProofEnvelope};
+use crate::crypto::proof_system::{Groth16, ProofSystem};
+use crate::crypto::zk_proofs::{decode_scalar, verify_prepared_proof};
+use crate::error::ZkError;
+use bellman::groth16::{batch, prepare_verifying_key, PreparedVerifyingKey, Proof, VerifyingKey};
+use bls12_381::{Bls12, Scalar};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// One proof to verify, as sent by a client: its envelope and the hex-encoded public
+/// inputs the verifier expects for it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchEntry {
+    pub proof: ProofEnvelope,
+    pub public_inputs: Vec<String>,
+}
+
+impl BatchEntry {
+    /// The proof and expected inputs, once the envelope is checked against the
+    /// Groth16 verifying key hashing to `vk_hash`
+    pub fn decode(&self, vk_hash: &[u8; 32]) -> Result<(Proof<Bls12>, Vec<Scalar>), ZkError> {
+        self.proof.check_key(Groth16.name(), vk_hash)?;
+        let proof = self.proof.decode_proof(&Groth16)?;
+        let inputs = self.public_inputs.iter()
+            .map(|input| decode_scalar(input))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((proof, inputs))
+    }
+}
+
+/// Outcome of a batch: how many entries verified and the indices of those that did not
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchVerification {
+    pub valid: usize,
+    pub failed: Vec<usize>,
+}
+
+impl BatchVerification {
+    pub fn all_valid(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Verify many proofs under `vk` (with `pvk` its prepared form), reporting which
+/// entries failed. Entries with the wrong number of public inputs count as failed.
+pub fn verify_batch(
+    vk: &VerifyingKey<Bls12>,
+    pvk: &PreparedVerifyingKey<Bls12>,
+    items: &[(Proof<Bls12>, Vec<Scalar>)],
+) -> BatchVerification {
+    let (candidates, mut failed): (Vec<usize>, Vec<usize>) = (0..items.len())
+        .partition(|&i| items[i].1.len() + 1 == vk.ic.len());
+
+    find_failures(vk, pvk, items, &candidates, &mut failed);
+    failed.sort_unstable();
+
+    BatchVerification { valid: items.len() - failed.len(), failed }
+}
+
+/// Verify decoded and undecodable entries alike; entries that fail to decode, or whose
+/// envelope names another key than the one hashing to `vk_hash`, count as failed
+pub fn verify_batch_entries(
+    vk: &VerifyingKey<Bls12>,
+    vk_hash: &[u8; 32],
+    pvk: &PreparedVerifyingKey<Bls12>,
+    entries: &[BatchEntry],
+) -> BatchVerification {
+    let mut undecodable = Vec::new();
+    let mut indices = Vec::with_capacity(entries.len());
+    let mut items = Vec::with_capacity(entries.len());
+    for (i, entry) in entries.iter().enumerate() {
+        match entry.decode(vk_hash) {
+            Ok(item) => {
+                indices.push(i);
+                items.push(item);
+            }
+            Err(_) => undecodable.push(i),
+        }
+    }
+
+    let decoded = verify_batch(vk, pvk, &items);
+    let mut failed: Vec<usize> = decoded.failed.iter().map(|&i| indices[i]).chain(undecodable).collect();
+    failed.sort_unstable();
+
+    BatchVerification { valid: entries.len() - failed.len(), failed }
+}
+
+// Bisect `indices` until every failing entry is isolated
+fn find_failures(
+    vk: &VerifyingKey<Bls12>,
+    pvk: &PreparedVerifyingKey<Bls12>,
+    items: &[(Proof<Bls12>, Vec<Scalar>)],
+    indices: &[usize],
+    failed: &mut Vec<usize>,
+) {
+    match indices {
+        [] => {}
+        [i] => {
+            if !matches!(verify_prepared_proof(pvk, &items[*i].0, &items[*i].1), Ok(true)) {
+                failed.push(*i);
+            }
+        }
+        _ => {
+            let mut verifier = batch::Verifier::new();
+            for &i in indices {
+                verifier.queue((&items[i].0, items[i].1.as_slice()));
+            }
+            if verifier.verify(OsRng, vk).is_ok() {
+                return;
+            }
+
+            let (left, right) = indices.split_at(indices.len() / 2);
+            find_failures(vk, pvk, items, left, failed);
+            find_failures(vk, pvk, items, right, failed);
+        }
+    }
+}
+
+// Prepared keys by `verifying_key_hash`
+type PreparedKeys = HashMap<[u8; 32], Arc<PreparedVerifyingKey<Bls12>>>;
+
+static PREPARED_KEYS: OnceLock<Mutex<PreparedKeys>> = OnceLock::new();
+
+/// The prepared form of `vk`, computed once per distinct key
+pub fn prepared_verifying_key(vk: &VerifyingKey<Bls12>) -> Result<Arc<PreparedVerifyingKey<Bls12>>, ZkError> {
+    let id = verifying_key_hash(&Groth16, vk)?;
+
+    let mut cache = PREPARED_KEYS.get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    Ok(cache.entry(id).or_insert_with(|| Arc::new(prepare_verifying_key(vk))).clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::envelope::verifying_key_hash;
+    use crate::crypto::zk_proofs::{create_square_proof, encode_scalar, scalar_from_u64, setup_square_circuit, ProofType};
+
+    #[test]
+    fn batch_reports_the_failing_entries() {
+        let params = setup_square_circuit(4, 16).unwrap();
+        let pvk = prepared_verifying_key(&params.vk).unwrap();
+
+        let mut items: Vec<_> = (1..=6u64)
+            .map(|x| create_square_proof(&Groth16, &params, x, x * x).unwrap())
+            .collect();
+        assert_eq!(verify_batch(&params.vk, &pvk, &items), BatchVerification { valid: 6, failed: vec![] });
+
+        // A wrong public input, a proof for another statement, and a wrong input count
+        items[1].1 = vec![scalar_from_u64(5)];
+        items[4].0 = items[0].0.clone();
+        items[5].1.push(scalar_from_u64(1));
+        let result = verify_batch(&params.vk, &pvk, &items);
+        assert_eq!(result, BatchVerification { valid: 3, failed: vec![1, 4, 5] });
+        assert!(!result.all_valid());
+    }
+
+    #[test]
+    fn undecodable_entries_fail() {
+        let params = setup_square_circuit(4, 16).unwrap();
+        let (proof, inputs) = create_square_proof(&Groth16, &params, 3, 9).unwrap();
+        let envelope = ProofEnvelope::seal(&Groth16, &params, ProofType::SquareRoot, &proof, &inputs, None).unwrap();
+        let public_inputs: Vec<_> = inputs.iter().map(encode_scalar).collect();
+        let entry = BatchEntry { proof: envelope.clone(), public_inputs: public_inputs.clone() };
+
+        // Unreadable proof bytes, and a proof made under another key
+        let mut garbled = envelope.clone();
+        garbled.proof.truncate(10);
+        let garbled = BatchEntry { proof: garbled, public_inputs: public_inputs.clone() };
+        let other_params = setup_square_circuit(4, 16).unwrap();
+        let (other_proof, _) = create_square_proof(&Groth16, &other_params, 3, 9).unwrap();
+        let other_key = BatchEntry {
+            proof: ProofEnvelope::seal(&Groth16, &other_params, ProofType::SquareRoot, &other_proof, &inputs, None).unwrap(),
+            public_inputs,
+        };
+
+        let pvk = prepared_verifying_key(&params.vk).unwrap();
+        let vk_hash = verifying_key_hash(&Groth16, &params.vk).unwrap();
+        let result = verify_batch_entries(&params.vk, &vk_hash, &pvk, &[garbled, entry, other_key]);
+        assert_eq!(result, BatchVerification { valid: 1, failed: vec![0, 2] });
+    }
+
+    #[test]
+    fn prepared_keys_are_cached_per_key() {
+        let first = setup_square_circuit(4, 16).unwrap();
+        let second = setup_square_circuit(4, 16).unwrap();
+
+        assert!(Arc::ptr_eq(&prepared_verifying_key(&first.vk).unwrap(), &prepared_verifying_key(&first.vk).unwrap()));
+        assert!(!Arc::ptr_eq(&prepared_verifying_key(&first.vk).unwrap(), &prepared_verifying_key(&second.vk).unwrap()));
+    }
+}
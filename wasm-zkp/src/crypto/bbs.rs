@@ -0,0 +1,603 @@
+//! BBS+ signatures on BLS12-381 with selective-disclosure proofs of knowledge.
+//!
+//! An issuer with key `x` and public key `w = g2^x` signs messages `m_1..m_L` as
+//! `(A, e, s)` with `A = (g1·h0^s·∏ h_i^{m_i})^{1/(x+e)}`, verified by
+//! `e(A, w·g2^e) = e(b, g2)` where `b` is the signed product. A holder derives a
+//! fresh proof of knowledge of the signature for every presentation (Camenisch,
+//! Drijvers and Lehmann, 2016), revealing only the messages it chooses. Proofs
+//! need no circuit or trusted setup, and two proofs of one signature are unlinkable.
+//!
+//! Presentations carry their proof in a `ProofEnvelope` of the `bbs+` proof
+//! system, keyed by the hash of the issuer's public key and bound to the
+//! verifier's nonce, so a presentation for another issuer or verifier is
+//! rejected before its proof is checked.
+
+use crate::crypto::credential::{check_issuable, Credential, CREDENTIAL_ATTRIBUTE_SLOTS};
+use crate::crypto::envelope::{key_hash, ProofEnvelope};
+use crate::crypto::hash_to_field::{hash_str_to_field, hash_to_field, HashDomain};
+use crate::crypto::zk_proofs::ProofType;
+use crate::error::ZkError;
+use crate::utils::get_error_message;
+use bls12_381::hash_to_curve::{ExpandMsgXmd, HashToCurve};
+use bls12_381::{multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Scalar};
+use ff::Field;
+use group::{Curve, Group};
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+/// Messages signed per credential: the credential id, then one per attribute slot
+pub const BBS_CREDENTIAL_MESSAGES: usize = 1 + CREDENTIAL_ATTRIBUTE_SLOTS;
+
+/// `ProofEnvelope::proof_system` of BBS+ presentations
+pub const BBS_PROOF_SYSTEM: &str = "bbs+";
+
+const GENERATOR_DST: &[u8] = b"WASM-ZKP-BBS-PLUS-V01-CS01-with-BLS12381G1_XMD:BLAKE2B_SSWU_RO_";
+
+const G1_BYTES: usize = 48;
+const SCALAR_BYTES: usize = 32;
+// A', Ā, d, then c, ê, r̂2, r̂3, ŝ
+const PROOF_FIXED_BYTES: usize = 3 * G1_BYTES + 5 * SCALAR_BYTES;
+
+/// Issuer secret key `x`
+#[derive(Clone, Copy, PartialEq)]
+pub struct BbsSecretKey(Scalar);
+
+/// Redacted: only the public half is printed
+impl fmt::Debug for BbsSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BbsSecretKey").field(&self.public_key()).finish()
+    }
+}
+
+/// Issuer public key `w = g2^x`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BbsPublicKey(G2Affine);
+
+/// Signature `(A, e, s)` on a list of messages
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BbsSignature {
+    pub a: G1Affine,
+    pub e: Scalar,
+    pub s: Scalar,
+}
+
+/// Proof of knowledge of a signature, revealing a subset of its messages
+#[derive(Clone, Debug, PartialEq)]
+pub struct BbsProof {
+    a_prime: G1Affine,
+    a_bar: G1Affine,
+    d: G1Affine,
+    challenge: Scalar,
+    e_hat: Scalar,
+    r2_hat: Scalar,
+    r3_hat: Scalar,
+    s_hat: Scalar,
+    /// Responses for the hidden messages, in message order
+    m_hat: Vec<Scalar>,
+}
+
+impl BbsSecretKey {
+    pub fn generate() -> Self {
+        Self(random_nonzero())
+    }
+
+    pub fn public_key(&self) -> BbsPublicKey {
+        BbsPublicKey((G2Projective::generator() * self.0).to_affine())
+    }
+
+    pub fn sign(&self, messages: &[Scalar]) -> BbsSignature {
+        let e = random_nonzero();
+        let s = Scalar::random(&mut OsRng);
+        let b = signed_product(&generators(messages.len()), &s, messages);
+
+        // x + e is zero only if e = -x, which a random e hits with negligible probability
+        let exponent = Option::<Scalar>::from((self.0 + e).invert()).expect("x + e is non-zero");
+        BbsSignature { a: (b * exponent).to_affine(), e, s }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ZkError> {
+        let key = decode_scalar_bytes(bytes, "BBS+ secret key")?;
+        if bool::from(key.is_zero()) {
+            return Err(ZkError::Decoding("BBS+ secret key must be non-zero".to_string()));
+        }
+        Ok(Self(key))
+    }
+}
+
+impl BbsPublicKey {
+    pub fn verify(&self, messages: &[Scalar], signature: &BbsSignature) -> bool {
+        if bool::from(signature.a.is_identity()) {
+            return false;
+        }
+        let b = signed_product(&generators(messages.len()), &signature.s, messages);
+        let w_e = (G2Projective::from(self.0) + G2Projective::generator() * signature.e).to_affine();
+
+        // e(A, w·g2^e) · e(-b, g2) = 1
+        pairing_product_is_one(&signature.a, &w_e, &(-b).to_affine(), &G2Affine::generator())
+    }
+
+    pub fn to_bytes(&self) -> [u8; 96] {
+        self.0.to_compressed()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ZkError> {
+        let bytes: [u8; 96] = bytes.try_into()
+            .map_err(|_| ZkError::Decoding("BBS+ public key must be 96 bytes".to_string()))?;
+        let key: G2Affine = Option::from(G2Affine::from_compressed(&bytes))
+            .ok_or_else(|| ZkError::Decoding("BBS+ public key is not a G2 point".to_string()))?;
+        if bool::from(key.is_identity()) {
+            return Err(ZkError::Decoding("BBS+ public key must not be the identity".to_string()));
+        }
+        Ok(Self(key))
+    }
+}
+
+impl BbsSignature {
+    /// 112-byte encoding: compressed `A`, then `e` and `s`
+    pub fn to_bytes(&self) -> [u8; 112] {
+        let mut bytes = [0u8; 112];
+        bytes[..48].copy_from_slice(&self.a.to_compressed());
+        bytes[48..80].copy_from_slice(&self.e.to_bytes());
+        bytes[80..].copy_from_slice(&self.s.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ZkError> {
+        if bytes.len() != 112 {
+            return Err(ZkError::Decoding("BBS+ signature must be 112 bytes".to_string()));
+        }
+        Ok(Self {
+            a: decode_g1(&bytes[..48])?,
+            e: decode_scalar_bytes(&bytes[48..80], "BBS+ signature")?,
+            s: decode_scalar_bytes(&bytes[80..], "BBS+ signature")?,
+        })
+    }
+}
+
+impl BbsProof {
+    /// Number of messages the proof keeps hidden
+    pub fn hidden_count(&self) -> usize {
+        self.m_hat.len()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PROOF_FIXED_BYTES + SCALAR_BYTES * self.m_hat.len());
+        for point in [&self.a_prime, &self.a_bar, &self.d] {
+            bytes.extend_from_slice(&point.to_compressed());
+        }
+        for scalar in [&self.challenge, &self.e_hat, &self.r2_hat, &self.r3_hat, &self.s_hat].into_iter().chain(&self.m_hat) {
+            bytes.extend_from_slice(&scalar.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ZkError> {
+        if bytes.len() < PROOF_FIXED_BYTES || !(bytes.len() - PROOF_FIXED_BYTES).is_multiple_of(SCALAR_BYTES) {
+            return Err(ZkError::Decoding("malformed BBS+ proof length".to_string()));
+        }
+        let (points, scalars) = bytes.split_at(3 * G1_BYTES);
+        let scalars = scalars.chunks(SCALAR_BYTES)
+            .map(|chunk| decode_scalar_bytes(chunk, "BBS+ proof"))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            a_prime: decode_g1(&points[..48])?,
+            a_bar: decode_g1(&points[48..96])?,
+            d: decode_g1(&points[96..])?,
+            challenge: scalars[0],
+            e_hat: scalars[1],
+            r2_hat: scalars[2],
+            r3_hat: scalars[3],
+            s_hat: scalars[4],
+            m_hat: scalars[5..].to_vec(),
+        })
+    }
+}
+
+/// Derive a proof of knowledge of `signature` on `messages` that reveals only the
+/// messages at the indices in `disclosed`, bound to the verifier's `nonce`
+pub fn create_bbs_proof(
+    signature: &BbsSignature,
+    messages: &[Scalar],
+    disclosed: &[usize],
+    nonce: &[u8],
+) -> Result<BbsProof, ZkError> {
+    if let Some(index) = disclosed.iter().find(|index| **index >= messages.len()) {
+        return Err(ZkError::InvalidInput(format!("cannot disclose message {} of {}", index, messages.len())));
+    }
+    let h = generators(messages.len());
+    let b = signed_product(&h, &signature.s, messages);
+    let hidden: Vec<usize> = (0..messages.len()).filter(|index| !disclosed.contains(index)).collect();
+
+    // Randomize the signature: A' = A^r1, Ā = A'^-e·b^r1, d = b^r1·h0^-r2, s' = s - r2·r3
+    let r1 = random_nonzero();
+    let r2 = Scalar::random(&mut OsRng);
+    let r3 = Option::<Scalar>::from(r1.invert()).expect("r1 is non-zero");
+    let a_prime = G1Projective::from(signature.a) * r1;
+    let a_bar = a_prime * (-signature.e) + b * r1;
+    let d = b * r1 - h[0] * r2;
+    let s_prime = signature.s - r2 * r3;
+
+    // Commitments to Ā/d = A'^-e·h0^r2 and g1·∏_D h_i^{m_i} = d^r3·h0^-s'·∏_hidden h_i^-{m_i}
+    let e_tilde = Scalar::random(&mut OsRng);
+    let r2_tilde = Scalar::random(&mut OsRng);
+    let r3_tilde = Scalar::random(&mut OsRng);
+    let s_tilde = Scalar::random(&mut OsRng);
+    let m_tilde: Vec<Scalar> = hidden.iter().map(|_| Scalar::random(&mut OsRng)).collect();
+
+    let t1 = a_prime * (-e_tilde) + h[0] * r2_tilde;
+    let t2 = hidden.iter().zip(&m_tilde)
+        .fold(d * r3_tilde - h[0] * s_tilde, |acc, (index, m)| acc - h[index + 1] * m);
+
+    let [a_prime, a_bar, d, t1, t2] = to_affine([a_prime, a_bar, d, t1, t2]);
+    let revealed: BTreeMap<usize, Scalar> = disclosed.iter().map(|index| (*index, messages[*index])).collect();
+    let challenge = proof_challenge(&[a_prime, a_bar, d, t1, t2], messages.len(), &revealed, nonce);
+
+    Ok(BbsProof {
+        a_prime,
+        a_bar,
+        d,
+        challenge,
+        e_hat: e_tilde + challenge * signature.e,
+        r2_hat: r2_tilde + challenge * r2,
+        r3_hat: r3_tilde + challenge * r3,
+        s_hat: s_tilde + challenge * s_prime,
+        m_hat: hidden.iter().zip(m_tilde).map(|(index, m)| m + challenge * messages[*index]).collect(),
+    })
+}
+
+/// Verify a proof that the holder knows a signature under `public_key` on
+/// `message_count` messages, of which those in `disclosed` (index to message) are revealed
+pub fn verify_bbs_proof(
+    public_key: &BbsPublicKey,
+    proof: &BbsProof,
+    message_count: usize,
+    disclosed: &BTreeMap<usize, Scalar>,
+    nonce: &[u8],
+) -> bool {
+    if disclosed.keys().any(|index| *index >= message_count)
+        || proof.m_hat.len() != message_count - disclosed.len()
+        || bool::from(proof.a_prime.is_identity())
+    {
+        return false;
+    }
+
+    // e(A', w) = e(Ā, g2) holds exactly when A' is a signature under w
+    if !pairing_product_is_one(&proof.a_prime, &public_key.0, &(-proof.a_bar), &G2Affine::generator()) {
+        return false;
+    }
+
+    let h = generators(message_count);
+    let c = proof.challenge;
+    let a_prime = G1Projective::from(proof.a_prime);
+    let a_bar = G1Projective::from(proof.a_bar);
+    let d = G1Projective::from(proof.d);
+
+    let t1 = a_prime * (-proof.e_hat) + h[0] * proof.r2_hat - (a_bar - d) * c;
+    let disclosed_product = disclosed.iter()
+        .fold(G1Projective::generator(), |acc, (index, m)| acc + h[index + 1] * m);
+    let hidden = (0..message_count).filter(|index| !disclosed.contains_key(index));
+    let t2 = hidden.zip(&proof.m_hat)
+        .fold(d * proof.r3_hat - h[0] * proof.s_hat, |acc, (index, m)| acc - h[index + 1] * m)
+        - disclosed_product * c;
+
+    let [t1, t2] = to_affine([t1, t2]);
+    proof_challenge(&[proof.a_prime, proof.a_bar, proof.d, t1, t2], message_count, disclosed, nonce) == c
+}
+
+/// The messages a BBS+ credential signature covers: the credential id (never
+/// disclosed), then each attribute slot, padded so every credential signs the same count
+pub fn bbs_credential_messages(credential: &Credential) -> Result<Vec<Scalar>, ZkError> {
+    if credential.attributes.len() > CREDENTIAL_ATTRIBUTE_SLOTS {
+        return Err(ZkError::InvalidInput(format!(
+            "credential has {} attributes, at most {} are supported",
+            credential.attributes.len(),
+            CREDENTIAL_ATTRIBUTE_SLOTS
+        )));
+    }
+
+    let mut messages = vec![hash_str_to_field(HashDomain::CredentialId, &credential.id)];
+    messages.extend(credential.attributes.iter().map(|attr| attribute_message(&attr.name, &attr.value)));
+    messages.resize(BBS_CREDENTIAL_MESSAGES, Scalar::zero());
+    Ok(messages)
+}
+
+/// Message for one attribute: its name and value, length-prefixed so neither can spill into the other
+pub fn attribute_message(name: &str, value: &str) -> Scalar {
+    let mut input = (name.len() as u64).to_le_bytes().to_vec();
+    input.extend_from_slice(name.as_bytes());
+    input.extend_from_slice(value.as_bytes());
+    hash_to_field(HashDomain::BbsMessage, &input)
+}
+
+/// Attach a BBS+ signature over the credential's messages; revoked or already expired credentials are refused
+pub fn sign_credential_bbs(credential: &Credential, issuer_key: &BbsSecretKey) -> Result<Credential, ZkError> {
+    check_issuable(credential)?;
+    let signature = issuer_key.sign(&bbs_credential_messages(credential)?);
+    let mut signed = credential.clone();
+    signed.bbs_signature = Some(hex::encode(signature.to_bytes()));
+    Ok(signed)
+}
+
+/// An attribute revealed by a BBS+ presentation, at its slot in the credential
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BbsDisclosedAttribute {
+    pub slot: usize,
+    pub name: String,
+    pub value: String,
+}
+
+/// Selective-disclosure presentation of a BBS+-signed credential. Nothing in it is
+/// shared between two presentations of one credential, so verifiers cannot link them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BbsPresentation {
+    /// The `BbsProof`, in an envelope naming the issuer key and nonce
+    pub proof: ProofEnvelope,
+    pub disclosed: Vec<BbsDisclosedAttribute>,
+}
+
+/// Present `credential`, signed by `issuer_public_key`, to the verifier that issued
+/// `nonce`, disclosing only the attributes marked `reveal`
+pub fn create_bbs_presentation(
+    credential: &Credential,
+    issuer_public_key: &BbsPublicKey,
+    nonce: &str,
+) -> Result<BbsPresentation, ZkError> {
+    let signature = credential.bbs_signature.as_ref()
+        .ok_or_else(|| ZkError::InvalidInput("credential has no BBS+ signature".to_string()))?;
+    let signature = BbsSignature::from_bytes(&hex::decode(signature)?)?;
+    let messages = bbs_credential_messages(credential)?;
+
+    let disclosed: Vec<BbsDisclosedAttribute> = credential.attributes.iter()
+        .enumerate()
+        .filter(|(_, attr)| attr.reveal)
+        .map(|(slot, attr)| BbsDisclosedAttribute { slot, name: attr.name.clone(), value: attr.value.clone() })
+        .collect();
+    let indices: Vec<usize> = disclosed.iter().map(|attr| attr.slot + 1).collect();
+
+    let proof = create_bbs_proof(&signature, &messages, &indices, nonce.as_bytes())?;
+    let proof = ProofEnvelope::new(
+        BBS_PROOF_SYSTEM,
+        ProofType::CredentialVerification,
+        issuer_key_hash(issuer_public_key),
+        &[],
+        proof.to_bytes(),
+        Some(nonce),
+    );
+    Ok(BbsPresentation { proof, disclosed })
+}
+
+/// Verify a presentation made for `nonce` from a credential signed by the trusted
+/// `issuer_public_key`; an envelope naming another issuer or nonce is an error
+pub fn verify_bbs_presentation(
+    issuer_public_key: &BbsPublicKey,
+    presentation: &BbsPresentation,
+    nonce: &str,
+) -> Result<bool, ZkError> {
+    presentation.proof.check(
+        BBS_PROOF_SYSTEM,
+        ProofType::CredentialVerification,
+        &issuer_key_hash(issuer_public_key),
+        Some(nonce),
+    )?;
+
+    let mut disclosed = BTreeMap::new();
+    for attr in &presentation.disclosed {
+        if attr.slot >= CREDENTIAL_ATTRIBUTE_SLOTS
+            || disclosed.insert(attr.slot + 1, attribute_message(&attr.name, &attr.value)).is_some()
+        {
+            return Ok(false);
+        }
+    }
+
+    let proof = BbsProof::from_bytes(&presentation.proof.proof)?;
+    Ok(verify_bbs_proof(issuer_public_key, &proof, BBS_CREDENTIAL_MESSAGES, &disclosed, nonce.as_bytes()))
+}
+
+/// `key_hash` of an issuer public key, naming it in presentation envelopes
+pub fn issuer_key_hash(issuer_public_key: &BbsPublicKey) -> [u8; 32] {
+    key_hash(&issuer_public_key.to_bytes())
+}
+
+/// Generate a BBS+ issuer key pair: `{ secretKey, publicKey }`, both hex-encoded
+#[wasm_bindgen]
+pub fn generate_bbs_issuer_keypair() -> Result<JsValue, JsValue> {
+    let key = BbsSecretKey::generate();
+    serde_wasm_bindgen::to_value(&serde_json::json!({
+        "secretKey": hex::encode(key.to_bytes()),
+        "publicKey": hex::encode(key.public_key().to_bytes()),
+    })).map_err(|e| JsValue::from_str(&get_error_message("Serialization error", &e)))
+}
+
+/// BBS+-sign a credential (JSON) with a hex-encoded issuer secret key; returns the signed credential JSON
+#[wasm_bindgen]
+pub fn issue_bbs_credential_signature(credential_json: &str, issuer_secret_key: &str) -> Result<String, JsValue> {
+    let credential: Credential = serde_json::from_str(credential_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid credential JSON: {:?}", e)))?;
+    let key = BbsSecretKey::from_bytes(&hex::decode(issuer_secret_key).map_err(ZkError::from)?)?;
+
+    let signed = sign_credential_bbs(&credential, &key)?;
+    serde_json::to_string(&signed).map_err(|e| JsValue::from_str(&get_error_message("Serialization error", &e)))
+}
+
+// h0, h1, ..., h_count, hashed to G1 so nobody knows their discrete logarithms
+fn generators(count: usize) -> Vec<G1Projective> {
+    (0..=count as u32)
+        .map(|index| {
+            <G1Projective as HashToCurve<ExpandMsgXmd<blake2_digest09::Blake2b>>>::hash_to_curve(
+                index.to_le_bytes(),
+                GENERATOR_DST,
+            )
+        })
+        .collect()
+}
+
+// b = g1·h0^s·∏ h_i^{m_i}
+fn signed_product(h: &[G1Projective], s: &Scalar, messages: &[Scalar]) -> G1Projective {
+    messages.iter()
+        .zip(&h[1..])
+        .fold(G1Projective::generator() + h[0] * s, |acc, (m, h)| acc + h * m)
+}
+
+// e(p1, q1)·e(p2, q2) = 1
+fn pairing_product_is_one(p1: &G1Affine, q1: &G2Affine, p2: &G1Affine, q2: &G2Affine) -> bool {
+    let (q1, q2) = (G2Prepared::from(*q1), G2Prepared::from(*q2));
+    bool::from(multi_miller_loop(&[(p1, &q1), (p2, &q2)]).final_exponentiation().is_identity())
+}
+
+// Fiat-Shamir challenge over the proof's points, the revealed messages and the verifier's nonce
+fn proof_challenge(points: &[G1Affine], message_count: usize, disclosed: &BTreeMap<usize, Scalar>, nonce: &[u8]) -> Scalar {
+    let mut input = Vec::new();
+    for point in points {
+        input.extend_from_slice(&point.to_compressed());
+    }
+    input.extend_from_slice(&(message_count as u64).to_le_bytes());
+    for (index, message) in disclosed {
+        input.extend_from_slice(&(*index as u64).to_le_bytes());
+        input.extend_from_slice(&message.to_bytes());
+    }
+    input.extend_from_slice(nonce);
+    hash_to_field(HashDomain::BbsChallenge, &input)
+}
+
+fn to_affine<const N: usize>(points: [G1Projective; N]) -> [G1Affine; N] {
+    let mut affine = [G1Affine::identity(); N];
+    G1Projective::batch_normalize(&points, &mut affine);
+    affine
+}
+
+fn random_nonzero() -> Scalar {
+    loop {
+        let scalar = Scalar::random(&mut OsRng);
+        if !bool::from(scalar.is_zero()) {
+            return scalar;
+        }
+    }
+}
+
+fn decode_g1(bytes: &[u8]) -> Result<G1Affine, ZkError> {
+    let bytes: [u8; 48] = bytes.try_into()
+        .map_err(|_| ZkError::Decoding("G1 point must be 48 bytes".to_string()))?;
+    Option::from(G1Affine::from_compressed(&bytes))
+        .ok_or_else(|| ZkError::Decoding("not a G1 point".to_string()))
+}
+
+fn decode_scalar_bytes(bytes: &[u8], what: &str) -> Result<Scalar, ZkError> {
+    let bytes: [u8; 32] = bytes.try_into()
+        .map_err(|_| ZkError::Decoding(format!("{} scalar must be 32 bytes", what)))?;
+    Option::from(Scalar::from_bytes(&bytes))
+        .ok_or_else(|| ZkError::Decoding(format!("{} scalar is not canonical", what)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::credential::{CredentialAttribute, CredentialType};
+
+    fn credential() -> Credential {
+        Credential {
+            id: "urn:uuid:3978344f-8596-4c3a-a978-8fcaba3903c5".to_string(),
+            issuer: "did:ethr:0x1234".to_string(),
+            subject: "did:ethr:0xabcd".to_string(),
+            type_: CredentialType::Kyc,
+            attributes: vec![
+                CredentialAttribute { name: "age".to_string(), value: "34".to_string(), reveal: false, salt: None },
+                CredentialAttribute { name: "country".to_string(), value: "NL".to_string(), reveal: true, salt: None },
+            ],
+            issued_at: "2024-01-01T00:00:00Z".to_string(),
+            expires_at: None,
+            revoked: false,
+            holder_commitment: None,
+            issuer_public_key: None,
+            issuer_signature: None,
+            bbs_signature: None,
+        }
+    }
+
+    #[test]
+    fn signatures_verify_only_on_their_messages() {
+        let key = BbsSecretKey::generate();
+        let messages = [Scalar::from(1), Scalar::from(2), Scalar::from(3)];
+        let signature = key.sign(&messages);
+
+        assert!(key.public_key().verify(&messages, &signature));
+        assert!(!key.public_key().verify(&[Scalar::from(1), Scalar::from(2), Scalar::from(4)], &signature));
+        assert!(!BbsSecretKey::generate().public_key().verify(&messages, &signature));
+
+        let decoded = BbsSignature::from_bytes(&signature.to_bytes()).unwrap();
+        assert_eq!(decoded, signature);
+        assert_eq!(BbsPublicKey::from_bytes(&key.public_key().to_bytes()).unwrap(), key.public_key());
+    }
+
+    #[test]
+    fn proofs_reveal_only_the_chosen_messages() {
+        let key = BbsSecretKey::generate();
+        let messages = [Scalar::from(10), Scalar::from(20), Scalar::from(30), Scalar::from(40)];
+        let signature = key.sign(&messages);
+
+        let proof = create_bbs_proof(&signature, &messages, &[1, 3], b"nonce").unwrap();
+        assert_eq!(proof.hidden_count(), 2);
+        let proof = BbsProof::from_bytes(&proof.to_bytes()).unwrap();
+
+        let disclosed = BTreeMap::from([(1, messages[1]), (3, messages[3])]);
+        assert!(verify_bbs_proof(&key.public_key(), &proof, messages.len(), &disclosed, b"nonce"));
+
+        // Another value for a revealed message, another nonce or another issuer all fail
+        let forged = BTreeMap::from([(1, messages[1]), (3, Scalar::from(41))]);
+        assert!(!verify_bbs_proof(&key.public_key(), &proof, messages.len(), &forged, b"nonce"));
+        assert!(!verify_bbs_proof(&key.public_key(), &proof, messages.len(), &disclosed, b"other"));
+        assert!(!verify_bbs_proof(&BbsSecretKey::generate().public_key(), &proof, messages.len(), &disclosed, b"nonce"));
+    }
+
+    #[test]
+    fn issued_signatures_survive_the_typescript_credential_round_trip() {
+        let key = BbsSecretKey::generate();
+        let issued = issue_bbs_credential_signature(&serde_json::to_string(&credential()).unwrap(), &hex::encode(key.to_bytes())).unwrap();
+
+        // The TypeScript `Credential` reads and writes the signature as `bbs_signature`
+        let parsed: serde_json::Value = serde_json::from_str(&issued).unwrap();
+        assert!(parsed["bbs_signature"].is_string());
+        let credential: Credential = serde_json::from_str(&parsed.to_string()).unwrap();
+        let presentation = create_bbs_presentation(&credential, &key.public_key(), "verifier-nonce").unwrap();
+        assert!(verify_bbs_presentation(&key.public_key(), &presentation, "verifier-nonce").unwrap());
+    }
+
+    #[test]
+    fn credential_presentations_disclose_marked_attributes_and_are_unlinkable() {
+        let key = BbsSecretKey::generate();
+        let credential = sign_credential_bbs(&credential(), &key).unwrap();
+
+        let first = create_bbs_presentation(&credential, &key.public_key(), "verifier-nonce").unwrap();
+        let second = create_bbs_presentation(&credential, &key.public_key(), "verifier-nonce").unwrap();
+        assert_eq!(first.disclosed, vec![BbsDisclosedAttribute { slot: 1, name: "country".to_string(), value: "NL".to_string() }]);
+        assert_eq!(first.proof.proof_system, BBS_PROOF_SYSTEM);
+        assert_ne!(first.proof.proof, second.proof.proof);
+
+        assert!(verify_bbs_presentation(&key.public_key(), &first, "verifier-nonce").unwrap());
+        assert!(verify_bbs_presentation(&key.public_key(), &second, "verifier-nonce").unwrap());
+
+        // Presentations for another verifier or issuer are rejected by their envelope
+        let other_issuer = BbsSecretKey::generate().public_key();
+        assert!(matches!(verify_bbs_presentation(&key.public_key(), &first, "another-nonce"), Err(ZkError::EnvelopeMismatch(_))));
+        assert!(matches!(verify_bbs_presentation(&other_issuer, &first, "verifier-nonce"), Err(ZkError::EnvelopeMismatch(_))));
+        let mut relabelled = first.clone();
+        relabelled.proof.vk_hash = issuer_key_hash(&other_issuer);
+        assert!(!verify_bbs_presentation(&other_issuer, &relabelled, "verifier-nonce").unwrap());
+
+        let mut lying = first.clone();
+        lying.disclosed[0].value = "DE".to_string();
+        assert!(!verify_bbs_presentation(&key.public_key(), &lying, "verifier-nonce").unwrap());
+
+        // A hidden attribute cannot be claimed at another slot
+        let mut moved = first;
+        moved.disclosed[0].slot = 0;
+        assert!(!verify_bbs_presentation(&key.public_key(), &moved, "verifier-nonce").unwrap());
+    }
+}
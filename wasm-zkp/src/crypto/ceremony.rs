@@ -0,0 +1,1065 @@
+//! Phase-2 trusted setup ceremony for the Groth16 circuits.
+//!
+//! Groth16 parameters generated by a single party leak the toxic waste to
+//! whoever ran the setup. The ceremony here follows the Bowe-Gabizon-Miers
+//! MPC: circuit-independent powers of tau (phase 1) are imported from a public
+//! ceremony, specialised to one circuit, and then any number of participants
+//! re-randomise `delta`. The final parameters are secure as long as a single
+//! participant destroyed their contribution.
+//!
+//! Phase 1 is read from the BLS12-381 accumulators of the Zcash Powers of Tau
+//! ceremony (<https://github.com/ebfull/powersoftau>), whose `challenge` and
+//! `response` files hold `2^21` powers; see `PowersOfTau::read_zcash`.
+
+use crate::utils::get_error_message;
+use bellman::{
+    domain::{self, EvaluationDomain},
+    groth16::{Parameters, VerifyingKey},
+    multicore::Worker,
+    Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable,
+};
+use blake2::{Blake2b512, Digest};
+use bls12_381::{Bls12, G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
+use ff::Field;
+use group::{prime::PrimeCurveAffine, Curve, Group};
+use pairing::Engine;
+use rand::{rngs::OsRng, RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+use wasm_bindgen::prelude::*;
+
+/// Errors raised while building, contributing to or verifying a ceremony
+#[derive(Debug, Error)]
+pub enum CeremonyError {
+    #[error("circuit synthesis failed: {0}")]
+    Synthesis(#[from] SynthesisError),
+    #[error("powers of tau support {available} constraints but the circuit needs {needed}")]
+    TooFewPowers { needed: usize, available: usize },
+    #[error("invalid powers of tau: {0}")]
+    InvalidPowersOfTau(&'static str),
+    #[error("parameters were not derived from this circuit and powers of tau")]
+    CircuitMismatch,
+    #[error("contribution {index} is invalid: {reason}")]
+    InvalidContribution { index: usize, reason: &'static str },
+    #[error("the ceremony has no contributions, so its toxic waste is public")]
+    NoContributions,
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+impl From<CeremonyError> for JsValue {
+    fn from(error: CeremonyError) -> Self {
+        JsValue::from_str(&format!("Ceremony error: {}", error))
+    }
+}
+
+/// Number of powers in the Zcash Powers of Tau accumulators (`TAU_POWERS_LENGTH`)
+pub const ZCASH_TAU_POWERS_LENGTH: usize = 1 << 21;
+
+/// Kind of Zcash Powers of Tau accumulator file, which fixes its point encoding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZcashAccumulator {
+    /// `challenge` files: uncompressed points
+    Challenge,
+    /// `response` files: compressed points followed by the contributor's public key
+    Response,
+}
+
+impl FromStr for ZcashAccumulator {
+    type Err = CeremonyError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "challenge" => Ok(ZcashAccumulator::Challenge),
+            "response" => Ok(ZcashAccumulator::Response),
+            _ => Err(CeremonyError::InvalidPowersOfTau("accumulator must be `challenge` or `response`")),
+        }
+    }
+}
+
+/// Phase-1 output: powers of a secret `tau` together with the `alpha` and `beta` shifts.
+///
+/// With `n = size()`, the file holds `tau^i·G1` for `i < 2n - 1` and
+/// `tau^i·G2`, `alpha·tau^i·G1`, `beta·tau^i·G1` for `i < n`, plus `beta·G2`.
+/// It can serve any circuit with at most `n` constraints (including one per public input).
+#[derive(Clone, PartialEq, Debug)]
+pub struct PowersOfTau {
+    pub tau_g1: Vec<G1Affine>,
+    pub tau_g2: Vec<G2Affine>,
+    pub alpha_tau_g1: Vec<G1Affine>,
+    pub beta_tau_g1: Vec<G1Affine>,
+    pub beta_g2: G2Affine,
+}
+
+impl PowersOfTau {
+    /// Generate powers of tau from a single local secret.
+    ///
+    /// Whoever runs this knows `tau`; it exists for tests and local development only.
+    pub fn generate_insecure<R: RngCore>(size: usize, rng: &mut R) -> Result<Self, CeremonyError> {
+        let g1_len = tau_g1_len(size).ok_or(CeremonyError::InvalidPowersOfTau("unsupported size"))?;
+        let tau = Scalar::random(&mut *rng);
+        let alpha = Scalar::random(&mut *rng);
+        let beta = Scalar::random(&mut *rng);
+
+        let mut powers = Vec::with_capacity(g1_len);
+        let mut current = Scalar::one();
+        for _ in 0..g1_len {
+            powers.push(current);
+            current *= tau;
+        }
+
+        let g1 = G1Affine::generator();
+        let g2 = G2Affine::generator();
+        let scale_g1 = |factor: Scalar, count: usize| -> Vec<G1Affine> {
+            let projective: Vec<G1Projective> = powers[..count].iter().map(|p| g1 * (p * factor)).collect();
+            let mut affine = vec![G1Affine::identity(); count];
+            G1Projective::batch_normalize(&projective, &mut affine);
+            affine
+        };
+
+        let tau_g2: Vec<G2Projective> = powers[..size].iter().map(|p| g2 * p).collect();
+        let mut tau_g2_affine = vec![G2Affine::identity(); size];
+        G2Projective::batch_normalize(&tau_g2, &mut tau_g2_affine);
+
+        Ok(Self {
+            tau_g1: scale_g1(Scalar::one(), g1_len),
+            tau_g2: tau_g2_affine,
+            alpha_tau_g1: scale_g1(alpha, size),
+            beta_tau_g1: scale_g1(beta, size),
+            beta_g2: (g2 * beta).to_affine(),
+        })
+    }
+
+    /// Number of constraints these powers can support
+    pub fn size(&self) -> usize {
+        self.tau_g2.len()
+    }
+
+    /// Check that every vector really holds consecutive powers of one `tau`
+    /// and that the `alpha`/`beta` vectors are consistent with it.
+    pub fn verify(&self) -> Result<(), CeremonyError> {
+        let size = self.size();
+        if tau_g1_len(size) != Some(self.tau_g1.len())
+            || self.alpha_tau_g1.len() != size
+            || self.beta_tau_g1.len() != size
+        {
+            return Err(CeremonyError::InvalidPowersOfTau("vector lengths are inconsistent"));
+        }
+
+        let g1 = G1Affine::generator();
+        let g2 = G2Affine::generator();
+        if self.tau_g1[0] != g1 || self.tau_g2[0] != g2 {
+            return Err(CeremonyError::InvalidPowersOfTau("powers must start at the generators"));
+        }
+        if bool::from(self.tau_g1[1].is_identity()) || bool::from(self.beta_g2.is_identity()) {
+            return Err(CeremonyError::InvalidPowersOfTau("degenerate tau or beta"));
+        }
+
+        let tau_ratio = (self.tau_g2[0], self.tau_g2[1]);
+        if !same_ratio(power_pairs_g1(&self.tau_g1), tau_ratio) {
+            return Err(CeremonyError::InvalidPowersOfTau("tau powers in G1 are not consecutive"));
+        }
+        if !same_ratio((self.tau_g1[0], self.tau_g1[1]), power_pairs_g2(&self.tau_g2)) {
+            return Err(CeremonyError::InvalidPowersOfTau("tau powers in G2 are not consecutive"));
+        }
+        if !same_ratio(power_pairs_g1(&self.alpha_tau_g1), tau_ratio) {
+            return Err(CeremonyError::InvalidPowersOfTau("alpha powers are not consecutive"));
+        }
+        if !same_ratio(power_pairs_g1(&self.beta_tau_g1), tau_ratio) {
+            return Err(CeremonyError::InvalidPowersOfTau("beta powers are not consecutive"));
+        }
+        if !same_ratio((g1, self.beta_tau_g1[0]), (g2, self.beta_g2)) {
+            return Err(CeremonyError::InvalidPowersOfTau("beta in G1 and G2 differ"));
+        }
+
+        Ok(())
+    }
+
+    /// Read powers of tau: a big-endian `u32` size followed by compressed points
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let size = usize::try_from(read_u32(&mut reader)?).unwrap_or(usize::MAX);
+        let g1_len = tau_g1_len(size)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unsupported powers of tau size"))?;
+
+        let tau_g1 = (0..g1_len).map(|_| read_g1(&mut reader)).collect::<io::Result<_>>()?;
+        let tau_g2 = (0..size).map(|_| read_g2(&mut reader)).collect::<io::Result<_>>()?;
+        let alpha_tau_g1 = (0..size).map(|_| read_g1(&mut reader)).collect::<io::Result<_>>()?;
+        let beta_tau_g1 = (0..size).map(|_| read_g1(&mut reader)).collect::<io::Result<_>>()?;
+        let beta_g2 = read_g2(&mut reader)?;
+
+        Ok(Self {
+            tau_g1,
+            tau_g2,
+            alpha_tau_g1,
+            beta_tau_g1,
+            beta_g2,
+        })
+    }
+
+    /// Import the first `size` powers from a Zcash Powers of Tau accumulator.
+    ///
+    /// The file is a 64-byte hash followed by `2N - 1` tau powers in G1, then
+    /// `N` tau powers in G2, `N` alpha and `N` beta powers in G1 and `beta·G2`,
+    /// where `N = ceremony_powers` (`ZCASH_TAU_POWERS_LENGTH` for the real
+    /// ceremony). Points beyond `size` are skipped without being decoded. The
+    /// result still has to be checked with `verify`.
+    pub fn read_zcash<R: Read>(
+        mut reader: R,
+        kind: ZcashAccumulator,
+        ceremony_powers: usize,
+        size: usize,
+    ) -> Result<Self, CeremonyError> {
+        let ceremony_g1_len = tau_g1_len(ceremony_powers).ok_or(CeremonyError::InvalidPowersOfTau("unsupported size"))?;
+        let g1_len = tau_g1_len(size).ok_or(CeremonyError::InvalidPowersOfTau("unsupported size"))?;
+        if size > ceremony_powers {
+            return Err(CeremonyError::InvalidPowersOfTau("requested more powers than the accumulator holds"));
+        }
+
+        let (g1_bytes, g2_bytes) = match kind {
+            ZcashAccumulator::Challenge => (96, 192),
+            ZcashAccumulator::Response => (48, 96),
+        };
+
+        skip(&mut reader, 64)?;
+        let tau_g1 = read_zcash_points(&mut reader, kind, (g1_len, ceremony_g1_len), g1_bytes, read_zcash_g1)?;
+        let tau_g2 = read_zcash_points(&mut reader, kind, (size, ceremony_powers), g2_bytes, read_zcash_g2)?;
+        let alpha_tau_g1 = read_zcash_points(&mut reader, kind, (size, ceremony_powers), g1_bytes, read_zcash_g1)?;
+        let beta_tau_g1 = read_zcash_points(&mut reader, kind, (size, ceremony_powers), g1_bytes, read_zcash_g1)?;
+        let beta_g2 = read_zcash_g2(&mut reader, kind)?;
+
+        Ok(Self {
+            tau_g1,
+            tau_g2,
+            alpha_tau_g1,
+            beta_tau_g1,
+            beta_g2,
+        })
+    }
+
+    /// Write powers of tau in the format accepted by `read`
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&(self.size() as u32).to_be_bytes())?;
+        for point in self.tau_g1.iter() {
+            writer.write_all(&point.to_compressed())?;
+        }
+        for point in self.tau_g2.iter() {
+            writer.write_all(&point.to_compressed())?;
+        }
+        for point in self.alpha_tau_g1.iter().chain(self.beta_tau_g1.iter()) {
+            writer.write_all(&point.to_compressed())?;
+        }
+        writer.write_all(&self.beta_g2.to_compressed())
+    }
+}
+
+/// Public record of one participant's contribution to the `delta` randomisation
+#[derive(Clone, PartialEq, Debug)]
+pub struct ContributionKey {
+    /// `delta·G1` after this contribution
+    pub delta_after: G1Affine,
+    /// Random `s` and `s·d` proving knowledge of this participant's factor `d`
+    pub s: G1Affine,
+    pub s_delta: G1Affine,
+    /// `r·d`, where `r` is derived from the transcript and `s`
+    pub r_delta: G2Affine,
+    /// Hash of the ceremony state this contribution was made on top of
+    pub transcript: [u8; 64],
+}
+
+impl ContributionKey {
+    fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.delta_after.to_compressed())?;
+        writer.write_all(&self.s.to_compressed())?;
+        writer.write_all(&self.s_delta.to_compressed())?;
+        writer.write_all(&self.r_delta.to_compressed())?;
+        writer.write_all(&self.transcript)
+    }
+
+    fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let delta_after = read_g1(&mut reader)?;
+        let s = read_g1(&mut reader)?;
+        let s_delta = read_g1(&mut reader)?;
+        let r_delta = read_g2(&mut reader)?;
+        let mut transcript = [0u8; 64];
+        reader.read_exact(&mut transcript)?;
+
+        Ok(Self {
+            delta_after,
+            s,
+            s_delta,
+            r_delta,
+            transcript,
+        })
+    }
+
+    /// Hash that a participant publishes to attest to their contribution
+    pub fn hash(&self) -> [u8; 64] {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes).expect("writing to a Vec cannot fail");
+        blake2b(&[&bytes])
+    }
+}
+
+/// Circuit-specific Groth16 parameters together with the ceremony transcript
+#[derive(Clone)]
+pub struct MPCParameters {
+    params: Parameters<Bls12>,
+    cs_hash: [u8; 64],
+    contributions: Vec<ContributionKey>,
+}
+
+impl MPCParameters {
+    /// Specialise phase-1 powers of tau to `circuit`, with `delta = 1`.
+    ///
+    /// The circuit only needs its shape; witness values may be `None`.
+    pub fn new<C: Circuit<Scalar>>(circuit: C, powers: &PowersOfTau) -> Result<Self, CeremonyError> {
+        let assembly = KeypairAssembly::synthesize(circuit)?;
+        let params = assembly.into_parameters(powers)?;
+
+        let mut bytes = Vec::new();
+        params.write(&mut bytes)?;
+        let cs_hash = blake2b(&[&bytes]);
+
+        Ok(Self {
+            params,
+            cs_hash,
+            contributions: vec![],
+        })
+    }
+
+    /// Parameters to hand to the prover once the ceremony is closed
+    pub fn params(&self) -> &Parameters<Bls12> {
+        &self.params
+    }
+
+    /// Contributions made so far, oldest first
+    pub fn contributions(&self) -> &[ContributionKey] {
+        &self.contributions
+    }
+
+    /// Randomise `delta` with a fresh secret and return the contribution hash.
+    ///
+    /// The secret never leaves this function and is dropped when it returns.
+    pub fn contribute<R: RngCore>(&mut self, rng: &mut R) -> [u8; 64] {
+        let delta = loop {
+            let candidate = Scalar::random(&mut *rng);
+            if !bool::from(candidate.is_zero()) {
+                break candidate;
+            }
+        };
+        let delta_inv = delta.invert().unwrap();
+
+        let transcript = self.transcript();
+        let s = G1Projective::random(&mut *rng).to_affine();
+        let s_delta = (s * delta).to_affine();
+        let r = transcript_point(&transcript, &s, &s_delta);
+        let r_delta = (r * delta).to_affine();
+
+        self.params.vk.delta_g1 = (self.params.vk.delta_g1 * delta).to_affine();
+        self.params.vk.delta_g2 = (self.params.vk.delta_g2 * delta).to_affine();
+        self.params.h = Arc::new(scale_all(&self.params.h, delta_inv));
+        self.params.l = Arc::new(scale_all(&self.params.l, delta_inv));
+
+        let key = ContributionKey {
+            delta_after: self.params.vk.delta_g1,
+            s,
+            s_delta,
+            r_delta,
+            transcript,
+        };
+        let hash = key.hash();
+        self.contributions.push(key);
+        hash
+    }
+
+    /// Verify the whole transcript against the circuit and the phase-1 powers.
+    ///
+    /// Returns the hash of every contribution, in order, so participants can
+    /// check their own contribution was included.
+    /// A transcript without contributions is rejected: its `delta` is the
+    /// generator, so anyone could forge proofs.
+    pub fn verify<C: Circuit<Scalar>>(&self, circuit: C, powers: &PowersOfTau) -> Result<Vec<[u8; 64]>, CeremonyError> {
+        if self.contributions.is_empty() {
+            return Err(CeremonyError::NoContributions);
+        }
+        powers.verify()?;
+        let initial = MPCParameters::new(circuit, powers)?;
+        if initial.cs_hash != self.cs_hash || !same_fixed_parameters(&initial.params, &self.params) {
+            return Err(CeremonyError::CircuitMismatch);
+        }
+
+        let mut hasher = Blake2b512::new();
+        hasher.update(self.cs_hash);
+
+        let mut delta = G1Affine::generator();
+        let mut hashes = Vec::with_capacity(self.contributions.len());
+        for (index, key) in self.contributions.iter().enumerate() {
+            let transcript: [u8; 64] = hasher.clone().finalize().into();
+            verify_key(index, key, &transcript, &delta)?;
+
+            let mut bytes = Vec::new();
+            key.write(&mut bytes)?;
+            hasher.update(&bytes);
+
+            delta = key.delta_after;
+            hashes.push(key.hash());
+        }
+
+        let vk = &self.params.vk;
+        let g1 = G1Affine::generator();
+        let g2 = G2Affine::generator();
+        if vk.delta_g1 != delta || !same_ratio((g1, vk.delta_g1), (g2, vk.delta_g2)) {
+            return Err(CeremonyError::InvalidContribution {
+                index: self.contributions.len(),
+                reason: "final delta does not match the transcript",
+            });
+        }
+
+        // H and L must have been divided by exactly the accumulated delta
+        if !same_ratio(merge_pairs(&initial.params.h, &self.params.h), (vk.delta_g2, g2))
+            || !same_ratio(merge_pairs(&initial.params.l, &self.params.l), (vk.delta_g2, g2))
+        {
+            return Err(CeremonyError::InvalidContribution {
+                index: self.contributions.len(),
+                reason: "H or L query is inconsistent with delta",
+            });
+        }
+
+        Ok(hashes)
+    }
+
+    /// Check that `after` is `self` plus exactly one valid contribution.
+    ///
+    /// This lets a coordinator vet each participant without re-running phase 1.
+    pub fn verify_contribution(&self, after: &MPCParameters) -> Result<[u8; 64], CeremonyError> {
+        let index = self.contributions.len();
+        let invalid = |reason| CeremonyError::InvalidContribution { index, reason };
+
+        if after.cs_hash != self.cs_hash
+            || after.contributions.len() != index + 1
+            || after.contributions[..index] != self.contributions[..]
+            || !same_fixed_parameters(&self.params, &after.params)
+        {
+            return Err(invalid("parameters do not extend the previous state"));
+        }
+
+        let key = &after.contributions[index];
+        verify_key(index, key, &self.transcript(), &self.params.vk.delta_g1)?;
+
+        let vk = &after.params.vk;
+        if vk.delta_g1 != key.delta_after
+            || !same_ratio((G1Affine::generator(), vk.delta_g1), (G2Affine::generator(), vk.delta_g2))
+        {
+            return Err(invalid("delta does not match the contribution"));
+        }
+
+        let r = transcript_point(&key.transcript, &key.s, &key.s_delta);
+        if !same_ratio(merge_pairs(&after.params.h, &self.params.h), (r, key.r_delta))
+            || !same_ratio(merge_pairs(&after.params.l, &self.params.l), (r, key.r_delta))
+        {
+            return Err(invalid("H or L query is inconsistent with delta"));
+        }
+
+        Ok(key.hash())
+    }
+
+    /// Serialize the parameters followed by the transcript
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.params.write(&mut writer)?;
+        writer.write_all(&self.cs_hash)?;
+        writer.write_all(&(self.contributions.len() as u32).to_be_bytes())?;
+        for key in self.contributions.iter() {
+            key.write(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    /// Deserialize parameters written by `write`, checking all curve points
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let params = Parameters::read(&mut reader, true)?;
+        let mut cs_hash = [0u8; 64];
+        reader.read_exact(&mut cs_hash)?;
+
+        let count = read_u32(&mut reader)? as usize;
+        let contributions = (0..count)
+            .map(|_| ContributionKey::read(&mut reader))
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            params,
+            cs_hash,
+            contributions,
+        })
+    }
+
+    // Hash of the circuit and every contribution so far
+    fn transcript(&self) -> [u8; 64] {
+        let mut hasher = Blake2b512::new();
+        hasher.update(self.cs_hash);
+        for key in self.contributions.iter() {
+            let mut bytes = Vec::new();
+            key.write(&mut bytes).expect("writing to a Vec cannot fail");
+            hasher.update(&bytes);
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// Add a contribution to serialized ceremony parameters from the browser.
+/// Returns the new parameters (base64) and the contribution hash (hex) to publish.
+#[wasm_bindgen]
+pub fn contribute_to_ceremony(parameters: &[u8]) -> Result<JsValue, JsValue> {
+    let mut mpc = MPCParameters::read(parameters).map_err(CeremonyError::from)?;
+    let hash = mpc.contribute(&mut OsRng);
+
+    let mut bytes = Vec::new();
+    mpc.write(&mut bytes).map_err(CeremonyError::from)?;
+
+    serde_wasm_bindgen::to_value(&serde_json::json!({
+        "parameters": base64::encode(bytes),
+        "contributionHash": hex::encode(hash),
+    })).map_err(|e| JsValue::from_str(&get_error_message("Serialization error", &e)))
+}
+
+// Check a single contribution's proof of knowledge and its link to the previous delta
+fn verify_key(index: usize, key: &ContributionKey, transcript: &[u8; 64], previous_delta: &G1Affine) -> Result<(), CeremonyError> {
+    let invalid = |reason| CeremonyError::InvalidContribution { index, reason };
+
+    if &key.transcript != transcript {
+        return Err(invalid("transcript hash does not match the previous state"));
+    }
+    if bool::from(key.s.is_identity()) || bool::from(key.delta_after.is_identity()) {
+        return Err(invalid("degenerate contribution"));
+    }
+
+    let r = transcript_point(transcript, &key.s, &key.s_delta);
+    if !same_ratio((key.s, key.s_delta), (r, key.r_delta)) {
+        return Err(invalid("proof of knowledge does not verify"));
+    }
+    if !same_ratio((*previous_delta, key.delta_after), (r, key.r_delta)) {
+        return Err(invalid("delta was not updated by the proven factor"));
+    }
+
+    Ok(())
+}
+
+// Everything except delta, H and L is fixed by phase 1 and the circuit
+fn same_fixed_parameters(a: &Parameters<Bls12>, b: &Parameters<Bls12>) -> bool {
+    a.vk.alpha_g1 == b.vk.alpha_g1
+        && a.vk.beta_g1 == b.vk.beta_g1
+        && a.vk.beta_g2 == b.vk.beta_g2
+        && a.vk.gamma_g2 == b.vk.gamma_g2
+        && a.vk.ic == b.vk.ic
+        && a.a == b.a
+        && a.b_g1 == b.b_g1
+        && a.b_g2 == b.b_g2
+        && a.h.len() == b.h.len()
+        && a.l.len() == b.l.len()
+}
+
+// Deterministic G2 point bound to the transcript and the participant's `s` pair
+fn transcript_point(transcript: &[u8; 64], s: &G1Affine, s_delta: &G1Affine) -> G2Affine {
+    let digest = blake2b(&[transcript, &s.to_compressed(), &s_delta.to_compressed()]);
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&digest[..32]);
+    G2Projective::random(ChaChaRng::from_seed(seed)).to_affine()
+}
+
+// e(g1.0, g2.1) == e(g1.1, g2.0), i.e. both pairs are related by the same scalar
+fn same_ratio(g1: (G1Affine, G1Affine), g2: (G2Affine, G2Affine)) -> bool {
+    Bls12::pairing(&g1.0, &g2.1) == Bls12::pairing(&g1.1, &g2.0)
+}
+
+// Random linear combination of two equal-length vectors, for batched ratio checks
+fn merge_pairs(v1: &[G1Affine], v2: &[G1Affine]) -> (G1Affine, G1Affine) {
+    let mut s = G1Projective::identity();
+    let mut sx = G1Projective::identity();
+    for (a, b) in v1.iter().zip(v2.iter()) {
+        let rho = Scalar::random(&mut OsRng);
+        s += a * rho;
+        sx += b * rho;
+    }
+    (s.to_affine(), sx.to_affine())
+}
+
+fn merge_pairs_g2(v1: &[G2Affine], v2: &[G2Affine]) -> (G2Affine, G2Affine) {
+    let mut s = G2Projective::identity();
+    let mut sx = G2Projective::identity();
+    for (a, b) in v1.iter().zip(v2.iter()) {
+        let rho = Scalar::random(&mut OsRng);
+        s += a * rho;
+        sx += b * rho;
+    }
+    (s.to_affine(), sx.to_affine())
+}
+
+// Pairs every power with its successor
+fn power_pairs_g1(powers: &[G1Affine]) -> (G1Affine, G1Affine) {
+    merge_pairs(&powers[..powers.len() - 1], &powers[1..])
+}
+
+fn power_pairs_g2(powers: &[G2Affine]) -> (G2Affine, G2Affine) {
+    merge_pairs_g2(&powers[..powers.len() - 1], &powers[1..])
+}
+
+fn scale_all(points: &[G1Affine], factor: Scalar) -> Vec<G1Affine> {
+    let projective: Vec<G1Projective> = points.iter().map(|p| p * factor).collect();
+    let mut affine = vec![G1Affine::identity(); projective.len()];
+    G1Projective::batch_normalize(&projective, &mut affine);
+    affine
+}
+
+fn blake2b(parts: &[&[u8]]) -> [u8; 64] {
+    let mut hasher = Blake2b512::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+pub(crate) fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+// Number of tau powers in G1 (`2n - 1`) for powers of tau of size `n >= 2`
+fn tau_g1_len(size: usize) -> Option<usize> {
+    if size < 2 {
+        return None;
+    }
+    size.checked_mul(2)?.checked_sub(1)
+}
+
+fn skip<R: Read>(reader: &mut R, bytes: u64) -> io::Result<()> {
+    if io::copy(&mut reader.take(bytes), &mut io::sink())? != bytes {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "accumulator file is truncated"));
+    }
+    Ok(())
+}
+
+// Decode the first `count` of `total` consecutive points and skip the rest
+fn read_zcash_points<R: Read, P>(
+    reader: &mut R,
+    kind: ZcashAccumulator,
+    (count, total): (usize, usize),
+    point_bytes: u64,
+    read_point: fn(&mut R, ZcashAccumulator) -> io::Result<P>,
+) -> io::Result<Vec<P>> {
+    let points = (0..count).map(|_| read_point(reader, kind)).collect::<io::Result<_>>()?;
+    skip(reader, (total - count) as u64 * point_bytes)?;
+    Ok(points)
+}
+
+fn read_zcash_g1<R: Read>(reader: &mut R, kind: ZcashAccumulator) -> io::Result<G1Affine> {
+    let point = match kind {
+        ZcashAccumulator::Response => return read_g1(reader),
+        ZcashAccumulator::Challenge => {
+            let mut bytes = [0u8; 96];
+            reader.read_exact(&mut bytes)?;
+            G1Affine::from_uncompressed(&bytes)
+        }
+    };
+    Option::from(point).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid G1 point"))
+}
+
+fn read_zcash_g2<R: Read>(reader: &mut R, kind: ZcashAccumulator) -> io::Result<G2Affine> {
+    let point = match kind {
+        ZcashAccumulator::Response => return read_g2(reader),
+        ZcashAccumulator::Challenge => {
+            let mut bytes = [0u8; 192];
+            reader.read_exact(&mut bytes)?;
+            G2Affine::from_uncompressed(&bytes)
+        }
+    };
+    Option::from(point).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid G2 point"))
+}
+
+pub(crate) fn read_g1<R: Read>(reader: &mut R) -> io::Result<G1Affine> {
+    let mut bytes = [0u8; 48];
+    reader.read_exact(&mut bytes)?;
+    Option::from(G1Affine::from_compressed(&bytes))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid G1 point"))
+}
+
+pub(crate) fn read_g2<R: Read>(reader: &mut R) -> io::Result<G2Affine> {
+    let mut bytes = [0u8; 96];
+    reader.read_exact(&mut bytes)?;
+    Option::from(G2Affine::from_compressed(&bytes))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid G2 point"))
+}
+
+/// Records the QAP of a circuit, mirroring bellman's key generator
+struct KeypairAssembly {
+    num_inputs: usize,
+    num_aux: usize,
+    num_constraints: usize,
+    at_inputs: Vec<Vec<(Scalar, usize)>>,
+    bt_inputs: Vec<Vec<(Scalar, usize)>>,
+    ct_inputs: Vec<Vec<(Scalar, usize)>>,
+    at_aux: Vec<Vec<(Scalar, usize)>>,
+    bt_aux: Vec<Vec<(Scalar, usize)>>,
+    ct_aux: Vec<Vec<(Scalar, usize)>>,
+}
+
+impl KeypairAssembly {
+    fn synthesize<C: Circuit<Scalar>>(circuit: C) -> Result<Self, SynthesisError> {
+        let mut assembly = KeypairAssembly {
+            num_inputs: 0,
+            num_aux: 0,
+            num_constraints: 0,
+            at_inputs: vec![],
+            bt_inputs: vec![],
+            ct_inputs: vec![],
+            at_aux: vec![],
+            bt_aux: vec![],
+            ct_aux: vec![],
+        };
+
+        // Allocate the "one" input variable
+        assembly.alloc_input(|| "", || Ok(Scalar::one()))?;
+
+        circuit.synthesize(&mut assembly)?;
+
+        // Input constraints to ensure full density of IC query, exactly as the prover adds them
+        for i in 0..assembly.num_inputs {
+            assembly.enforce(|| "", |lc| lc + Variable::new_unchecked(Index::Input(i)), |lc| lc, |lc| lc);
+        }
+
+        Ok(assembly)
+    }
+
+    fn into_parameters(self, powers: &PowersOfTau) -> Result<Parameters<Bls12>, CeremonyError> {
+        let worker = Worker::new();
+
+        // Same radix-2 domain size the prover will use
+        let domain_size = self.num_constraints.next_power_of_two();
+        if domain_size > powers.size() {
+            return Err(CeremonyError::TooFewPowers {
+                needed: domain_size,
+                available: powers.size(),
+            });
+        }
+
+        // Evaluations of the Lagrange basis at tau, in the exponent
+        let tau_lagrange_g1 = lagrange_g1(&powers.tau_g1[..domain_size], &worker)?;
+        let tau_lagrange_g2 = lagrange_g2(&powers.tau_g2[..domain_size], &worker)?;
+        let alpha_lagrange = lagrange_g1(&powers.alpha_tau_g1[..domain_size], &worker)?;
+        let beta_lagrange = lagrange_g1(&powers.beta_tau_g1[..domain_size], &worker)?;
+
+        // H query: tau^i · t(tau) = tau^(i + m) - tau^i with delta = 1
+        let h: Vec<G1Projective> = (0..domain_size - 1)
+            .map(|i| powers.tau_g1[i + domain_size].to_curve() - powers.tau_g1[i])
+            .collect();
+
+        let eval = |at: &[Vec<(Scalar, usize)>], bt: &[Vec<(Scalar, usize)>], ct: &[Vec<(Scalar, usize)>]| {
+            let mut a = Vec::with_capacity(at.len());
+            let mut b_g1 = Vec::with_capacity(at.len());
+            let mut b_g2 = Vec::with_capacity(at.len());
+            let mut ext = Vec::with_capacity(at.len());
+
+            for ((at, bt), ct) in at.iter().zip(bt.iter()).zip(ct.iter()) {
+                let mut a_acc = G1Projective::identity();
+                let mut b_g1_acc = G1Projective::identity();
+                let mut b_g2_acc = G2Projective::identity();
+                let mut ext_acc = G1Projective::identity();
+
+                for &(coeff, index) in at {
+                    a_acc += tau_lagrange_g1[index] * coeff;
+                    ext_acc += beta_lagrange[index] * coeff;
+                }
+                for &(coeff, index) in bt {
+                    b_g1_acc += tau_lagrange_g1[index] * coeff;
+                    b_g2_acc += tau_lagrange_g2[index] * coeff;
+                    ext_acc += alpha_lagrange[index] * coeff;
+                }
+                for &(coeff, index) in ct {
+                    ext_acc += tau_lagrange_g1[index] * coeff;
+                }
+
+                a.push(a_acc);
+                b_g1.push(b_g1_acc);
+                b_g2.push(b_g2_acc);
+                ext.push(ext_acc);
+            }
+
+            (a, b_g1, b_g2, ext)
+        };
+
+        let (mut a, mut b_g1, mut b_g2, ic) = eval(&self.at_inputs, &self.bt_inputs, &self.ct_inputs);
+        let (a_aux, b_g1_aux, b_g2_aux, l) = eval(&self.at_aux, &self.bt_aux, &self.ct_aux);
+        a.extend(a_aux);
+        b_g1.extend(b_g1_aux);
+        b_g2.extend(b_g2_aux);
+
+        let l = normalize_g1(&l);
+        // Don't allow any elements be unconstrained, so that the L query is always fully dense
+        if l.iter().any(|e| bool::from(e.is_identity())) {
+            return Err(SynthesisError::UnconstrainedVariable.into());
+        }
+
+        let vk = VerifyingKey::<Bls12> {
+            alpha_g1: powers.alpha_tau_g1[0],
+            beta_g1: powers.beta_tau_g1[0],
+            beta_g2: powers.beta_g2,
+            gamma_g2: G2Affine::generator(),
+            delta_g1: G1Affine::generator(),
+            delta_g2: G2Affine::generator(),
+            ic: normalize_g1(&ic),
+        };
+
+        // Points at infinity are filtered from the A/B queries, as the prover expects
+        Ok(Parameters {
+            vk,
+            h: Arc::new(normalize_g1(&h)),
+            l: Arc::new(l),
+            a: Arc::new(normalize_g1(&a).into_iter().filter(|e| !bool::from(e.is_identity())).collect()),
+            b_g1: Arc::new(normalize_g1(&b_g1).into_iter().filter(|e| !bool::from(e.is_identity())).collect()),
+            b_g2: Arc::new(normalize_g2(&b_g2).into_iter().filter(|e| !bool::from(e.is_identity())).collect()),
+        })
+    }
+}
+
+impl ConstraintSystem<Scalar> for KeypairAssembly {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // There is no assignment, so we don't even invoke the
+        // function for obtaining one.
+        let index = self.num_aux;
+        self.num_aux += 1;
+
+        self.at_aux.push(vec![]);
+        self.bt_aux.push(vec![]);
+        self.ct_aux.push(vec![]);
+
+        Ok(Variable::new_unchecked(Index::Aux(index)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.num_inputs;
+        self.num_inputs += 1;
+
+        self.at_inputs.push(vec![]);
+        self.bt_inputs.push(vec![]);
+        self.ct_inputs.push(vec![]);
+
+        Ok(Variable::new_unchecked(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        fn eval(
+            l: LinearCombination<Scalar>,
+            inputs: &mut [Vec<(Scalar, usize)>],
+            aux: &mut [Vec<(Scalar, usize)>],
+            this_constraint: usize,
+        ) {
+            for (var, coeff) in l.as_ref() {
+                match var.get_unchecked() {
+                    Index::Input(id) => inputs[id].push((*coeff, this_constraint)),
+                    Index::Aux(id) => aux[id].push((*coeff, this_constraint)),
+                }
+            }
+        }
+
+        eval(a(LinearCombination::zero()), &mut self.at_inputs, &mut self.at_aux, self.num_constraints);
+        eval(b(LinearCombination::zero()), &mut self.bt_inputs, &mut self.bt_aux, self.num_constraints);
+        eval(c(LinearCombination::zero()), &mut self.ct_inputs, &mut self.ct_aux, self.num_constraints);
+
+        self.num_constraints += 1;
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Namespaces carry no meaning for key generation
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+// Curve point wrapper so bellman's FFT can run "in the exponent"
+#[derive(Clone, Copy, PartialEq)]
+struct Exponent<G>(G);
+
+impl<G> domain::Group<Scalar> for Exponent<G>
+where
+    G: Group<Scalar = Scalar> + Copy + Send + Sync,
+{
+    fn group_zero() -> Self {
+        Exponent(G::identity())
+    }
+    fn group_mul_assign(&mut self, by: &Scalar) {
+        self.0 *= by;
+    }
+    fn group_add_assign(&mut self, other: &Self) {
+        self.0 += other.0;
+    }
+    fn group_sub_assign(&mut self, other: &Self) {
+        self.0 -= other.0;
+    }
+}
+
+// Inverse FFT in the exponent turns `tau^i·G` into `L_i(tau)·G`
+fn lagrange_g1(powers: &[G1Affine], worker: &Worker) -> Result<Vec<G1Affine>, SynthesisError> {
+    let mut domain = EvaluationDomain::from_coeffs(powers.iter().map(|p| Exponent(p.to_curve())).collect())?;
+    domain.ifft(worker);
+    let projective: Vec<G1Projective> = domain.into_coeffs().into_iter().map(|p| p.0).collect();
+    Ok(normalize_g1(&projective))
+}
+
+fn lagrange_g2(powers: &[G2Affine], worker: &Worker) -> Result<Vec<G2Affine>, SynthesisError> {
+    let mut domain = EvaluationDomain::from_coeffs(powers.iter().map(|p| Exponent(p.to_curve())).collect())?;
+    domain.ifft(worker);
+    let projective: Vec<G2Projective> = domain.into_coeffs().into_iter().map(|p| p.0).collect();
+    Ok(normalize_g2(&projective))
+}
+
+fn normalize_g1(points: &[G1Projective]) -> Vec<G1Affine> {
+    let mut affine = vec![G1Affine::identity(); points.len()];
+    G1Projective::batch_normalize(points, &mut affine);
+    affine
+}
+
+fn normalize_g2(points: &[G2Projective]) -> Vec<G2Affine> {
+    let mut affine = vec![G2Affine::identity(); points.len()];
+    G2Projective::batch_normalize(points, &mut affine);
+    affine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::proof_system::{Groth16, ProofSystem};
+    use crate::crypto::zk_proofs::{create_square_proof, verify_square_proof, SquareDemo};
+
+    fn square_shape() -> SquareDemo {
+        SquareDemo { x: None, y: None }
+    }
+
+    #[test]
+    fn multi_party_parameters_produce_verifiable_proofs() {
+        let powers = PowersOfTau::generate_insecure(4, &mut OsRng).unwrap();
+        powers.verify().unwrap();
+
+        let mut mpc = MPCParameters::new(square_shape(), &powers).unwrap();
+        let before = mpc.clone();
+        let first = mpc.contribute(&mut OsRng);
+        assert_eq!(before.verify_contribution(&mpc).unwrap(), first);
+        let second = mpc.contribute(&mut OsRng);
+
+        let mut bytes = Vec::new();
+        mpc.write(&mut bytes).unwrap();
+        let mpc = MPCParameters::read(&bytes[..]).unwrap();
+
+        assert_eq!(mpc.verify(square_shape(), &powers).unwrap(), vec![first, second]);
+
+        let (proof, inputs) = create_square_proof(&Groth16, mpc.params(), 6, 36).unwrap();
+        let pvk = Groth16.prepare_verifying_key(&mpc.params().vk).unwrap();
+        assert!(verify_square_proof(&Groth16, &pvk, &proof, &inputs).unwrap());
+    }
+
+    #[test]
+    fn tampered_delta_is_rejected() {
+        let powers = PowersOfTau::generate_insecure(4, &mut OsRng).unwrap();
+        let mut mpc = MPCParameters::new(square_shape(), &powers).unwrap();
+        mpc.contribute(&mut OsRng);
+
+        // Re-randomising delta without a contribution key must not verify
+        let mut forged = mpc.clone();
+        forged.params.vk.delta_g1 = (forged.params.vk.delta_g1 * Scalar::from(2u64)).to_affine();
+        forged.params.vk.delta_g2 = (forged.params.vk.delta_g2 * Scalar::from(2u64)).to_affine();
+        assert!(forged.verify(square_shape(), &powers).is_err());
+    }
+
+    #[test]
+    fn powers_of_tau_round_trip_and_verify() {
+        let powers = PowersOfTau::generate_insecure(4, &mut OsRng).unwrap();
+        let mut bytes = Vec::new();
+        powers.write(&mut bytes).unwrap();
+        let restored = PowersOfTau::read(&bytes[..]).unwrap();
+        assert_eq!(restored, powers);
+
+        let mut broken = restored;
+        broken.tau_g1.swap(2, 3);
+        assert!(broken.verify().is_err());
+
+        // Sizes whose lengths underflow or overflow are rejected rather than panicking
+        assert!(PowersOfTau::generate_insecure(0, &mut OsRng).is_err());
+        assert!(PowersOfTau::read(&u32::MAX.to_be_bytes()[..]).is_err());
+    }
+
+    #[test]
+    fn empty_transcript_is_rejected() {
+        let powers = PowersOfTau::generate_insecure(4, &mut OsRng).unwrap();
+        let mpc = MPCParameters::new(square_shape(), &powers).unwrap();
+        assert!(matches!(mpc.verify(square_shape(), &powers), Err(CeremonyError::NoContributions)));
+    }
+
+    // Lay out powers the way the Zcash ceremony writes its accumulators
+    fn zcash_accumulator(powers: &PowersOfTau, kind: ZcashAccumulator) -> Vec<u8> {
+        let g1 = |p: &G1Affine| match kind {
+            ZcashAccumulator::Challenge => p.to_uncompressed().to_vec(),
+            ZcashAccumulator::Response => p.to_compressed().to_vec(),
+        };
+        let g2 = |p: &G2Affine| match kind {
+            ZcashAccumulator::Challenge => p.to_uncompressed().to_vec(),
+            ZcashAccumulator::Response => p.to_compressed().to_vec(),
+        };
+
+        let mut bytes = vec![0u8; 64];
+        bytes.extend(powers.tau_g1.iter().flat_map(g1));
+        bytes.extend(powers.tau_g2.iter().flat_map(g2));
+        bytes.extend(powers.alpha_tau_g1.iter().chain(&powers.beta_tau_g1).flat_map(g1));
+        bytes.extend(g2(&powers.beta_g2));
+        bytes
+    }
+
+    #[test]
+    fn zcash_accumulators_import_a_prefix_of_the_powers() {
+        let ceremony = PowersOfTau::generate_insecure(8, &mut OsRng).unwrap();
+        let expected = PowersOfTau {
+            tau_g1: ceremony.tau_g1[..7].to_vec(),
+            tau_g2: ceremony.tau_g2[..4].to_vec(),
+            alpha_tau_g1: ceremony.alpha_tau_g1[..4].to_vec(),
+            beta_tau_g1: ceremony.beta_tau_g1[..4].to_vec(),
+            beta_g2: ceremony.beta_g2,
+        };
+
+        for kind in [ZcashAccumulator::Challenge, ZcashAccumulator::Response] {
+            let bytes = zcash_accumulator(&ceremony, kind);
+            let imported = PowersOfTau::read_zcash(&bytes[..], kind, 8, 4).unwrap();
+            assert_eq!(imported, expected);
+            imported.verify().unwrap();
+
+            assert!(PowersOfTau::read_zcash(&bytes[..], kind, 8, 16).is_err());
+            assert!(PowersOfTau::read_zcash(&bytes[..bytes.len() - 1], kind, 8, 4).is_err());
+        }
+    }
+}
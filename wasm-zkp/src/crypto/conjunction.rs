@@ -0,0 +1,364 @@
+//! Conjunctions of predicates over several credentials held by one person.
+//!
+//! `ConjunctionCircuit` proves facts drawn from several credentials at once, such
+//! as KYC from one issuer and membership from another. For each credential it
+//! checks the issuer signature and a set of predicate slots exactly as
+//! `PredicateCircuit` does, and it constrains every credential to commit to the
+//! same holder secret. The secret, the credential ids and all attributes stay
+//! private; the only holder-specific output is one nullifier for the verifier's
+//! context, so presentations remain unlinkable across verifiers.
+
+use crate::crypto::credential::CredentialValues;
+use crate::crypto::envelope::ProofEnvelope;
+use crate::crypto::mimc::MiMC;
+use crate::crypto::nullifier::{context_id, nullifier, nullifier_gadget};
+use crate::crypto::predicate::{
+    enforce_credential_predicates, predicate_slots, statement_inputs, CredentialPredicates, CredentialRequest,
+    PredicatePresentation, PredicateSlot, MAX_PREDICATES,
+};
+use crate::crypto::proof_system::ProofSystem;
+use crate::crypto::zk_proofs::{check_satisfied, encode_scalar, scalar_from_u64, ProofType};
+use crate::error::ZkError;
+use bellman::{gadgets::num::AllocatedNum, Circuit, ConstraintSystem, SynthesisError};
+use bls12_381::Scalar;
+use serde::{Deserialize, Serialize};
+
+/// Most credentials one conjunction proof covers; each number of credentials from
+/// two up has its own circuit and keys (see `CircuitId::conjunction`)
+pub const MAX_CONJUNCTION_CREDENTIALS: usize = 4;
+
+/// One credential of a `ConjunctionCircuit`
+#[derive(Clone)]
+pub struct ConjunctCredential {
+    // Credential and holder secret (private)
+    pub credential: CredentialValues,
+    // Issuer public key (public)
+    pub issuer_public_key: Option<(Scalar, Scalar)>,
+    // Root of the issuer's revocation tree (public)
+    pub revocation_root: Option<Scalar>,
+    // MAX_PREDICATES slots
+    pub predicates: Vec<PredicateSlot>,
+}
+
+/// Proves that several credentials, each signed under its public issuer key,
+/// satisfy their predicates and belong to one holder (see the module documentation).
+///
+/// Public inputs: for each credential in order, the issuer key, revocation root
+/// and predicate slots as in `PredicateCircuit`, then the current time every credential must
+/// be unexpired at, the context id and the nullifier.
+#[derive(Clone)]
+pub struct ConjunctionCircuit {
+    pub credentials: Vec<ConjunctCredential>,
+    // Verifier's current time in Unix seconds (public)
+    pub current_time: Option<Scalar>,
+    // Verifier/context id (public)
+    pub context: Option<Scalar>,
+}
+
+impl ConjunctionCircuit {
+    /// Circuit shape over `credentials` credentials without a witness, for key generation
+    pub fn blank(credentials: usize) -> Self {
+        let credential = ConjunctCredential {
+            credential: CredentialValues::blank(),
+            issuer_public_key: None,
+            revocation_root: None,
+            predicates: vec![PredicateSlot::blank(); MAX_PREDICATES],
+        };
+        Self { credentials: vec![credential; credentials], current_time: None, context: None }
+    }
+}
+
+impl Circuit<Scalar> for ConjunctionCircuit {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mimc = MiMC::new();
+
+        let current_time = AllocatedNum::alloc(cs.namespace(|| "current_time"), || {
+            self.current_time.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let mut holder_secret: Option<AllocatedNum<Scalar>> = None;
+        for (i, conjunct) in self.credentials.iter().enumerate() {
+            let credential = enforce_credential_predicates(
+                cs.namespace(|| format!("credential {}", i)),
+                &mimc,
+                &conjunct.credential,
+                conjunct.issuer_public_key,
+                conjunct.revocation_root,
+                &conjunct.predicates,
+                &current_time,
+            )?;
+
+            // Every credential commits to the first one's holder secret
+            match &holder_secret {
+                Some(first) => cs.enforce(
+                    || format!("credential {} has the same holder", i),
+                    |lc| lc + credential.holder_secret.get_variable() - first.get_variable(),
+                    |lc| lc + CS::one(),
+                    |lc| lc,
+                ),
+                None => holder_secret = Some(credential.holder_secret),
+            }
+        }
+        let holder_secret = holder_secret.ok_or(SynthesisError::Unsatisfiable)?;
+        current_time.inputize(cs.namespace(|| "current_time input"))?;
+
+        // Nullifier (public) = H(context, holder secret)
+        let context = AllocatedNum::alloc(cs.namespace(|| "context"), || {
+            self.context.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        context.inputize(cs.namespace(|| "context input"))?;
+        nullifier_gadget(cs.namespace(|| "nullifier"), &mimc, &holder_secret, &context)?
+            .inputize(cs.namespace(|| "nullifier input"))?;
+
+        Ok(())
+    }
+}
+
+/// Public inputs of a conjunction proof for `requests`, presented in `context` at
+/// `current_time` with `nullifier`
+pub fn conjunction_public_inputs(
+    requests: &[CredentialRequest],
+    context: &str,
+    current_time: u64,
+    nullifier: &Scalar,
+) -> Result<Vec<Scalar>, ZkError> {
+    let mut inputs = Vec::new();
+    for request in requests {
+        inputs.extend(statement_inputs(request)?);
+    }
+    inputs.extend([scalar_from_u64(current_time), context_id(context), *nullifier]);
+    Ok(inputs)
+}
+
+/// Compile a conjunction over `credentials`, all held by the holder of
+/// `holder_secret_str` and presented in `context` at `current_time`, into a
+/// `ConjunctionCircuit` instance and its public inputs. Fails unless every
+/// credential commits to that holder, is valid then, is unrevoked in its issuer's
+/// registry and satisfies its predicates.
+pub fn compile_conjunction(
+    credentials: &[CredentialPredicates],
+    holder_secret_str: &str,
+    context: &str,
+    current_time: u64,
+) -> Result<(ConjunctionCircuit, Vec<Scalar>), ZkError> {
+    if !(2..=MAX_CONJUNCTION_CREDENTIALS).contains(&credentials.len()) {
+        return Err(ZkError::InvalidInput(format!(
+            "a conjunction covers 2 to {} credentials, got {}",
+            MAX_CONJUNCTION_CREDENTIALS,
+            credentials.len()
+        )));
+    }
+
+    let mut conjuncts = Vec::with_capacity(credentials.len());
+    let mut requests = Vec::with_capacity(credentials.len());
+    for entry in credentials {
+        // Checks the holder commitment, so every credential belongs to this holder
+        let registry = &entry.revocation_registry;
+        let (values, issuer_key) = CredentialValues::new(&entry.credential, registry, holder_secret_str, current_time)?;
+        let revocation_root = registry.root()?;
+        conjuncts.push(ConjunctCredential {
+            credential: values,
+            issuer_public_key: Some(issuer_key.coordinates()),
+            revocation_root: Some(revocation_root),
+            predicates: predicate_slots(&entry.credential, &entry.predicates)?,
+        });
+        requests.push(CredentialRequest::new(&issuer_key, &revocation_root, &entry.predicates));
+    }
+
+    let holder_secret = conjuncts[0].credential.holder_secret.expect("values carry the holder secret");
+    let inputs = conjunction_public_inputs(&requests, context, current_time, &nullifier(&holder_secret, &context_id(context)))?;
+    let circuit = ConjunctionCircuit {
+        credentials: conjuncts,
+        current_time: Some(scalar_from_u64(current_time)),
+        context: Some(context_id(context)),
+    };
+    Ok((circuit, inputs))
+}
+
+/// Witness file for a conjunction proof, as consumed by native tooling
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConjunctionWitness {
+    pub credentials: Vec<CredentialPredicates>,
+    pub holder_secret: String,
+    /// Verifier/context id the nullifier is scoped to
+    pub context: String,
+    /// Verifier's current time in Unix seconds, which every credential must not have expired by
+    pub current_time: u64,
+}
+
+impl ConjunctionWitness {
+    /// Build the circuit instance and its public inputs
+    pub fn build_circuit(&self) -> Result<(ConjunctionCircuit, Vec<Scalar>), ZkError> {
+        compile_conjunction(&self.credentials, &self.holder_secret, &self.context, self.current_time)
+    }
+}
+
+/// Prove that `credentials` all belong to the holder of `holder_secret_str` and satisfy
+/// their predicates, to the verifier identified by `context` at its `current_time`
+pub fn create_conjunction_proof<P: ProofSystem>(
+    system: &P,
+    pk: &P::ProvingKey,
+    credentials: &[CredentialPredicates],
+    holder_secret_str: &str,
+    context: &str,
+    current_time: u64,
+) -> Result<PredicatePresentation, ZkError> {
+    let (circuit, inputs) = compile_conjunction(credentials, holder_secret_str, context, current_time)?;
+    check_satisfied(circuit.clone())?;
+    let proof = system.prove(pk, circuit)?;
+
+    Ok(PredicatePresentation {
+        proof: ProofEnvelope::seal(system, pk, ProofType::Conjunction, &proof, &inputs, Some(context))?,
+        nullifier: encode_scalar(&inputs[inputs.len() - 1]),
+    })
+}
+
+/// Verify that a presentation made to `context` proves `requests`, in order, about
+/// credentials of one holder, all unrevoked under their request's revocation root and
+/// unexpired at `current_time`
+pub fn verify_conjunction_proof<P: ProofSystem>(
+    system: &P,
+    pvk: &P::PreparedVerifyingKey,
+    presentation: &PredicatePresentation,
+    requests: &[CredentialRequest],
+    context: &str,
+    current_time: u64,
+) -> Result<bool, ZkError> {
+    let proof = presentation.proof.decode_proof(system)?;
+    let inputs = conjunction_public_inputs(requests, context, current_time, &presentation.nullifier()?)?;
+    system.verify(pvk, &proof, &inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::credential::{compute_holder_commitment, sign_credential, Credential, CredentialAttribute, CredentialType};
+    use crate::crypto::revocation::RevocationRegistry;
+    use crate::crypto::eddsa::SigningKey;
+    use bellman::gadgets::test::TestConstraintSystem;
+    use serde_json::json;
+
+    // 2024-07-03T09:46:40Z
+    const NOW: u64 = 1_720_000_000;
+
+    fn credential(issuer: u8, type_: CredentialType, attributes: &[(&str, &str)], holder_secret: &str) -> Credential {
+        let unsigned = Credential {
+            id: format!("urn:uuid:credential-{}", issuer),
+            issuer: format!("did:ethr:0x{:02x}", issuer),
+            subject: "did:ethr:0xabcd".to_string(),
+            type_,
+            attributes: attributes
+                .iter()
+                .map(|(name, value)| CredentialAttribute { name: name.to_string(), value: value.to_string(), reveal: false, salt: None })
+                .collect(),
+            issued_at: "2024-01-01T00:00:00Z".to_string(),
+            expires_at: None,
+            revoked: false,
+            holder_commitment: Some(compute_holder_commitment(holder_secret)),
+            issuer_public_key: None,
+            issuer_signature: None,
+            bbs_signature: None,
+        };
+        sign_credential(&unsigned, &SigningKey::from_bytes(&[issuer; 32]).unwrap()).unwrap()
+    }
+
+    fn kyc_and_membership(membership_holder: &str) -> Vec<CredentialPredicates> {
+        vec![
+            CredentialPredicates {
+                credential: credential(1, CredentialType::Kyc, &[("level", "2"), ("country", "DE")], "secret"),
+                predicates: serde_json::from_value(json!([{"field": "level", "operator": ">=", "value": 2}])).unwrap(),
+                revocation_registry: RevocationRegistry::new(),
+            },
+            CredentialPredicates {
+                credential: credential(2, CredentialType::Membership, &[("club", "chess"), ("since", "2019")], membership_holder),
+                predicates: serde_json::from_value(json!([{"field": "club", "operator": "=", "value": "chess"}])).unwrap(),
+                revocation_registry: RevocationRegistry::new(),
+            },
+        ]
+    }
+
+    fn requests(credentials: &[CredentialPredicates]) -> Vec<CredentialRequest> {
+        credentials
+            .iter()
+            .map(|entry| CredentialRequest {
+                issuer_public_key: entry.credential.issuer_public_key.clone().unwrap(),
+                revocation_root: encode_scalar(&entry.revocation_registry.root().unwrap()),
+                predicates: entry.predicates.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn credentials_of_one_holder_prove_together() {
+        let credentials = kyc_and_membership("secret");
+        let (circuit, inputs) = compile_conjunction(&credentials, "secret", "exchange", NOW).unwrap();
+
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&inputs));
+
+        // The verifier derives the same inputs from its requests and the presented nullifier
+        let nullifier = inputs[inputs.len() - 1];
+        assert_eq!(conjunction_public_inputs(&requests(&credentials), "exchange", NOW, &nullifier).unwrap(), inputs);
+    }
+
+    #[test]
+    fn conjunctions_cover_up_to_the_maximum_number_of_credentials() {
+        let mut credentials = kyc_and_membership("secret");
+        credentials.push(CredentialPredicates {
+            credential: credential(3, CredentialType::Identity, &[("age", "34")], "secret"),
+            predicates: serde_json::from_value(json!([{"field": "age", "operator": ">=", "value": 18}])).unwrap(),
+            revocation_registry: RevocationRegistry::new(),
+        });
+        let (circuit, inputs) = compile_conjunction(&credentials, "secret", "exchange", NOW).unwrap();
+        assert_eq!(circuit.credentials.len(), 3);
+
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+        let nullifier = inputs[inputs.len() - 1];
+        assert!(cs.verify(&conjunction_public_inputs(&requests(&credentials), "exchange", NOW, &nullifier).unwrap()));
+
+        let too_many = vec![credentials[0].clone(); MAX_CONJUNCTION_CREDENTIALS + 1];
+        assert!(compile_conjunction(&too_many, "secret", "exchange", NOW).is_err());
+    }
+
+    #[test]
+    fn credentials_of_different_holders_are_rejected() {
+        // The membership credential commits to another holder secret
+        assert!(compile_conjunction(&kyc_and_membership("other-secret"), "secret", "exchange", NOW).is_err());
+
+        // Swapping in the other holder's credential values breaks the shared-holder constraint
+        let (mut circuit, _) = compile_conjunction(&kyc_and_membership("secret"), "secret", "exchange", NOW).unwrap();
+        let other = credential(2, CredentialType::Membership, &[("club", "chess"), ("since", "2019")], "other-secret");
+        circuit.credentials[1].credential = CredentialValues::new(&other, &RevocationRegistry::new(), "other-secret", NOW).unwrap().0;
+        assert!(check_satisfied(circuit).is_err());
+
+        assert!(compile_conjunction(&kyc_and_membership("secret")[..1], "secret", "exchange", NOW).is_err());
+    }
+
+    #[test]
+    fn each_credential_is_checked_against_its_issuers_registry() {
+        let mut credentials = kyc_and_membership("secret");
+        let mut other = credentials[1].credential.clone();
+        other.id = "urn:uuid:another-membership".to_string();
+        credentials[1].revocation_registry.revoke(&other).unwrap();
+
+        let (circuit, inputs) = compile_conjunction(&credentials, "secret", "exchange", NOW).unwrap();
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+        let nullifier = inputs[inputs.len() - 1];
+        assert_eq!(conjunction_public_inputs(&requests(&credentials), "exchange", NOW, &nullifier).unwrap(), inputs);
+
+        // The membership issuer's root does not stand in for the KYC issuer's
+        let mut swapped = requests(&credentials);
+        swapped[0].revocation_root = swapped[1].revocation_root.clone();
+        assert!(!cs.verify(&conjunction_public_inputs(&swapped, "exchange", NOW, &nullifier).unwrap()));
+
+        let revoked = credentials[1].credential.clone();
+        credentials[1].revocation_registry.revoke(&revoked).unwrap();
+        assert!(compile_conjunction(&credentials, "secret", "exchange", NOW).is_err());
+    }
+}
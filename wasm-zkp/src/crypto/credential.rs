@@ -1,9 +1,17 @@
-use crate::utils::{get_error_message, hash_string};
-use bellman::{
-    groth16::{self, create_random_proof, generate_random_parameters, verify_proof},
-    Circuit, ConstraintSystem, SynthesisError,
-};
-use bls12_381::{Bls12, Scalar};
+use crate::crypto::envelope::ProofEnvelope;
+use crate::crypto::eddsa::{verify_signature_gadget, EdwardsPoint, Signature, SigningKey, VerifyingKey};
+use crate::crypto::hash_to_field::{hash_str_to_field, HashDomain};
+use crate::crypto::merkle::MerkleTree;
+use crate::crypto::mimc::MiMC;
+use crate::crypto::nullifier::{context_id, nullifier, nullifier_gadget};
+use crate::crypto::proof_system::ProofSystem;
+use crate::crypto::range_proof::enforce_bits;
+use crate::crypto::revocation::{non_revocation_gadget, NonRevocationWitness, RevocationRegistry};
+use crate::crypto::zk_proofs::{check_satisfied, decode_scalar, encode_scalar, scalar_from_u64, ProofType};
+use crate::error::ZkError;
+use crate::utils::{get_error_message, unix_time};
+use bellman::{gadgets::num::AllocatedNum, Circuit, ConstraintSystem, LinearCombination, SynthesisError};
+use bls12_381::Scalar;
 use ff::Field;
 use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
@@ -20,15 +28,19 @@ pub enum CredentialType {
     Custom,
 }
 
-#[wasm_bindgen]
+#[wasm_bindgen(getter_with_clone)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CredentialAttribute {
     pub name: String,
     pub value: String,
+    /// Whether presentations disclose this attribute; all others stay hidden
     pub reveal: bool,
+    /// Hex-encoded blinding of the attribute's leaf in the attribute tree, set by `sign_credential`
+    #[serde(default)]
+    pub salt: Option<String>,
 }
 
-#[wasm_bindgen]
+#[wasm_bindgen(getter_with_clone)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Credential {
     pub id: String,
@@ -39,147 +51,1139 @@ pub struct Credential {
     pub issued_at: String,
     pub expires_at: Option<String>,
     pub revoked: bool,
+    /// Hex-encoded `holder_commitment` of the holder's secret, set at issuance
+    #[serde(default)]
+    pub holder_commitment: Option<String>,
+    /// Hex-encoded Jubjub public key of the issuer, set by `sign_credential`
+    #[serde(default)]
+    pub issuer_public_key: Option<String>,
+    /// Hex-encoded EdDSA signature on `credential_message`, set by `sign_credential`
+    #[serde(default)]
+    pub issuer_signature: Option<String>,
+    /// Hex-encoded BBS+ signature over `bbs_credential_messages`, set by `sign_credential_bbs`
+    #[serde(default)]
+    pub bbs_signature: Option<String>,
 }
 
-/// Circuit for proving credential attributes
-pub struct CredentialCircuit {
-    // Credential holder secret (private)
+/// Witness file for a credential presentation, as consumed by native tooling
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialWitness {
+    pub credential: Credential,
+    pub holder_secret: String,
+    /// Verifier/context id the nullifier is scoped to
+    pub context: String,
+    /// Verifier's current time in Unix seconds, which the credential must not have expired by
+    pub current_time: u64,
+    /// The issuer's revocation registry, empty if none was published
+    #[serde(default)]
+    pub revocation_registry: RevocationRegistry,
+}
+
+impl CredentialWitness {
+    /// Build the circuit instance and its public inputs
+    pub fn build_circuit(&self) -> Result<(CredentialCircuit, Vec<Scalar>), ZkError> {
+        build_credential_circuit(
+            &self.credential,
+            &self.revocation_registry,
+            &self.holder_secret,
+            &self.context,
+            self.current_time,
+        )
+    }
+}
+
+/// Expiry signed into credentials without an `expires_at`
+pub const NO_EXPIRY: u64 = u64::MAX;
+
+/// Seconds since the Unix epoch of an RFC 3339 timestamp such as `2024-01-01T00:00:00Z`
+/// or `2024-01-01T02:00:00.000+02:00`; fractions of a second are dropped
+pub fn parse_timestamp(timestamp: &str) -> Result<u64, ZkError> {
+    let invalid = || ZkError::InvalidInput(format!("invalid RFC 3339 timestamp: {}", timestamp));
+    let digits = |field: Option<&str>| {
+        field.filter(|field| !field.is_empty() && field.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|field| field.parse::<i64>().ok())
+            .ok_or_else(invalid)
+    };
+
+    let separators = timestamp.as_bytes();
+    if separators.len() < 20
+        || separators[4] != b'-'
+        || separators[7] != b'-'
+        || !matches!(separators[10], b'T' | b't' | b' ')
+        || separators[13] != b':'
+        || separators[16] != b':'
+    {
+        return Err(invalid());
+    }
+    let (year, month, day) = (digits(timestamp.get(0..4))?, digits(timestamp.get(5..7))?, digits(timestamp.get(8..10))?);
+    let (hour, minute, second) = (digits(timestamp.get(11..13))?, digits(timestamp.get(14..16))?, digits(timestamp.get(17..19))?);
+
+    let mut offset = &timestamp[19..];
+    if let Some(fraction) = offset.strip_prefix('.') {
+        let length = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if length == 0 {
+            return Err(invalid());
+        }
+        offset = &fraction[length..];
+    }
+    let offset = match offset {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match offset.as_bytes().first() {
+                Some(b'+') => 1,
+                Some(b'-') => -1,
+                _ => return Err(invalid()),
+            };
+            if offset.len() != 6 || offset.as_bytes()[3] != b':' {
+                return Err(invalid());
+            }
+            sign * (digits(offset.get(1..3))? * 3600 + digits(offset.get(4..6))? * 60)
+        }
+    };
+
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) || hour > 23 || minute > 59 || second > 60 {
+        return Err(invalid());
+    }
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    u64::try_from(seconds).map_err(|_| invalid())
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days from 1970-01-01 to the given proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// A credential's validity period in Unix seconds: `(issued at, expiry)`, with
+/// `NO_EXPIRY` for credentials that do not expire
+pub fn validity_period(credential: &Credential) -> Result<(u64, u64), ZkError> {
+    let issued_at = parse_timestamp(&credential.issued_at)?;
+    let expiry = credential.expires_at.as_deref().map(parse_timestamp).transpose()?.unwrap_or(NO_EXPIRY);
+    if expiry <= issued_at {
+        return Err(ZkError::InvalidInput("credential expires before it is issued".to_string()));
+    }
+    Ok((issued_at, expiry))
+}
+
+/// Fail unless `credential` is unrevoked, already issued and unexpired at `current_time` (Unix seconds)
+pub fn check_validity(credential: &Credential, current_time: u64) -> Result<(), ZkError> {
+    let (issued_at, expiry) = validity_period(credential)?;
+    if credential.revoked {
+        return Err(ZkError::InvalidInput("credential has been revoked".to_string()));
+    }
+    if current_time < issued_at {
+        return Err(ZkError::InvalidInput("credential is not valid yet".to_string()));
+    }
+    if current_time >= expiry {
+        return Err(ZkError::InvalidInput("credential has expired".to_string()));
+    }
+    Ok(())
+}
+
+/// Depth of the Merkle tree a credential's attributes are committed to
+pub const ATTRIBUTE_TREE_DEPTH: usize = 3;
+
+/// Number of attribute slots, the leaves of the attribute tree; credentials with
+/// fewer attributes are padded with zero slots
+pub const CREDENTIAL_ATTRIBUTE_SLOTS: usize = 1 << ATTRIBUTE_TREE_DEPTH;
+
+/// Private field encodings of a signed credential and of its holder's secret
+#[derive(Clone)]
+pub struct CredentialValues {
     pub holder_secret: Option<Scalar>,
-    
-    // Credential public parameters
-    pub credential_hash: Option<Scalar>,
-    pub issuer_hash: Option<Scalar>,
-    
-    // Selected attributes to reveal
-    pub selected_attributes: Vec<(String, Option<Scalar>)>,
-    
-    // Attribute hash (public input)
-    pub attribute_hash: Option<Scalar>,
+    pub credential_id: Option<Scalar>,
+    pub issuer: Option<Scalar>,
+    // All attributes as (name, value) pairs, CREDENTIAL_ATTRIBUTE_SLOTS long
+    pub attributes: Vec<(Option<Scalar>, Option<Scalar>)>,
+    // Leaf blinding per attribute slot
+    pub attribute_salts: Vec<Option<Scalar>>,
+    // Expiry in Unix seconds, NO_EXPIRY if none
+    pub expiry: Option<u64>,
+    // Low leaf skipping over the credential hash in the revocation tree, with its path
+    pub revocation: NonRevocationWitness,
+    // Issuer signature on the credential commitments
+    pub signature: Option<Signature>,
 }
 
-impl Circuit<Scalar> for CredentialCircuit {
-    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
-        // Allocate the holder secret (private)
-        let holder_secret = cs.alloc(
-            || "holder_secret",
-            || self.holder_secret.ok_or(SynthesisError::AssignmentMissing),
+impl CredentialValues {
+    /// Values without a witness, for key generation
+    pub fn blank() -> Self {
+        Self {
+            holder_secret: None,
+            credential_id: None,
+            issuer: None,
+            attributes: vec![(None, None); CREDENTIAL_ATTRIBUTE_SLOTS],
+            attribute_salts: vec![None; CREDENTIAL_ATTRIBUTE_SLOTS],
+            expiry: None,
+            revocation: NonRevocationWitness::blank(),
+            signature: None,
+        }
+    }
+
+    /// Encode `credential` for the holder of `holder_secret_str`. Fails unless the
+    /// credential was issued to that holder, carries a valid issuer signature, is
+    /// valid at `current_time` and is not revoked in the issuer's `registry`; returns
+    /// the issuer key alongside.
+    pub fn new(
+        credential: &Credential,
+        registry: &RevocationRegistry,
+        holder_secret_str: &str,
+        current_time: u64,
+    ) -> Result<(Self, VerifyingKey), ZkError> {
+        check_validity(credential, current_time)?;
+
+        // Only the holder the credential was issued to can present it
+        let bound_to = credential.holder_commitment.as_deref().map(decode_scalar).transpose()?;
+        if bound_to != Some(holder_commitment(holder_secret_str)) {
+            return Err(ZkError::InvalidInput("holder secret does not match the credential".to_string()));
+        }
+
+        let (issuer_key, signature) = issuer_signature(credential)?;
+        if !issuer_key.verify(&credential_message(credential)?, &signature) {
+            return Err(ZkError::InvalidInput("issuer signature does not verify".to_string()));
+        }
+
+        let values = Self {
+            holder_secret: Some(hash_str_to_field(HashDomain::HolderSecret, holder_secret_str)),
+            credential_id: Some(hash_str_to_field(HashDomain::CredentialId, &credential.id)),
+            issuer: Some(hash_str_to_field(HashDomain::Issuer, &credential.issuer)),
+            attributes: encode_attributes(credential)?
+                .into_iter()
+                .map(|(name, value)| (Some(name), Some(value)))
+                .collect(),
+            attribute_salts: attribute_salts(credential)?.into_iter().map(Some).collect(),
+            expiry: Some(validity_period(credential)?.1),
+            revocation: registry.non_revocation_witness(credential)?,
+            signature: Some(signature),
+        };
+        Ok((values, issuer_key))
+    }
+}
+
+/// A signed credential witnessed in a circuit, with its commitments
+pub struct AllocatedCredential {
+    pub holder_secret: AllocatedNum<Scalar>,
+    pub credential_hash: AllocatedNum<Scalar>,
+    pub issuer_hash: AllocatedNum<Scalar>,
+    pub attribute_root: AllocatedNum<Scalar>,
+    /// (name, value) per attribute slot
+    pub attributes: Vec<(AllocatedNum<Scalar>, AllocatedNum<Scalar>)>,
+}
+
+impl AllocatedCredential {
+    /// Witness `values`, commit to them and check the issuer's signature on the
+    /// commitments under `issuer_key`:
+    ///
+    /// - credential hash = H(credential id, H(holder secret))
+    /// - issuer hash = H(issuer)
+    /// - attribute root = Merkle root over the leaves H(name_i, value_i, salt_i)
+    /// - signed message = H(credential hash, issuer hash, attribute root, expiry)
+    ///
+    /// and that the signed expiry lies after `current_time` and the credential hash is
+    /// absent from the issuer's revocation tree with root `revocation_root` (see
+    /// `crypto::revocation`), both of which the caller makes public.
+    pub fn alloc<CS: ConstraintSystem<Scalar>>(
+        mut cs: CS,
+        mimc: &MiMC,
+        values: &CredentialValues,
+        issuer_key: &EdwardsPoint,
+        revocation_root: &AllocatedNum<Scalar>,
+        current_time: &AllocatedNum<Scalar>,
+    ) -> Result<Self, SynthesisError> {
+        let holder_secret = AllocatedNum::alloc(cs.namespace(|| "holder_secret"), || {
+            values.holder_secret.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let holder_commitment = mimc.hash_gadget(cs.namespace(|| "holder_commitment"), std::slice::from_ref(&holder_secret))?;
+
+        let credential_id = AllocatedNum::alloc(cs.namespace(|| "credential_id"), || {
+            values.credential_id.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let credential_hash = mimc.hash_gadget(cs.namespace(|| "credential_hash"), &[credential_id, holder_commitment])?;
+        non_revocation_gadget(
+            cs.namespace(|| "not revoked"),
+            mimc,
+            &credential_hash,
+            &values.revocation,
+            revocation_root,
         )?;
 
-        // Allocate credential hash (public)
-        let credential_hash = cs.alloc_input(
-            || "credential_hash",
-            || self.credential_hash.ok_or(SynthesisError::AssignmentMissing),
+        let issuer = AllocatedNum::alloc(cs.namespace(|| "issuer"), || {
+            values.issuer.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let issuer_hash = mimc.hash_gadget(cs.namespace(|| "issuer_hash"), &[issuer])?;
+
+        let mut attributes = Vec::with_capacity(values.attributes.len());
+        let mut level = Vec::with_capacity(values.attributes.len());
+        for (i, ((name, value), salt)) in values.attributes.iter().zip(&values.attribute_salts).enumerate() {
+            let name = AllocatedNum::alloc(cs.namespace(|| format!("attribute {} name", i)), || {
+                name.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let value = AllocatedNum::alloc(cs.namespace(|| format!("attribute {} value", i)), || {
+                value.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let salt = AllocatedNum::alloc(cs.namespace(|| format!("attribute {} salt", i)), || {
+                salt.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            level.push(mimc.hash_gadget(cs.namespace(|| format!("attribute {} leaf", i)), &[name.clone(), value.clone(), salt])?);
+            attributes.push((name, value));
+        }
+
+        // Hash the leaves pairwise up to the attribute root
+        let mut height = 0;
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .enumerate()
+                .map(|(i, pair)| mimc.hash_gadget(cs.namespace(|| format!("attribute node {} {}", height, i)), pair))
+                .collect::<Result<_, _>>()?;
+            height += 1;
+        }
+        let attribute_root = level.pop().ok_or(SynthesisError::Unsatisfiable)?;
+
+        // expiry - current time - 1 fits in 64 bits exactly when the credential is unexpired
+        let expiry = AllocatedNum::alloc(cs.namespace(|| "expiry"), || {
+            values.expiry.map(scalar_from_u64).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let remaining = expiry.get_value().zip(current_time.get_value()).map(|(expiry, now)| {
+            let bytes = (expiry - now - Scalar::one()).to_bytes();
+            u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes"))
+        });
+        enforce_bits(
+            cs.namespace(|| "unexpired"),
+            LinearCombination::zero() + expiry.get_variable() - current_time.get_variable() - CS::one(),
+            remaining,
+            64,
         )?;
-        
-        // Allocate issuer hash (public)
-        let issuer_hash = cs.alloc_input(
-            || "issuer_hash",
-            || self.issuer_hash.ok_or(SynthesisError::AssignmentMissing),
+
+        let message = mimc.hash_gadget(
+            cs.namespace(|| "signed message"),
+            &[credential_hash.clone(), issuer_hash.clone(), attribute_root.clone(), expiry],
         )?;
-        
-        // Allocate attribute hash (public)
-        let attribute_hash = cs.alloc_input(
-            || "attribute_hash",
-            || self.attribute_hash.ok_or(SynthesisError::AssignmentMissing),
+        verify_signature_gadget(
+            cs.namespace(|| "issuer signature verification"),
+            mimc,
+            issuer_key,
+            &message,
+            values.signature.as_ref(),
         )?;
-        
-        // Enforce constraints for credential verification
-        // This is simplified for demonstration - in reality we would need more complex constraints
-        
-        // Example constraint: credential must be signed by issuer
-        cs.enforce(
-            || "issuer signature verification",
-            |lc| lc + credential_hash,
-            |lc| lc + issuer_hash,
-            |lc| lc + attribute_hash,
-        );
-        
-        // Additional constraints would go here in a real implementation
+
+        Ok(Self { holder_secret, credential_hash, issuer_hash, attribute_root, attributes })
+    }
+}
+
+/// Circuit for proving credential attributes.
+///
+/// The credential id, issuer and attributes are private field encodings; the
+/// circuit hashes them with MiMC into the credential hash, issuer hash and
+/// attribute root, which stay private too, so a proof attests to knowing their
+/// preimages without handing verifiers a value that recurs across presentations.
+/// The credential commitment also covers `H(holder_secret)`, so only the holder
+/// can present it. The attributes are committed to as the root of a Merkle tree
+/// over salted leaves; a presentation discloses the attributes marked `reveal`
+/// as public inputs, one `SlotDisclosure` per attribute slot, and the circuit
+/// checks each disclosed name and value against the attribute in its slot, so
+/// neither salts nor paths leave the prover. The three commitments and the
+/// credential's expiry must carry the issuer's EdDSA signature under the public
+/// issuer key, and the expiry must lie after the verifier's public current time
+/// (see `AllocatedCredential`), so expired credentials yield no valid proof.
+/// Revoked credentials yield none either: the credential hash must be absent
+/// from the tree behind the issuer's public revocation root.
+///
+/// The proof also outputs the holder's nullifier for a public verifier/context
+/// id (see `crypto::nullifier`), so a relying party can reject a second
+/// presentation by the same holder without learning who they are.
+///
+/// Public inputs: issuer public key `(u, v)`, the revocation root, then
+/// `CREDENTIAL_ATTRIBUTE_SLOTS` times the inputs of a `SlotDisclosure`, then the
+/// current time, the context id and the nullifier.
+#[derive(Clone)]
+pub struct CredentialCircuit {
+    // Credential and holder secret (private)
+    pub credential: CredentialValues,
+    
+    // Issuer public key (public)
+    pub issuer_public_key: Option<(Scalar, Scalar)>,
+
+    // Root of the issuer's revocation tree (public)
+    pub revocation_root: Option<Scalar>,
+
+    // Disclosed attribute per slot, CREDENTIAL_ATTRIBUTE_SLOTS long (public)
+    pub disclosures: Vec<Option<SlotDisclosure>>,
+
+    // Verifier's current time in Unix seconds (public)
+    pub current_time: Option<Scalar>,
+
+    // Verifier/context id (public)
+    pub context: Option<Scalar>,
+}
+
+impl Circuit<Scalar> for CredentialCircuit {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mimc = MiMC::new();
+
+        let issuer_key = EdwardsPoint::witness(cs.namespace(|| "issuer_public_key"), self.issuer_public_key)?;
+        let revocation_root = AllocatedNum::alloc(cs.namespace(|| "revocation_root"), || {
+            self.revocation_root.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let current_time = AllocatedNum::alloc(cs.namespace(|| "current_time"), || {
+            self.current_time.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let credential = AllocatedCredential::alloc(
+            cs.namespace(|| "credential"),
+            &mimc,
+            &self.credential,
+            &issuer_key,
+            &revocation_root,
+            &current_time,
+        )?;
+
+        // Issuer key and revocation root (public); the commitments stay private
+        issuer_key.inputize(cs.namespace(|| "issuer_public_key input"))?;
+        revocation_root.inputize(cs.namespace(|| "revocation_root input"))?;
+
+        for (i, (disclosure, attribute)) in self.disclosures.iter().zip(&credential.attributes).enumerate() {
+            enforce_disclosure(cs.namespace(|| format!("disclosure {}", i)), disclosure.as_ref(), attribute)?;
+        }
+        current_time.inputize(cs.namespace(|| "current_time input"))?;
+
+        // Nullifier (public) = H(context, holder secret)
+        let context = AllocatedNum::alloc(cs.namespace(|| "context"), || {
+            self.context.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        context.inputize(cs.namespace(|| "context input"))?;
+        nullifier_gadget(cs.namespace(|| "nullifier"), &mimc, &credential.holder_secret, &context)?
+            .inputize(cs.namespace(|| "nullifier input"))?;
         
         Ok(())
     }
 }
 
-/// Prepare a credential for zero-knowledge proof
-pub fn prepare_credential_proof(
+// Make `disclosure` public and, when it reveals its slot, constrain the slot's
+// attribute to the disclosed name and value
+fn enforce_disclosure<CS: ConstraintSystem<Scalar>>(
+    mut cs: CS,
+    disclosure: Option<&SlotDisclosure>,
+    (name, value): &(AllocatedNum<Scalar>, AllocatedNum<Scalar>),
+) -> Result<(), SynthesisError> {
+    let mut input = |label: &'static str, value: Option<Scalar>| {
+        cs.alloc_input(|| label, || value.ok_or(SynthesisError::AssignmentMissing))
+    };
+    let revealed = input("revealed", disclosure.map(|d| Scalar::from(d.revealed as u64)))?;
+    let disclosed_name = input("name", disclosure.map(|d| d.name))?;
+    let disclosed_value = input("value", disclosure.map(|d| d.value))?;
+
+    cs.enforce(
+        || "revealed is boolean",
+        |lc| lc + revealed,
+        |lc| lc + CS::one() - revealed,
+        |lc| lc,
+    );
+    cs.enforce(
+        || "revealed name matches",
+        |lc| lc + revealed,
+        |lc| lc + name.get_variable() - disclosed_name,
+        |lc| lc,
+    );
+    cs.enforce(
+        || "revealed value matches",
+        |lc| lc + revealed,
+        |lc| lc + value.get_variable() - disclosed_value,
+        |lc| lc,
+    );
+    Ok(())
+}
+
+impl CredentialCircuit {
+    /// Circuit shape without a witness, for key generation
+    pub fn blank() -> Self {
+        Self {
+            credential: CredentialValues::blank(),
+            issuer_public_key: None,
+            revocation_root: None,
+            disclosures: vec![None; CREDENTIAL_ATTRIBUTE_SLOTS],
+            current_time: None,
+            context: None,
+        }
+    }
+}
+
+/// Public statement about one attribute slot of a presentation: whether it is
+/// disclosed and, if so, the field encodings of its name and value (zero otherwise)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SlotDisclosure {
+    pub revealed: bool,
+    pub name: Scalar,
+    pub value: Scalar,
+}
+
+impl SlotDisclosure {
+    /// The slot's public inputs: revealed flag, name, value
+    pub fn public_inputs(&self) -> [Scalar; 3] {
+        [Scalar::from(self.revealed as u64), self.name, self.value]
+    }
+}
+
+/// One `SlotDisclosure` per attribute slot of `credential`, revealing the attributes marked `reveal`
+pub fn credential_disclosures(credential: &Credential) -> Result<Vec<SlotDisclosure>, ZkError> {
+    let mut disclosures = credential.attributes.iter()
+        .zip(encode_attributes(credential)?)
+        .map(|(attr, (name, value))| match attr.reveal {
+            true => SlotDisclosure { revealed: true, name, value },
+            false => SlotDisclosure::default(),
+        })
+        .collect::<Vec<_>>();
+    disclosures.resize(CREDENTIAL_ATTRIBUTE_SLOTS, SlotDisclosure::default());
+    Ok(disclosures)
+}
+
+/// Field encodings of a credential's attributes as (name, value) pairs, padded to
+/// `CREDENTIAL_ATTRIBUTE_SLOTS`
+pub fn encode_attributes(credential: &Credential) -> Result<Vec<(Scalar, Scalar)>, ZkError> {
+    if credential.attributes.len() > CREDENTIAL_ATTRIBUTE_SLOTS {
+        return Err(ZkError::InvalidInput(format!(
+            "credential has {} attributes, at most {} are supported",
+            credential.attributes.len(),
+            CREDENTIAL_ATTRIBUTE_SLOTS
+        )));
+    }
+
+    let mut encoded = credential.attributes.iter()
+        .map(|attr| Ok((hash_str_to_field(HashDomain::AttributeName, &attr.name), encode_attribute_value(&attr.value)?)))
+        .collect::<Result<Vec<_>, ZkError>>()?;
+    encoded.resize(CREDENTIAL_ATTRIBUTE_SLOTS, (Scalar::zero(), Scalar::zero()));
+    Ok(encoded)
+}
+
+/// Leaf blinding per attribute slot, zero for unused slots. Fails if an attribute
+/// has no salt, i.e. the credential was not issued through `sign_credential`.
+pub fn attribute_salts(credential: &Credential) -> Result<Vec<Scalar>, ZkError> {
+    let mut salts = credential.attributes.iter()
+        .map(|attr| {
+            let salt = attr.salt.as_deref()
+                .ok_or_else(|| ZkError::InvalidInput(format!("attribute {} has no salt", attr.name)))?;
+            decode_scalar(salt)
+        })
+        .collect::<Result<Vec<_>, ZkError>>()?;
+    salts.resize(CREDENTIAL_ATTRIBUTE_SLOTS, Scalar::zero());
+    Ok(salts)
+}
+
+/// Leaf committing to one attribute slot: `H(name, value, salt)`
+pub fn attribute_leaf(name: &Scalar, value: &Scalar, salt: &Scalar) -> Scalar {
+    MiMC::new().hash(&[*name, *value, *salt])
+}
+
+/// The Merkle tree over a credential's attribute slots, whose root the issuer signs
+pub fn attribute_tree(credential: &Credential) -> Result<MerkleTree, ZkError> {
+    let leaves: Vec<Scalar> = encode_attributes(credential)?
+        .iter()
+        .zip(attribute_salts(credential)?)
+        .map(|((name, value), salt)| attribute_leaf(name, value, &salt))
+        .collect();
+    MerkleTree::new(ATTRIBUTE_TREE_DEPTH, &leaves)
+}
+
+/// Number of elements a list-valued attribute may hold
+pub const LIST_ATTRIBUTE_ELEMENTS: usize = 4;
+
+/// Field encoding of an attribute value, chosen so circuits can reason about it:
+///
+/// - a canonical decimal `u64` (`"34"`) encodes as the number itself, for comparisons;
+/// - a JSON array of strings (`["admin","staff"]`) encodes as the MiMC hash of its
+///   element encodings (see `list_attribute_elements`), for membership tests;
+/// - any other value is hashed.
+pub fn encode_attribute_value(value: &str) -> Result<Scalar, ZkError> {
+    if let Some(number) = numeric_attribute_value(value) {
+        return Ok(scalar_from_u64(number));
+    }
+    Ok(match list_attribute_elements(value)? {
+        Some(elements) => MiMC::new().hash(&elements),
+        None => hash_str_to_field(HashDomain::AttributeValue, value),
+    })
+}
+
+/// The number a canonical decimal attribute value stands for
+pub fn numeric_attribute_value(value: &str) -> Option<u64> {
+    let canonical = !value.is_empty()
+        && value.bytes().all(|b| b.is_ascii_digit())
+        && (value == "0" || !value.starts_with('0'));
+    canonical.then(|| value.parse().ok()).flatten()
+}
+
+/// Element encodings of a list-valued attribute, zero-padded to `LIST_ATTRIBUTE_ELEMENTS`;
+/// `None` if the value is not a JSON array of strings
+pub fn list_attribute_elements(value: &str) -> Result<Option<Vec<Scalar>>, ZkError> {
+    let elements: Vec<String> = match value.starts_with('[').then(|| serde_json::from_str(value)) {
+        Some(Ok(elements)) => elements,
+        _ => return Ok(None),
+    };
+    if elements.len() > LIST_ATTRIBUTE_ELEMENTS {
+        return Err(ZkError::InvalidInput(format!(
+            "list attribute has {} elements, at most {} are supported",
+            elements.len(),
+            LIST_ATTRIBUTE_ELEMENTS
+        )));
+    }
+
+    let mut encoded: Vec<Scalar> = elements.iter().map(|element| list_element(element)).collect();
+    encoded.resize(LIST_ATTRIBUTE_ELEMENTS, Scalar::zero());
+    Ok(Some(encoded))
+}
+
+/// Field encoding of one element of a list-valued attribute
+pub fn list_element(element: &str) -> Scalar {
+    hash_str_to_field(HashDomain::AttributeValue, element)
+}
+
+/// Commitment to a holder secret, embedded in credentials at issuance
+pub fn holder_commitment(holder_secret: &str) -> Scalar {
+    MiMC::new().hash(&[hash_str_to_field(HashDomain::HolderSecret, holder_secret)])
+}
+
+/// Hex-encoded holder commitment for a holder secret, to hand to the issuer
+#[wasm_bindgen]
+pub fn compute_holder_commitment(holder_secret: &str) -> String {
+    encode_scalar(&holder_commitment(holder_secret))
+}
+
+/// Commitment to a credential and its holder: `H(credential id, holder commitment)`
+pub fn credential_hash(credential: &Credential) -> Result<Scalar, ZkError> {
+    let holder = credential.holder_commitment.as_deref()
+        .ok_or_else(|| ZkError::InvalidInput("credential is not bound to a holder commitment".to_string()))?;
+    Ok(MiMC::new().hash(&[hash_str_to_field(HashDomain::CredentialId, &credential.id), decode_scalar(holder)?]))
+}
+
+/// Public commitments a verifier expects for a credential:
+/// (credential hash, issuer hash, attribute root)
+pub fn credential_commitments(credential: &Credential) -> Result<Vec<Scalar>, ZkError> {
+    Ok(vec![
+        credential_hash(credential)?,
+        MiMC::new().hash(&[hash_str_to_field(HashDomain::Issuer, &credential.issuer)]),
+        attribute_tree(credential)?.root(),
+    ])
+}
+
+/// The field element an issuer signs: `H(credential hash, issuer hash, attribute root, expiry)`
+pub fn credential_message(credential: &Credential) -> Result<Scalar, ZkError> {
+    let mut signed = credential_commitments(credential)?;
+    signed.push(scalar_from_u64(validity_period(credential)?.1));
+    Ok(MiMC::new().hash(&signed))
+}
+
+/// Sign a credential as its issuer, filling in `issuer_public_key` and `issuer_signature`
+/// and a fresh salt for every attribute that has none. The holder commitment must
+/// already be set; revoked or already expired credentials are refused.
+pub fn sign_credential(credential: &Credential, issuer_key: &SigningKey) -> Result<Credential, ZkError> {
+    check_issuable(credential)?;
+    let mut signed = credential.clone();
+    for attr in signed.attributes.iter_mut().filter(|attr| attr.salt.is_none()) {
+        attr.salt = Some(encode_scalar(&Scalar::random(&mut OsRng)));
+    }
+    let signature = issuer_key.sign(&credential_message(&signed)?);
+
+    signed.issuer_public_key = Some(hex::encode(issuer_key.verifying_key().to_bytes()));
+    signed.issuer_signature = Some(hex::encode(signature.to_bytes()));
+    Ok(signed)
+}
+
+/// Fail unless `credential` has a well-formed validity period, is unrevoked and has not yet expired
+pub(crate) fn check_issuable(credential: &Credential) -> Result<(), ZkError> {
+    if credential.revoked {
+        return Err(ZkError::InvalidInput("cannot issue a revoked credential".to_string()));
+    }
+    if validity_period(credential)?.1 <= unix_time() {
+        return Err(ZkError::InvalidInput("cannot issue an expired credential".to_string()));
+    }
+    Ok(())
+}
+
+/// Issuer public key and signature carried by a signed credential
+pub fn issuer_signature(credential: &Credential) -> Result<(VerifyingKey, Signature), ZkError> {
+    let unsigned = || ZkError::InvalidInput("credential is not signed by its issuer".to_string());
+    let public_key = VerifyingKey::from_bytes(&hex::decode(credential.issuer_public_key.as_ref().ok_or_else(unsigned)?)?)?;
+    let signature = Signature::from_bytes(&hex::decode(credential.issuer_signature.as_ref().ok_or_else(unsigned)?)?)?;
+    Ok((public_key, signature))
+}
+
+/// Generate an issuer key pair: `{ secretKey, publicKey }`, both hex-encoded
+#[wasm_bindgen]
+pub fn generate_issuer_keypair() -> Result<JsValue, JsValue> {
+    let key = SigningKey::generate();
+    serde_wasm_bindgen::to_value(&serde_json::json!({
+        "secretKey": hex::encode(key.to_bytes()),
+        "publicKey": hex::encode(key.verifying_key().to_bytes()),
+    })).map_err(|e| JsValue::from_str(&get_error_message("Serialization error", &e)))
+}
+
+/// Sign a credential (JSON) with a hex-encoded issuer secret key; returns the signed credential JSON
+#[wasm_bindgen]
+pub fn issue_credential_signature(credential_json: &str, issuer_secret_key: &str) -> Result<String, JsValue> {
+    let credential: Credential = serde_json::from_str(credential_json)
+        .map_err(|e| JsValue::from_str(&format!("Invalid credential JSON: {:?}", e)))?;
+    let key = SigningKey::from_bytes(&hex::decode(issuer_secret_key).map_err(ZkError::from)?)?;
+
+    let signed = sign_credential(&credential, &key)?;
+    serde_json::to_string(&signed).map_err(|e| JsValue::from_str(&get_error_message("Serialization error", &e)))
+}
+
+/// All public inputs of a presentation by the holder of `holder_secret_str` in `context`
+/// at `current_time`, against the issuer's `revocation_root`
+pub fn credential_public_inputs(
     credential: &Credential,
+    revocation_root: &Scalar,
     holder_secret_str: &str,
-    revealed_attributes: &[String],
-) -> Result<JsValue, JsValue> {
-    // Hash the holder secret to a scalar
-    let holder_secret = hash_to_scalar(holder_secret_str);
-    
-    // Hash the credential ID
-    let credential_hash = hash_to_scalar(&credential.id);
-    
-    // Hash the issuer
-    let issuer_hash = hash_to_scalar(&credential.issuer);
-    
-    // Create attribute vector and compute hash of selected attributes
-    let mut selected_attributes = Vec::new();
-    let mut attribute_hash_input = String::new();
-    
-    for attr_name in revealed_attributes {
-        if let Some(attr) = credential.attributes.iter().find(|a| &a.name == attr_name) {
-            let attr_value_hash = hash_to_scalar(&attr.value);
-            selected_attributes.push((attr.name.clone(), Some(attr_value_hash)));
-            attribute_hash_input.push_str(&format!("{}:{},", attr.name, attr.value));
-        } else {
-            return Err(JsValue::from_str(&format!("Attribute not found: {}", attr_name)));
-        }
+    context: &str,
+    current_time: u64,
+) -> Result<Vec<Scalar>, ZkError> {
+    let (issuer_key, _) = issuer_signature(credential)?;
+    let nullifier = nullifier(&hash_str_to_field(HashDomain::HolderSecret, holder_secret_str), &context_id(context));
+    statement_inputs(&issuer_key, revocation_root, &credential_disclosures(credential)?, context, current_time, &nullifier)
+}
+
+// Public inputs in `CredentialCircuit` order
+fn statement_inputs(
+    issuer_key: &VerifyingKey,
+    revocation_root: &Scalar,
+    disclosures: &[SlotDisclosure],
+    context: &str,
+    current_time: u64,
+    nullifier: &Scalar,
+) -> Result<Vec<Scalar>, ZkError> {
+    let (u, v) = issuer_key.coordinates();
+    let mut inputs = vec![u, v, *revocation_root];
+    for disclosure in disclosures {
+        inputs.extend(disclosure.public_inputs());
     }
+    inputs.extend([scalar_from_u64(current_time), context_id(context), *nullifier]);
+    Ok(inputs)
+}
+
+/// Build a credential circuit against the issuer's revocation `registry` and its
+/// public inputs (see `CredentialCircuit` for their layout)
+pub fn build_credential_circuit(
+    credential: &Credential,
+    registry: &RevocationRegistry,
+    holder_secret_str: &str,
+    context: &str,
+    current_time: u64,
+) -> Result<(CredentialCircuit, Vec<Scalar>), ZkError> {
+    let revocation_root = registry.root()?;
+    let inputs = credential_public_inputs(credential, &revocation_root, holder_secret_str, context, current_time)?;
+    let (values, issuer_key) = CredentialValues::new(credential, registry, holder_secret_str, current_time)?;
     
-    // Hash the attribute string
-    let attribute_hash = hash_to_scalar(&attribute_hash_input);
-    
-    // Create the circuit
     let circuit = CredentialCircuit {
-        holder_secret: Some(holder_secret),
-        credential_hash: Some(credential_hash),
-        issuer_hash: Some(issuer_hash),
-        selected_attributes,
-        attribute_hash: Some(attribute_hash),
+        credential: values,
+        issuer_public_key: Some(issuer_key.coordinates()),
+        revocation_root: Some(revocation_root),
+        disclosures: credential_disclosures(credential)?.into_iter().map(Some).collect(),
+        current_time: Some(scalar_from_u64(current_time)),
+        context: Some(context_id(context)),
     };
     
-    // In a real implementation, we would generate a proof here
-    // For now, just return a mock result
-    let result = serde_wasm_bindgen::to_value(&serde_json::json!({
-        "success": true,
-        "message": "Credential proof prepared successfully",
-        "credentialHash": credential_hash.to_string(),
-        "issuerHash": issuer_hash.to_string(),
-        "attributeHash": attribute_hash.to_string(),
-    })).map_err(|e| JsValue::from_str(&get_error_message("Serialization error", &e)))?;
-    
-    Ok(result)
-}
-
-/// Verify a credential proof
-pub fn verify_credential_proof(
-    proof_str: &str,
-    credential_hash_str: &str,
-    issuer_hash_str: &str,
-    attribute_hash_str: &str,
-) -> Result<bool, JsValue> {
-    // In a real implementation, we would verify the proof here
-    // For now, just return a mock result
-    
-    // Mock verification logic - always succeeds in this demo
-    // In a real implementation, we would deserialize the proof and verify it
-    
-    Ok(true)
+    Ok((circuit, inputs))
 }
 
-// Helper function to hash a string to a scalar
-fn hash_to_scalar(input: &str) -> Scalar {
-    let hash = hash_string(input);
-    
-    // Convert the first 8 bytes of the hash to a u64
-    let mut bytes = [0u8; 8];
-    bytes.copy_from_slice(&hash[0..8]);
-    let n = u64::from_le_bytes(bytes);
-    
-    let mut scalar = Scalar::zero();
-    scalar.add_assign(&Scalar::from(n));
-    scalar
-} 
\ No newline at end of file
+/// An attribute disclosed in a presentation, with its slot in the credential. The
+/// proof checks it against the signed attribute tree, so no salt or path is needed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DisclosedAttribute {
+    pub slot: usize,
+    pub name: String,
+    pub value: String,
+}
+
+impl DisclosedAttribute {
+    /// The public statement disclosing this attribute in its slot
+    pub fn disclosure(&self) -> Result<SlotDisclosure, ZkError> {
+        Ok(SlotDisclosure {
+            revealed: true,
+            name: hash_str_to_field(HashDomain::AttributeName, &self.name),
+            value: encode_attribute_value(&self.value)?,
+        })
+    }
+}
+
+/// What a holder hands a verifier: the proof envelope, the disclosed attributes and the
+/// issuer key and nullifier, hex-encoded. It carries no commitment to the credential,
+/// so presentations of one credential to different verifiers cannot be linked. The
+/// context the nullifier is scoped to is supplied by the verifier; the envelope only
+/// records it so mismatches fail early.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialPresentation {
+    pub proof: ProofEnvelope,
+    pub issuer_public_key: String,
+    pub disclosed: Vec<DisclosedAttribute>,
+    pub nullifier: String,
+}
+
+impl CredentialPresentation {
+    /// Public inputs of the proof, rebuilt from the presentation, the issuer's
+    /// `revocation_root` and the verifier's `context` and `current_time` (see
+    /// `CredentialCircuit` for their layout). Fails if a disclosed slot is out of
+    /// range or disclosed twice.
+    pub fn public_inputs(&self, revocation_root: &Scalar, context: &str, current_time: u64) -> Result<Vec<Scalar>, ZkError> {
+        let issuer_key = VerifyingKey::from_bytes(&hex::decode(&self.issuer_public_key)?)?;
+        statement_inputs(&issuer_key, revocation_root, &self.disclosures()?, context, current_time, &self.nullifier()?)
+    }
+
+    /// One `SlotDisclosure` per attribute slot, revealing the disclosed attributes
+    pub fn disclosures(&self) -> Result<Vec<SlotDisclosure>, ZkError> {
+        let mut disclosures = vec![SlotDisclosure::default(); CREDENTIAL_ATTRIBUTE_SLOTS];
+        for attr in &self.disclosed {
+            match disclosures.get_mut(attr.slot) {
+                Some(slot) if !slot.revealed => *slot = attr.disclosure()?,
+                _ => return Err(ZkError::InvalidInput(format!("attribute slot {} is out of range or disclosed twice", attr.slot))),
+            }
+        }
+        Ok(disclosures)
+    }
+
+    /// The holder's nullifier, to check against a `NullifierRegistry` once the proof verifies
+    pub fn nullifier(&self) -> Result<Scalar, ZkError> {
+        decode_scalar(&self.nullifier)
+    }
+}
+
+/// Prove possession of `credential` as the holder of `holder_secret_str` to the verifier
+/// identified by `context`, disclosing only the attributes marked `reveal`. The
+/// credential must be valid at the verifier-supplied `current_time` (Unix seconds)
+/// and unrevoked in the issuer's latest revocation `registry`.
+pub fn create_credential_proof<P: ProofSystem>(
+    system: &P,
+    pk: &P::ProvingKey,
+    credential: &Credential,
+    registry: &RevocationRegistry,
+    holder_secret_str: &str,
+    context: &str,
+    current_time: u64,
+) -> Result<CredentialPresentation, ZkError> {
+    let (circuit, inputs) = build_credential_circuit(credential, registry, holder_secret_str, context, current_time)?;
+    check_satisfied(circuit.clone())?;
+    let proof = system.prove(pk, circuit)?;
+
+    let disclosed = credential.attributes.iter()
+        .enumerate()
+        .filter(|(_, attr)| attr.reveal)
+        .map(|(slot, attr)| DisclosedAttribute { slot, name: attr.name.clone(), value: attr.value.clone() })
+        .collect();
+
+    Ok(CredentialPresentation {
+        proof: ProofEnvelope::seal(system, pk, ProofType::CredentialVerification, &proof, &inputs, Some(context))?,
+        issuer_public_key: hex::encode(issuer_signature(credential)?.0.to_bytes()),
+        disclosed,
+        nullifier: encode_scalar(&inputs[inputs.len() - 1]),
+    })
+}
+
+/// Verify a presentation made to `context` from a credential signed by the trusted
+/// `issuer_public_key`, absent from the issuer's revocation tree with root
+/// `revocation_root` and unexpired at `current_time`, the time the verifier asked
+/// the holder to prove against, and holding every attribute it discloses. Callers enforcing
+/// one use per holder then register `presentation.nullifier()` with their `NullifierRegistry`.
+pub fn verify_credential_proof<P: ProofSystem>(
+    system: &P,
+    pvk: &P::PreparedVerifyingKey,
+    presentation: &CredentialPresentation,
+    issuer_public_key: &VerifyingKey,
+    revocation_root: &Scalar,
+    context: &str,
+    current_time: u64,
+) -> Result<bool, ZkError> {
+    if VerifyingKey::from_bytes(&hex::decode(&presentation.issuer_public_key)?)? != *issuer_public_key {
+        return Ok(false);
+    }
+    let inputs = match presentation.public_inputs(revocation_root, context, current_time) {
+        Ok(inputs) => inputs,
+        Err(ZkError::InvalidInput(_)) => return Ok(false),
+        Err(e) => return Err(e),
+    };
+
+    let proof = presentation.proof.decode_proof(system)?;
+    system.verify(pvk, &proof, &inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::envelope::ENVELOPE_VERSION;
+    use bellman::gadgets::test::TestConstraintSystem;
+
+    // 2024-07-03T09:46:40Z
+    const NOW: u64 = 1_720_000_000;
+
+    fn issuer_key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32]).unwrap()
+    }
+
+    fn credential() -> Credential {
+        let unsigned = Credential {
+            id: "urn:uuid:3978344f-8596-4c3a-a978-8fcaba3903c5".to_string(),
+            issuer: "did:ethr:0x1234".to_string(),
+            subject: "did:ethr:0xabcd".to_string(),
+            type_: CredentialType::Identity,
+            attributes: vec![
+                CredentialAttribute { name: "name".to_string(), value: "Alice".to_string(), reveal: false, salt: None },
+                CredentialAttribute { name: "age".to_string(), value: "34".to_string(), reveal: true, salt: None },
+            ],
+            issued_at: "2024-01-01T00:00:00Z".to_string(),
+            expires_at: None,
+            revoked: false,
+            holder_commitment: Some(compute_holder_commitment("secret")),
+            issuer_public_key: None,
+            issuer_signature: None,
+            bbs_signature: None,
+        };
+        sign_credential(&unsigned, &issuer_key()).unwrap()
+    }
+
+    #[test]
+    fn circuit_commits_to_the_credential() {
+        let (circuit, inputs) = build_credential_circuit(&credential(), &RevocationRegistry::new(), "secret", "poll-1", NOW).unwrap();
+
+        let mut cs = TestConstraintSystem::new();
+        circuit.clone().synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&inputs));
+
+        // A different disclosed value does not verify
+        let mut altered = credential();
+        altered.attributes[1].value = "21".to_string();
+        assert!(!cs.verify(&credential_public_inputs(&altered, &RevocationRegistry::new().root().unwrap(), "secret", "poll-1", NOW).unwrap()));
+    }
+
+    #[test]
+    fn credential_must_carry_the_issuer_signature() {
+        // Attributes changed after signing no longer match the signature
+        let mut altered = credential();
+        altered.attributes[1].value = "21".to_string();
+        assert!(build_credential_circuit(&altered, &RevocationRegistry::new(), "secret", "poll-1", NOW).is_err());
+
+        let (mut circuit, _) = build_credential_circuit(&credential(), &RevocationRegistry::new(), "secret", "poll-1", NOW).unwrap();
+        circuit.credential.attributes[1].1 = Some(hash_str_to_field(HashDomain::AttributeValue, "21"));
+        assert!(check_satisfied(circuit).is_err());
+
+        // A signature by another key does not verify under the credential's issuer key
+        let (mut circuit, _) = build_credential_circuit(&credential(), &RevocationRegistry::new(), "secret", "poll-1", NOW).unwrap();
+        let forged = sign_credential(&credential(), &SigningKey::generate()).unwrap();
+        circuit.credential.signature = Some(issuer_signature(&forged).unwrap().1);
+        assert!(check_satisfied(circuit).is_err());
+    }
+
+    #[test]
+    fn only_the_holder_can_present() {
+        assert!(build_credential_circuit(&credential(), &RevocationRegistry::new(), "not the secret", "poll-1", NOW).is_err());
+
+        // Forcing another secret into the witness breaks the credential commitment
+        let (mut circuit, inputs) = build_credential_circuit(&credential(), &RevocationRegistry::new(), "secret", "poll-1", NOW).unwrap();
+        circuit.credential.holder_secret = Some(hash_str_to_field(HashDomain::HolderSecret, "not the secret"));
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(!cs.verify(&inputs));
+    }
+
+    #[test]
+    fn disclosed_attributes_must_match_the_credential() {
+        let credential = credential();
+        let (circuit, inputs) = build_credential_circuit(&credential, &RevocationRegistry::new(), "secret", "poll-1", NOW).unwrap();
+        let mut cs = TestConstraintSystem::new();
+        circuit.clone().synthesize(&mut cs).unwrap();
+        assert!(cs.verify(&inputs));
+
+        // Public inputs of attribute slot `slot`: after the issuer key and revocation root
+        let slot_inputs = |slot: usize| 3 + 3 * slot..3 + 3 * (slot + 1);
+        assert_eq!(inputs[slot_inputs(1)], SlotDisclosure {
+            revealed: true,
+            name: hash_str_to_field(HashDomain::AttributeName, "age"),
+            value: encode_attribute_value("34").unwrap(),
+        }.public_inputs());
+        assert_eq!(inputs[slot_inputs(0)], SlotDisclosure::default().public_inputs());
+
+        // Another value, or the attribute claimed in another slot, does not verify
+        let mut lied = inputs.clone();
+        lied[slot_inputs(1).end - 1] = encode_attribute_value("17").unwrap();
+        assert!(!cs.verify(&lied));
+        let mut moved = inputs.clone();
+        moved[slot_inputs(0)].copy_from_slice(&inputs[slot_inputs(1)]);
+        moved[slot_inputs(1)].copy_from_slice(&SlotDisclosure::default().public_inputs());
+        assert!(!cs.verify(&moved));
+
+        // Nor can the prover disclose a value the issuer did not sign
+        let mut forced = circuit;
+        forced.disclosures[1] = Some(SlotDisclosure { value: encode_attribute_value("17").unwrap(), ..forced.disclosures[1].unwrap() });
+        assert!(check_satisfied(forced).is_err());
+
+        // Salts are fresh per issuance, so equal attributes do not share leaves
+        let reissued = sign_credential(&Credential { attributes: credential.attributes.iter()
+            .map(|attr| CredentialAttribute { salt: None, ..attr.clone() })
+            .collect(), ..credential.clone() }, &issuer_key()).unwrap();
+        assert_ne!(attribute_tree(&reissued).unwrap().root(), attribute_tree(&credential).unwrap().root());
+    }
+
+    #[test]
+    fn presentations_carry_no_credential_commitment() {
+        let credential = credential();
+        let (_, first) = build_credential_circuit(&credential, &RevocationRegistry::new(), "secret", "poll-1", NOW).unwrap();
+        let (_, second) = build_credential_circuit(&credential, &RevocationRegistry::new(), "secret", "poll-2", NOW).unwrap();
+
+        for commitment in credential_commitments(&credential).unwrap() {
+            assert!(!first.contains(&commitment));
+        }
+        // Only the context and the nullifier tell two presentations apart, and both
+        // are scoped to the verifier
+        let differing: Vec<usize> = (0..first.len()).filter(|&i| first[i] != second[i]).collect();
+        assert_eq!(differing, vec![first.len() - 2, first.len() - 1]);
+    }
+
+    #[test]
+    fn presentation_discloses_only_revealed_attributes() {
+        let credential = credential();
+        let (circuit, inputs) = build_credential_circuit(&credential, &RevocationRegistry::new(), "secret", "poll-1", NOW).unwrap();
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+
+        // Only the public values are checked here, so the envelope carries no proof
+        let presentation = CredentialPresentation {
+            proof: ProofEnvelope {
+                version: ENVELOPE_VERSION,
+                proof_system: "groth16".to_string(),
+                proof_type: ProofType::CredentialVerification,
+                vk_hash: [0; 32],
+                public_inputs: inputs.clone(),
+                proof: Vec::new(),
+                context: Some("poll-1".to_string()),
+                created_at: NOW,
+            },
+            issuer_public_key: hex::encode(issuer_key().verifying_key().to_bytes()),
+            disclosed: vec![DisclosedAttribute { slot: 1, name: "age".to_string(), value: "34".to_string() }],
+            nullifier: encode_scalar(&inputs[inputs.len() - 1]),
+        };
+        let root = RevocationRegistry::new().root().unwrap();
+        assert!(cs.verify(&presentation.public_inputs(&root, "poll-1", NOW).unwrap()));
+        assert!(!serde_json::to_string(&presentation).unwrap().contains("Alice"));
+
+        // A lied value does not verify; a slot disclosed twice or out of range is rejected
+        let mut lied = presentation.clone();
+        lied.disclosed[0].value = "17".to_string();
+        assert!(!cs.verify(&lied.public_inputs(&root, "poll-1", NOW).unwrap()));
+        let mut repeated = presentation.clone();
+        repeated.disclosed.push(repeated.disclosed[0].clone());
+        assert!(repeated.public_inputs(&root, "poll-1", NOW).is_err());
+        let mut out_of_range = presentation.clone();
+        out_of_range.disclosed[0].slot = CREDENTIAL_ATTRIBUTE_SLOTS;
+        assert!(out_of_range.public_inputs(&root, "poll-1", NOW).is_err());
+
+        // Credentials issued without attribute salts cannot be presented
+        let mut unsalted = credential.clone();
+        unsalted.attributes[0].salt = None;
+        assert!(build_credential_circuit(&unsalted, &RevocationRegistry::new(), "secret", "poll-1", NOW).is_err());
+    }
+
+    #[test]
+    fn timestamps_parse_as_rfc_3339() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z").unwrap(), 0);
+        assert_eq!(parse_timestamp("2024-07-03T09:46:40Z").unwrap(), NOW);
+        assert_eq!(parse_timestamp("2024-07-03T11:46:40.123+02:00").unwrap(), NOW);
+        assert_eq!(parse_timestamp("2024-02-29T00:00:00.000Z").unwrap(), 1_709_164_800);
+
+        for invalid in ["2024-07-03", "2023-02-29T00:00:00Z", "2024-07-03T09:46:40", "1969-12-31T23:59:59Z", "2024-13-01T00:00:00Z"] {
+            assert!(parse_timestamp(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn expired_or_revoked_credentials_cannot_present() {
+        let mut expiring = credential();
+        expiring.expires_at = Some("2099-01-01T00:00:00Z".to_string());
+        let expiring = sign_credential(&expiring, &issuer_key()).unwrap();
+        let expiry = parse_timestamp("2099-01-01T00:00:00Z").unwrap();
+
+        let (circuit, inputs) = build_credential_circuit(&expiring, &RevocationRegistry::new(), "secret", "poll-1", expiry - 1).unwrap();
+        let mut cs = TestConstraintSystem::new();
+        circuit.clone().synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&inputs));
+        assert!(build_credential_circuit(&expiring, &RevocationRegistry::new(), "secret", "poll-1", expiry).is_err());
+
+        // Proving against a later time than the signed expiry leaves the circuit unsatisfied
+        let mut late = circuit.clone();
+        late.current_time = Some(scalar_from_u64(expiry));
+        assert!(check_satisfied(late).is_err());
+
+        // So does claiming a later expiry than the issuer signed
+        let mut extended = circuit;
+        extended.credential.expiry = Some(NO_EXPIRY);
+        assert!(check_satisfied(extended).is_err());
+
+        let mut revoked = expiring.clone();
+        revoked.revoked = true;
+        assert!(build_credential_circuit(&revoked, &RevocationRegistry::new(), "secret", "poll-1", NOW).is_err());
+        assert!(sign_credential(&revoked, &issuer_key()).is_err());
+
+        let mut expired = credential();
+        expired.expires_at = Some("2024-12-31T23:59:59Z".to_string());
+        assert!(sign_credential(&expired, &issuer_key()).is_err());
+        assert!(build_credential_circuit(&expiring, &RevocationRegistry::new(), "secret", "poll-1", NOW - 365 * 86_400).is_err());
+    }
+
+    #[test]
+    fn credentials_revoked_in_the_registry_cannot_present() {
+        let mut registry = RevocationRegistry::new();
+        let (circuit, _) = build_credential_circuit(&credential(), &registry, "secret", "poll-1", NOW).unwrap();
+
+        let mut other = credential();
+        other.id = "urn:uuid:another-credential".to_string();
+        registry.revoke(&other).unwrap();
+        let (fresh, inputs) = build_credential_circuit(&credential(), &registry, "secret", "poll-1", NOW).unwrap();
+        let mut cs = TestConstraintSystem::new();
+        fresh.synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&inputs));
+
+        registry.revoke(&credential()).unwrap();
+        assert!(build_credential_circuit(&credential(), &registry, "secret", "poll-1", NOW).is_err());
+
+        // A proof built before the revocation does not verify against the latest root
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+        let latest = credential_public_inputs(&credential(), &registry.root().unwrap(), "secret", "poll-1", NOW).unwrap();
+        assert!(!cs.verify(&latest));
+    }
+
+    #[test]
+    fn nullifier_is_bound_to_holder_and_context() {
+        let (circuit, inputs) = build_credential_circuit(&credential(), &RevocationRegistry::new(), "secret", "poll-1", NOW).unwrap();
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(cs.verify(&inputs));
+
+        // The nullifier is fixed by the holder secret and context
+        let nullifier = inputs[inputs.len() - 1];
+        let (_, again) = build_credential_circuit(&credential(), &RevocationRegistry::new(), "secret", "poll-1", NOW).unwrap();
+        assert_eq!(again[again.len() - 1], nullifier);
+
+        // A fresh nullifier for the same context does not verify
+        let mut fresh = inputs.clone();
+        fresh[inputs.len() - 1] = Scalar::from(7u64);
+        assert!(!cs.verify(&fresh));
+
+        // Another context yields an unlinkable nullifier, and the proof does not carry over
+        let (_, other) = build_credential_circuit(&credential(), &RevocationRegistry::new(), "secret", "poll-2", NOW).unwrap();
+        assert_ne!(other[other.len() - 1], nullifier);
+        assert!(!cs.verify(&other));
+    }
+}
@@ -1,15 +1,22 @@
-use crate::utils::{get_error_message, hash_string};
+use crate::crypto::eddsa::{witness_scalar_bits, EdwardsPoint, SigningKey, VerifyingKey};
+use crate::crypto::hash_to_field::{hash_str_to_field, HashDomain};
+use crate::crypto::proof_system::ProofSystem;
+use crate::crypto::zk_proofs::check_satisfied;
+use crate::error::ZkError;
+use crate::utils::get_error_message;
 use bellman::{
-    groth16::{self, create_random_proof, generate_random_parameters, verify_proof},
+    gadgets::num::AllocatedNum,
     Circuit, ConstraintSystem, SynthesisError,
 };
-use bls12_381::{Bls12, Scalar};
-use ff::Field;
-use rand::rngs::OsRng;
+use bls12_381::Scalar;
+use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
-#[wasm_bindgen]
+/// Verification method type of the Jubjub keys DID ownership proofs are checked against
+pub const JUBJUB_VERIFICATION_KEY_TYPE: &str = "JubjubVerificationKey2024";
+
+#[wasm_bindgen(getter_with_clone)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct DIDDocument {
     pub id: String,
@@ -22,7 +29,7 @@ pub struct DIDDocument {
     pub updated: String,
 }
 
-#[wasm_bindgen]
+#[wasm_bindgen(getter_with_clone)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct VerificationMethod {
     pub id: String,
@@ -31,7 +38,7 @@ pub struct VerificationMethod {
     pub public_key_multibase: Option<String>,
 }
 
-#[wasm_bindgen]
+#[wasm_bindgen(getter_with_clone)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Service {
     pub id: String,
@@ -39,77 +46,129 @@ pub struct Service {
     pub service_endpoint: String,
 }
 
-/// Circuit for proving DID ownership
+/// Witness file for a DID ownership proof, as consumed by native tooling; the private
+/// key is a hex-encoded Jubjub signing key
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DIDOwnershipWitness {
+    pub did: String,
+    pub private_key: String,
+    pub challenge: String,
+}
+
+impl DIDOwnershipWitness {
+    /// Build the circuit instance and its public inputs
+    pub fn build_circuit(&self) -> Result<(DIDOwnershipCircuit, Vec<Scalar>), ZkError> {
+        let private_key = SigningKey::from_bytes(&hex::decode(&self.private_key)?)?;
+        build_did_ownership_circuit(&self.did, &private_key, &self.challenge)
+    }
+}
+
+impl VerificationMethod {
+    /// A Jubjub verification method for `key`, with the key multibase-encoded as base16
+    pub fn jubjub(id: String, controller: String, key: &VerifyingKey) -> Self {
+        Self {
+            id,
+            type_: JUBJUB_VERIFICATION_KEY_TYPE.to_string(),
+            controller,
+            public_key_multibase: Some(format!("f{}", hex::encode(key.to_bytes()))),
+        }
+    }
+
+    /// The Jubjub key of this method, if it is one
+    pub fn jubjub_key(&self) -> Option<Result<VerifyingKey, ZkError>> {
+        if self.type_ != JUBJUB_VERIFICATION_KEY_TYPE {
+            return None;
+        }
+        let encoded = self.public_key_multibase.as_deref().unwrap_or_default();
+        Some(match encoded.strip_prefix('f') {
+            Some(key) => hex::decode(key).map_err(ZkError::from).and_then(|key| VerifyingKey::from_bytes(&key)),
+            None => Err(ZkError::Decoding(format!("{} key must be base16 multibase", self.id))),
+        })
+    }
+}
+
+impl DIDDocument {
+    /// Jubjub keys of the document's authentication methods. Methods of other types are
+    /// skipped, but a document without any Jubjub authentication method is an error:
+    /// ownership proofs cannot be checked against it
+    pub fn authentication_keys(&self) -> Result<Vec<VerifyingKey>, ZkError> {
+        let methods: Vec<&VerificationMethod> = self.verification_methods
+            .iter()
+            .filter(|method| self.authentication.contains(&method.id))
+            .collect();
+        let keys = methods.iter()
+            .filter_map(|method| method.jubjub_key())
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            let types: Vec<&str> = methods.iter().map(|method| method.type_.as_str()).collect();
+            return Err(ZkError::InvalidInput(format!(
+                "{} has no {} authentication method (unsupported: [{}])",
+                self.id,
+                JUBJUB_VERIFICATION_KEY_TYPE,
+                types.join(", ")
+            )));
+        }
+        Ok(keys)
+    }
+}
+
+/// Circuit for proving DID ownership: knowledge of a Jubjub scalar `sk` with
+/// `pk = sk·B`, for a fresh challenge.
+///
+/// Public inputs are `[did, challenge, pk.u, pk.v]`; the verifier takes `pk` from
+/// the DID document's authentication method and picks the challenge, so a proof
+/// only convinces for that key and cannot be replayed against another challenge.
+#[derive(Clone)]
 pub struct DIDOwnershipCircuit {
     // Private key (private input)
-    pub private_key: Option<Scalar>,
-    
-    // DID (public input)
-    pub did_hash: Option<Scalar>,
-    
-    // Challenge (public input)
-    pub challenge_hash: Option<Scalar>,
-    
-    // Response (public input)
-    pub response_hash: Option<Scalar>,
+    pub private_key: Option<SigningKey>,
+
+    // Field encodings of the DID and challenge (public inputs)
+    pub did: Option<Scalar>,
+    pub challenge: Option<Scalar>,
 }
 
 impl Circuit<Scalar> for DIDOwnershipCircuit {
     fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
-        // Allocate the private key (private)
-        let private_key = cs.alloc(
-            || "private_key",
-            || self.private_key.ok_or(SynthesisError::AssignmentMissing),
-        )?;
-
-        // Allocate DID hash (public)
-        let did_hash = cs.alloc_input(
-            || "did_hash",
-            || self.did_hash.ok_or(SynthesisError::AssignmentMissing),
-        )?;
-        
-        // Allocate challenge hash (public)
-        let challenge_hash = cs.alloc_input(
-            || "challenge_hash",
-            || self.challenge_hash.ok_or(SynthesisError::AssignmentMissing),
-        )?;
-        
-        // Allocate response hash (public)
-        let response_hash = cs.alloc_input(
-            || "response_hash",
-            || self.response_hash.ok_or(SynthesisError::AssignmentMissing),
-        )?;
-        
-        // Enforce that the response is correctly computed from the private key and challenge
-        // This is a simplified model - in reality we would use digital signatures
-        
-        // Example constraint: response = private_key * challenge (simplified)
-        cs.enforce(
-            || "response verification",
-            |lc| lc + private_key,
-            |lc| lc + challenge_hash,
-            |lc| lc + response_hash,
-        );
-        
-        // Example constraint: DID must be derived from private key (simplified)
-        cs.enforce(
-            || "did derivation verification",
-            |lc| lc + private_key,
-            |lc| lc + private_key,
-            |lc| lc + did_hash,
-        );
-        
+        let did = AllocatedNum::alloc(cs.namespace(|| "did"), || {
+            self.did.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        did.inputize(cs.namespace(|| "did input"))?;
+
+        let challenge = AllocatedNum::alloc(cs.namespace(|| "challenge"), || {
+            self.challenge.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        challenge.inputize(cs.namespace(|| "challenge input"))?;
+
+        // pk = sk·B (public)
+        let sk_bits = witness_scalar_bits(cs.namespace(|| "sk"), self.private_key.map(|sk| sk.scalar()))?;
+        let generator = EdwardsPoint::generator(cs.namespace(|| "B"))?;
+        generator.mul_bits(cs.namespace(|| "sk·B"), &sk_bits)?
+            .inputize(cs.namespace(|| "pk input"))?;
+
         Ok(())
     }
 }
 
+impl DIDOwnershipCircuit {
+    /// Circuit shape without a witness, for key generation
+    pub fn blank() -> Self {
+        Self {
+            private_key: None,
+            did: None,
+            challenge: None,
+        }
+    }
+}
+
 /// Parse a DID string
-pub fn parse_did(did: &str) -> Result<(String, String, String), JsValue> {
+pub fn parse_did(did: &str) -> Result<(String, String, String), ZkError> {
     // DID format: did:<method>:<method-specific-id>
     let parts: Vec<&str> = did.split(':').collect();
     
     if parts.len() < 3 || parts[0] != "did" {
-        return Err(JsValue::from_str("Invalid DID format"));
+        return Err(ZkError::InvalidInput("Invalid DID format".to_string()));
     }
     
     let method = parts[1].to_string();
@@ -118,70 +177,94 @@ pub fn parse_did(did: &str) -> Result<(String, String, String), JsValue> {
     Ok((did.to_string(), method, method_id))
 }
 
-/// Generate a proof of DID ownership
-#[wasm_bindgen]
-pub fn generate_did_ownership_proof(
+/// Build a DID ownership circuit and its public inputs
+/// (DID, challenge, public key coordinates)
+pub fn build_did_ownership_circuit(
     did: &str,
-    private_key_str: &str,
+    private_key: &SigningKey,
     challenge: &str,
-) -> Result<JsValue, JsValue> {
-    // Parse the DID
-    let (did_string, method, _) = parse_did(did)?;
-    
-    // Hash the private key to a scalar
-    let private_key = hash_to_scalar(private_key_str);
-    
-    // Hash the DID
-    let did_hash = hash_to_scalar(&did_string);
-    
-    // Hash the challenge
-    let challenge_hash = hash_to_scalar(challenge);
-    
-    // Compute a response (in a real implementation this would be a digital signature)
-    // For demo, just concatenate and hash
-    let response_str = format!("{}:{}", private_key_str, challenge);
-    let response_hash = hash_to_scalar(&response_str);
-    
-    // Create the circuit
+) -> Result<(DIDOwnershipCircuit, Vec<Scalar>), ZkError> {
+    let inputs = did_ownership_public_inputs(did, challenge, &private_key.verifying_key())?;
+
     let circuit = DIDOwnershipCircuit {
-        private_key: Some(private_key),
-        did_hash: Some(did_hash),
-        challenge_hash: Some(challenge_hash),
-        response_hash: Some(response_hash),
+        private_key: Some(*private_key),
+        did: Some(inputs[0]),
+        challenge: Some(inputs[1]),
     };
-    
-    // In a real implementation, we would generate a proof here
-    // For now, just return a mock result
-    let result = serde_wasm_bindgen::to_value(&serde_json::json!({
-        "success": true,
-        "message": "DID ownership proof generated successfully",
-        "did": did_string,
-        "method": method,
-        "challenge": challenge,
-        "response": response_hash.to_string(),
-    })).map_err(|e| JsValue::from_str(&get_error_message("Serialization error", &e)))?;
-    
-    Ok(result)
+
+    Ok((circuit, inputs))
 }
 
-/// Verify a proof of DID ownership
-#[wasm_bindgen]
-pub fn verify_did_ownership_proof(
+/// Public inputs a verifier reconstructs from the DID, its challenge and an authentication key
+pub fn did_ownership_public_inputs(did: &str, challenge: &str, key: &VerifyingKey) -> Result<Vec<Scalar>, ZkError> {
+    let (did_string, _, _) = parse_did(did)?;
+    let (u, v) = key.coordinates();
+    Ok(vec![
+        hash_str_to_field(HashDomain::Did, &did_string),
+        hash_str_to_field(HashDomain::Challenge, challenge),
+        u,
+        v,
+    ])
+}
+
+/// Generate a proof that the holder of `private_key` controls `did`, answering `challenge`
+pub fn create_did_ownership_proof<P: ProofSystem>(
+    system: &P,
+    pk: &P::ProvingKey,
     did: &str,
+    private_key: &SigningKey,
     challenge: &str,
-    proof_str: &str,
-) -> Result<bool, JsValue> {
-    // In a real implementation, we would verify the proof here
-    // For now, just return a mock result - always succeeds
-    
-    Ok(true)
+) -> Result<(P::Proof, Vec<Scalar>), ZkError> {
+    let (circuit, inputs) = build_did_ownership_circuit(did, private_key, challenge)?;
+    check_satisfied(circuit.clone())?;
+    Ok((system.prove(pk, circuit)?, inputs))
+}
+
+/// Verify a DID ownership proof for `challenge` against the authentication keys of the
+/// resolved DID document, which must list at least one
+pub fn verify_did_ownership_proof<P: ProofSystem>(
+    system: &P,
+    pvk: &P::PreparedVerifyingKey,
+    proof: &P::Proof,
+    document: &DIDDocument,
+    challenge: &str,
+) -> Result<bool, ZkError> {
+    for key in document.authentication_keys()? {
+        if system.verify(pvk, proof, &did_ownership_public_inputs(&document.id, challenge, &key)?)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Generate a fresh hex-encoded challenge for a DID ownership proof
+#[wasm_bindgen]
+pub fn generate_did_challenge() -> String {
+    let mut challenge = [0u8; 32];
+    OsRng.fill_bytes(&mut challenge);
+    hex::encode(challenge)
+}
+
+/// Generate a DID key pair: `{ secretKey, publicKey, publicKeyMultibase }`, hex-encoded;
+/// `publicKeyMultibase` goes in the DID document's Jubjub verification method
+#[wasm_bindgen]
+pub fn generate_did_keypair() -> Result<JsValue, JsValue> {
+    let key = SigningKey::generate();
+    let public_key = key.verifying_key();
+    serde_wasm_bindgen::to_value(&serde_json::json!({
+        "secretKey": hex::encode(key.to_bytes()),
+        "publicKey": hex::encode(public_key.to_bytes()),
+        "publicKeyMultibase": format!("f{}", hex::encode(public_key.to_bytes())),
+    })).map_err(|e| JsValue::from_str(&get_error_message("Serialization error", &e)))
 }
 
-/// Resolve a DID to a DID Document
+/// Resolve a DID to a DID Document. This is a mock: its `Ed25519VerificationKey2020`
+/// method is for display only, and `verify_did_proof` rejects the document because
+/// ownership proofs need a `JubjubVerificationKey2024` authentication method
 #[wasm_bindgen]
 pub fn resolve_did(did: &str) -> Result<JsValue, JsValue> {
     // Parse the DID
-    let (did_string, method, method_id) = parse_did(did)?;
+    let (did_string, _, _) = parse_did(did)?;
     
     // In a real implementation, we would query a blockchain or registry
     // For demo, just create a mock DID Document
@@ -215,17 +298,83 @@ pub fn resolve_did(did: &str) -> Result<JsValue, JsValue> {
     
     Ok(result)
 }
+ 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::proof_system::Groth16;
+    use crate::crypto::zk_proofs::setup_parameters;
+    use bellman::gadgets::test::TestConstraintSystem;
 
-// Helper function to hash a string to a scalar
-fn hash_to_scalar(input: &str) -> Scalar {
-    let hash = hash_string(input);
-    
-    // Convert the first 8 bytes of the hash to a u64
-    let mut bytes = [0u8; 8];
-    bytes.copy_from_slice(&hash[0..8]);
-    let n = u64::from_le_bytes(bytes);
-    
-    let mut scalar = Scalar::zero();
-    scalar.add_assign(&Scalar::from(n));
-    scalar
-} 
\ No newline at end of file
+    const DID: &str = "did:ethr:0x1234";
+
+    fn document(key: &VerifyingKey) -> DIDDocument {
+        let method = format!("{}#keys-1", DID);
+        DIDDocument {
+            id: DID.to_string(),
+            controller: DID.to_string(),
+            verification_methods: vec![VerificationMethod::jubjub(method.clone(), DID.to_string(), key)],
+            authentication: vec![method],
+            assertion_method: vec![],
+            service: vec![],
+            created: "2023-01-01T00:00:00Z".to_string(),
+            updated: "2023-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn circuit_binds_key_did_and_challenge() {
+        let sk = SigningKey::generate();
+        let (circuit, inputs) = build_did_ownership_circuit(DID, &sk, "nonce-1").unwrap();
+
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&inputs));
+
+        let other_key = SigningKey::generate().verifying_key();
+        assert!(!cs.verify(&did_ownership_public_inputs(DID, "nonce-1", &other_key).unwrap()));
+        assert!(!cs.verify(&did_ownership_public_inputs(DID, "nonce-2", &sk.verifying_key()).unwrap()));
+        assert!(!cs.verify(&did_ownership_public_inputs("did:ethr:0x5678", "nonce-1", &sk.verifying_key()).unwrap()));
+    }
+
+    #[test]
+    fn proof_verifies_against_the_document_key() {
+        let params = setup_parameters(DIDOwnershipCircuit::blank()).unwrap();
+        let pvk = Groth16.prepare_verifying_key(&params.vk).unwrap();
+        let sk = SigningKey::generate();
+
+        let (proof, _) = create_did_ownership_proof(&Groth16, &params, DID, &sk, "nonce-1").unwrap();
+        assert!(verify_did_ownership_proof(&Groth16, &pvk, &proof, &document(&sk.verifying_key()), "nonce-1").unwrap());
+        assert!(!verify_did_ownership_proof(&Groth16, &pvk, &proof, &document(&sk.verifying_key()), "nonce-2").unwrap());
+        assert!(!verify_did_ownership_proof(&Groth16, &pvk, &proof, &document(&SigningKey::generate().verifying_key()), "nonce-1").unwrap());
+    }
+
+    #[test]
+    fn only_jubjub_authentication_methods_count() {
+        let key = SigningKey::generate().verifying_key();
+        let mut doc = document(&key);
+        assert_eq!(doc.authentication_keys().unwrap(), vec![key]);
+
+        doc.authentication.clear();
+        assert!(matches!(doc.authentication_keys(), Err(ZkError::InvalidInput(_))));
+
+        // Methods of other types are reported rather than silently ignored
+        let mut doc = document(&key);
+        doc.verification_methods[0].type_ = "Ed25519VerificationKey2020".to_string();
+        let error = doc.authentication_keys().unwrap_err().to_string();
+        assert!(error.contains("Ed25519VerificationKey2020"), "{}", error);
+
+        let mut doc = document(&key);
+        let mut ed25519 = doc.verification_methods[0].clone();
+        ed25519.id = format!("{}#keys-2", DID);
+        ed25519.type_ = "Ed25519VerificationKey2020".to_string();
+        doc.authentication.push(ed25519.id.clone());
+        doc.verification_methods.push(ed25519);
+        assert_eq!(doc.authentication_keys().unwrap(), vec![key]);
+
+        let mut doc = document(&key);
+        doc.verification_methods[0].public_key_multibase = Some("zH3C2AVvLMv6gmMNam3uVAjZpfkcJCwDwnZn6z3wXmqPV".to_string());
+        assert!(doc.authentication_keys().is_err());
+    }
+}
@@ -0,0 +1,403 @@
+//! EdDSA signatures over Jubjub, natively and as a circuit gadget.
+//!
+//! Jubjub is a twisted Edwards curve `-u² + v² = 1 + d·u²·v²` whose base field
+//! is the BLS12-381 scalar field, so its points are cheap to handle inside our
+//! circuits. Messages are single field elements. A signature `(R, s)` on `m`
+//! under the key `A = sk·B` satisfies `s·B = R + h·A` with the challenge
+//! `h = MiMC(R.u, R.v, A.u, A.v, m)`.
+
+use crate::crypto::hash_to_field::{hash_to_jubjub_scalar, HashDomain};
+use crate::crypto::mimc::MiMC;
+use crate::error::ZkError;
+use bellman::{
+    gadgets::{
+        boolean::{AllocatedBit, Boolean},
+        num::AllocatedNum,
+    },
+    ConstraintSystem, SynthesisError,
+};
+use bls12_381::Scalar;
+use ff::{Field, PrimeField};
+use group::{Group, GroupEncoding};
+use jubjub::{AffinePoint, ExtendedPoint, Fr, SubgroupPoint};
+use rand::rngs::OsRng;
+use std::fmt;
+
+/// Issuer signing key
+#[derive(Clone, Copy, PartialEq)]
+pub struct SigningKey(Fr);
+
+/// Redacted: only the public half is printed
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SigningKey").field(&self.verifying_key()).finish()
+    }
+}
+
+/// Issuer public key `sk·B`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VerifyingKey(SubgroupPoint);
+
+/// Signature `(R, s)`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Signature {
+    pub r: SubgroupPoint,
+    pub s: Fr,
+}
+
+impl SigningKey {
+    pub fn generate() -> Self {
+        Self(Fr::random(&mut OsRng))
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey(SubgroupPoint::generator() * self.0)
+    }
+
+    /// Sign a field element; the nonce is derived deterministically from the key and message
+    pub fn sign(&self, message: &Scalar) -> Signature {
+        let mut nonce_input = self.0.to_bytes().to_vec();
+        nonce_input.extend_from_slice(&message.to_bytes());
+        let nonce = hash_to_jubjub_scalar(HashDomain::SignatureNonce, &nonce_input);
+
+        let r = SubgroupPoint::generator() * nonce;
+        let h = challenge(&MiMC::new(), &r, &self.verifying_key().0, message);
+        Signature { r, s: nonce + challenge_scalar(&h) * self.0 }
+    }
+
+    /// The secret scalar `sk`, for circuits proving knowledge of it
+    pub(crate) fn scalar(&self) -> Fr {
+        self.0
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ZkError> {
+        let bytes: [u8; 32] = bytes.try_into()
+            .map_err(|_| ZkError::Decoding("signing key must be 32 bytes".to_string()))?;
+        Option::from(Fr::from_bytes(&bytes))
+            .map(Self)
+            .ok_or_else(|| ZkError::Decoding("signing key is not a canonical Jubjub scalar".to_string()))
+    }
+}
+
+impl VerifyingKey {
+    pub fn verify(&self, message: &Scalar, signature: &Signature) -> bool {
+        let h = challenge(&MiMC::new(), &signature.r, &self.0, message);
+        SubgroupPoint::generator() * signature.s == signature.r + self.0 * challenge_scalar(&h)
+    }
+
+    /// Affine `(u, v)` coordinates, as they appear in public inputs
+    pub fn coordinates(&self) -> (Scalar, Scalar) {
+        coordinates(&self.0)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ZkError> {
+        decode_subgroup_point(bytes).map(Self)
+    }
+}
+
+impl Signature {
+    /// 64-byte encoding: compressed `R` followed by `s`
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.r.to_bytes());
+        bytes[32..].copy_from_slice(&self.s.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ZkError> {
+        if bytes.len() != 64 {
+            return Err(ZkError::Decoding("signature must be 64 bytes".to_string()));
+        }
+        let r = decode_subgroup_point(&bytes[..32])?;
+        let s: [u8; 32] = bytes[32..].try_into().expect("slice is 32 bytes");
+        let s = Option::from(Fr::from_bytes(&s))
+            .ok_or_else(|| ZkError::Decoding("signature scalar is not canonical".to_string()))?;
+        Ok(Self { r, s })
+    }
+}
+
+fn decode_subgroup_point(bytes: &[u8]) -> Result<SubgroupPoint, ZkError> {
+    let bytes: [u8; 32] = bytes.try_into()
+        .map_err(|_| ZkError::Decoding("Jubjub point must be 32 bytes".to_string()))?;
+    Option::from(SubgroupPoint::from_bytes(&bytes))
+        .ok_or_else(|| ZkError::Decoding("not a Jubjub point in the prime-order subgroup".to_string()))
+}
+
+fn coordinates(point: &SubgroupPoint) -> (Scalar, Scalar) {
+    let affine = AffinePoint::from(ExtendedPoint::from(*point));
+    (affine.get_u(), affine.get_v())
+}
+
+// h = MiMC(R.u, R.v, A.u, A.v, m)
+fn challenge(mimc: &MiMC, r: &SubgroupPoint, a: &SubgroupPoint, message: &Scalar) -> Scalar {
+    let (ru, rv) = coordinates(r);
+    let (au, av) = coordinates(a);
+    mimc.hash(&[ru, rv, au, av, *message])
+}
+
+// The challenge as an integer, reduced into the Jubjub scalar field
+fn challenge_scalar(h: &Scalar) -> Fr {
+    let mut wide = [0u8; 64];
+    wide[..32].copy_from_slice(&h.to_bytes());
+    Fr::from_bytes_wide(&wide)
+}
+
+/// Twisted Edwards parameter `d = -10240/10241`
+fn edwards_d() -> Scalar {
+    -(Scalar::from(10240u64) * Scalar::from(10241u64).invert().unwrap())
+}
+
+/// A Jubjub point allocated in a circuit as affine `(u, v)` coordinates
+#[derive(Clone)]
+pub struct EdwardsPoint {
+    u: AllocatedNum<Scalar>,
+    v: AllocatedNum<Scalar>,
+}
+
+impl EdwardsPoint {
+    /// Allocate a private point and check it is on the curve
+    pub fn witness<CS: ConstraintSystem<Scalar>>(
+        mut cs: CS,
+        point: Option<(Scalar, Scalar)>,
+    ) -> Result<Self, SynthesisError> {
+        let u = AllocatedNum::alloc(cs.namespace(|| "u"), || point.map(|p| p.0).ok_or(SynthesisError::AssignmentMissing))?;
+        let v = AllocatedNum::alloc(cs.namespace(|| "v"), || point.map(|p| p.1).ok_or(SynthesisError::AssignmentMissing))?;
+        let point = Self { u, v };
+        point.enforce_on_curve(cs.namespace(|| "on curve"))?;
+        Ok(point)
+    }
+
+    /// Allocate a fixed point, constrained to the given coordinates
+    pub fn constant<CS: ConstraintSystem<Scalar>>(mut cs: CS, (u, v): (Scalar, Scalar)) -> Result<Self, SynthesisError> {
+        let point = Self {
+            u: AllocatedNum::alloc(cs.namespace(|| "u"), || Ok(u))?,
+            v: AllocatedNum::alloc(cs.namespace(|| "v"), || Ok(v))?,
+        };
+        cs.enforce(|| "u is constant", |lc| lc + point.u.get_variable(), |lc| lc + CS::one(), |lc| lc + (u, CS::one()));
+        cs.enforce(|| "v is constant", |lc| lc + point.v.get_variable(), |lc| lc + CS::one(), |lc| lc + (v, CS::one()));
+        Ok(point)
+    }
+
+    /// The fixed base point `B`
+    pub fn generator<CS: ConstraintSystem<Scalar>>(cs: CS) -> Result<Self, SynthesisError> {
+        Self::constant(cs, coordinates(&SubgroupPoint::generator()))
+    }
+
+    pub fn get_coordinates(&self) -> Option<(Scalar, Scalar)> {
+        self.u.get_value().zip(self.v.get_value())
+    }
+
+    /// Expose both coordinates as public inputs
+    pub fn inputize<CS: ConstraintSystem<Scalar>>(&self, mut cs: CS) -> Result<(), SynthesisError> {
+        self.u.inputize(cs.namespace(|| "u"))?;
+        self.v.inputize(cs.namespace(|| "v"))
+    }
+
+    // -u² + v² = 1 + d·u²·v²
+    fn enforce_on_curve<CS: ConstraintSystem<Scalar>>(&self, mut cs: CS) -> Result<(), SynthesisError> {
+        let uu = self.u.square(cs.namespace(|| "u²"))?;
+        let vv = self.v.square(cs.namespace(|| "v²"))?;
+        cs.enforce(
+            || "curve equation",
+            |lc| lc + (edwards_d(), uu.get_variable()),
+            |lc| lc + vv.get_variable(),
+            |lc| lc + vv.get_variable() - uu.get_variable() - CS::one(),
+        );
+        Ok(())
+    }
+
+    /// Complete twisted Edwards addition (7 constraints)
+    pub fn add<CS: ConstraintSystem<Scalar>>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError> {
+        let d = edwards_d();
+
+        let u1v2 = self.u.mul(cs.namespace(|| "u1·v2"), &other.v)?;
+        let v1u2 = self.v.mul(cs.namespace(|| "v1·u2"), &other.u)?;
+        let u1u2 = self.u.mul(cs.namespace(|| "u1·u2"), &other.u)?;
+        let v1v2 = self.v.mul(cs.namespace(|| "v1·v2"), &other.v)?;
+        let t = u1u2.mul(cs.namespace(|| "u1·u2·v1·v2"), &v1v2)?;
+
+        // u3 = (u1·v2 + v1·u2) / (1 + d·t)
+        let u = AllocatedNum::alloc(cs.namespace(|| "u3"), || {
+            let numerator = u1v2.get_value().zip(v1u2.get_value()).map(|(a, b)| a + b);
+            let denominator = t.get_value().map(|t| Scalar::one() + d * t);
+            numerator.zip(denominator)
+                .and_then(|(n, d)| Option::from(d.invert()).map(|inv: Scalar| n * inv))
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        cs.enforce(
+            || "u3 · (1 + d·t) = u1·v2 + v1·u2",
+            |lc| lc + u.get_variable(),
+            |lc| lc + CS::one() + (d, t.get_variable()),
+            |lc| lc + u1v2.get_variable() + v1u2.get_variable(),
+        );
+
+        // v3 = (v1·v2 + u1·u2) / (1 - d·t), since a = -1
+        let v = AllocatedNum::alloc(cs.namespace(|| "v3"), || {
+            let numerator = v1v2.get_value().zip(u1u2.get_value()).map(|(a, b)| a + b);
+            let denominator = t.get_value().map(|t| Scalar::one() - d * t);
+            numerator.zip(denominator)
+                .and_then(|(n, d)| Option::from(d.invert()).map(|inv: Scalar| n * inv))
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        cs.enforce(
+            || "v3 · (1 - d·t) = v1·v2 + u1·u2",
+            |lc| lc + v.get_variable(),
+            |lc| lc + CS::one() - (d, t.get_variable()),
+            |lc| lc + v1v2.get_variable() + u1u2.get_variable(),
+        );
+
+        Ok(Self { u, v })
+    }
+
+    /// `other` when `bit` is set, `self` otherwise
+    fn select<CS: ConstraintSystem<Scalar>>(&self, mut cs: CS, bit: &AllocatedBit, other: &Self) -> Result<Self, SynthesisError> {
+        Ok(Self {
+            u: select_num(cs.namespace(|| "u"), bit, &self.u, &other.u)?,
+            v: select_num(cs.namespace(|| "v"), bit, &self.v, &other.v)?,
+        })
+    }
+
+    /// Multiply by the integer with the given little-endian bits
+    pub fn mul_bits<CS: ConstraintSystem<Scalar>>(&self, mut cs: CS, bits: &[AllocatedBit]) -> Result<Self, SynthesisError> {
+        let mut acc = Self::constant(cs.namespace(|| "identity"), (Scalar::zero(), Scalar::one()))?;
+
+        for (i, bit) in bits.iter().enumerate().rev() {
+            let mut cs = cs.namespace(|| format!("bit {}", i));
+            let doubled = acc.add(cs.namespace(|| "double"), &acc)?;
+            let added = doubled.add(cs.namespace(|| "add"), self)?;
+            acc = doubled.select(cs.namespace(|| "select"), bit, &added)?;
+        }
+
+        Ok(acc)
+    }
+
+    pub fn enforce_equal<CS: ConstraintSystem<Scalar>>(&self, mut cs: CS, other: &Self) {
+        cs.enforce(|| "u equal", |lc| lc + self.u.get_variable(), |lc| lc + CS::one(), |lc| lc + other.u.get_variable());
+        cs.enforce(|| "v equal", |lc| lc + self.v.get_variable(), |lc| lc + CS::one(), |lc| lc + other.v.get_variable());
+    }
+}
+
+// b when bit is set, a otherwise: out - a = bit · (b - a)
+fn select_num<CS: ConstraintSystem<Scalar>>(
+    mut cs: CS,
+    bit: &AllocatedBit,
+    a: &AllocatedNum<Scalar>,
+    b: &AllocatedNum<Scalar>,
+) -> Result<AllocatedNum<Scalar>, SynthesisError> {
+    let out = AllocatedNum::alloc(cs.namespace(|| "out"), || {
+        let bit = bit.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+        (if bit { b } else { a }).get_value().ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    cs.enforce(
+        || "select",
+        |lc| lc + bit.get_variable(),
+        |lc| lc + b.get_variable() - a.get_variable(),
+        |lc| lc + out.get_variable() - a.get_variable(),
+    );
+    Ok(out)
+}
+
+/// Allocate a private Jubjub scalar as `Fr::NUM_BITS` little-endian bits, for `mul_bits`
+pub fn witness_scalar_bits<CS: ConstraintSystem<Scalar>>(
+    mut cs: CS,
+    scalar: Option<Fr>,
+) -> Result<Vec<AllocatedBit>, SynthesisError> {
+    let bytes = scalar.map(|scalar| scalar.to_bytes());
+    (0..Fr::NUM_BITS as usize)
+        .map(|i| AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), bytes.map(|b| (b[i / 8] >> (i % 8)) & 1 == 1)))
+        .collect()
+}
+
+/// Constrain `signature` to be a valid signature on `message` under `public_key`
+pub fn verify_signature_gadget<CS: ConstraintSystem<Scalar>>(
+    mut cs: CS,
+    mimc: &MiMC,
+    public_key: &EdwardsPoint,
+    message: &AllocatedNum<Scalar>,
+    signature: Option<&Signature>,
+) -> Result<(), SynthesisError> {
+    let r = EdwardsPoint::witness(cs.namespace(|| "R"), signature.map(|sig| coordinates(&sig.r)))?;
+
+    // Unlike `Signature::from_bytes`, any s < 2^252 is accepted, so both s and s + r
+    // verify. That malleability is harmless: the signature is a private witness
+    // that no verifier sees or compares, and either form proves the issuer signed.
+    let s_bits = witness_scalar_bits(cs.namespace(|| "s"), signature.map(|sig| sig.s))?;
+
+    // h = MiMC(R.u, R.v, A.u, A.v, m), used as an integer
+    let h = mimc.hash_gadget(
+        cs.namespace(|| "challenge"),
+        &[r.u.clone(), r.v.clone(), public_key.u.clone(), public_key.v.clone(), message.clone()],
+    )?;
+    let h_bits = h.to_bits_le_strict(cs.namespace(|| "challenge bits"))?
+        .into_iter()
+        .map(|bit| match bit {
+            Boolean::Is(bit) => Ok(bit),
+            _ => Err(SynthesisError::Unsatisfiable),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // s·B = R + h·A
+    let generator = EdwardsPoint::generator(cs.namespace(|| "B"))?;
+    let lhs = generator.mul_bits(cs.namespace(|| "s·B"), &s_bits)?;
+    let h_a = public_key.mul_bits(cs.namespace(|| "h·A"), &h_bits)?;
+    let rhs = r.add(cs.namespace(|| "R + h·A"), &h_a)?;
+    lhs.enforce_equal(cs.namespace(|| "s·B = R + h·A"), &rhs);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellman::gadgets::test::TestConstraintSystem;
+
+    fn synthesize(public_key: &VerifyingKey, message: Scalar, signature: &Signature) -> TestConstraintSystem<Scalar> {
+        let mut cs = TestConstraintSystem::new();
+        let pk = EdwardsPoint::witness(cs.namespace(|| "A"), Some(public_key.coordinates())).unwrap();
+        let m = AllocatedNum::alloc(cs.namespace(|| "m"), || Ok(message)).unwrap();
+        verify_signature_gadget(cs.namespace(|| "verify"), &MiMC::new(), &pk, &m, Some(signature)).unwrap();
+        cs
+    }
+
+    #[test]
+    fn signatures_verify_natively_and_in_circuit() {
+        let sk = SigningKey::generate();
+        let pk = sk.verifying_key();
+        let message = Scalar::from(42u64);
+        let signature = sk.sign(&message);
+
+        assert!(pk.verify(&message, &signature));
+        assert_eq!(Signature::from_bytes(&signature.to_bytes()).unwrap(), signature);
+        assert_eq!(VerifyingKey::from_bytes(&pk.to_bytes()).unwrap(), pk);
+        assert!(synthesize(&pk, message, &signature).is_satisfied());
+    }
+
+    #[test]
+    fn debug_output_redacts_the_secret() {
+        let sk = SigningKey::generate();
+        let printed = format!("{:?}", sk);
+        assert!(!printed.contains(&format!("{:?}", sk.0)));
+        assert!(printed.contains(&format!("{:?}", sk.verifying_key())));
+    }
+
+    #[test]
+    fn wrong_message_or_key_is_rejected() {
+        let sk = SigningKey::generate();
+        let message = Scalar::from(42u64);
+        let signature = sk.sign(&message);
+        let other = SigningKey::generate().verifying_key();
+
+        assert!(!sk.verifying_key().verify(&Scalar::from(43u64), &signature));
+        assert!(!other.verify(&message, &signature));
+        assert!(!synthesize(&sk.verifying_key(), Scalar::from(43u64), &signature).is_satisfied());
+        assert!(!synthesize(&other, message, &signature).is_satisfied());
+    }
+}
@@ -0,0 +1,342 @@
+//! Self-describing, versioned container for proofs.
+//!
+//! Every proving API returns a `ProofEnvelope` and every verifying API takes one.
+//! Besides the proof bytes it records what a verifier checks before running the
+//! proof: the proof system, the circuit (`ProofType`), the hash of the verifying
+//! key the proof was made under and the context it was made for (the relying
+//! party of a presentation, the challenge of a DID proof). A proof for another
+//! circuit, key or context is rejected with `ZkError::EnvelopeMismatch` instead
+//! of failing verification for no stated reason.
+//!
+//! Proofs that no `ProofSystem` makes travel in envelopes too, under their own
+//! `proof_system` name and key hash: SnarkPack aggregates (see
+//! `crypto::aggregation`) and BBS+ presentations (see `crypto::bbs`).
+//!
+//! The recorded public inputs and creation time are informational: verifiers
+//! still rebuild the inputs from their own request.
+//!
+//! Envelopes encode as JSON, with base64 proof bytes and hex-encoded key hash and
+//! inputs, or as compact CBOR carrying the same fields as byte strings.
+
+use crate::crypto::proof_system::ProofSystem;
+use crate::crypto::zk_proofs::{decode_scalar, encode_scalar, ProofType};
+use crate::error::ZkError;
+use crate::utils::unix_time;
+use bls12_381::Scalar;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use wasm_bindgen::prelude::*;
+
+/// Envelope format written by this version
+pub const ENVELOPE_VERSION: u32 = 1;
+
+/// A proof and what it was made for
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofEnvelope {
+    pub version: u32,
+    /// `ProofSystem::name` of the system that made the proof
+    pub proof_system: String,
+    pub proof_type: ProofType,
+    /// SHA-256 of the serialized key the proof verifies under (see `key_hash`)
+    #[serde(with = "hash_encoding")]
+    pub vk_hash: [u8; 32],
+    #[serde(with = "scalar_encoding")]
+    pub public_inputs: Vec<Scalar>,
+    #[serde(with = "bytes_encoding")]
+    pub proof: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    /// Unix seconds
+    pub created_at: u64,
+}
+
+impl ProofEnvelope {
+    /// Wrap a proof made with `pk` for the circuit behind `proof_type`
+    pub fn seal<P: ProofSystem>(
+        system: &P,
+        pk: &P::ProvingKey,
+        proof_type: ProofType,
+        proof: &P::Proof,
+        public_inputs: &[Scalar],
+        context: Option<&str>,
+    ) -> Result<Self, ZkError> {
+        Ok(Self::new(
+            system.name(),
+            proof_type,
+            verifying_key_hash(system, &system.verifying_key(pk))?,
+            public_inputs,
+            system.serialize_proof(proof)?,
+            context,
+        ))
+    }
+
+    /// Wrap proof bytes made by `proof_system` under the key hashing to `vk_hash`
+    pub fn new(
+        proof_system: &str,
+        proof_type: ProofType,
+        vk_hash: [u8; 32],
+        public_inputs: &[Scalar],
+        proof: Vec<u8>,
+        context: Option<&str>,
+    ) -> Self {
+        Self {
+            version: ENVELOPE_VERSION,
+            proof_system: proof_system.to_string(),
+            proof_type,
+            vk_hash,
+            public_inputs: public_inputs.to_vec(),
+            proof,
+            context: context.map(str::to_string),
+            created_at: unix_time(),
+        }
+    }
+
+    /// Reject an envelope of another version, or not made by `proof_system` under
+    /// the verifying key hashing to `vk_hash`
+    pub fn check_key(&self, proof_system: &str, vk_hash: &[u8; 32]) -> Result<(), ZkError> {
+        self.check_format(proof_system)?;
+        if self.vk_hash != *vk_hash {
+            return Err(ZkError::EnvelopeMismatch(format!(
+                "{} proof made under verifying key {}, expected {}",
+                self.proof_type.name(),
+                hex::encode(self.vk_hash),
+                hex::encode(vk_hash)
+            )));
+        }
+        Ok(())
+    }
+
+    /// `check_key`, and also reject a proof for another circuit than `proof_type`
+    /// or made for another context
+    pub fn check(
+        &self,
+        proof_system: &str,
+        proof_type: ProofType,
+        vk_hash: &[u8; 32],
+        context: Option<&str>,
+    ) -> Result<(), ZkError> {
+        self.check_format(proof_system)?;
+        if self.proof_type != proof_type {
+            return Err(ZkError::EnvelopeMismatch(format!(
+                "{} proof, expected {}",
+                self.proof_type.name(),
+                proof_type.name()
+            )));
+        }
+        self.check_key(proof_system, vk_hash)?;
+        if self.context.as_deref() != context {
+            return Err(ZkError::EnvelopeMismatch(format!(
+                "proof made for context {:?}, expected {:?}",
+                self.context.as_deref().unwrap_or_default(),
+                context.unwrap_or_default()
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_format(&self, proof_system: &str) -> Result<(), ZkError> {
+        if self.version != ENVELOPE_VERSION {
+            return Err(ZkError::EnvelopeMismatch(format!("unsupported envelope version {}", self.version)));
+        }
+        if self.proof_system != proof_system {
+            return Err(ZkError::EnvelopeMismatch(format!(
+                "proof made with {}, expected {}",
+                self.proof_system, proof_system
+            )));
+        }
+        Ok(())
+    }
+
+    /// The proof itself; check the envelope first
+    pub fn decode_proof<P: ProofSystem>(&self, system: &P) -> Result<P::Proof, ZkError> {
+        system.deserialize_proof(&self.proof)
+    }
+
+    pub fn to_json(&self) -> Result<String, ZkError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ZkError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_cbor(&self) -> Result<Vec<u8>, ZkError> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(self, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, ZkError> {
+        Ok(ciborium::de::from_reader(bytes)?)
+    }
+
+    /// Decode either encoding: a JSON envelope is an object, while a CBOR one starts
+    /// with a map header, never `{`
+    pub fn decode(bytes: &[u8]) -> Result<Self, ZkError> {
+        match bytes.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'{') => {
+                let json = std::str::from_utf8(bytes).map_err(|e| ZkError::Decoding(format!("UTF-8: {}", e)))?;
+                Self::from_json(json)
+            }
+            _ => Self::from_cbor(bytes),
+        }
+    }
+}
+
+/// Hash identifying a key in envelopes and key caches: SHA-256 of its serialization
+pub fn key_hash(serialized_key: &[u8]) -> [u8; 32] {
+    Sha256::digest(serialized_key).into()
+}
+
+/// `key_hash` of a verifying key of `system`
+pub fn verifying_key_hash<P: ProofSystem>(system: &P, vk: &P::VerifyingKey) -> Result<[u8; 32], ZkError> {
+    Ok(key_hash(&system.serialize_verifying_key(vk)?))
+}
+
+/// Re-encode a JSON envelope as CBOR, e.g. for QR codes or on-chain storage
+#[wasm_bindgen]
+pub fn proof_envelope_to_cbor(envelope_json: &str) -> Result<Vec<u8>, JsValue> {
+    Ok(ProofEnvelope::from_json(envelope_json)?.to_cbor()?)
+}
+
+/// Decode a CBOR (or JSON) envelope into the JSON the verifying APIs take
+#[wasm_bindgen]
+pub fn proof_envelope_to_json(envelope: &[u8]) -> Result<String, JsValue> {
+    Ok(ProofEnvelope::decode(envelope)?.to_json()?)
+}
+
+// Byte fields are text in human-readable formats (JSON, JS values) and byte strings in CBOR
+
+mod bytes_encoding {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&base64::encode(bytes))
+        } else {
+            serializer.serialize_bytes(bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            base64::decode(String::deserialize(deserializer)?).map_err(D::Error::custom)
+        } else {
+            Ok(serde_bytes::ByteBuf::deserialize(deserializer)?.into_vec())
+        }
+    }
+}
+
+mod hash_encoding {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(hash: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(hash))
+        } else {
+            serializer.serialize_bytes(hash)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let bytes = if deserializer.is_human_readable() {
+            hex::decode(String::deserialize(deserializer)?).map_err(D::Error::custom)?
+        } else {
+            serde_bytes::ByteBuf::deserialize(deserializer)?.into_vec()
+        };
+        bytes.try_into().map_err(|_| D::Error::custom("verifying key hash must be 32 bytes"))
+    }
+}
+
+mod scalar_encoding {
+    use super::{decode_scalar, encode_scalar};
+    use bls12_381::Scalar;
+    use serde::{de::Error, ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(scalars: &[Scalar], serializer: S) -> Result<S::Ok, S::Error> {
+        let human_readable = serializer.is_human_readable();
+        let mut seq = serializer.serialize_seq(Some(scalars.len()))?;
+        for scalar in scalars {
+            if human_readable {
+                seq.serialize_element(&encode_scalar(scalar))?;
+            } else {
+                seq.serialize_element(serde_bytes::Bytes::new(&scalar.to_bytes()))?;
+            }
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Scalar>, D::Error> {
+        if deserializer.is_human_readable() {
+            Vec::<String>::deserialize(deserializer)?
+                .iter()
+                .map(|encoded| decode_scalar(encoded).map_err(D::Error::custom))
+                .collect()
+        } else {
+            Vec::<serde_bytes::ByteBuf>::deserialize(deserializer)?
+                .into_iter()
+                .map(|bytes| {
+                    let bytes: [u8; 32] = bytes.into_vec().try_into()
+                        .map_err(|_| D::Error::custom("public input must be 32 bytes"))?;
+                    Option::from(Scalar::from_bytes(&bytes))
+                        .ok_or_else(|| D::Error::custom("public input is not a canonical field element"))
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::proof_system::Groth16;
+    use crate::crypto::zk_proofs::{create_square_proof, scalar_from_u64, setup_square_circuit, verify_square_proof};
+    use crate::crypto::batch::prepared_verifying_key;
+
+    fn square_envelope() -> (bellman::groth16::Parameters<bls12_381::Bls12>, ProofEnvelope) {
+        let params = setup_square_circuit(4, 16).unwrap();
+        let (proof, inputs) = create_square_proof(&Groth16, &params, 5, 25).unwrap();
+        let envelope = ProofEnvelope::seal(&Groth16, &params, ProofType::SquareRoot, &proof, &inputs, Some("poll-1")).unwrap();
+        (params, envelope)
+    }
+
+    #[test]
+    fn envelopes_round_trip_through_json_and_cbor() {
+        let (params, envelope) = square_envelope();
+        let json = envelope.to_json().unwrap();
+        let cbor = envelope.to_cbor().unwrap();
+        assert!(cbor.len() < json.len());
+
+        for decoded in [ProofEnvelope::decode(json.as_bytes()).unwrap(), ProofEnvelope::decode(&cbor).unwrap()] {
+            assert_eq!(decoded, envelope);
+            let proof = decoded.decode_proof(&Groth16).unwrap();
+            let pvk = prepared_verifying_key(&params.vk).unwrap();
+            assert!(verify_square_proof(&Groth16, &pvk, &proof, &[scalar_from_u64(25)]).unwrap());
+        }
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["proofType"], "SquareRoot");
+        assert_eq!(value["publicInputs"][0], encode_scalar(&scalar_from_u64(25)));
+    }
+
+    #[test]
+    fn mismatched_envelopes_are_rejected_before_verification() {
+        let (params, envelope) = square_envelope();
+        let vk_hash = verifying_key_hash(&Groth16, &params.vk).unwrap();
+        assert!(envelope.check("groth16", ProofType::SquareRoot, &vk_hash, Some("poll-1")).is_ok());
+
+        let other_key = verifying_key_hash(&Groth16, &setup_square_circuit(4, 16).unwrap().vk).unwrap();
+        let rejections = [
+            envelope.check("marlin", ProofType::SquareRoot, &vk_hash, Some("poll-1")),
+            envelope.check("groth16", ProofType::RangeProof, &vk_hash, Some("poll-1")),
+            envelope.check("groth16", ProofType::SquareRoot, &other_key, Some("poll-1")),
+            envelope.check("groth16", ProofType::SquareRoot, &vk_hash, Some("poll-2")),
+            envelope.check("groth16", ProofType::SquareRoot, &vk_hash, None),
+            ProofEnvelope { version: ENVELOPE_VERSION + 1, ..envelope.clone() }.check_key("groth16", &vk_hash),
+        ];
+        for rejection in rejections {
+            assert!(matches!(rejection, Err(ZkError::EnvelopeMismatch(_))));
+        }
+    }
+}
@@ -0,0 +1,357 @@
+//! Universal-setup backend: Marlin with KZG commitments over BLS12-381.
+//!
+//! One universal reference string serves every circuit up to its size bound;
+//! the keys of a circuit are derived from it deterministically ("indexing"),
+//! so adding a circuit needs no new ceremony. Proofs are larger and slower to
+//! verify than Groth16 proofs.
+//!
+//! Circuits stay bellman `Circuit<Scalar>`s: synthesis is replayed into an
+//! arkworks constraint system over the same scalar field, one variable and
+//! constraint at a time.
+
+use crate::crypto::proof_system::ProofSystem;
+use crate::error::ZkError;
+use ark_bls12_381::{Bls12_381, Fr};
+use ark_marlin::{ahp::AHPForR1CS, IndexProverKey, IndexVerifierKey};
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::marlin_pc::MarlinKZG10;
+use ark_relations::r1cs::{
+    self, ConstraintSynthesizer, ConstraintSystemRef, LinearCombination as ArkLinearCombination,
+    Variable as ArkVariable,
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use bellman::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+use blake2_digest09::Blake2s;
+use bls12_381::Scalar;
+use rand::{rngs::OsRng, RngCore};
+use std::sync::Arc;
+
+type MarlinPc = MarlinKZG10<Bls12_381, DensePolynomial<Fr>>;
+type MarlinInstance = ark_marlin::Marlin<Fr, MarlinPc, Blake2s>;
+
+/// Universal reference string shared by all circuits
+pub type UniversalSrs = ark_marlin::UniversalSRS<Fr, MarlinPc>;
+
+/// Size bound of a universal reference string
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CircuitSize {
+    pub constraints: usize,
+    pub variables: usize,
+    pub non_zero: usize,
+}
+
+impl CircuitSize {
+    /// Size of `circuit` as Marlin indexes it
+    pub fn of<C: Circuit<Scalar>>(circuit: C) -> Result<Self, ZkError> {
+        let index = AHPForR1CS::<Fr>::index(ArkCircuit(circuit)).map_err(marlin_error)?;
+        Ok(Self {
+            constraints: index.index_info.num_constraints,
+            variables: index.index_info.num_variables,
+            non_zero: index.index_info.num_non_zero,
+        })
+    }
+
+    /// The smallest bound covering both `self` and `other`
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            constraints: self.constraints.max(other.constraints),
+            variables: self.variables.max(other.variables),
+            non_zero: self.non_zero.max(other.non_zero),
+        }
+    }
+}
+
+/// Marlin proofs; `setup` needs the universal reference string, proving and
+/// verifying only need the derived keys
+#[derive(Clone, Default)]
+pub struct Marlin {
+    srs: Option<Arc<UniversalSrs>>,
+}
+
+impl Marlin {
+    pub fn new(srs: UniversalSrs) -> Self {
+        Self { srs: Some(Arc::new(srs)) }
+    }
+
+    /// Generate a universal reference string for circuits up to `size` from a local secret.
+    ///
+    /// Whoever runs this knows the trapdoor and can forge proofs; it exists for
+    /// tests and local development only.
+    pub fn generate_insecure_srs<R: RngCore>(size: CircuitSize, rng: &mut R) -> Result<UniversalSrs, ZkError> {
+        MarlinInstance::universal_setup(size.constraints, size.variables, size.non_zero, rng).map_err(marlin_error)
+    }
+
+    pub fn serialize_srs(srs: &UniversalSrs) -> Result<Vec<u8>, ZkError> {
+        to_bytes(srs)
+    }
+
+    pub fn deserialize_srs(bytes: &[u8]) -> Result<UniversalSrs, ZkError> {
+        from_bytes(bytes)
+    }
+}
+
+impl ProofSystem for Marlin {
+    type ProvingKey = IndexProverKey<Fr, MarlinPc>;
+    type VerifyingKey = IndexVerifierKey<Fr, MarlinPc>;
+    type PreparedVerifyingKey = IndexVerifierKey<Fr, MarlinPc>;
+    type Proof = ark_marlin::Proof<Fr, MarlinPc>;
+
+    fn name(&self) -> &'static str {
+        "marlin"
+    }
+
+    fn setup<C: Circuit<Scalar>>(&self, circuit: C) -> Result<Self::ProvingKey, ZkError> {
+        let srs = self.srs.as_ref()
+            .ok_or_else(|| ZkError::InvalidInput("no universal reference string loaded".to_string()))?;
+        let (pk, _) = MarlinInstance::index(srs, ArkCircuit(circuit)).map_err(marlin_error)?;
+        Ok(pk)
+    }
+
+    fn verifying_key(&self, pk: &Self::ProvingKey) -> Self::VerifyingKey {
+        pk.index_vk.clone()
+    }
+
+    fn prepare_verifying_key(&self, vk: &Self::VerifyingKey) -> Result<Self::PreparedVerifyingKey, ZkError> {
+        Ok(vk.clone())
+    }
+
+    fn prove<C: Circuit<Scalar>>(&self, pk: &Self::ProvingKey, circuit: C) -> Result<Self::Proof, ZkError> {
+        MarlinInstance::prove(pk, ArkCircuit(circuit), &mut OsRng).map_err(marlin_error)
+    }
+
+    fn verify(&self, pvk: &Self::PreparedVerifyingKey, proof: &Self::Proof, inputs: &[Scalar]) -> Result<bool, ZkError> {
+        if inputs.len() + 1 != pvk.index_info.num_instance_variables {
+            return Err(ZkError::InvalidInput(format!(
+                "expected {} public inputs, got {}",
+                pvk.index_info.num_instance_variables - 1,
+                inputs.len()
+            )));
+        }
+        let inputs: Vec<Fr> = inputs.iter().map(to_ark).collect();
+        MarlinInstance::verify(pvk, &inputs, proof, &mut OsRng).map_err(marlin_error)
+    }
+
+    fn serialize_proof(&self, proof: &Self::Proof) -> Result<Vec<u8>, ZkError> {
+        to_bytes(proof)
+    }
+
+    fn deserialize_proof(&self, bytes: &[u8]) -> Result<Self::Proof, ZkError> {
+        from_bytes(bytes)
+    }
+
+    fn serialize_proving_key(&self, pk: &Self::ProvingKey) -> Result<Vec<u8>, ZkError> {
+        to_bytes(pk)
+    }
+
+    fn deserialize_proving_key(&self, bytes: &[u8]) -> Result<Self::ProvingKey, ZkError> {
+        from_bytes(bytes)
+    }
+
+    fn serialize_verifying_key(&self, vk: &Self::VerifyingKey) -> Result<Vec<u8>, ZkError> {
+        to_bytes(vk)
+    }
+
+    fn deserialize_verifying_key(&self, bytes: &[u8]) -> Result<Self::VerifyingKey, ZkError> {
+        from_bytes(bytes)
+    }
+}
+
+fn marlin_error<E: std::fmt::Debug>(error: E) -> ZkError {
+    ZkError::InvalidInput(format!("Marlin: {:?}", error))
+}
+
+fn to_bytes<T: CanonicalSerialize>(value: &T) -> Result<Vec<u8>, ZkError> {
+    let mut bytes = Vec::new();
+    value.serialize(&mut bytes)
+        .map_err(|e| ZkError::Decoding(format!("Marlin serialization: {}", e)))?;
+    Ok(bytes)
+}
+
+fn from_bytes<T: CanonicalDeserialize>(bytes: &[u8]) -> Result<T, ZkError> {
+    T::deserialize(bytes).map_err(|e| ZkError::Decoding(format!("Marlin: {}", e)))
+}
+
+fn to_ark(scalar: &Scalar) -> Fr {
+    Fr::deserialize(&scalar.to_bytes()[..]).expect("canonical scalars are canonical in both libraries")
+}
+
+const MIN_CONSTRAINTS: usize = 3;
+
+/// A bellman circuit as an arkworks constraint synthesizer
+struct ArkCircuit<C>(C);
+
+impl<C: Circuit<Scalar>> ConstraintSynthesizer<Fr> for ArkCircuit<C> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> r1cs::Result<()> {
+        let mut bridge = Bridge { cs: cs.clone(), inputs: vec![ArkVariable::One], aux: Vec::new(), error: None };
+        let result = self.0.synthesize(&mut bridge);
+        match (bridge.error, result) {
+            (Some(error), _) => return Err(error),
+            (None, Err(_)) => return Err(r1cs::SynthesisError::Unsatisfiable),
+            (None, Ok(())) => {}
+        }
+
+        // ark-poly-commit 0.3 cannot trim keys for the zero degree bound of circuits
+        // with fewer than three constraints, so pad tiny circuits with `1 * 1 = 1`
+        while cs.num_constraints() < MIN_CONSTRAINTS {
+            let one = || ArkLinearCombination::from(ArkVariable::One);
+            cs.enforce_constraint(one(), one(), one())?;
+        }
+        Ok(())
+    }
+}
+
+/// Replays bellman synthesis into an arkworks constraint system
+struct Bridge {
+    cs: ConstraintSystemRef<Fr>,
+    // arkworks variables by bellman index; input 0 is the constant one
+    inputs: Vec<ArkVariable>,
+    aux: Vec<ArkVariable>,
+    // First arkworks error, which bellman's error type cannot carry
+    error: Option<r1cs::SynthesisError>,
+}
+
+impl Bridge {
+    fn record(&mut self, error: r1cs::SynthesisError) -> SynthesisError {
+        let converted = match error {
+            r1cs::SynthesisError::AssignmentMissing => SynthesisError::AssignmentMissing,
+            r1cs::SynthesisError::DivisionByZero => SynthesisError::DivisionByZero,
+            _ => SynthesisError::Unsatisfiable,
+        };
+        self.error.get_or_insert(error);
+        converted
+    }
+
+    fn convert(&self, lc: LinearCombination<Scalar>) -> ArkLinearCombination<Fr> {
+        let mut converted = ArkLinearCombination::zero();
+        for (var, coeff) in lc.as_ref() {
+            let var = match var.get_unchecked() {
+                Index::Input(id) => self.inputs[id],
+                Index::Aux(id) => self.aux[id],
+            };
+            converted += (to_ark(coeff), var);
+        }
+        converted
+    }
+}
+
+impl ConstraintSystem<Scalar> for Bridge {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let var = self.cs
+            .new_witness_variable(|| f().map(|value| to_ark(&value)).map_err(|_| r1cs::SynthesisError::AssignmentMissing))
+            .map_err(|e| self.record(e))?;
+        self.aux.push(var);
+        Ok(Variable::new_unchecked(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let var = self.cs
+            .new_input_variable(|| f().map(|value| to_ark(&value)).map_err(|_| r1cs::SynthesisError::AssignmentMissing))
+            .map_err(|e| self.record(e))?;
+        self.inputs.push(var);
+        Ok(Variable::new_unchecked(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        let a = self.convert(a(LinearCombination::zero()));
+        let b = self.convert(b(LinearCombination::zero()));
+        let c = self.convert(c(LinearCombination::zero()));
+        if let Err(e) = self.cs.enforce_constraint(a, b, c) {
+            // bellman's `enforce` cannot fail; the error surfaces when synthesis returns
+            self.record(e);
+        }
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Namespaces only name constraints, which arkworks does not need
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::credential::holder_commitment;
+    use crate::crypto::eddsa::SigningKey;
+    use crate::crypto::range_proof::{
+        create_range_proof, verify_range_proof, Comparison, RangeProofCircuit, RangeRequest, SignedValueCommitment,
+    };
+    use crate::crypto::zk_proofs::{scalar_from_u64, SquareDemo};
+    use ff::Field;
+
+    #[test]
+    fn one_reference_string_serves_several_circuits() {
+        let blank_range = RangeProofCircuit::blank();
+        let size = CircuitSize::of(SquareDemo { x: None, y: None }).unwrap().max(CircuitSize::of(blank_range.clone()).unwrap());
+        let system = Marlin::new(Marlin::generate_insecure_srs(size, &mut OsRng).unwrap());
+
+        let square_pk = system.setup(SquareDemo { x: None, y: None }).unwrap();
+        let circuit = SquareDemo { x: Some(scalar_from_u64(3)), y: Some(scalar_from_u64(9)) };
+        let proof = system.prove(&square_pk, circuit).unwrap();
+        let pvk = system.prepare_verifying_key(&system.verifying_key(&square_pk)).unwrap();
+        assert!(system.verify(&pvk, &proof, &[scalar_from_u64(9)]).unwrap());
+        assert!(!system.verify(&pvk, &proof, &[scalar_from_u64(10)]).unwrap());
+
+        // Keys and proofs survive serialization
+        let vk = system.deserialize_verifying_key(&system.serialize_verifying_key(&pvk).unwrap()).unwrap();
+        let proof = system.deserialize_proof(&system.serialize_proof(&proof).unwrap()).unwrap();
+        assert!(system.verify(&vk, &proof, &[scalar_from_u64(9)]).unwrap());
+
+        // Indexing is deterministic: anyone can rederive a circuit's keys from the reference string
+        let range_pk = system.setup(blank_range).unwrap();
+        let again = system.setup(RangeProofCircuit::blank()).unwrap();
+        assert_eq!(
+            system.serialize_verifying_key(&range_pk.index_vk).unwrap(),
+            system.serialize_verifying_key(&again.index_vk).unwrap()
+        );
+
+        // The circuit modules prove and verify through any backend
+        let issuer = SigningKey::generate();
+        let age = SignedValueCommitment::issue(&issuer, "age", 34, &Scalar::random(OsRng), &holder_commitment("secret"));
+        let presentation = create_range_proof(&system, &range_pk, &age, "secret", Comparison::GreaterOrEqual, 18, "shop").unwrap();
+        let mut request = RangeRequest {
+            issuer_public_key: age.issuer_public_key.clone(),
+            attribute: "age".to_string(),
+            comparison: Comparison::GreaterOrEqual,
+            threshold: 18,
+        };
+        assert!(verify_range_proof(&system, &range_pk.index_vk, &presentation, &request, "shop").unwrap());
+        request.threshold = 40;
+        assert!(!verify_range_proof(&system, &range_pk.index_vk, &presentation, &request, "shop").unwrap());
+    }
+
+    #[test]
+    fn circuits_beyond_the_reference_string_are_rejected() {
+        let size = CircuitSize::of(SquareDemo { x: None, y: None }).unwrap();
+        let system = Marlin::new(Marlin::generate_insecure_srs(size, &mut OsRng).unwrap());
+        assert!(system.setup(RangeProofCircuit::blank()).is_err());
+        assert!(Marlin::default().setup(SquareDemo { x: None, y: None }).is_err());
+    }
+}
@@ -0,0 +1,344 @@
+//! Merkle-tree set membership.
+//!
+//! A set is committed to as the root of a fixed-depth binary MiMC Merkle tree
+//! whose leaves are field elements (unused leaves are zero). Each member's leaf
+//! is `H(member_secret)`, so knowing the member list is not enough to prove
+//! membership: the circuit derives the leaf from the private secret and proves
+//! that it, together with a private authentication path, hashes up to a public
+//! root, without revealing the leaf or its position.
+
+use crate::crypto::hash_to_field::{hash_str_to_field, HashDomain};
+use crate::crypto::mimc::MiMC;
+use crate::crypto::proof_system::ProofSystem;
+use crate::crypto::zk_proofs::{check_satisfied, decode_scalar, encode_scalar};
+use crate::error::ZkError;
+use bellman::{
+    gadgets::{
+        boolean::{AllocatedBit, Boolean},
+        num::AllocatedNum,
+    },
+    Circuit, ConstraintSystem, SynthesisError,
+};
+use bls12_381::Scalar;
+use ff::Field;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Depth of the membership trees used by `ZkProver` and the CLI (up to 65536 members)
+pub const MEMBERSHIP_TREE_DEPTH: usize = 16;
+
+/// Fixed-depth Merkle tree over field-element leaves
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    // levels[0] are the leaves, levels[depth] holds the root. Only the populated
+    // prefix of each level is stored; the rest are roots of empty subtrees.
+    levels: Vec<Vec<Scalar>>,
+    // empty[l] is the root of an all-zero subtree of height l
+    empty: Vec<Scalar>,
+}
+
+/// Authentication path from a leaf to the root
+#[derive(Clone, Debug, PartialEq)]
+pub struct MerklePath {
+    /// Sibling at each level, starting next to the leaf
+    pub siblings: Vec<Scalar>,
+    /// Whether the running node is the right child at each level
+    pub is_right: Vec<bool>,
+}
+
+impl MerkleTree {
+    /// Build a tree of the given depth, padding missing leaves with zero.
+    /// Zero marks an unused slot, so it cannot be a member's leaf.
+    pub fn new(depth: usize, leaves: &[Scalar]) -> Result<Self, ZkError> {
+        let capacity = 1usize.checked_shl(depth as u32).unwrap_or(0);
+        if depth == 0 || depth >= usize::BITS as usize || leaves.is_empty() || leaves.len() > capacity {
+            return Err(ZkError::InvalidInput(format!(
+                "{} leaves do not fit a tree of depth {}",
+                leaves.len(),
+                depth
+            )));
+        }
+        if leaves.iter().any(|leaf| bool::from(leaf.is_zero())) {
+            return Err(ZkError::InvalidInput("membership leaves must be non-zero".to_string()));
+        }
+
+        let mimc = MiMC::new();
+        let mut empty = vec![Scalar::zero()];
+        for height in 0..depth {
+            empty.push(mimc.hash2(&empty[height], &empty[height]));
+        }
+
+        let mut levels = vec![leaves.to_vec()];
+        for height in 0..depth {
+            let next = levels[height]
+                .chunks(2)
+                .map(|pair| mimc.hash2(&pair[0], pair.get(1).unwrap_or(&empty[height])))
+                .collect();
+            levels.push(next);
+        }
+
+        Ok(Self { levels, empty })
+    }
+
+    fn node(&self, height: usize, position: usize) -> Scalar {
+        self.levels[height].get(position).copied().unwrap_or(self.empty[height])
+    }
+
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    pub fn root(&self) -> Scalar {
+        self.node(self.depth(), 0)
+    }
+
+    /// Number of members; positions from here on are unused
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Whether the tree has no members, which `new` never allows
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    /// Member leaf at `index`
+    pub fn leaf(&self, index: usize) -> Option<Scalar> {
+        self.levels[0].get(index).copied()
+    }
+
+    /// Position of a member leaf
+    pub fn position(&self, leaf: &Scalar) -> Option<usize> {
+        self.levels[0].iter().position(|member| member == leaf)
+    }
+
+    /// Authentication path for the member at `index`
+    pub fn path(&self, index: usize) -> Result<MerklePath, ZkError> {
+        if index >= self.len() {
+            return Err(ZkError::InvalidInput(format!("leaf index {} is out of range", index)));
+        }
+
+        let mut siblings = Vec::with_capacity(self.depth());
+        let mut is_right = Vec::with_capacity(self.depth());
+        let mut position = index;
+        for height in 0..self.depth() {
+            siblings.push(self.node(height, position ^ 1));
+            is_right.push(position & 1 == 1);
+            position >>= 1;
+        }
+
+        Ok(MerklePath { siblings, is_right })
+    }
+}
+
+impl MerklePath {
+    /// Recompute the root reached from `leaf` along this path
+    pub fn root(&self, mimc: &MiMC, leaf: Scalar) -> Scalar {
+        self.siblings.iter().zip(&self.is_right).fold(leaf, |node, (sibling, is_right)| {
+            if *is_right {
+                mimc.hash2(sibling, &node)
+            } else {
+                mimc.hash2(&node, sibling)
+            }
+        })
+    }
+}
+
+/// Member secret as a field element, from the member's secret string
+pub fn member_secret(secret: &str) -> Scalar {
+    hash_str_to_field(HashDomain::MemberSecret, secret)
+}
+
+/// Leaf a member publishes in the set: `H(member_secret)`
+pub fn member_leaf(secret: &Scalar) -> Scalar {
+    MiMC::new().hash(&[*secret])
+}
+
+/// Hex-encoded leaf for a member's secret string, for the set publisher
+#[wasm_bindgen]
+pub fn compute_member_leaf(secret: &str) -> String {
+    encode_scalar(&member_leaf(&member_secret(secret)))
+}
+
+/// Proves knowledge of a secret whose leaf `H(secret)` is in the tree with the public root
+#[derive(Clone)]
+pub struct SetMembershipCircuit {
+    // Private member secret and authentication path
+    pub secret: Option<Scalar>,
+    pub siblings: Vec<Option<Scalar>>,
+    pub is_right: Vec<Option<bool>>,
+    // Public root
+    pub root: Option<Scalar>,
+}
+
+impl SetMembershipCircuit {
+    /// Circuit shape for trees of the given depth, for parameter generation
+    pub fn blank(depth: usize) -> Self {
+        Self {
+            secret: None,
+            siblings: vec![None; depth],
+            is_right: vec![None; depth],
+            root: None,
+        }
+    }
+}
+
+impl Circuit<Scalar> for SetMembershipCircuit {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mimc = MiMC::new();
+
+        let secret = AllocatedNum::alloc(cs.namespace(|| "secret"), || {
+            self.secret.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let mut node = mimc.hash_gadget(cs.namespace(|| "leaf"), &[secret])?;
+
+        for (i, (sibling, is_right)) in self.siblings.iter().zip(&self.is_right).enumerate() {
+            let mut cs = cs.namespace(|| format!("level {}", i));
+
+            let sibling = AllocatedNum::alloc(cs.namespace(|| "sibling"), || {
+                sibling.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let is_right = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "is right"), *is_right)?);
+
+            // (node, sibling) when the node is a left child, (sibling, node) otherwise
+            let (left, right) = AllocatedNum::conditionally_reverse(
+                cs.namespace(|| "order children"),
+                &node,
+                &sibling,
+                &is_right,
+            )?;
+            node = mimc.hash_gadget(cs.namespace(|| "hash"), &[left, right])?;
+        }
+
+        let root = cs.alloc_input(|| "root", || self.root.ok_or(SynthesisError::AssignmentMissing))?;
+        cs.enforce(
+            || "computed root = root",
+            |lc| lc + node.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + root,
+        );
+
+        Ok(())
+    }
+}
+
+/// Witness file for the set-membership circuit: hex-encoded leaves and the prover's secret string
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MembershipWitness {
+    pub leaves: Vec<String>,
+    pub secret: String,
+}
+
+impl MembershipWitness {
+    pub fn build_circuit(&self) -> Result<(SetMembershipCircuit, Vec<Scalar>), ZkError> {
+        let leaves = decode_leaves(&self.leaves)?;
+        let tree = MerkleTree::new(MEMBERSHIP_TREE_DEPTH, &leaves)?;
+        build_membership_circuit(&tree, &member_secret(&self.secret))
+    }
+}
+
+/// Decode hex-encoded leaves (see `zk_proofs::encode_scalar`)
+pub fn decode_leaves(leaves: &[String]) -> Result<Vec<Scalar>, ZkError> {
+    leaves.iter().map(|leaf| decode_scalar(leaf)).collect()
+}
+
+/// Root of the membership tree over hex-encoded leaves, for verifiers and set publishers
+#[wasm_bindgen]
+pub fn compute_membership_root(leaves: Vec<String>) -> Result<String, JsValue> {
+    let tree = MerkleTree::new(MEMBERSHIP_TREE_DEPTH, &decode_leaves(&leaves)?)?;
+    Ok(encode_scalar(&tree.root()))
+}
+
+/// Build a membership instance for the member holding `secret` and its public input `[root]`
+pub fn build_membership_circuit(tree: &MerkleTree, secret: &Scalar) -> Result<(SetMembershipCircuit, Vec<Scalar>), ZkError> {
+    let index = tree.position(&member_leaf(secret))
+        .ok_or_else(|| ZkError::InvalidInput("secret does not belong to a member of the set".to_string()))?;
+    let path = tree.path(index)?;
+    let circuit = SetMembershipCircuit {
+        secret: Some(*secret),
+        siblings: path.siblings.into_iter().map(Some).collect(),
+        is_right: path.is_right.into_iter().map(Some).collect(),
+        root: Some(tree.root()),
+    };
+    Ok((circuit, vec![tree.root()]))
+}
+
+/// Generate a proof that the member holding `secret` belongs to `tree`
+pub fn create_membership_proof<P: ProofSystem>(
+    system: &P,
+    pk: &P::ProvingKey,
+    tree: &MerkleTree,
+    secret: &Scalar,
+) -> Result<(P::Proof, Vec<Scalar>), ZkError> {
+    let (circuit, inputs) = build_membership_circuit(tree, secret)?;
+    check_satisfied(circuit.clone())?;
+    Ok((system.prove(pk, circuit)?, inputs))
+}
+
+/// Verify a membership proof against the public root
+pub fn verify_membership_proof<P: ProofSystem>(
+    system: &P,
+    pvk: &P::PreparedVerifyingKey,
+    proof: &P::Proof,
+    root: &Scalar,
+) -> Result<bool, ZkError> {
+    system.verify(pvk, proof, &[*root])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::proof_system::Groth16;
+    use crate::crypto::zk_proofs::setup_parameters;
+
+    fn secrets() -> Vec<Scalar> {
+        (1..=5).map(|i| member_secret(&format!("member {}", i))).collect()
+    }
+
+    fn members() -> Vec<Scalar> {
+        secrets().iter().map(member_leaf).collect()
+    }
+
+    #[test]
+    fn paths_recompute_the_root() {
+        let tree = MerkleTree::new(3, &members()).unwrap();
+        let mimc = MiMC::new();
+
+        for index in 0..tree.len() {
+            let path = tree.path(index).unwrap();
+            assert_eq!(path.root(&mimc, tree.leaf(index).unwrap()), tree.root());
+        }
+        assert!(MerkleTree::new(2, &members()).is_err());
+        assert!(MerkleTree::new(3, &[]).is_err());
+        assert!(MerkleTree::new(3, &[members()[0], Scalar::zero()]).is_err());
+
+        // Unused slots are not members
+        assert!(tree.path(5).is_err());
+        assert_eq!(tree.leaf(5), None);
+    }
+
+    #[test]
+    fn member_proves_and_verifies_against_root() {
+        let tree = MerkleTree::new(3, &members()).unwrap();
+        let params = setup_parameters(SetMembershipCircuit::blank(3)).unwrap();
+        let pvk = Groth16.prepare_verifying_key(&params.vk).unwrap();
+
+        let (proof, inputs) = create_membership_proof(&Groth16, &params, &tree, &secrets()[3]).unwrap();
+        assert_eq!(inputs, vec![tree.root()]);
+        assert!(verify_membership_proof(&Groth16, &pvk, &proof, &tree.root()).unwrap());
+
+        let other = MerkleTree::new(3, &members()[..4]).unwrap();
+        assert!(!verify_membership_proof(&Groth16, &pvk, &proof, &other.root()).unwrap());
+    }
+
+    #[test]
+    fn non_member_cannot_satisfy_circuit() {
+        let tree = MerkleTree::new(4, &members()).unwrap();
+        assert!(build_membership_circuit(&tree, &member_secret("outsider")).is_err());
+
+        // Knowing a published leaf is not enough: the circuit hashes the secret
+        let (mut circuit, _) = build_membership_circuit(&tree, &secrets()[2]).unwrap();
+        circuit.secret = Some(members()[2]);
+        assert!(check_satisfied(circuit).is_err());
+    }
+}
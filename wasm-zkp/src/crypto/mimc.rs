@@ -0,0 +1,142 @@
+//! MiMC sponge hash over the BLS12-381 scalar field, natively and as a circuit gadget.
+//!
+//! The permutation is a two-branch Feistel network with round function
+//! `(x + c_i)^5` over 220 rounds; `x^5` is a bijection on `Scalar` since
+//! `gcd(5, r - 1) = 1`. The sponge has rate one and capacity one, and the
+//! capacity starts at the number of absorbed elements so inputs of different
+//! lengths never collide. Each round costs three constraints.
+
+use crate::crypto::hash_to_field::{hash_to_field, HashDomain};
+use bellman::{gadgets::num::AllocatedNum, ConstraintSystem, LinearCombination, SynthesisError};
+use bls12_381::Scalar;
+
+/// Number of Feistel rounds in the permutation
+pub const MIMC_ROUNDS: usize = 220;
+
+/// MiMC sponge with its round constants precomputed
+#[derive(Clone, Debug)]
+pub struct MiMC {
+    constants: Vec<Scalar>,
+}
+
+impl Default for MiMC {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MiMC {
+    pub fn new() -> Self {
+        let constants = (0..MIMC_ROUNDS as u32)
+            .map(|i| hash_to_field(HashDomain::MimcRoundConstant, &i.to_le_bytes()))
+            .collect();
+
+        Self { constants }
+    }
+
+    /// Hash field elements to a single field element
+    pub fn hash(&self, inputs: &[Scalar]) -> Scalar {
+        let mut left = Scalar::zero();
+        let mut right = Scalar::from(inputs.len() as u64);
+
+        for input in inputs {
+            left += input;
+            for c in &self.constants {
+                let t = left + c;
+                let t2 = t.square();
+                let next = right + t2.square() * t;
+                right = left;
+                left = next;
+            }
+        }
+
+        left
+    }
+
+    /// Two-to-one compression, as used for Merkle tree nodes
+    pub fn hash2(&self, left: &Scalar, right: &Scalar) -> Scalar {
+        self.hash(&[*left, *right])
+    }
+
+    /// Constrain the output of `hash` over allocated inputs; at least one input is required
+    pub fn hash_gadget<CS: ConstraintSystem<Scalar>>(
+        &self,
+        mut cs: CS,
+        inputs: &[AllocatedNum<Scalar>],
+    ) -> Result<AllocatedNum<Scalar>, SynthesisError> {
+        if inputs.is_empty() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let mut left_lc = LinearCombination::<Scalar>::zero();
+        let mut left_value = Some(Scalar::zero());
+        let mut right_lc = LinearCombination::<Scalar>::zero() + (Scalar::from(inputs.len() as u64), CS::one());
+        let mut right_value = Some(Scalar::from(inputs.len() as u64));
+        let mut output = None;
+
+        for (i, input) in inputs.iter().enumerate() {
+            left_lc = left_lc + input.get_variable();
+            left_value = left_value.zip(input.get_value()).map(|(l, x)| l + x);
+
+            for (round, c) in self.constants.iter().enumerate() {
+                let mut cs = cs.namespace(|| format!("absorb {} round {}", i, round));
+
+                // t = left + c
+                let t_lc = left_lc.clone() + (*c, CS::one());
+                let t_value = left_value.map(|l| l + c);
+
+                let t2 = AllocatedNum::alloc(cs.namespace(|| "t^2"), || {
+                    t_value.map(|t| t.square()).ok_or(SynthesisError::AssignmentMissing)
+                })?;
+                cs.enforce(|| "t * t = t^2", |_| t_lc.clone(), |_| t_lc.clone(), |lc| lc + t2.get_variable());
+
+                let t4 = t2.square(cs.namespace(|| "t^4"))?;
+
+                // next = right + t^5
+                let next_value = right_value.zip(t4.get_value()).zip(t_value).map(|((r, t4), t)| r + t4 * t);
+                let next = AllocatedNum::alloc(cs.namespace(|| "next"), || {
+                    next_value.ok_or(SynthesisError::AssignmentMissing)
+                })?;
+                cs.enforce(
+                    || "t^4 * t = next - right",
+                    |lc| lc + t4.get_variable(),
+                    |_| t_lc,
+                    |lc| lc + next.get_variable() - &right_lc,
+                );
+
+                right_lc = left_lc;
+                right_value = left_value;
+                left_lc = LinearCombination::zero() + next.get_variable();
+                left_value = next.get_value();
+                output = Some(next);
+            }
+        }
+
+        Ok(output.expect("at least one round was synthesized"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellman::gadgets::test::TestConstraintSystem;
+
+    #[test]
+    fn gadget_matches_native_hash() {
+        let mimc = MiMC::new();
+        let inputs = [Scalar::from(7u64), Scalar::from(11u64), -Scalar::one()];
+
+        let mut cs = TestConstraintSystem::new();
+        let allocated: Vec<_> = inputs.iter().enumerate()
+            .map(|(i, x)| AllocatedNum::alloc(cs.namespace(|| format!("input {}", i)), || Ok(*x)).unwrap())
+            .collect();
+        let out = mimc.hash_gadget(cs.namespace(|| "hash"), &allocated).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(out.get_value().unwrap(), mimc.hash(&inputs));
+        assert_eq!(cs.num_constraints(), 3 * MIMC_ROUNDS * inputs.len());
+
+        // Length is domain separated
+        assert_ne!(mimc.hash(&[Scalar::zero()]), mimc.hash(&[Scalar::zero(), Scalar::zero()]));
+    }
+}
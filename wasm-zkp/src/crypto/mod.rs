@@ -1,6 +1,26 @@
 pub mod did_resolver;
 pub mod credential;
 pub mod zk_proofs;
+pub mod ceremony;
+pub mod range_proof;
+pub mod hash_to_field;
+pub mod mimc;
+pub mod merkle;
+pub mod eddsa;
+pub mod nullifier;
+pub mod batch;
+pub mod target_group;
+pub mod aggregation;
+pub mod proof_system;
+pub mod predicate;
+pub mod conjunction;
+pub mod bbs;
+pub mod revocation;
+pub mod progress;
+pub mod envelope;
+pub mod registry;
+#[cfg(feature = "universal")]
+pub mod marlin;
 
 // Re-export main types and functions
 pub use did_resolver::{
@@ -8,9 +28,15 @@ pub use did_resolver::{
     VerificationMethod,
     Service,
     DIDOwnershipCircuit,
+    DIDOwnershipWitness,
+    JUBJUB_VERIFICATION_KEY_TYPE,
+    build_did_ownership_circuit,
+    did_ownership_public_inputs,
     parse_did,
-    generate_did_ownership_proof,
+    create_did_ownership_proof,
     verify_did_ownership_proof,
+    generate_did_challenge,
+    generate_did_keypair,
     resolve_did
 };
 
@@ -19,7 +45,21 @@ pub use credential::{
     CredentialAttribute,
     Credential,
     CredentialCircuit,
-    prepare_credential_proof,
+    CredentialWitness,
+    CredentialPresentation,
+    DisclosedAttribute,
+    SlotDisclosure,
+    build_credential_circuit,
+    create_credential_proof,
+    compute_holder_commitment,
+    credential_disclosures,
+    credential_hash,
+    credential_message,
+    credential_public_inputs,
+    generate_issuer_keypair,
+    issue_credential_signature,
+    sign_credential,
+    holder_commitment,
     verify_credential_proof
 };
 
@@ -27,8 +67,158 @@ pub use zk_proofs::{
     ProofType,
     ProofResult,
     SquareDemo,
+    SquareWitness,
     scalar_from_u64,
     setup_square_circuit,
     create_square_proof,
     verify_square_proof
-}; 
\ No newline at end of file
+}; 
+
+pub use ceremony::{
+    CeremonyError,
+    ContributionKey,
+    MPCParameters,
+    PowersOfTau,
+    ZcashAccumulator,
+    ZCASH_TAU_POWERS_LENGTH,
+    contribute_to_ceremony
+};
+
+pub use range_proof::{
+    Comparison,
+    RangePresentation,
+    RangeProofCircuit,
+    RangeRequest,
+    RangeWitness,
+    SignedValueCommitment,
+    build_range_proof_circuit,
+    create_range_proof,
+    issue_value_commitment,
+    value_commitment,
+    verify_range_proof
+};
+
+pub use hash_to_field::{HashDomain, hash_to_field, hash_str_to_field};
+
+pub use mimc::MiMC;
+
+pub use eddsa::{Signature, SigningKey, VerifyingKey};
+
+pub use merkle::{
+    MerklePath,
+    MerkleTree,
+    MembershipWitness,
+    SetMembershipCircuit,
+    build_membership_circuit,
+    compute_member_leaf,
+    compute_membership_root,
+    create_membership_proof,
+    member_leaf,
+    member_secret,
+    verify_membership_proof
+};
+
+pub use nullifier::{NullifierRegistry, context_id, nullifier};
+
+pub use revocation::{
+    NonRevocationWitness,
+    RevocationRegistry,
+    REVOCATION_TREE_DEPTH,
+    compute_revocation_root,
+    non_revocation_gadget,
+    revocation_order,
+    revoke_credential
+};
+
+pub use progress::{
+    CancellationToken,
+    JsProgress,
+    Observed,
+    ProgressObserver,
+    ProvingStage,
+    REPORT_INTERVAL,
+    count_constraints
+};
+
+pub use envelope::{
+    ENVELOPE_VERSION,
+    ProofEnvelope,
+    key_hash,
+    proof_envelope_to_cbor,
+    proof_envelope_to_json,
+    verifying_key_hash
+};
+
+pub use registry::{
+    CircuitId,
+    CircuitRegistry,
+    CircuitVisitor,
+    KeySlot,
+    KeySource,
+    blank_circuit,
+    constraint_count,
+    setup_circuit,
+    verify_ceremony
+};
+
+pub use batch::{BatchEntry, BatchVerification, prepared_verifying_key, verify_batch, verify_batch_entries};
+
+pub use target_group::TargetElement;
+
+pub use aggregation::{
+    AggregateProof,
+    AggregationSrs,
+    AggregationVerifyingKey,
+    AGGREGATE_PROOF_SYSTEM,
+    aggregate_key_hash,
+    aggregate_proofs,
+    verify_aggregate_proof
+};
+
+pub use predicate::{
+    CompiledPredicate,
+    CredentialPredicates,
+    CredentialRequest,
+    Predicate,
+    PredicateCircuit,
+    PredicateOperator,
+    PredicatePresentation,
+    PredicateWitness,
+    compile_predicates,
+    create_predicate_proof,
+    predicate_public_inputs,
+    verify_predicate_proof
+};
+
+pub use conjunction::{
+    ConjunctionCircuit,
+    ConjunctionWitness,
+    MAX_CONJUNCTION_CREDENTIALS,
+    compile_conjunction,
+    conjunction_public_inputs,
+    create_conjunction_proof,
+    verify_conjunction_proof
+};
+
+pub use bbs::{
+    BbsDisclosedAttribute,
+    BbsPresentation,
+    BbsProof,
+    BbsPublicKey,
+    BbsSecretKey,
+    BbsSignature,
+    BBS_CREDENTIAL_MESSAGES,
+    BBS_PROOF_SYSTEM,
+    bbs_credential_messages,
+    create_bbs_presentation,
+    create_bbs_proof,
+    issuer_key_hash,
+    sign_credential_bbs,
+    verify_bbs_presentation,
+    verify_bbs_proof
+};
+
+pub use proof_system::{Groth16, ProofSystem};
+
+#[cfg(feature = "universal")]
+pub use marlin::{CircuitSize, Marlin, UniversalSrs};
@@ -0,0 +1,110 @@
+//! Verifier-scoped nullifiers.
+//!
+//! A presentation exposes `nullifier = H(context, holder_secret)`, where
+//! `context` is the field encoding of a public verifier/context id (a relying
+//! party, a poll, an airdrop round). The same holder always produces the same
+//! nullifier in a given context, so a relying party can reject a second use,
+//! while nullifiers from different contexts are unlinkable and reveal nothing
+//! about the holder.
+
+use crate::crypto::hash_to_field::{hash_str_to_field, HashDomain};
+use crate::crypto::mimc::MiMC;
+use crate::crypto::zk_proofs::encode_scalar;
+use crate::error::ZkError;
+use bellman::{gadgets::num::AllocatedNum, ConstraintSystem, SynthesisError};
+use bls12_381::Scalar;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Field encoding of a verifier/context id
+pub fn context_id(context: &str) -> Scalar {
+    hash_str_to_field(HashDomain::VerifierContext, context)
+}
+
+/// Nullifier of the holder of `holder_secret` in `context`
+pub fn nullifier(holder_secret: &Scalar, context: &Scalar) -> Scalar {
+    MiMC::new().hash(&[*context, *holder_secret])
+}
+
+/// Constrain the nullifier of `holder_secret` in `context`
+pub fn nullifier_gadget<CS: ConstraintSystem<Scalar>>(
+    cs: CS,
+    mimc: &MiMC,
+    holder_secret: &AllocatedNum<Scalar>,
+    context: &AllocatedNum<Scalar>,
+) -> Result<AllocatedNum<Scalar>, SynthesisError> {
+    mimc.hash_gadget(cs, &[context.clone(), holder_secret.clone()])
+}
+
+/// Nullifiers a relying party has accepted.
+///
+/// Register a nullifier only after its proof has verified, so that invalid
+/// proofs cannot burn someone else's nullifier.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct NullifierRegistry {
+    seen: HashSet<[u8; 32]>,
+}
+
+impl NullifierRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, nullifier: &Scalar) -> bool {
+        self.seen.contains(&nullifier.to_bytes())
+    }
+
+    /// Record a nullifier, rejecting one that was already used
+    pub fn register(&mut self, nullifier: &Scalar) -> Result<(), ZkError> {
+        if !self.seen.insert(nullifier.to_bytes()) {
+            return Err(ZkError::NullifierReused(encode_scalar(nullifier)));
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellman::gadgets::test::TestConstraintSystem;
+
+    #[test]
+    fn nullifiers_are_scoped_to_the_context() {
+        let secret = hash_str_to_field(HashDomain::HolderSecret, "secret");
+        let other = hash_str_to_field(HashDomain::HolderSecret, "other secret");
+        let poll = context_id("poll-1");
+
+        assert_eq!(nullifier(&secret, &poll), nullifier(&secret, &context_id("poll-1")));
+        assert_ne!(nullifier(&secret, &poll), nullifier(&secret, &context_id("poll-2")));
+        assert_ne!(nullifier(&secret, &poll), nullifier(&other, &poll));
+
+        let mut cs = TestConstraintSystem::new();
+        let s = AllocatedNum::alloc(cs.namespace(|| "secret"), || Ok(secret)).unwrap();
+        let c = AllocatedNum::alloc(cs.namespace(|| "context"), || Ok(poll)).unwrap();
+        let out = nullifier_gadget(cs.namespace(|| "nullifier"), &MiMC::new(), &s, &c).unwrap();
+        assert_eq!(out.get_value(), Some(nullifier(&secret, &poll)));
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn registry_rejects_reuse() {
+        let secret = hash_str_to_field(HashDomain::HolderSecret, "secret");
+        let mut registry = NullifierRegistry::new();
+
+        registry.register(&nullifier(&secret, &context_id("poll-1"))).unwrap();
+        assert!(matches!(
+            registry.register(&nullifier(&secret, &context_id("poll-1"))),
+            Err(ZkError::NullifierReused(_))
+        ));
+        registry.register(&nullifier(&secret, &context_id("poll-2"))).unwrap();
+        assert_eq!(registry.len(), 2);
+    }
+}
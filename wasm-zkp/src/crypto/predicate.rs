@@ -0,0 +1,890 @@
+//! Predicates over credential attributes, compiled into one circuit.
+//!
+//! A verifier's proof request lists predicates such as `age >= 18` and
+//! `nationality in [...]` (the frontend's `Predicate` type). `PredicateCircuit`
+//! proves that a credential signed under a public issuer key satisfies all of
+//! them at once, without revealing the credential or any attribute, and outputs
+//! the holder's nullifier for the verifier's context (see `crypto::nullifier`).
+//!
+//! The circuit is a fixed template of `MAX_PREDICATES` slots, each able to check
+//! any operator, so one set of keys serves every request. `compile_predicates`
+//! turns a request into the template's public inputs and the holder's witness.
+//! Each slot picks the attribute named by its predicate with a private one-hot
+//! selector, so the proof does not reveal where the attribute sits, and then
+//! applies the checks enabled by public operator flags:
+//!
+//! - `lower <= value <= upper` for `>`, `>=`, `<` and `<=` on numeric attributes;
+//! - `value ∈ {t_1, ..., t_k}` for `=` and `in`;
+//! - `value ≠ t_1` for `!=`;
+//! - `t_1 ∈ elements` for `contains` on list-valued attributes.
+//!
+//! Values and operands use the encodings of `credential::encode_attribute_value`.
+
+use crate::crypto::credential::{
+    encode_attribute_value, list_attribute_elements, list_element, numeric_attribute_value, AllocatedCredential,
+    Credential, CredentialValues, LIST_ATTRIBUTE_ELEMENTS,
+};
+use crate::crypto::eddsa::{EdwardsPoint, VerifyingKey};
+use crate::crypto::envelope::ProofEnvelope;
+use crate::crypto::hash_to_field::{hash_str_to_field, HashDomain};
+use crate::crypto::mimc::MiMC;
+use crate::crypto::nullifier::{context_id, nullifier, nullifier_gadget};
+use crate::crypto::proof_system::ProofSystem;
+use crate::crypto::range_proof::{enforce_bits, Comparison, RANGE_BITS};
+use crate::crypto::revocation::RevocationRegistry;
+use crate::crypto::zk_proofs::{check_satisfied, decode_scalar, encode_scalar, scalar_from_u64, ProofType};
+use crate::error::ZkError;
+use bellman::{
+    gadgets::{boolean::AllocatedBit, num::AllocatedNum},
+    Circuit, ConstraintSystem, LinearCombination, SynthesisError, Variable,
+};
+use bls12_381::Scalar;
+use serde::{Deserialize, Serialize};
+
+/// Predicate slots in `PredicateCircuit`; shorter requests leave slots unused
+pub const MAX_PREDICATES: usize = 4;
+
+/// Largest set an `in` predicate may list
+pub const PREDICATE_SET_SIZE: usize = 32;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PredicateOperator {
+    #[serde(rename = ">")]
+    GreaterThan,
+    #[serde(rename = ">=")]
+    GreaterOrEqual,
+    #[serde(rename = "=")]
+    Equal,
+    #[serde(rename = "<=")]
+    LessOrEqual,
+    #[serde(rename = "<")]
+    LessThan,
+    #[serde(rename = "!=")]
+    NotEqual,
+    #[serde(rename = "in")]
+    In,
+    #[serde(rename = "contains")]
+    Contains,
+}
+
+impl PredicateOperator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            PredicateOperator::GreaterThan => ">",
+            PredicateOperator::GreaterOrEqual => ">=",
+            PredicateOperator::Equal => "=",
+            PredicateOperator::LessOrEqual => "<=",
+            PredicateOperator::LessThan => "<",
+            PredicateOperator::NotEqual => "!=",
+            PredicateOperator::In => "in",
+            PredicateOperator::Contains => "contains",
+        }
+    }
+
+    fn comparison(&self) -> Option<Comparison> {
+        match self {
+            PredicateOperator::GreaterThan => Some(Comparison::GreaterThan),
+            PredicateOperator::GreaterOrEqual => Some(Comparison::GreaterOrEqual),
+            PredicateOperator::LessOrEqual => Some(Comparison::LessOrEqual),
+            PredicateOperator::LessThan => Some(Comparison::LessThan),
+            _ => None,
+        }
+    }
+}
+
+/// A condition on one credential attribute, e.g.
+/// `{"field": "age", "operator": ">=", "value": 18}` or
+/// `{"field": "nationality", "operator": "in", "value": ["DE", "FR"]}`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Predicate {
+    pub field: String,
+    pub operator: PredicateOperator,
+    pub value: serde_json::Value,
+}
+
+impl Predicate {
+    /// The public statement the circuit checks for this predicate
+    pub fn compile(&self) -> Result<CompiledPredicate, ZkError> {
+        let mut compiled = CompiledPredicate {
+            field: hash_str_to_field(HashDomain::AttributeName, &self.field),
+            ..CompiledPredicate::default()
+        };
+
+        if let Some(comparison) = self.operator.comparison() {
+            let threshold = match &self.value {
+                serde_json::Value::Number(n) => n.as_u64(),
+                serde_json::Value::String(s) => numeric_attribute_value(s),
+                _ => None,
+            }
+            .ok_or_else(|| self.invalid("needs an unsigned integer"))?;
+            (compiled.lower, compiled.upper) = comparison.bounds(threshold)?;
+            compiled.range = true;
+            return Ok(compiled);
+        }
+
+        match self.operator {
+            PredicateOperator::Equal => {
+                compiled.member = true;
+                compiled.set_targets(&[operand_encoding(&self.value).ok_or_else(|| self.invalid("needs a string or number"))??]);
+            }
+            PredicateOperator::In => {
+                let set = self.value.as_array()
+                    .filter(|set| !set.is_empty() && set.len() <= PREDICATE_SET_SIZE)
+                    .ok_or_else(|| self.invalid(&format!("needs a list of 1 to {} values", PREDICATE_SET_SIZE)))?;
+                let targets = set.iter()
+                    .map(|value| operand_encoding(value).ok_or_else(|| self.invalid("needs strings or numbers"))?)
+                    .collect::<Result<Vec<_>, _>>()?;
+                compiled.member = true;
+                compiled.set_targets(&targets);
+            }
+            PredicateOperator::NotEqual => {
+                compiled.distinct = true;
+                compiled.set_targets(&[operand_encoding(&self.value).ok_or_else(|| self.invalid("needs a string or number"))??]);
+            }
+            PredicateOperator::Contains => {
+                let element = self.value.as_str().ok_or_else(|| self.invalid("needs a string"))?;
+                compiled.contains = true;
+                compiled.set_targets(&[list_element(element)]);
+            }
+            _ => unreachable!("comparisons are handled above"),
+        }
+        Ok(compiled)
+    }
+
+    fn invalid(&self, reason: &str) -> ZkError {
+        ZkError::InvalidInput(format!("predicate {} {} {}", self.field, self.operator.symbol(), reason))
+    }
+}
+
+// Encoding of a scalar operand, as an attribute with that value would carry it
+fn operand_encoding(value: &serde_json::Value) -> Option<Result<Scalar, ZkError>> {
+    match value {
+        serde_json::Value::String(s) => Some(encode_attribute_value(s)),
+        serde_json::Value::Number(n) => Some(encode_attribute_value(&n.to_string())),
+        _ => None,
+    }
+}
+
+/// A predicate as `PredicateCircuit` checks it. Every field is a public input;
+/// the default value is an unused slot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompiledPredicate {
+    /// Encoding of the attribute name
+    pub field: Scalar,
+    /// Operator flags; at most one is set
+    pub range: bool,
+    pub member: bool,
+    pub distinct: bool,
+    pub contains: bool,
+    /// Inclusive bounds of a range check
+    pub lower: u64,
+    pub upper: u64,
+    /// Operands of the other checks, padded to `PREDICATE_SET_SIZE` by repeating the first
+    pub targets: Vec<Scalar>,
+}
+
+impl Default for CompiledPredicate {
+    fn default() -> Self {
+        Self {
+            field: Scalar::zero(),
+            range: false,
+            member: false,
+            distinct: false,
+            contains: false,
+            lower: 0,
+            upper: 0,
+            targets: vec![Scalar::zero(); PREDICATE_SET_SIZE],
+        }
+    }
+}
+
+impl CompiledPredicate {
+    fn set_targets(&mut self, targets: &[Scalar]) {
+        self.targets = (0..PREDICATE_SET_SIZE).map(|i| *targets.get(i).unwrap_or(&targets[0])).collect();
+    }
+
+    fn is_active(&self) -> bool {
+        self.range || self.member || self.distinct || self.contains
+    }
+
+    /// Whether an attribute with `value` satisfies the predicate
+    pub fn holds(&self, value: &str) -> Result<bool, ZkError> {
+        let encoded = encode_attribute_value(value)?;
+        let first = self.targets[0];
+        Ok(if self.range {
+            numeric_attribute_value(value).is_some_and(|n| self.lower <= n && n <= self.upper)
+        } else if self.member {
+            self.targets.contains(&encoded)
+        } else if self.distinct {
+            encoded != first
+        } else if self.contains {
+            list_attribute_elements(value)?.is_some_and(|elements| elements.contains(&first))
+        } else {
+            true
+        })
+    }
+
+    /// Public inputs of the slot: field, the four flags, lower, upper, then the targets
+    pub fn public_inputs(&self) -> Vec<Scalar> {
+        let flag = |set: bool| Scalar::from(set as u64);
+        let mut inputs = vec![
+            self.field,
+            flag(self.range),
+            flag(self.member),
+            flag(self.distinct),
+            flag(self.contains),
+            scalar_from_u64(self.lower),
+            scalar_from_u64(self.upper),
+        ];
+        inputs.extend(&self.targets);
+        inputs
+    }
+}
+
+/// The statements of a request, padded with unused slots to `MAX_PREDICATES`
+pub fn compile_request(predicates: &[Predicate]) -> Result<Vec<CompiledPredicate>, ZkError> {
+    if predicates.len() > MAX_PREDICATES {
+        return Err(ZkError::InvalidInput(format!(
+            "{} predicates requested, at most {} are supported",
+            predicates.len(),
+            MAX_PREDICATES
+        )));
+    }
+    let mut compiled = predicates.iter().map(Predicate::compile).collect::<Result<Vec<_>, _>>()?;
+    compiled.resize(MAX_PREDICATES, CompiledPredicate::default());
+    Ok(compiled)
+}
+
+/// One predicate slot of `PredicateCircuit`
+#[derive(Clone)]
+pub struct PredicateSlot {
+    // Statement (public)
+    pub predicate: Option<CompiledPredicate>,
+    // Attribute slot the predicate is about (private); `None` for unused slots
+    pub attribute: Option<usize>,
+    // Element encodings of a list-valued attribute, LIST_ATTRIBUTE_ELEMENTS long (private)
+    pub elements: Vec<Option<Scalar>>,
+}
+
+impl PredicateSlot {
+    pub(crate) fn blank() -> Self {
+        Self { predicate: None, attribute: None, elements: vec![None; LIST_ATTRIBUTE_ELEMENTS] }
+    }
+
+    fn unused() -> Self {
+        Self {
+            predicate: Some(CompiledPredicate::default()),
+            attribute: None,
+            elements: vec![Some(Scalar::zero()); LIST_ATTRIBUTE_ELEMENTS],
+        }
+    }
+}
+
+/// The `MAX_PREDICATES` slots proving `predicates` about `credential`; fails if the
+/// credential does not satisfy every predicate
+pub(crate) fn predicate_slots(credential: &Credential, predicates: &[Predicate]) -> Result<Vec<PredicateSlot>, ZkError> {
+    let mut slots = Vec::with_capacity(MAX_PREDICATES);
+    for (predicate, compiled) in predicates.iter().zip(compile_request(predicates)?) {
+        let attribute = credential.attributes.iter().position(|a| a.name == predicate.field)
+            .ok_or_else(|| ZkError::InvalidInput(format!("Attribute not found: {}", predicate.field)))?;
+        let value = &credential.attributes[attribute].value;
+        if !compiled.holds(value)? {
+            return Err(predicate.invalid("is not satisfied by the credential"));
+        }
+
+        let elements = match compiled.contains {
+            true => list_attribute_elements(value)?.expect("contains holds only for list attributes"),
+            false => vec![Scalar::zero(); LIST_ATTRIBUTE_ELEMENTS],
+        };
+        slots.push(PredicateSlot {
+            predicate: Some(compiled),
+            attribute: Some(attribute),
+            elements: elements.into_iter().map(Some).collect(),
+        });
+    }
+    slots.resize(MAX_PREDICATES, PredicateSlot::unused());
+    Ok(slots)
+}
+
+/// A credential, its issuer's revocation registry and the predicates to prove about it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialPredicates {
+    pub credential: Credential,
+    pub predicates: Vec<Predicate>,
+    /// Empty if the issuer has not published a registry
+    #[serde(default)]
+    pub revocation_registry: RevocationRegistry,
+}
+
+/// What a verifier asks of one credential: the trusted issuer's hex-encoded public
+/// key and latest revocation root, and the predicates its credential must satisfy
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialRequest {
+    pub issuer_public_key: String,
+    pub revocation_root: String,
+    pub predicates: Vec<Predicate>,
+}
+
+impl CredentialRequest {
+    /// Request `predicates` of a credential by the issuer with `issuer_key` and revocation root `revocation_root`
+    pub fn new(issuer_key: &VerifyingKey, revocation_root: &Scalar, predicates: &[Predicate]) -> Self {
+        Self {
+            issuer_public_key: hex::encode(issuer_key.to_bytes()),
+            revocation_root: encode_scalar(revocation_root),
+            predicates: predicates.to_vec(),
+        }
+    }
+
+    pub fn issuer_key(&self) -> Result<VerifyingKey, ZkError> {
+        VerifyingKey::from_bytes(&hex::decode(&self.issuer_public_key)?)
+    }
+
+    pub fn revocation_root(&self) -> Result<Scalar, ZkError> {
+        decode_scalar(&self.revocation_root)
+    }
+}
+
+/// Proves that a credential signed under the public issuer key satisfies every
+/// predicate of a request (see the module documentation).
+///
+/// Public inputs: issuer public key `(u, v)`, the issuer's revocation root, then
+/// `MAX_PREDICATES` times the inputs of a `CompiledPredicate`, then the current
+/// time, the context id and the nullifier.
+#[derive(Clone)]
+pub struct PredicateCircuit {
+    // Credential and holder secret (private)
+    pub credential: CredentialValues,
+    // Issuer public key (public)
+    pub issuer_public_key: Option<(Scalar, Scalar)>,
+    // Root of the issuer's revocation tree (public)
+    pub revocation_root: Option<Scalar>,
+    // MAX_PREDICATES slots
+    pub predicates: Vec<PredicateSlot>,
+    // Verifier's current time in Unix seconds (public)
+    pub current_time: Option<Scalar>,
+    // Verifier/context id (public)
+    pub context: Option<Scalar>,
+}
+
+impl PredicateCircuit {
+    /// Circuit shape without a witness, for key generation
+    pub fn blank() -> Self {
+        Self {
+            credential: CredentialValues::blank(),
+            issuer_public_key: None,
+            revocation_root: None,
+            predicates: vec![PredicateSlot::blank(); MAX_PREDICATES],
+            current_time: None,
+            context: None,
+        }
+    }
+}
+
+impl Circuit<Scalar> for PredicateCircuit {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mimc = MiMC::new();
+
+        let current_time = AllocatedNum::alloc(cs.namespace(|| "current_time"), || {
+            self.current_time.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let credential = enforce_credential_predicates(
+            cs.namespace(|| "credential"),
+            &mimc,
+            &self.credential,
+            self.issuer_public_key,
+            self.revocation_root,
+            &self.predicates,
+            &current_time,
+        )?;
+        current_time.inputize(cs.namespace(|| "current_time input"))?;
+
+        // Nullifier (public) = H(context, holder secret)
+        let context = AllocatedNum::alloc(cs.namespace(|| "context"), || {
+            self.context.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        context.inputize(cs.namespace(|| "context input"))?;
+        nullifier_gadget(cs.namespace(|| "nullifier"), &mimc, &credential.holder_secret, &context)?
+            .inputize(cs.namespace(|| "nullifier input"))?;
+
+        Ok(())
+    }
+}
+
+/// Allocate a credential signed under the witnessed issuer key, unrevoked under
+/// the witnessed revocation root and unexpired at `current_time`, and enforce its
+/// predicate slots; the key, the root and the slots' statements become public inputs
+pub(crate) fn enforce_credential_predicates<CS: ConstraintSystem<Scalar>>(
+    mut cs: CS,
+    mimc: &MiMC,
+    values: &CredentialValues,
+    issuer_public_key: Option<(Scalar, Scalar)>,
+    revocation_root: Option<Scalar>,
+    slots: &[PredicateSlot],
+    current_time: &AllocatedNum<Scalar>,
+) -> Result<AllocatedCredential, SynthesisError> {
+    let issuer_key = EdwardsPoint::witness(cs.namespace(|| "issuer_public_key"), issuer_public_key)?;
+    issuer_key.inputize(cs.namespace(|| "issuer_public_key input"))?;
+    let revocation_root = AllocatedNum::alloc(cs.namespace(|| "revocation_root"), || {
+        revocation_root.ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    revocation_root.inputize(cs.namespace(|| "revocation_root input"))?;
+    let credential = AllocatedCredential::alloc(
+        cs.namespace(|| "credential"),
+        mimc,
+        values,
+        &issuer_key,
+        &revocation_root,
+        current_time,
+    )?;
+
+    for (i, slot) in slots.iter().enumerate() {
+        enforce_predicate(cs.namespace(|| format!("predicate {}", i)), mimc, &credential, slot)?;
+    }
+    Ok(credential)
+}
+
+fn enforce_predicate<CS: ConstraintSystem<Scalar>>(
+    mut cs: CS,
+    mimc: &MiMC,
+    credential: &AllocatedCredential,
+    slot: &PredicateSlot,
+) -> Result<(), SynthesisError> {
+    let statement = slot.predicate.as_ref();
+    let mut input = |name: &'static str, value: Option<Scalar>| {
+        cs.alloc_input(|| name, || value.ok_or(SynthesisError::AssignmentMissing))
+    };
+    let flag = |set: fn(&CompiledPredicate) -> bool| statement.map(|p| Scalar::from(set(p) as u64));
+
+    let field = input("field", statement.map(|p| p.field))?;
+    let range = input("range", flag(|p| p.range))?;
+    let member = input("member", flag(|p| p.member))?;
+    let distinct = input("distinct", flag(|p| p.distinct))?;
+    let contains = input("contains", flag(|p| p.contains))?;
+    let lower = input("lower", statement.map(|p| scalar_from_u64(p.lower)))?;
+    let upper = input("upper", statement.map(|p| scalar_from_u64(p.upper)))?;
+    let targets = (0..PREDICATE_SET_SIZE)
+        .map(|j| cs.alloc_input(|| format!("target {}", j), || {
+            statement.map(|p| p.targets[j]).ok_or(SynthesisError::AssignmentMissing)
+        }))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Select the attribute named `field`: one selector bit per attribute slot, exactly
+    // one set when the predicate is active and none otherwise
+    let selected_value = statement.map(|p| match slot.attribute {
+        Some(j) if p.is_active() => credential.attributes[j].1.get_value(),
+        _ => Some(Scalar::zero()),
+    });
+    let value = AllocatedNum::alloc(cs.namespace(|| "value"), || {
+        selected_value.flatten().ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    let mut selected = LinearCombination::zero();
+    for (j, (name, attribute_value)) in credential.attributes.iter().enumerate() {
+        let bit = AllocatedBit::alloc(
+            cs.namespace(|| format!("select {}", j)),
+            statement.map(|p| p.is_active() && slot.attribute == Some(j)),
+        )?;
+        cs.enforce(
+            || format!("selected name {} matches", j),
+            |lc| lc + bit.get_variable(),
+            |lc| lc + name.get_variable() - field,
+            |lc| lc,
+        );
+        cs.enforce(
+            || format!("selected value {} matches", j),
+            |lc| lc + bit.get_variable(),
+            |lc| lc + attribute_value.get_variable() - value.get_variable(),
+            |lc| lc,
+        );
+        selected = selected + bit.get_variable();
+    }
+    cs.enforce(
+        || "one attribute selected when active",
+        |_| selected,
+        |lc| lc + CS::one(),
+        |lc| lc + range + member + distinct + contains,
+    );
+
+    let value_u64 = value.get_value().map(scalar_to_u64);
+    let v = value.get_variable();
+
+    // Range: range * (value - lower) and range * (upper - value) fit in RANGE_BITS bits
+    let gaps = [
+        ("value >= lower", LinearCombination::zero() + v - lower, statement.zip(value_u64).map(|(p, n)| n.map(|n| n.wrapping_sub(p.lower)))),
+        ("value <= upper", LinearCombination::zero() + upper - v, statement.zip(value_u64).map(|(p, n)| n.map(|n| p.upper.wrapping_sub(n)))),
+    ];
+    for (name, difference, assignment) in gaps {
+        let mut cs = cs.namespace(|| name);
+        let gated = statement.zip(assignment).and_then(|(p, gap)| if p.range { gap } else { Some(0) });
+        let gated_num = AllocatedNum::alloc(cs.namespace(|| "gated"), || {
+            gated.map(scalar_from_u64).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        cs.enforce(
+            || "gate",
+            |lc| lc + range,
+            |_| difference,
+            |lc| lc + gated_num.get_variable(),
+        );
+        enforce_bits(cs.namespace(|| "bits"), LinearCombination::zero() + gated_num.get_variable(), gated, RANGE_BITS)?;
+    }
+
+    // Membership: member * Π (value - t_j) = 0
+    let target_values = statement.map(|p| p.targets.clone());
+    let factors = targets.iter().enumerate().map(|(j, t)| {
+        let assignment = value.get_value().zip(target_values.as_ref()).map(|(v, ts)| v - ts[j]);
+        (LinearCombination::zero() + v - *t, assignment)
+    });
+    enforce_vanishing_product(cs.namespace(|| "value in targets"), member, factors)?;
+
+    // Distinctness: (value - t_1) * inverse = distinct
+    let inverse = AllocatedNum::alloc(cs.namespace(|| "distinct inverse"), || {
+        let (on, first) = statement.map(|p| (p.distinct, p.targets[0])).ok_or(SynthesisError::AssignmentMissing)?;
+        let difference = value.get_value().ok_or(SynthesisError::AssignmentMissing)? - first;
+        Ok(if on { Option::from(difference.invert()).unwrap_or_else(Scalar::zero) } else { Scalar::zero() })
+    })?;
+    cs.enforce(
+        || "value differs from target",
+        |lc| lc + v - targets[0],
+        |lc| lc + inverse.get_variable(),
+        |lc| lc + distinct,
+    );
+
+    // Containment: contains * (H(elements) - value) = 0 and contains * Π (e_i - t_1) = 0
+    let elements = slot.elements.iter().enumerate()
+        .map(|(i, element)| AllocatedNum::alloc(cs.namespace(|| format!("element {}", i)), || {
+            element.ok_or(SynthesisError::AssignmentMissing)
+        }))
+        .collect::<Result<Vec<_>, _>>()?;
+    let list = mimc.hash_gadget(cs.namespace(|| "list commitment"), &elements)?;
+    cs.enforce(
+        || "list opens the value",
+        |lc| lc + contains,
+        |lc| lc + list.get_variable() - v,
+        |lc| lc,
+    );
+    let factors = elements.iter().map(|element| {
+        let assignment = element.get_value().zip(target_values.as_ref()).map(|(e, ts)| e - ts[0]);
+        (LinearCombination::zero() + element.get_variable() - targets[0], assignment)
+    });
+    enforce_vanishing_product(cs.namespace(|| "target in elements"), contains, factors)
+}
+
+// Enforce `flag * Π factors = 0`, multiplying the factors pairwise into fresh variables
+fn enforce_vanishing_product<CS, I>(mut cs: CS, flag: Variable, factors: I) -> Result<(), SynthesisError>
+where
+    CS: ConstraintSystem<Scalar>,
+    I: IntoIterator<Item = (LinearCombination<Scalar>, Option<Scalar>)>,
+{
+    let mut factors = factors.into_iter();
+    let (mut product, mut product_value) = factors.next().expect("at least one factor");
+    for (i, (factor, factor_value)) in factors.enumerate() {
+        let value = product_value.zip(factor_value).map(|(p, f)| p * f);
+        let next = AllocatedNum::alloc(cs.namespace(|| format!("product {}", i)), || {
+            value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        cs.enforce(
+            || format!("multiply {}", i),
+            |_| product,
+            |_| factor,
+            |lc| lc + next.get_variable(),
+        );
+        product = LinearCombination::zero() + next.get_variable();
+        product_value = value;
+    }
+    cs.enforce(
+        || "vanishes when enabled",
+        |lc| lc + flag,
+        |_| product,
+        |lc| lc,
+    );
+    Ok(())
+}
+
+// The value of a scalar below 2^64, if it is one
+fn scalar_to_u64(scalar: Scalar) -> Option<u64> {
+    let bytes = scalar.to_bytes();
+    bytes[8..].iter().all(|b| *b == 0).then(|| u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes")))
+}
+
+/// Public inputs of a proof that a credential satisfies `request`, presented in
+/// `context` at `current_time` with `nullifier`
+pub fn predicate_public_inputs(
+    request: &CredentialRequest,
+    context: &str,
+    current_time: u64,
+    nullifier: &Scalar,
+) -> Result<Vec<Scalar>, ZkError> {
+    let mut inputs = statement_inputs(request)?;
+    inputs.extend([scalar_from_u64(current_time), context_id(context), *nullifier]);
+    Ok(inputs)
+}
+
+/// Public inputs of `enforce_credential_predicates` for `request`: the issuer key,
+/// the revocation root, then every slot's statement
+pub(crate) fn statement_inputs(request: &CredentialRequest) -> Result<Vec<Scalar>, ZkError> {
+    let (u, v) = request.issuer_key()?.coordinates();
+    let mut inputs = vec![u, v, request.revocation_root()?];
+    for compiled in compile_request(&request.predicates)? {
+        inputs.extend(compiled.public_inputs());
+    }
+    Ok(inputs)
+}
+
+/// Compile the predicates of `entry` into a `PredicateCircuit` instance for its
+/// credential, held by the holder of `holder_secret_str` and presented in `context`
+/// at `current_time`, and its public inputs. Fails if the credential is not valid
+/// then, is revoked in the issuer's registry or does not satisfy every predicate.
+pub fn compile_predicates(
+    entry: &CredentialPredicates,
+    holder_secret_str: &str,
+    context: &str,
+    current_time: u64,
+) -> Result<(PredicateCircuit, Vec<Scalar>), ZkError> {
+    let (credential, registry) = (&entry.credential, &entry.revocation_registry);
+    let (values, issuer_key) = CredentialValues::new(credential, registry, holder_secret_str, current_time)?;
+    let revocation_root = registry.root()?;
+
+    let slots = predicate_slots(credential, &entry.predicates)?;
+
+    let holder_secret = values.holder_secret.expect("values carry the holder secret");
+    let inputs = predicate_public_inputs(
+        &CredentialRequest::new(&issuer_key, &revocation_root, &entry.predicates),
+        context,
+        current_time,
+        &nullifier(&holder_secret, &context_id(context)),
+    )?;
+    let circuit = PredicateCircuit {
+        credential: values,
+        issuer_public_key: Some(issuer_key.coordinates()),
+        revocation_root: Some(revocation_root),
+        predicates: slots,
+        current_time: Some(scalar_from_u64(current_time)),
+        context: Some(context_id(context)),
+    };
+    Ok((circuit, inputs))
+}
+
+/// Witness file for a predicate proof, as consumed by native tooling
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PredicateWitness {
+    pub credential: Credential,
+    pub holder_secret: String,
+    pub predicates: Vec<Predicate>,
+    /// Verifier/context id the nullifier is scoped to
+    pub context: String,
+    /// Verifier's current time in Unix seconds, which the credential must not have expired by
+    pub current_time: u64,
+    /// The issuer's revocation registry, empty if none was published
+    #[serde(default)]
+    pub revocation_registry: RevocationRegistry,
+}
+
+impl PredicateWitness {
+    /// Build the circuit instance and its public inputs
+    pub fn build_circuit(&self) -> Result<(PredicateCircuit, Vec<Scalar>), ZkError> {
+        let entry = CredentialPredicates {
+            credential: self.credential.clone(),
+            predicates: self.predicates.clone(),
+            revocation_registry: self.revocation_registry.clone(),
+        };
+        compile_predicates(&entry, &self.holder_secret, &self.context, self.current_time)
+    }
+}
+
+/// What a holder hands a verifier for a predicate request: the proof envelope and the
+/// hex-encoded nullifier. The verifier supplies the issuer key, predicates and context.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PredicatePresentation {
+    pub proof: ProofEnvelope,
+    pub nullifier: String,
+}
+
+impl PredicatePresentation {
+    /// The holder's nullifier, to check against a `NullifierRegistry` once the proof verifies
+    pub fn nullifier(&self) -> Result<Scalar, ZkError> {
+        decode_scalar(&self.nullifier)
+    }
+}
+
+/// Prove that the credential of `entry`, unrevoked in its issuer's registry, satisfies
+/// its predicates as the holder of `holder_secret_str`, to the verifier identified by
+/// `context` at its `current_time`
+pub fn create_predicate_proof<P: ProofSystem>(
+    system: &P,
+    pk: &P::ProvingKey,
+    entry: &CredentialPredicates,
+    holder_secret_str: &str,
+    context: &str,
+    current_time: u64,
+) -> Result<PredicatePresentation, ZkError> {
+    let (circuit, inputs) = compile_predicates(entry, holder_secret_str, context, current_time)?;
+    check_satisfied(circuit.clone())?;
+    let proof = system.prove(pk, circuit)?;
+
+    Ok(PredicatePresentation {
+        proof: ProofEnvelope::seal(system, pk, ProofType::Predicates, &proof, &inputs, Some(context))?,
+        nullifier: encode_scalar(&inputs[inputs.len() - 1]),
+    })
+}
+
+/// Verify that a presentation made to `context` proves the predicates of `request`
+/// about a credential signed under its trusted issuer key, absent from the issuer's
+/// revocation tree with its root and unexpired at `current_time`
+pub fn verify_predicate_proof<P: ProofSystem>(
+    system: &P,
+    pvk: &P::PreparedVerifyingKey,
+    presentation: &PredicatePresentation,
+    request: &CredentialRequest,
+    context: &str,
+    current_time: u64,
+) -> Result<bool, ZkError> {
+    let proof = presentation.proof.decode_proof(system)?;
+    let inputs = predicate_public_inputs(request, context, current_time, &presentation.nullifier()?)?;
+    system.verify(pvk, &proof, &inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::credential::{compute_holder_commitment, sign_credential, CredentialAttribute, CredentialType};
+    use crate::crypto::eddsa::SigningKey;
+    use crate::crypto::proof_system::Groth16;
+    use crate::crypto::zk_proofs::setup_parameters;
+    use bellman::gadgets::test::TestConstraintSystem;
+    use serde_json::json;
+
+    // 2024-07-03T09:46:40Z
+    const NOW: u64 = 1_720_000_000;
+
+    fn issuer_key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32]).unwrap()
+    }
+
+    fn credential() -> Credential {
+        let attribute = |name: &str, value: &str| CredentialAttribute {
+            name: name.to_string(),
+            value: value.to_string(),
+            reveal: false,
+            salt: None,
+        };
+        let unsigned = Credential {
+            id: "urn:uuid:3978344f-8596-4c3a-a978-8fcaba3903c5".to_string(),
+            issuer: "did:ethr:0x1234".to_string(),
+            subject: "did:ethr:0xabcd".to_string(),
+            type_: CredentialType::Identity,
+            attributes: vec![
+                attribute("name", "Alice"),
+                attribute("age", "34"),
+                attribute("nationality", "DE"),
+                attribute("languages", r#"["de","en"]"#),
+            ],
+            issued_at: "2024-01-01T00:00:00Z".to_string(),
+            expires_at: None,
+            revoked: false,
+            holder_commitment: Some(compute_holder_commitment("secret")),
+            issuer_public_key: None,
+            issuer_signature: None,
+            bbs_signature: None,
+        };
+        sign_credential(&unsigned, &issuer_key()).unwrap()
+    }
+
+    fn predicates(value: serde_json::Value) -> Vec<Predicate> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn adult_eu_citizen() -> Vec<Predicate> {
+        predicates(json!([
+            {"field": "age", "operator": ">=", "value": 18},
+            {"field": "nationality", "operator": "in", "value": ["AT", "BE", "DE", "FR", "NL"]},
+        ]))
+    }
+
+    fn entry(predicates: &[Predicate]) -> CredentialPredicates {
+        CredentialPredicates {
+            credential: credential(),
+            predicates: predicates.to_vec(),
+            revocation_registry: RevocationRegistry::new(),
+        }
+    }
+
+    fn request(predicates: &[Predicate], revocation_root: &Scalar) -> CredentialRequest {
+        CredentialRequest::new(&issuer_key().verifying_key(), revocation_root, predicates)
+    }
+
+    fn satisfied(predicates: &[Predicate]) -> bool {
+        let (circuit, inputs) = compile_predicates(&entry(predicates), "secret", "shop", NOW).unwrap();
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+        cs.is_satisfied() && cs.verify(&inputs)
+    }
+
+    #[test]
+    fn every_operator_compiles_to_a_satisfied_circuit() {
+        assert!(satisfied(&adult_eu_citizen()));
+        assert!(satisfied(&predicates(json!([
+            {"field": "age", "operator": "<", "value": "65"},
+            {"field": "name", "operator": "=", "value": "Alice"},
+            {"field": "nationality", "operator": "!=", "value": "FR"},
+            {"field": "languages", "operator": "contains", "value": "en"},
+        ]))));
+        assert!(satisfied(&[]));
+    }
+
+    #[test]
+    fn unsatisfied_predicates_are_rejected_before_proving() {
+        for unsatisfied in [
+            json!([{"field": "age", "operator": ">", "value": 40}]),
+            json!([{"field": "nationality", "operator": "in", "value": ["FR", "NL"]}]),
+            json!([{"field": "name", "operator": "!=", "value": "Alice"}]),
+            json!([{"field": "languages", "operator": "contains", "value": "fr"}]),
+            json!([{"field": "email", "operator": "=", "value": "alice@example.com"}]),
+        ] {
+            assert!(compile_predicates(&entry(&predicates(unsatisfied)), "secret", "shop", NOW).is_err());
+        }
+
+        let too_many = vec![adult_eu_citizen()[0].clone(); MAX_PREDICATES + 1];
+        assert!(compile_predicates(&entry(&too_many), "secret", "shop", NOW).is_err());
+    }
+
+    #[test]
+    fn witness_must_select_the_named_attribute() {
+        // Pointing the age predicate at the nationality slot does not satisfy the circuit
+        let (mut circuit, _) = compile_predicates(&entry(&adult_eu_citizen()), "secret", "shop", NOW).unwrap();
+        circuit.predicates[0].attribute = Some(2);
+        assert!(check_satisfied(circuit).is_err());
+
+        // An instance for one request does not verify against a stricter one
+        let (circuit, inputs) = compile_predicates(&entry(&adult_eu_citizen()), "secret", "shop", NOW).unwrap();
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+        let stricter = predicates(json!([
+            {"field": "age", "operator": ">=", "value": 40},
+            {"field": "nationality", "operator": "in", "value": ["AT", "BE", "DE", "FR", "NL"]},
+        ]));
+        let nullifier = inputs[inputs.len() - 1];
+        assert!(!cs.verify(&predicate_public_inputs(&request(&stricter, &RevocationRegistry::new().root().unwrap()), "shop", NOW, &nullifier).unwrap()));
+    }
+
+    #[test]
+    fn predicate_presentation_proves_and_verifies() {
+        let params = setup_parameters(PredicateCircuit::blank()).unwrap();
+        let pvk = Groth16.prepare_verifying_key(&params.vk).unwrap();
+        let mut entry = entry(&adult_eu_citizen());
+        entry.revocation_registry.revoke(&Credential { id: "urn:uuid:revoked".to_string(), ..credential() }).unwrap();
+        let root = entry.revocation_registry.root().unwrap();
+        let predicates = &entry.predicates;
+
+        let presentation = create_predicate_proof(&Groth16, &params, &entry, "secret", "shop", NOW).unwrap();
+        assert!(verify_predicate_proof(&Groth16, &pvk, &presentation, &request(predicates, &root), "shop", NOW).unwrap());
+        assert!(!verify_predicate_proof(&Groth16, &pvk, &presentation, &request(predicates, &root), "other-shop", NOW).unwrap());
+        assert!(!verify_predicate_proof(&Groth16, &pvk, &presentation, &request(&predicates[..1], &root), "shop", NOW).unwrap());
+
+        // The verifier checks against the issuer's latest root, not an earlier one
+        let stale = RevocationRegistry::new().root().unwrap();
+        assert!(!verify_predicate_proof(&Groth16, &pvk, &presentation, &request(predicates, &stale), "shop", NOW).unwrap());
+    }
+}
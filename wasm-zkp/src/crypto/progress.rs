@@ -0,0 +1,451 @@
+//! Progress reporting and cancellation for key generation and proving.
+//!
+//! Proving is synchronous: it holds the thread it runs on until the proof is
+//! done, so browsers run it in a web worker (see `src/lib/wasm-zkp/prover.worker.ts`)
+//! and follow it through a `ProgressObserver`. `Observed` wraps any
+//! `ProofSystem` so that every circuit it sets up or proves reports the
+//! constraints synthesized so far, every `REPORT_INTERVAL` constraints, and then
+//! the multiexponentiations, which bellman runs without further hooks.
+//!
+//! At each report the observer is also asked whether to stop; a cancelled run
+//! fails at its next allocation with `ZkError::Cancelled`. Once synthesis is
+//! over, a run can no longer be stopped from inside, and callers that must
+//! stop it anyway terminate the worker.
+
+use crate::crypto::proof_system::ProofSystem;
+use crate::error::ZkError;
+use bellman::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+use bls12_381::Scalar;
+use std::cell::Cell;
+use std::io;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+
+/// Constraints synthesized between two progress reports
+pub const REPORT_INTERVAL: usize = 1024;
+
+/// Phase of key generation or proving a progress report refers to
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProvingStage {
+    /// Synthesizing the circuit; progress counts constraints
+    Synthesis,
+    /// FFTs and multiexponentiations over the synthesized circuit; reported
+    /// once when they start (0 of 1) and once when they are done (1 of 1)
+    Multiexp,
+}
+
+/// Told how far a run has got, and asked whether it should stop
+pub trait ProgressObserver {
+    fn report(&self, stage: ProvingStage, done: usize, total: usize);
+
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// Observes nothing, for runs nobody follows
+impl ProgressObserver for () {
+    fn report(&self, _stage: ProvingStage, _done: usize, _total: usize) {}
+}
+
+/// Cancels a running key generation or proof.
+///
+/// A token made with `new` is cancelled through `cancel`, which only takes
+/// effect between runs when called on the thread doing the work, since that
+/// thread is busy until the run is over. To cancel a run in a web worker, share
+/// an `Int32Array` over a `SharedArrayBuffer` with the worker and build its
+/// token with `from_shared_flag`; the page then cancels with `Atomics.store(flag, 0, 1)`.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    flag: CancelFlag,
+}
+
+#[derive(Clone, Debug)]
+enum CancelFlag {
+    Local(Rc<Cell<bool>>),
+    // First element of an Int32Array over memory shared with another thread
+    Shared(js_sys::Int32Array),
+}
+
+impl Default for CancelFlag {
+    fn default() -> Self {
+        CancelFlag::Local(Rc::default())
+    }
+}
+
+#[wasm_bindgen]
+impl CancellationToken {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token cancelled once another thread stores a non-zero value at index 0 of `flag`
+    #[wasm_bindgen]
+    pub fn from_shared_flag(flag: js_sys::Int32Array) -> CancellationToken {
+        Self { flag: CancelFlag::Shared(flag) }
+    }
+
+    #[wasm_bindgen]
+    pub fn cancel(&self) {
+        match &self.flag {
+            CancelFlag::Local(cancelled) => cancelled.set(true),
+            CancelFlag::Shared(flag) => {
+                let _ = js_sys::Atomics::store(flag, 0, 1);
+            }
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn is_cancelled(&self) -> bool {
+        match &self.flag {
+            CancelFlag::Local(cancelled) => cancelled.get(),
+            CancelFlag::Shared(flag) => js_sys::Atomics::load(flag, 0).map(|value| value != 0).unwrap_or(false),
+        }
+    }
+}
+
+/// Reports progress to a JavaScript callback `(stage, done, total)` and stops
+/// once `token` is cancelled. Errors thrown by the callback are ignored.
+pub struct JsProgress {
+    callback: Option<js_sys::Function>,
+    token: Option<CancellationToken>,
+}
+
+impl JsProgress {
+    pub fn new(callback: Option<js_sys::Function>, token: Option<CancellationToken>) -> Self {
+        Self { callback, token }
+    }
+}
+
+impl ProgressObserver for JsProgress {
+    fn report(&self, stage: ProvingStage, done: usize, total: usize) {
+        if let Some(callback) = &self.callback {
+            let _ = callback.call3(&JsValue::NULL, &JsValue::from(stage), &JsValue::from(done), &JsValue::from(total));
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.token.as_ref().is_some_and(CancellationToken::is_cancelled)
+    }
+}
+
+/// Settle a promise with the outcome of a run that has already finished
+pub fn settle<T: Into<JsValue> + 'static>(result: Result<T, JsValue>) -> js_sys::Promise {
+    future_to_promise(async move { result.map(Into::into) })
+}
+
+/// A proof system whose setups and proofs report to `observer`; `constraints`
+/// is the size of the circuits it is used with, the total of synthesis reports
+pub struct Observed<'a, P, O: ?Sized> {
+    system: &'a P,
+    observer: &'a O,
+    constraints: usize,
+}
+
+impl<'a, P: ProofSystem, O: ProgressObserver + ?Sized> Observed<'a, P, O> {
+    pub fn new(system: &'a P, observer: &'a O, constraints: usize) -> Self {
+        Self { system, observer, constraints }
+    }
+
+    // Run `f` on the observed circuit, reporting the multiexponentiations after it
+    fn run<T, C: Circuit<Scalar>>(
+        &self,
+        circuit: C,
+        f: impl FnOnce(ObservedCircuit<'_, C, O>) -> Result<T, ZkError>,
+    ) -> Result<T, ZkError> {
+        if self.observer.is_cancelled() {
+            return Err(ZkError::Cancelled);
+        }
+        match f(ObservedCircuit { circuit, observer: self.observer, total: self.constraints }) {
+            Ok(value) => {
+                self.observer.report(ProvingStage::Multiexp, 1, 1);
+                Ok(value)
+            }
+            Err(_) if self.observer.is_cancelled() => Err(ZkError::Cancelled),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+impl<P: ProofSystem, O: ProgressObserver + ?Sized> ProofSystem for Observed<'_, P, O> {
+    type ProvingKey = P::ProvingKey;
+    type VerifyingKey = P::VerifyingKey;
+    type PreparedVerifyingKey = P::PreparedVerifyingKey;
+    type Proof = P::Proof;
+
+    fn name(&self) -> &'static str {
+        self.system.name()
+    }
+
+    fn setup<C: Circuit<Scalar>>(&self, circuit: C) -> Result<Self::ProvingKey, ZkError> {
+        self.run(circuit, |observed| self.system.setup(observed))
+    }
+
+    fn verifying_key(&self, pk: &Self::ProvingKey) -> Self::VerifyingKey {
+        self.system.verifying_key(pk)
+    }
+
+    fn prepare_verifying_key(&self, vk: &Self::VerifyingKey) -> Result<Self::PreparedVerifyingKey, ZkError> {
+        self.system.prepare_verifying_key(vk)
+    }
+
+    fn prove<C: Circuit<Scalar>>(&self, pk: &Self::ProvingKey, circuit: C) -> Result<Self::Proof, ZkError> {
+        self.run(circuit, |observed| self.system.prove(pk, observed))
+    }
+
+    fn verify(&self, pvk: &Self::PreparedVerifyingKey, proof: &Self::Proof, inputs: &[Scalar]) -> Result<bool, ZkError> {
+        self.system.verify(pvk, proof, inputs)
+    }
+
+    fn serialize_proof(&self, proof: &Self::Proof) -> Result<Vec<u8>, ZkError> {
+        self.system.serialize_proof(proof)
+    }
+
+    fn deserialize_proof(&self, bytes: &[u8]) -> Result<Self::Proof, ZkError> {
+        self.system.deserialize_proof(bytes)
+    }
+
+    fn serialize_proving_key(&self, pk: &Self::ProvingKey) -> Result<Vec<u8>, ZkError> {
+        self.system.serialize_proving_key(pk)
+    }
+
+    fn deserialize_proving_key(&self, bytes: &[u8]) -> Result<Self::ProvingKey, ZkError> {
+        self.system.deserialize_proving_key(bytes)
+    }
+
+    fn serialize_verifying_key(&self, vk: &Self::VerifyingKey) -> Result<Vec<u8>, ZkError> {
+        self.system.serialize_verifying_key(vk)
+    }
+
+    fn deserialize_verifying_key(&self, bytes: &[u8]) -> Result<Self::VerifyingKey, ZkError> {
+        self.system.deserialize_verifying_key(bytes)
+    }
+}
+
+/// A circuit that reports its synthesis to an observer and stops when it cancels
+pub struct ObservedCircuit<'a, C, O: ?Sized> {
+    circuit: C,
+    observer: &'a O,
+    total: usize,
+}
+
+impl<C: Circuit<Scalar>, O: ProgressObserver + ?Sized> Circuit<Scalar> for ObservedCircuit<'_, C, O> {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mut observed = ObservedCs { cs, observer: self.observer, constraints: 0, total: self.total, cancelled: false };
+        self.circuit.synthesize(&mut observed)?;
+
+        // Last chance to stop before the multiexponentiations
+        if self.observer.is_cancelled() {
+            return Err(cancelled());
+        }
+        self.observer.report(ProvingStage::Synthesis, observed.constraints, self.total.max(observed.constraints));
+        self.observer.report(ProvingStage::Multiexp, 0, 1);
+        Ok(())
+    }
+}
+
+fn cancelled() -> SynthesisError {
+    SynthesisError::IoError(io::Error::new(io::ErrorKind::Interrupted, "proving cancelled"))
+}
+
+// Counts the constraints passed on to `cs`, checking for cancellation at each report
+struct ObservedCs<'a, CS, O: ?Sized> {
+    cs: &'a mut CS,
+    observer: &'a O,
+    constraints: usize,
+    total: usize,
+    cancelled: bool,
+}
+
+impl<CS: ConstraintSystem<Scalar>, O: ProgressObserver + ?Sized> ConstraintSystem<Scalar> for ObservedCs<'_, CS, O> {
+    type Root = Self;
+
+    fn one() -> Variable {
+        CS::one()
+    }
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        if self.cancelled {
+            return Err(cancelled());
+        }
+        self.cs.alloc(annotation, f)
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        if self.cancelled {
+            return Err(cancelled());
+        }
+        self.cs.alloc_input(annotation, f)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        self.cs.enforce(annotation, a, b, c);
+        self.constraints += 1;
+        if self.constraints.is_multiple_of(REPORT_INTERVAL) {
+            self.observer.report(ProvingStage::Synthesis, self.constraints, self.total.max(self.constraints));
+            self.cancelled = self.observer.is_cancelled();
+        }
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.cs.get_root().push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self) {
+        self.cs.get_root().pop_namespace()
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// Number of constraints in `circuit`, counted without evaluating its witness
+pub fn count_constraints<C: Circuit<Scalar>>(circuit: C) -> Result<usize, ZkError> {
+    let mut counter = ConstraintCounter::default();
+    circuit.synthesize(&mut counter)?;
+    Ok(counter.constraints)
+}
+
+#[derive(Default)]
+struct ConstraintCounter {
+    variables: usize,
+    constraints: usize,
+}
+
+impl ConstraintSystem<Scalar> for ConstraintCounter {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _annotation: A, _f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.variables += 1;
+        Ok(Variable::new_unchecked(Index::Aux(self.variables - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _annotation: A, _f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // Index 0 is the constant one
+        self.variables += 1;
+        Ok(Variable::new_unchecked(Index::Input(self.variables)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _annotation: A, _a: LA, _b: LB, _c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        self.constraints += 1;
+    }
+
+    fn push_namespace<NR, N>(&mut self, _name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::proof_system::Groth16;
+    use crate::crypto::credential::CredentialCircuit;
+    use crate::crypto::zk_proofs::SquareDemo;
+    use std::cell::RefCell;
+
+    // Records every report, and cancels after `cancel_after` of them
+    #[derive(Default)]
+    struct Recorder {
+        reports: RefCell<Vec<(ProvingStage, usize, usize)>>,
+        cancel_after: Option<usize>,
+    }
+
+    impl ProgressObserver for Recorder {
+        fn report(&self, stage: ProvingStage, done: usize, total: usize) {
+            self.reports.borrow_mut().push((stage, done, total));
+        }
+
+        fn is_cancelled(&self) -> bool {
+            self.cancel_after.is_some_and(|after| self.reports.borrow().len() >= after)
+        }
+    }
+
+    #[test]
+    fn reports_synthesis_then_multiexp() {
+        let constraints = count_constraints(SquareDemo { x: None, y: None }).unwrap();
+        let recorder = Recorder::default();
+        let system = Observed::new(&Groth16, &recorder, constraints);
+
+        let pk = system.setup(SquareDemo { x: None, y: None }).unwrap();
+        let proof = system.prove(&pk, SquareDemo { x: Some(Scalar::from(3)), y: Some(Scalar::from(9)) }).unwrap();
+        let pvk = system.prepare_verifying_key(&system.verifying_key(&pk)).unwrap();
+        assert!(system.verify(&pvk, &proof, &[Scalar::from(9)]).unwrap());
+
+        let run = vec![
+            (ProvingStage::Synthesis, constraints, constraints),
+            (ProvingStage::Multiexp, 0, 1),
+            (ProvingStage::Multiexp, 1, 1),
+        ];
+        assert_eq!(*recorder.reports.borrow(), [run.clone(), run].concat());
+    }
+
+    #[test]
+    fn cancelled_runs_stop_during_synthesis() {
+        let constraints = count_constraints(CredentialCircuit::blank()).unwrap();
+        assert!(constraints > REPORT_INTERVAL);
+
+        let recorder = Recorder { cancel_after: Some(1), ..Recorder::default() };
+        let result = Observed::new(&Groth16, &recorder, constraints).setup(CredentialCircuit::blank());
+        assert!(matches!(result, Err(ZkError::Cancelled)));
+        assert_eq!(*recorder.reports.borrow(), [(ProvingStage::Synthesis, REPORT_INTERVAL, constraints)]);
+
+        // A token cancelled before the run stops it before any work
+        let token = CancellationToken::new();
+        token.cancel();
+        let observer = JsProgress::new(None, Some(token));
+        let result = Observed::new(&Groth16, &observer, constraints).setup(SquareDemo { x: None, y: None });
+        assert!(matches!(result, Err(ZkError::Cancelled)));
+    }
+}
@@ -0,0 +1,114 @@
+//! Proving backends for the `wasm-zkp` circuits.
+//!
+//! Circuits are written once against bellman's `Circuit<Scalar>` and proven
+//! with any `ProofSystem`. `Groth16` needs a trusted setup per circuit (see
+//! `crypto::ceremony`) but has the smallest proofs and cheapest verification;
+//! with the `universal` feature, `crypto::marlin::Marlin` derives the keys of
+//! every circuit from one universal reference string instead.
+
+use crate::crypto::batch::prepared_verifying_key;
+use crate::crypto::zk_proofs::{
+    deserialize_parameters, deserialize_proof, deserialize_verifying_key, serialize_parameters, serialize_proof,
+    serialize_verifying_key, verify_prepared_proof,
+};
+use crate::error::ZkError;
+use bellman::groth16::{self, create_random_proof, generate_random_parameters, Parameters, PreparedVerifyingKey};
+use bellman::Circuit;
+use bls12_381::{Bls12, Scalar};
+use rand::rngs::OsRng;
+use std::sync::Arc;
+
+/// A proof system for circuits over the BLS12-381 scalar field
+pub trait ProofSystem {
+    /// Everything needed to prove statements about one circuit, including its verifying key
+    type ProvingKey;
+    type VerifyingKey;
+    /// Verifying key in the form used for verification
+    type PreparedVerifyingKey;
+    type Proof;
+
+    /// Stable name recorded in proof envelopes: `groth16` or `marlin`
+    fn name(&self) -> &'static str;
+
+    /// Derive keys for the shape of `circuit`; its witness is not used
+    fn setup<C: Circuit<Scalar>>(&self, circuit: C) -> Result<Self::ProvingKey, ZkError>;
+
+    fn verifying_key(&self, pk: &Self::ProvingKey) -> Self::VerifyingKey;
+
+    fn prepare_verifying_key(&self, vk: &Self::VerifyingKey) -> Result<Self::PreparedVerifyingKey, ZkError>;
+
+    /// Prove that the witness carried by `circuit` satisfies it
+    fn prove<C: Circuit<Scalar>>(&self, pk: &Self::ProvingKey, circuit: C) -> Result<Self::Proof, ZkError>;
+
+    /// An invalid proof yields `Ok(false)`; a malformed key or input count is an error
+    fn verify(&self, pvk: &Self::PreparedVerifyingKey, proof: &Self::Proof, inputs: &[Scalar]) -> Result<bool, ZkError>;
+
+    fn serialize_proof(&self, proof: &Self::Proof) -> Result<Vec<u8>, ZkError>;
+    fn deserialize_proof(&self, bytes: &[u8]) -> Result<Self::Proof, ZkError>;
+    fn serialize_proving_key(&self, pk: &Self::ProvingKey) -> Result<Vec<u8>, ZkError>;
+    fn deserialize_proving_key(&self, bytes: &[u8]) -> Result<Self::ProvingKey, ZkError>;
+    fn serialize_verifying_key(&self, vk: &Self::VerifyingKey) -> Result<Vec<u8>, ZkError>;
+    fn deserialize_verifying_key(&self, bytes: &[u8]) -> Result<Self::VerifyingKey, ZkError>;
+}
+
+/// Groth16 over BLS12-381 (bellman), with a trusted setup per circuit.
+///
+/// `setup` samples the toxic waste locally; production keys come from a
+/// ceremony and are loaded with `deserialize_proving_key`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Groth16;
+
+impl ProofSystem for Groth16 {
+    type ProvingKey = Parameters<Bls12>;
+    type VerifyingKey = groth16::VerifyingKey<Bls12>;
+    type PreparedVerifyingKey = Arc<PreparedVerifyingKey<Bls12>>;
+    type Proof = groth16::Proof<Bls12>;
+
+    fn name(&self) -> &'static str {
+        "groth16"
+    }
+
+    fn setup<C: Circuit<Scalar>>(&self, circuit: C) -> Result<Self::ProvingKey, ZkError> {
+        Ok(generate_random_parameters::<Bls12, _, _>(circuit, &mut OsRng)?)
+    }
+
+    fn verifying_key(&self, pk: &Self::ProvingKey) -> Self::VerifyingKey {
+        pk.vk.clone()
+    }
+
+    fn prepare_verifying_key(&self, vk: &Self::VerifyingKey) -> Result<Self::PreparedVerifyingKey, ZkError> {
+        prepared_verifying_key(vk)
+    }
+
+    fn prove<C: Circuit<Scalar>>(&self, pk: &Self::ProvingKey, circuit: C) -> Result<Self::Proof, ZkError> {
+        Ok(create_random_proof(circuit, pk, &mut OsRng)?)
+    }
+
+    fn verify(&self, pvk: &Self::PreparedVerifyingKey, proof: &Self::Proof, inputs: &[Scalar]) -> Result<bool, ZkError> {
+        verify_prepared_proof(pvk, proof, inputs)
+    }
+
+    fn serialize_proof(&self, proof: &Self::Proof) -> Result<Vec<u8>, ZkError> {
+        serialize_proof(proof)
+    }
+
+    fn deserialize_proof(&self, bytes: &[u8]) -> Result<Self::Proof, ZkError> {
+        deserialize_proof(bytes)
+    }
+
+    fn serialize_proving_key(&self, pk: &Self::ProvingKey) -> Result<Vec<u8>, ZkError> {
+        serialize_parameters(pk)
+    }
+
+    fn deserialize_proving_key(&self, bytes: &[u8]) -> Result<Self::ProvingKey, ZkError> {
+        deserialize_parameters(bytes)
+    }
+
+    fn serialize_verifying_key(&self, vk: &Self::VerifyingKey) -> Result<Vec<u8>, ZkError> {
+        serialize_verifying_key(vk)
+    }
+
+    fn deserialize_verifying_key(&self, bytes: &[u8]) -> Result<Self::VerifyingKey, ZkError> {
+        deserialize_verifying_key(bytes)
+    }
+}
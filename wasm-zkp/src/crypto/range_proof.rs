@@ -0,0 +1,548 @@
+//! Range and comparison proofs over private numeric attributes.
+//!
+//! The circuit proves `lower <= value <= upper` for a private `value` and public
+//! bounds, by decomposing `value - lower` and `upper - value` into 64 boolean
+//! bits. Every comparison in the TypeScript `Predicate` type (`>`, `>=`, `<`,
+//! `<=`) maps onto one of these ranges, so a single set of keys serves them all.
+//!
+//! The value is not a free witness: when it issues the attribute, the issuer signs
+//! `H(name, value, blinding, holder commitment)` over the domain-separated
+//! attribute name and the holder's `credential::holder_commitment` (see
+//! `SignedValueCommitment`). The circuit recomputes that message from the holder
+//! secret and verifies the issuer's EdDSA signature on it, so the value, the
+//! commitment and the signature all stay private: a proof reveals only the issuer
+//! key, the attribute name and the bounds, plus the holder's nullifier for the
+//! verifier's context (see `crypto::nullifier`), and proofs to different
+//! verifiers cannot be linked.
+
+use crate::crypto::credential::holder_commitment;
+use crate::crypto::eddsa::{verify_signature_gadget, EdwardsPoint, Signature, SigningKey, VerifyingKey};
+use crate::crypto::envelope::ProofEnvelope;
+use crate::crypto::hash_to_field::{hash_str_to_field, HashDomain};
+use crate::crypto::mimc::MiMC;
+use crate::crypto::nullifier::{context_id, nullifier, nullifier_gadget};
+use crate::crypto::proof_system::ProofSystem;
+use crate::crypto::zk_proofs::{check_satisfied, decode_scalar, encode_scalar, scalar_from_u64, ProofType};
+use crate::error::ZkError;
+use bellman::{
+    gadgets::{boolean::AllocatedBit, num::AllocatedNum},
+    Circuit, ConstraintSystem, LinearCombination, SynthesisError,
+};
+use bls12_381::Scalar;
+use ff::Field;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// Width of the range check. Attributes and bounds are `u64`, so 64 bits
+/// cannot overflow the scalar field.
+pub const RANGE_BITS: usize = 64;
+
+/// Numeric comparison of a private attribute against a public threshold
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    #[serde(rename = ">")]
+    GreaterThan,
+    #[serde(rename = ">=")]
+    GreaterOrEqual,
+    #[serde(rename = "<")]
+    LessThan,
+    #[serde(rename = "<=")]
+    LessOrEqual,
+}
+
+impl Comparison {
+    /// The inclusive `[lower, upper]` range satisfied exactly by values passing the comparison
+    pub fn bounds(&self, threshold: u64) -> Result<(u64, u64), ZkError> {
+        let unsatisfiable = || ZkError::InvalidInput(format!("no u64 value is {} {}", self.symbol(), threshold));
+
+        match self {
+            Comparison::GreaterThan => Ok((threshold.checked_add(1).ok_or_else(unsatisfiable)?, u64::MAX)),
+            Comparison::GreaterOrEqual => Ok((threshold, u64::MAX)),
+            Comparison::LessThan => Ok((0, threshold.checked_sub(1).ok_or_else(unsatisfiable)?)),
+            Comparison::LessOrEqual => Ok((0, threshold)),
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::GreaterThan => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::LessThan => "<",
+            Comparison::LessOrEqual => "<=",
+        }
+    }
+
+    pub fn holds(&self, value: u64, threshold: u64) -> bool {
+        match self {
+            Comparison::GreaterThan => value > threshold,
+            Comparison::GreaterOrEqual => value >= threshold,
+            Comparison::LessThan => value < threshold,
+            Comparison::LessOrEqual => value <= threshold,
+        }
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = ZkError;
+
+    fn from_str(symbol: &str) -> Result<Self, Self::Err> {
+        match symbol {
+            ">" => Ok(Comparison::GreaterThan),
+            ">=" => Ok(Comparison::GreaterOrEqual),
+            "<" => Ok(Comparison::LessThan),
+            "<=" => Ok(Comparison::LessOrEqual),
+            _ => Err(ZkError::InvalidInput(format!("unsupported comparison: {}", symbol))),
+        }
+    }
+}
+
+/// The message an issuer signs for a numeric attribute:
+/// `H(H(attribute name), value, blinding, holder commitment)`
+pub fn value_commitment(attribute: &str, value: u64, blinding: &Scalar, holder_commitment: &Scalar) -> Scalar {
+    MiMC::new().hash(&[
+        hash_str_to_field(HashDomain::AttributeName, attribute),
+        scalar_from_u64(value),
+        *blinding,
+        *holder_commitment,
+    ])
+}
+
+/// A numeric attribute signed by its issuer for one holder. It stays with the holder,
+/// who proves statements about it with `create_range_proof`; verifiers never see it.
+/// The blinding, issuer key and signature are hex-encoded.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedValueCommitment {
+    pub attribute: String,
+    pub value: u64,
+    pub blinding: String,
+    pub issuer_public_key: String,
+    pub signature: String,
+}
+
+impl SignedValueCommitment {
+    /// Sign `attribute = value` under `blinding` as its issuer, for the holder with `holder_commitment`
+    pub fn issue(issuer_key: &SigningKey, attribute: &str, value: u64, blinding: &Scalar, holder_commitment: &Scalar) -> Self {
+        let message = value_commitment(attribute, value, blinding, holder_commitment);
+        Self {
+            attribute: attribute.to_string(),
+            value,
+            blinding: encode_scalar(blinding),
+            issuer_public_key: hex::encode(issuer_key.verifying_key().to_bytes()),
+            signature: hex::encode(issuer_key.sign(&message).to_bytes()),
+        }
+    }
+
+    /// The issuer key and signature, once the signature verifies for the holder with `holder_commitment`
+    pub fn verify(&self, holder_commitment: &Scalar) -> Result<(VerifyingKey, Signature), ZkError> {
+        let issuer_key = VerifyingKey::from_bytes(&hex::decode(&self.issuer_public_key)?)?;
+        let signature = Signature::from_bytes(&hex::decode(&self.signature)?)?;
+        let message = value_commitment(&self.attribute, self.value, &decode_scalar(&self.blinding)?, holder_commitment);
+
+        if !issuer_key.verify(&message, &signature) {
+            return Err(ZkError::InvalidInput(format!("{} is not signed by the issuer for this holder", self.attribute)));
+        }
+        Ok((issuer_key, signature))
+    }
+}
+
+/// Issue a numeric attribute to the holder with the hex-encoded `holder_commitment`
+/// (see `compute_holder_commitment`): picks a fresh blinding and signs the attribute
+/// with the hex-encoded issuer secret key. Returns the `SignedValueCommitment` as JSON,
+/// for the holder only.
+#[wasm_bindgen]
+pub fn issue_value_commitment(issuer_secret_key: &str, attribute: &str, value: u64, holder_commitment: &str) -> Result<String, JsValue> {
+    let issuer_key = SigningKey::from_bytes(&hex::decode(issuer_secret_key).map_err(ZkError::from)?)?;
+    let holder_commitment = decode_scalar(holder_commitment)?;
+    let signed = SignedValueCommitment::issue(&issuer_key, attribute, value, &Scalar::random(&mut OsRng), &holder_commitment);
+    Ok(serde_json::to_string(&signed).map_err(ZkError::from)?)
+}
+
+/// Proves that the holder of a secret knows a numeric attribute signed for them
+/// under the public issuer key, with `lower <= value <= upper`.
+///
+/// Public inputs: issuer public key `(u, v)`, the attribute name's field encoding,
+/// `lower`, `upper`, then the context id and the nullifier.
+#[derive(Clone)]
+pub struct RangeProofCircuit {
+    // Holder secret, attribute value, blinding and issuer signature (private)
+    pub holder_secret: Option<Scalar>,
+    pub value: Option<u64>,
+    pub blinding: Option<Scalar>,
+    pub signature: Option<Signature>,
+    // Issuer public key (public)
+    pub issuer_public_key: Option<(Scalar, Scalar)>,
+    // Attribute name (public)
+    pub attribute: Option<Scalar>,
+    // Bounds (public)
+    pub lower: Option<u64>,
+    pub upper: Option<u64>,
+    // Verifier/context id (public)
+    pub context: Option<Scalar>,
+}
+
+impl RangeProofCircuit {
+    /// Circuit shape without a witness, for parameter generation
+    pub fn blank() -> Self {
+        Self {
+            holder_secret: None,
+            value: None,
+            blinding: None,
+            signature: None,
+            issuer_public_key: None,
+            attribute: None,
+            lower: None,
+            upper: None,
+            context: None,
+        }
+    }
+}
+
+impl Circuit<Scalar> for RangeProofCircuit {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let mimc = MiMC::new();
+
+        let issuer_key = EdwardsPoint::witness(cs.namespace(|| "issuer_public_key"), self.issuer_public_key)?;
+        issuer_key.inputize(cs.namespace(|| "issuer_public_key input"))?;
+        let attribute = AllocatedNum::alloc(cs.namespace(|| "attribute"), || {
+            self.attribute.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        attribute.inputize(cs.namespace(|| "attribute input"))?;
+
+        // Signed message = H(attribute, value, blinding, H(holder secret))
+        let holder_secret = AllocatedNum::alloc(cs.namespace(|| "holder_secret"), || {
+            self.holder_secret.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let holder_commitment = mimc.hash_gadget(cs.namespace(|| "holder_commitment"), std::slice::from_ref(&holder_secret))?;
+        let value_num = AllocatedNum::alloc(cs.namespace(|| "value"), || {
+            self.value.map(scalar_from_u64).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let blinding = AllocatedNum::alloc(cs.namespace(|| "blinding"), || {
+            self.blinding.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let message = mimc.hash_gadget(
+            cs.namespace(|| "signed message"),
+            &[attribute, value_num.clone(), blinding, holder_commitment],
+        )?;
+        verify_signature_gadget(
+            cs.namespace(|| "issuer signature verification"),
+            &mimc,
+            &issuer_key,
+            &message,
+            self.signature.as_ref(),
+        )?;
+
+        let value = value_num.get_variable();
+        let lower = cs.alloc_input(
+            || "lower",
+            || self.lower.map(scalar_from_u64).ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        let upper = cs.alloc_input(
+            || "upper",
+            || self.upper.map(scalar_from_u64).ok_or(SynthesisError::AssignmentMissing),
+        )?;
+
+        // value - lower and upper - value must both fit in RANGE_BITS bits; a negative
+        // difference wraps to a field element far above 2^64 and has no such decomposition
+        let above_lower = self.value.zip(self.lower).map(|(v, l)| v.wrapping_sub(l));
+        enforce_bits(
+            cs.namespace(|| "value >= lower"),
+            LinearCombination::zero() + value - lower,
+            above_lower,
+            RANGE_BITS,
+        )?;
+
+        let below_upper = self.upper.zip(self.value).map(|(u, v)| u.wrapping_sub(v));
+        enforce_bits(
+            cs.namespace(|| "value <= upper"),
+            LinearCombination::zero() + upper - value,
+            below_upper,
+            RANGE_BITS,
+        )?;
+
+        // Nullifier (public) = H(context, holder secret)
+        let context = AllocatedNum::alloc(cs.namespace(|| "context"), || {
+            self.context.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        context.inputize(cs.namespace(|| "context input"))?;
+        nullifier_gadget(cs.namespace(|| "nullifier"), &mimc, &holder_secret, &context)?
+            .inputize(cs.namespace(|| "nullifier input"))?;
+
+        Ok(())
+    }
+}
+
+/// Constrain `lc` to equal `Σ 2^i · b_i` over `bits` allocated boolean bits,
+/// i.e. to lie in `[0, 2^bits)`. `assignment` is the prover's claimed value.
+pub fn enforce_bits<CS: ConstraintSystem<Scalar>>(
+    mut cs: CS,
+    lc: LinearCombination<Scalar>,
+    assignment: Option<u64>,
+    bits: usize,
+) -> Result<Vec<AllocatedBit>, SynthesisError> {
+    let mut allocated = Vec::with_capacity(bits);
+    let mut sum = LinearCombination::zero();
+    let mut coeff = Scalar::one();
+
+    for i in 0..bits {
+        let bit = AllocatedBit::alloc(
+            cs.namespace(|| format!("bit {}", i)),
+            assignment.map(|v| i < 64 && (v >> i) & 1 == 1),
+        )?;
+        sum = sum + (coeff, bit.get_variable());
+        coeff = coeff.double();
+        allocated.push(bit);
+    }
+
+    cs.enforce(
+        || "bit decomposition",
+        |_| sum,
+        |lc| lc + CS::one(),
+        |_| lc,
+    );
+
+    Ok(allocated)
+}
+
+/// What a verifier asks of a signed numeric attribute: the trusted issuer's hex-encoded
+/// public key, the attribute name and the comparison its value must pass, e.g.
+/// `{"issuerPublicKey": "<hex>", "attribute": "age", "comparison": ">=", "threshold": 18}`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeRequest {
+    pub issuer_public_key: String,
+    pub attribute: String,
+    pub comparison: Comparison,
+    pub threshold: u64,
+}
+
+impl RangeRequest {
+    pub fn issuer_key(&self) -> Result<VerifyingKey, ZkError> {
+        VerifyingKey::from_bytes(&hex::decode(&self.issuer_public_key)?)
+    }
+}
+
+/// Witness file for the range-proof circuit: the holder's signed attribute and secret,
+/// the comparison to prove and the verifier/context id
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeWitness {
+    pub signed_commitment: SignedValueCommitment,
+    pub holder_secret: String,
+    pub comparison: Comparison,
+    pub threshold: u64,
+    pub context: String,
+}
+
+impl RangeWitness {
+    pub fn build_circuit(&self) -> Result<(RangeProofCircuit, Vec<Scalar>), ZkError> {
+        build_range_proof_circuit(&self.signed_commitment, &self.holder_secret, self.comparison, self.threshold, &self.context)
+    }
+}
+
+/// Build a range-proof instance for `value <op> threshold` about the attribute in `signed`,
+/// held by the holder of `holder_secret_str` and presented in `context`, and its public
+/// inputs. Fails unless the attribute is signed for that holder.
+pub fn build_range_proof_circuit(
+    signed: &SignedValueCommitment,
+    holder_secret_str: &str,
+    comparison: Comparison,
+    threshold: u64,
+    context: &str,
+) -> Result<(RangeProofCircuit, Vec<Scalar>), ZkError> {
+    let (issuer_key, signature) = signed.verify(&holder_commitment(holder_secret_str))?;
+    let (lower, upper) = comparison.bounds(threshold)?;
+    let holder_secret = hash_str_to_field(HashDomain::HolderSecret, holder_secret_str);
+
+    let request = RangeRequest {
+        issuer_public_key: signed.issuer_public_key.clone(),
+        attribute: signed.attribute.clone(),
+        comparison,
+        threshold,
+    };
+    let inputs = range_public_inputs(&request, context, &nullifier(&holder_secret, &context_id(context)))?;
+    let circuit = RangeProofCircuit {
+        holder_secret: Some(holder_secret),
+        value: Some(signed.value),
+        blinding: Some(decode_scalar(&signed.blinding)?),
+        signature: Some(signature),
+        issuer_public_key: Some(issuer_key.coordinates()),
+        attribute: Some(hash_str_to_field(HashDomain::AttributeName, &signed.attribute)),
+        lower: Some(lower),
+        upper: Some(upper),
+        context: Some(context_id(context)),
+    };
+    Ok((circuit, inputs))
+}
+
+/// Public inputs of a proof answering `request` in `context` with `nullifier` (see
+/// `RangeProofCircuit` for their layout)
+pub fn range_public_inputs(request: &RangeRequest, context: &str, nullifier: &Scalar) -> Result<Vec<Scalar>, ZkError> {
+    let (u, v) = request.issuer_key()?.coordinates();
+    let (lower, upper) = request.comparison.bounds(request.threshold)?;
+    Ok(vec![
+        u,
+        v,
+        hash_str_to_field(HashDomain::AttributeName, &request.attribute),
+        scalar_from_u64(lower),
+        scalar_from_u64(upper),
+        context_id(context),
+        *nullifier,
+    ])
+}
+
+/// What a holder hands a verifier for a range request: the proof envelope and the
+/// hex-encoded nullifier
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RangePresentation {
+    pub proof: ProofEnvelope,
+    pub nullifier: String,
+}
+
+impl RangePresentation {
+    /// The holder's nullifier, to check against a `NullifierRegistry` once the proof verifies
+    pub fn nullifier(&self) -> Result<Scalar, ZkError> {
+        decode_scalar(&self.nullifier)
+    }
+}
+
+/// Prove that the attribute in `signed`, held by the holder of `holder_secret_str`,
+/// satisfies `value <op> threshold`, to the verifier identified by `context`
+pub fn create_range_proof<P: ProofSystem>(
+    system: &P,
+    pk: &P::ProvingKey,
+    signed: &SignedValueCommitment,
+    holder_secret_str: &str,
+    comparison: Comparison,
+    threshold: u64,
+    context: &str,
+) -> Result<RangePresentation, ZkError> {
+    if !comparison.holds(signed.value, threshold) {
+        return Err(ZkError::InvalidInput(format!(
+            "{} does not satisfy {} {}",
+            signed.attribute,
+            comparison.symbol(),
+            threshold
+        )));
+    }
+
+    let (circuit, inputs) = build_range_proof_circuit(signed, holder_secret_str, comparison, threshold, context)?;
+    check_satisfied(circuit.clone())?;
+    let proof = system.prove(pk, circuit)?;
+
+    Ok(RangePresentation {
+        proof: ProofEnvelope::seal(system, pk, ProofType::RangeProof, &proof, &inputs, Some(context))?,
+        nullifier: encode_scalar(&inputs[inputs.len() - 1]),
+    })
+}
+
+/// Verify that a presentation made to `context` answers `request` about an attribute
+/// signed under its trusted issuer key
+pub fn verify_range_proof<P: ProofSystem>(
+    system: &P,
+    pvk: &P::PreparedVerifyingKey,
+    presentation: &RangePresentation,
+    request: &RangeRequest,
+    context: &str,
+) -> Result<bool, ZkError> {
+    let proof = presentation.proof.decode_proof(system)?;
+    system.verify(pvk, &proof, &range_public_inputs(request, context, &presentation.nullifier()?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::proof_system::Groth16;
+    use crate::crypto::zk_proofs::setup_parameters;
+    use bellman::gadgets::test::TestConstraintSystem;
+
+    fn issuer_key() -> SigningKey {
+        SigningKey::from_bytes(&[3; 32]).unwrap()
+    }
+
+    fn signed(attribute: &str, value: u64) -> SignedValueCommitment {
+        SignedValueCommitment::issue(&issuer_key(), attribute, value, &Scalar::from(7u64), &holder_commitment("secret"))
+    }
+
+    fn request(attribute: &str, comparison: Comparison, threshold: u64) -> RangeRequest {
+        RangeRequest {
+            issuer_public_key: hex::encode(issuer_key().verifying_key().to_bytes()),
+            attribute: attribute.to_string(),
+            comparison,
+            threshold,
+        }
+    }
+
+    #[test]
+    fn age_over_threshold_proves_and_verifies() {
+        let params = setup_parameters(RangeProofCircuit::blank()).unwrap();
+        let pvk = Groth16.prepare_verifying_key(&params.vk).unwrap();
+        let age = signed("age", 34);
+
+        let presentation = create_range_proof(&Groth16, &params, &age, "secret", Comparison::GreaterOrEqual, 18, "shop").unwrap();
+        assert!(verify_range_proof(&Groth16, &pvk, &presentation, &request("age", Comparison::GreaterOrEqual, 18), "shop").unwrap());
+        assert!(!verify_range_proof(&Groth16, &pvk, &presentation, &request("age", Comparison::GreaterOrEqual, 40), "shop").unwrap());
+
+        // The proof is about the named attribute, to one verifier
+        assert!(!verify_range_proof(&Groth16, &pvk, &presentation, &request("salary", Comparison::GreaterOrEqual, 18), "shop").unwrap());
+        assert!(!verify_range_proof(&Groth16, &pvk, &presentation, &request("age", Comparison::GreaterOrEqual, 18), "bank").unwrap());
+
+        // Nothing in it identifies the signed attribute
+        let json = serde_json::to_string(&presentation).unwrap();
+        assert!(!json.contains(&age.signature) && !json.contains(&age.blinding));
+
+        let salary = signed("salary", 52_000);
+        let presentation = create_range_proof(&Groth16, &params, &salary, "secret", Comparison::LessThan, 60_000, "bank").unwrap();
+        assert!(verify_range_proof(&Groth16, &pvk, &presentation, &request("salary", Comparison::LessThan, 60_000), "bank").unwrap());
+    }
+
+    #[test]
+    fn attribute_must_be_signed_by_the_issuer_for_the_holder() {
+        let age = signed("age", 34);
+        assert_eq!(age.verify(&holder_commitment("secret")).unwrap().0, issuer_key().verifying_key());
+        assert!(age.verify(&holder_commitment("other secret")).is_err());
+        assert!(build_range_proof_circuit(&age, "other secret", Comparison::GreaterOrEqual, 18, "shop").is_err());
+
+        for forged in [
+            SignedValueCommitment { value: 99, ..age.clone() },
+            SignedValueCommitment { attribute: "salary".to_string(), ..age.clone() },
+            SignedValueCommitment { issuer_public_key: hex::encode(SigningKey::generate().verifying_key().to_bytes()), ..age.clone() },
+        ] {
+            assert!(forged.verify(&holder_commitment("secret")).is_err());
+        }
+
+        // A witness whose value, attribute or holder differs from what was signed is unsatisfied
+        let (circuit, _) = build_range_proof_circuit(&age, "secret", Comparison::GreaterOrEqual, 18, "shop").unwrap();
+        let mut inflated = circuit.clone();
+        inflated.value = Some(99);
+        assert!(check_satisfied(inflated).is_err());
+        let mut renamed = circuit.clone();
+        renamed.attribute = Some(hash_str_to_field(HashDomain::AttributeName, "salary"));
+        assert!(check_satisfied(renamed).is_err());
+        let mut stolen = circuit;
+        stolen.holder_secret = Some(hash_str_to_field(HashDomain::HolderSecret, "other secret"));
+        assert!(check_satisfied(stolen).is_err());
+    }
+
+    #[test]
+    fn witness_outside_range_is_unsatisfied() {
+        for (value, comparison, threshold) in [
+            (17, Comparison::GreaterOrEqual, 18),
+            (18, Comparison::GreaterThan, 18),
+            (0, Comparison::GreaterThan, 0),
+            (u64::MAX, Comparison::LessThan, u64::MAX),
+        ] {
+            let (circuit, _) = build_range_proof_circuit(&signed("age", value), "secret", comparison, threshold, "shop").unwrap();
+            assert!(check_satisfied(circuit).is_err(), "{} {} {}", value, comparison.symbol(), threshold);
+        }
+
+        let (circuit, inputs) = build_range_proof_circuit(&signed("age", u64::MAX), "secret", Comparison::GreaterOrEqual, 0, "shop").unwrap();
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied() && cs.verify(&inputs));
+        assert!(Comparison::LessThan.bounds(0).is_err());
+    }
+}
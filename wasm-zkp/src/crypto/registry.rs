@@ -0,0 +1,392 @@
+//! Circuits by `ProofType`, and the keys a prover holds for each.
+//!
+//! `blank_circuit` is the one place a `CircuitId` (a `ProofType`, plus the number
+//! of credentials for conjunctions) is mapped to the circuit its keys are made
+//! for: setup, constraint counting and universal sizing all go through it.
+//!
+//! `CircuitRegistry` keeps each circuit's keys behind a `KeySource` that is only
+//! read the first time the circuit is used, so a prover can be handed every key
+//! a frontend may ask for and only pay for deserializing (or, under the
+//! universal backend, deriving) the ones a session proves with. Registering or
+//! inserting keys for a circuit replaces the ones it had, so keys can be
+//! swapped at runtime without a new prover.
+
+use crate::crypto::ceremony::{MPCParameters, PowersOfTau};
+use crate::crypto::conjunction::{ConjunctionCircuit, MAX_CONJUNCTION_CREDENTIALS};
+use crate::crypto::credential::CredentialCircuit;
+use crate::crypto::did_resolver::DIDOwnershipCircuit;
+use crate::crypto::envelope::verifying_key_hash;
+#[cfg(feature = "universal")]
+use crate::crypto::marlin::CircuitSize;
+use crate::crypto::merkle::{SetMembershipCircuit, MEMBERSHIP_TREE_DEPTH};
+use crate::crypto::predicate::PredicateCircuit;
+use crate::crypto::progress::count_constraints;
+use crate::crypto::proof_system::ProofSystem;
+use crate::crypto::range_proof::RangeProofCircuit;
+use crate::crypto::zk_proofs::{ProofType, SquareDemo};
+use crate::error::ZkError;
+use bellman::Circuit;
+use bls12_381::Scalar;
+use std::cell::{Cell, OnceCell};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// A circuit with keys of its own: the one behind a `ProofType` and, for
+/// conjunctions, the number of credentials, which sets the circuit's shape
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CircuitId {
+    pub proof_type: ProofType,
+    credentials: usize,
+}
+
+impl CircuitId {
+    /// The conjunction circuit over `credentials` credentials, from 2 to `MAX_CONJUNCTION_CREDENTIALS`
+    pub fn conjunction(credentials: usize) -> Result<Self, ZkError> {
+        if !(2..=MAX_CONJUNCTION_CREDENTIALS).contains(&credentials) {
+            return Err(ZkError::InvalidInput(format!(
+                "conjunction proofs cover 2 to {} credentials, got {}",
+                MAX_CONJUNCTION_CREDENTIALS, credentials
+            )));
+        }
+        Ok(Self { proof_type: ProofType::Conjunction, credentials })
+    }
+
+    /// The circuit behind `proof_type`; `credentials` is the size of a conjunction
+    /// (two when omitted) and is rejected for other proof types
+    pub fn new(proof_type: ProofType, credentials: Option<usize>) -> Result<Self, ZkError> {
+        match (proof_type, credentials) {
+            (ProofType::Conjunction, credentials) => Self::conjunction(credentials.unwrap_or(2)),
+            (proof_type, None) => Ok(proof_type.into()),
+            (proof_type, Some(_)) => Err(ZkError::InvalidInput(format!(
+                "{} circuits do not take a number of credentials",
+                proof_type.name()
+            ))),
+        }
+    }
+
+    /// Every circuit, with one per conjunction size
+    pub fn all() -> impl Iterator<Item = CircuitId> {
+        let single = ProofType::ALL.into_iter().filter(|proof_type| *proof_type != ProofType::Conjunction);
+        let conjunctions = (2..=MAX_CONJUNCTION_CREDENTIALS)
+            .map(|credentials| CircuitId { proof_type: ProofType::Conjunction, credentials });
+        single.map(CircuitId::from).chain(conjunctions)
+    }
+
+    /// Credentials a conjunction covers; zero for other circuits
+    pub fn credentials(&self) -> usize {
+        self.credentials
+    }
+
+    /// `ProofType::name`, suffixed with the number of credentials for conjunctions
+    pub fn name(&self) -> String {
+        match self.proof_type {
+            ProofType::Conjunction => format!("{}-{}", self.proof_type.name(), self.credentials),
+            proof_type => proof_type.name().to_string(),
+        }
+    }
+}
+
+/// The circuit behind `proof_type`; conjunctions over two credentials
+impl From<ProofType> for CircuitId {
+    fn from(proof_type: ProofType) -> Self {
+        let credentials = if proof_type == ProofType::Conjunction { 2 } else { 0 };
+        Self { proof_type, credentials }
+    }
+}
+
+/// Something done with a circuit of any shape, e.g. generating its keys
+pub trait CircuitVisitor {
+    type Output;
+
+    fn visit<C: Circuit<Scalar>>(self, blank: C) -> Result<Self::Output, ZkError>;
+}
+
+/// Run `visitor` on the blank circuit behind `circuit`, the one its keys are made for
+pub fn blank_circuit<V: CircuitVisitor>(circuit: impl Into<CircuitId>, visitor: V) -> Result<V::Output, ZkError> {
+    let circuit = circuit.into();
+    match circuit.proof_type {
+        ProofType::SquareRoot => visitor.visit(SquareDemo { x: None, y: None }),
+        ProofType::SetMembership => visitor.visit(SetMembershipCircuit::blank(MEMBERSHIP_TREE_DEPTH)),
+        ProofType::DIDOwnership => visitor.visit(DIDOwnershipCircuit::blank()),
+        ProofType::CredentialVerification => visitor.visit(CredentialCircuit::blank()),
+        ProofType::RangeProof => visitor.visit(RangeProofCircuit::blank()),
+        ProofType::Predicates => visitor.visit(PredicateCircuit::blank()),
+        ProofType::Conjunction => visitor.visit(ConjunctionCircuit::blank(circuit.credentials)),
+    }
+}
+
+struct Setup<'a, P>(&'a P);
+
+impl<P: ProofSystem> CircuitVisitor for Setup<'_, P> {
+    type Output = P::ProvingKey;
+
+    fn visit<C: Circuit<Scalar>>(self, blank: C) -> Result<P::ProvingKey, ZkError> {
+        self.0.setup(blank)
+    }
+}
+
+/// Keys for the shape of the circuit behind `circuit`
+pub fn setup_circuit<P: ProofSystem>(system: &P, circuit: impl Into<CircuitId>) -> Result<P::ProvingKey, ZkError> {
+    blank_circuit(circuit, Setup(system))
+}
+
+struct VerifyCeremony<'a>(&'a MPCParameters, &'a PowersOfTau);
+
+impl CircuitVisitor for VerifyCeremony<'_> {
+    type Output = Vec<[u8; 64]>;
+
+    fn visit<C: Circuit<Scalar>>(self, blank: C) -> Result<Vec<[u8; 64]>, ZkError> {
+        Ok(self.0.verify(blank, self.1)?)
+    }
+}
+
+/// Check that a phase-2 ceremony transcript was run on the circuit behind `circuit`
+/// over the phase-1 `powers`; returns the hashes of its contributions
+pub fn verify_ceremony(mpc: &MPCParameters, circuit: impl Into<CircuitId>, powers: &PowersOfTau) -> Result<Vec<[u8; 64]>, ZkError> {
+    blank_circuit(circuit, VerifyCeremony(mpc, powers))
+}
+
+struct CountConstraints;
+
+impl CircuitVisitor for CountConstraints {
+    type Output = usize;
+
+    fn visit<C: Circuit<Scalar>>(self, blank: C) -> Result<usize, ZkError> {
+        count_constraints(blank)
+    }
+}
+
+static CONSTRAINT_COUNTS: OnceLock<Mutex<HashMap<CircuitId, usize>>> = OnceLock::new();
+
+/// Constraints in the circuit behind `circuit`, counted once per process
+pub fn constraint_count(circuit: impl Into<CircuitId>) -> Result<usize, ZkError> {
+    let circuit = circuit.into();
+    let mut counts = CONSTRAINT_COUNTS.get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(count) = counts.get(&circuit) {
+        return Ok(*count);
+    }
+
+    let count = blank_circuit(circuit, CountConstraints)?;
+    counts.insert(circuit, count);
+    Ok(count)
+}
+
+#[cfg(feature = "universal")]
+struct SizeCircuit;
+
+#[cfg(feature = "universal")]
+impl CircuitVisitor for SizeCircuit {
+    type Output = CircuitSize;
+
+    fn visit<C: Circuit<Scalar>>(self, blank: C) -> Result<CircuitSize, ZkError> {
+        CircuitSize::of(blank)
+    }
+}
+
+/// Size of the circuit behind `circuit`, as a universal reference string must cover it
+#[cfg(feature = "universal")]
+pub fn circuit_size(circuit: impl Into<CircuitId>) -> Result<CircuitSize, ZkError> {
+    blank_circuit(circuit, SizeCircuit)
+}
+
+/// Proving key and verifying key for one circuit; for Groth16 the prepared key
+/// comes from the shared cache (see `crypto::batch::prepared_verifying_key`)
+pub struct KeySlot<P: ProofSystem> {
+    pub pk: Option<P::ProvingKey>,
+    pub vk: P::VerifyingKey,
+    pub pvk: P::PreparedVerifyingKey,
+    /// Hash of `vk`, as proof envelopes name it
+    pub vk_hash: [u8; 32],
+}
+
+impl<P: ProofSystem> KeySlot<P> {
+    pub fn with_proving_key(system: &P, pk: P::ProvingKey) -> Result<Self, ZkError> {
+        let mut slot = Self::with_verifying_key(system, system.verifying_key(&pk))?;
+        slot.pk = Some(pk);
+        Ok(slot)
+    }
+
+    pub fn with_verifying_key(system: &P, vk: P::VerifyingKey) -> Result<Self, ZkError> {
+        Ok(Self {
+            pk: None,
+            pvk: system.prepare_verifying_key(&vk)?,
+            vk_hash: verifying_key_hash(system, &vk)?,
+            vk,
+        })
+    }
+}
+
+/// Where a circuit's keys come from; read the first time the circuit is used
+pub enum KeySource {
+    /// Serialized proving key, as `ProofSystem::serialize_proving_key` writes it
+    ProvingKey(Vec<u8>),
+    /// Serialized verifying key, for verifiers
+    VerifyingKey(Vec<u8>),
+    /// `ProofSystem::setup` on the blank circuit. Meant for the universal backend,
+    /// where the reference string determines the keys; Groth16 keys made this way
+    /// are local to the process.
+    Setup,
+}
+
+impl KeySource {
+    fn load<P: ProofSystem>(&self, system: &P, circuit: CircuitId) -> Result<KeySlot<P>, ZkError> {
+        match self {
+            KeySource::ProvingKey(bytes) => KeySlot::with_proving_key(system, system.deserialize_proving_key(bytes)?),
+            KeySource::VerifyingKey(bytes) => KeySlot::with_verifying_key(system, system.deserialize_verifying_key(bytes)?),
+            KeySource::Setup => KeySlot::with_proving_key(system, setup_circuit(system, circuit)?),
+        }
+    }
+}
+
+// A circuit's keys, or the source they are loaded from on first use. The source
+// is dropped once loaded so serialized keys are not held twice.
+struct Entry<P: ProofSystem> {
+    source: Cell<Option<KeySource>>,
+    keys: OnceCell<KeySlot<P>>,
+}
+
+impl<P: ProofSystem> Entry<P> {
+    fn pending(source: KeySource) -> Self {
+        Self { source: Cell::new(Some(source)), keys: OnceCell::new() }
+    }
+
+    fn loaded(slot: KeySlot<P>) -> Self {
+        Self { source: Cell::new(None), keys: OnceCell::from(slot) }
+    }
+}
+
+/// Keys per circuit, loaded lazily from their registered sources
+pub struct CircuitRegistry<P: ProofSystem> {
+    circuits: HashMap<CircuitId, Entry<P>>,
+}
+
+impl<P: ProofSystem> Default for CircuitRegistry<P> {
+    fn default() -> Self {
+        Self { circuits: HashMap::new() }
+    }
+}
+
+impl<P: ProofSystem> CircuitRegistry<P> {
+    /// A registry deriving every circuit's keys with `KeySource::Setup` on first use
+    pub fn with_setup() -> Self {
+        let mut registry = Self::default();
+        for circuit in CircuitId::all() {
+            registry.register(circuit, KeySource::Setup);
+        }
+        registry
+    }
+
+    /// Load the keys for `circuit` from `source` on its first use, replacing any it has
+    pub fn register(&mut self, circuit: impl Into<CircuitId>, source: KeySource) {
+        self.circuits.insert(circuit.into(), Entry::pending(source));
+    }
+
+    /// Use `slot` for `circuit` from now on, replacing any keys or source it has
+    pub fn insert(&mut self, circuit: impl Into<CircuitId>, slot: KeySlot<P>) {
+        self.circuits.insert(circuit.into(), Entry::loaded(slot));
+    }
+
+    /// Forget the keys and source for `circuit`
+    pub fn remove(&mut self, circuit: impl Into<CircuitId>) {
+        self.circuits.remove(&circuit.into());
+    }
+
+    /// Whether keys for `circuit` are loaded, as opposed to registered or missing
+    pub fn is_loaded(&self, circuit: impl Into<CircuitId>) -> bool {
+        self.circuits.get(&circuit.into()).is_some_and(|entry| entry.keys.get().is_some())
+    }
+
+    /// The keys for `circuit`, loading them from their source if this is their
+    /// first use; `None` when nothing is registered for it
+    pub fn get(&self, system: &P, circuit: impl Into<CircuitId>) -> Result<Option<&KeySlot<P>>, ZkError> {
+        let circuit = circuit.into();
+        let Some(entry) = self.circuits.get(&circuit) else {
+            return Ok(None);
+        };
+        if let Some(slot) = entry.keys.get() {
+            return Ok(Some(slot));
+        }
+        let Some(source) = entry.source.take() else {
+            return Ok(None);
+        };
+
+        match source.load(system, circuit) {
+            Ok(slot) => Ok(Some(entry.keys.get_or_init(|| slot))),
+            Err(e) => {
+                // Keep the source so the error is reported again on the next use
+                entry.source.set(Some(source));
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::proof_system::Groth16;
+
+    #[test]
+    fn registered_keys_load_on_first_use_and_can_be_swapped() {
+        let first = setup_circuit(&Groth16, ProofType::SquareRoot).unwrap();
+        let second = setup_circuit(&Groth16, ProofType::SquareRoot).unwrap();
+
+        let mut registry = CircuitRegistry::default();
+        assert!(registry.get(&Groth16, ProofType::SquareRoot).unwrap().is_none());
+
+        registry.register(ProofType::SquareRoot, KeySource::ProvingKey(Groth16.serialize_proving_key(&first).unwrap()));
+        assert!(!registry.is_loaded(ProofType::SquareRoot));
+        let slot = registry.get(&Groth16, ProofType::SquareRoot).unwrap().unwrap();
+        assert_eq!(slot.vk_hash, verifying_key_hash(&Groth16, &first.vk).unwrap());
+        assert!(registry.is_loaded(ProofType::SquareRoot));
+
+        // Verifiers swap in the other key without a proving key
+        registry.register(ProofType::SquareRoot, KeySource::VerifyingKey(Groth16.serialize_verifying_key(&second.vk).unwrap()));
+        let slot = registry.get(&Groth16, ProofType::SquareRoot).unwrap().unwrap();
+        assert_eq!(slot.vk_hash, verifying_key_hash(&Groth16, &second.vk).unwrap());
+        assert!(slot.pk.is_none());
+        assert!(!registry.is_loaded(ProofType::RangeProof));
+    }
+
+    #[test]
+    fn conjunctions_have_keys_per_number_of_credentials() {
+        let two = CircuitId::conjunction(2).unwrap();
+        let three = CircuitId::conjunction(3).unwrap();
+        assert_eq!(CircuitId::from(ProofType::Conjunction), two);
+        assert_eq!(CircuitId::new(ProofType::Conjunction, Some(3)).unwrap(), three);
+        assert!(CircuitId::conjunction(1).is_err());
+        assert!(CircuitId::conjunction(MAX_CONJUNCTION_CREDENTIALS + 1).is_err());
+        assert!(CircuitId::new(ProofType::RangeProof, Some(2)).is_err());
+        assert_eq!(CircuitId::all().filter(|circuit| circuit.proof_type == ProofType::Conjunction).count(), MAX_CONJUNCTION_CREDENTIALS - 1);
+        assert!(constraint_count(three).unwrap() > constraint_count(two).unwrap());
+
+        let mut registry = CircuitRegistry::<Groth16>::default();
+        registry.register(three, KeySource::VerifyingKey(vec![1, 2, 3]));
+        assert!(registry.get(&Groth16, ProofType::Conjunction).unwrap().is_none());
+        assert!(registry.get(&Groth16, three).is_err());
+    }
+
+    #[test]
+    fn ceremonies_verify_only_against_their_own_circuit() {
+        use rand::rngs::OsRng;
+
+        let powers = PowersOfTau::generate_insecure(4, &mut OsRng).unwrap();
+        let mut mpc = MPCParameters::new(SquareDemo { x: None, y: None }, &powers).unwrap();
+        let contribution = mpc.contribute(&mut OsRng);
+
+        assert_eq!(verify_ceremony(&mpc, ProofType::SquareRoot, &powers).unwrap(), vec![contribution]);
+        assert!(verify_ceremony(&mpc, ProofType::DIDOwnership, &powers).is_err());
+    }
+
+    #[test]
+    fn malformed_sources_fail_on_every_use() {
+        let mut registry = CircuitRegistry::<Groth16>::default();
+        registry.register(ProofType::DIDOwnership, KeySource::VerifyingKey(vec![1, 2, 3]));
+
+        assert!(registry.get(&Groth16, ProofType::DIDOwnership).is_err());
+        assert!(registry.get(&Groth16, ProofType::DIDOwnership).is_err());
+        assert!(!registry.is_loaded(ProofType::DIDOwnership));
+    }
+}
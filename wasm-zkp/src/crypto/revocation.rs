@@ -0,0 +1,430 @@
+//! Issuer revocation registries and non-revocation proofs.
+//!
+//! An issuer revokes a credential by adding its credential hash
+//! `H(credential id, holder commitment)` to a `RevocationRegistry`, committed to
+//! as an indexed MiMC Merkle tree of depth `REVOCATION_TREE_DEPTH`. Its leaves
+//! form a sorted linked list of the revoked hashes: leaf `i` is
+//! `H(value_i, next_i)`, where `next_i` is the smallest revoked hash above
+//! `value_i`, or zero past the largest one. A sentinel leaf with value zero
+//! comes first, and unused leaves are zero. The issuer publishes the root after
+//! every revocation.
+//!
+//! A presentation proves, inside the credential circuit, that the credential
+//! hash falls strictly between the value and the next value of some leaf (its
+//! "low leaf"), so no leaf holds the hash itself: non-membership is exact, over
+//! the whole hash. The hash is a private witness that is never a public input,
+//! so neither the credential id, its hash nor the low leaf are revealed, and the
+//! verifier only needs the issuer's latest root.
+
+use crate::crypto::credential::{credential_hash, Credential};
+use crate::crypto::mimc::MiMC;
+use crate::crypto::zk_proofs::{decode_scalar, encode_scalar};
+use crate::error::ZkError;
+use bellman::{
+    gadgets::{
+        boolean::{AllocatedBit, Boolean},
+        num::AllocatedNum,
+    },
+    ConstraintSystem, SynthesisError,
+};
+use bls12_381::Scalar;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// Depth of revocation trees, whose 2^32 leaves hold the sentinel and up to
+/// 2^32 - 1 revoked credentials
+pub const REVOCATION_TREE_DEPTH: usize = 32;
+
+/// Order of credential hashes in revocation trees: that of their canonical integers
+pub fn revocation_order(a: &Scalar, b: &Scalar) -> Ordering {
+    a.to_bytes().iter().rev().cmp(b.to_bytes().iter().rev())
+}
+
+/// What a credential circuit needs to prove a credential hash unrevoked: the low
+/// leaf `(low, next)` with `low < hash < next` (or `next` zero) and its path to
+/// the root. `None` throughout for key generation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NonRevocationWitness {
+    pub low_value: Option<Scalar>,
+    pub next_value: Option<Scalar>,
+    /// Sibling at each level, starting next to the low leaf
+    pub siblings: Vec<Option<Scalar>>,
+    /// Whether the running node is the right child at each level
+    pub is_right: Vec<Option<bool>>,
+}
+
+impl NonRevocationWitness {
+    /// Witness shape without values, for key generation
+    pub fn blank() -> Self {
+        Self {
+            low_value: None,
+            next_value: None,
+            siblings: vec![None; REVOCATION_TREE_DEPTH],
+            is_right: vec![None; REVOCATION_TREE_DEPTH],
+        }
+    }
+}
+
+/// Credentials an issuer has revoked.
+///
+/// Only the revoked credential hashes are stored; the tree is rebuilt from them
+/// when a root or path is needed. The registry is public, so holders fetch it
+/// (or a path from it) to prove non-revocation.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct RevocationRegistry {
+    // Hex-encoded credential hashes, in revocation order
+    revoked: Vec<String>,
+}
+
+impl RevocationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Revoke `credential`, returning the new root to publish
+    pub fn revoke(&mut self, credential: &Credential) -> Result<Scalar, ZkError> {
+        self.revoke_hash(&credential_hash(credential)?)
+    }
+
+    pub fn is_revoked(&self, credential: &Credential) -> Result<bool, ZkError> {
+        Ok(self.revoked.contains(&encode_scalar(&credential_hash(credential)?)))
+    }
+
+    pub fn root(&self) -> Result<Scalar, ZkError> {
+        Ok(self.tree()?.0.node(REVOCATION_TREE_DEPTH, 0))
+    }
+
+    /// Low leaf of `credential` and its path to the root. Fails if the credential
+    /// has been revoked.
+    pub fn non_revocation_witness(&self, credential: &Credential) -> Result<NonRevocationWitness, ZkError> {
+        self.hash_witness(&credential_hash(credential)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.revoked.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.revoked.is_empty()
+    }
+
+    fn revoke_hash(&mut self, hash: &Scalar) -> Result<Scalar, ZkError> {
+        let hash = encode_scalar(hash);
+        if self.revoked.contains(&hash) {
+            return Err(ZkError::InvalidInput("credential is already revoked".to_string()));
+        }
+        if self.revoked.len() + 1 >= 1 << REVOCATION_TREE_DEPTH {
+            return Err(ZkError::InvalidInput("revocation registry is full".to_string()));
+        }
+        self.revoked.push(hash);
+        self.root()
+    }
+
+    fn hash_witness(&self, hash: &Scalar) -> Result<NonRevocationWitness, ZkError> {
+        let (tree, values) = self.tree()?;
+        // The low leaf is the last one whose value lies below the hash
+        let low = values.partition_point(|value| revocation_order(value, hash) == Ordering::Less);
+        if low == 0 {
+            return Err(ZkError::InvalidInput("credential hash is zero".to_string()));
+        }
+        let low = low - 1;
+        let next = values.get(low + 1).copied().unwrap_or_else(Scalar::zero);
+        if next == *hash {
+            return Err(ZkError::InvalidInput("credential has been revoked".to_string()));
+        }
+
+        let position = low as u64;
+        Ok(NonRevocationWitness {
+            low_value: Some(values[low]),
+            next_value: Some(next),
+            siblings: (0..REVOCATION_TREE_DEPTH).map(|height| Some(tree.node(height, (position >> height) ^ 1))).collect(),
+            is_right: (0..REVOCATION_TREE_DEPTH).map(|height| Some((position >> height) & 1 == 1)).collect(),
+        })
+    }
+
+    // The tree and its leaf values: the zero sentinel, then the revoked hashes in order
+    fn tree(&self) -> Result<(SparseTree, Vec<Scalar>), ZkError> {
+        let mut values = vec![Scalar::zero()];
+        for hash in &self.revoked {
+            values.push(decode_scalar(hash)?);
+        }
+        values[1..].sort_by(revocation_order);
+
+        let mimc = MiMC::new();
+        let leaves = values.iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let next = values.get(index + 1).copied().unwrap_or_else(Scalar::zero);
+                (index as u64, mimc.hash2(value, &next))
+            })
+            .collect();
+        Ok((SparseTree::new(leaves), values))
+    }
+}
+
+// Non-empty nodes of a revocation tree by height and position
+struct SparseTree {
+    levels: Vec<HashMap<u64, Scalar>>,
+    // empty[h] is the root of an empty subtree of height h
+    empty: Vec<Scalar>,
+}
+
+impl SparseTree {
+    fn new(leaves: HashMap<u64, Scalar>) -> Self {
+        let mimc = MiMC::new();
+        let mut empty = vec![Scalar::zero()];
+        for height in 0..REVOCATION_TREE_DEPTH {
+            empty.push(mimc.hash2(&empty[height], &empty[height]));
+        }
+
+        let mut tree = Self { levels: vec![leaves], empty };
+        for height in 0..REVOCATION_TREE_DEPTH {
+            let mut next = HashMap::new();
+            for position in tree.levels[height].keys() {
+                let parent = position >> 1;
+                next.entry(parent)
+                    .or_insert_with(|| mimc.hash2(&tree.node(height, parent << 1), &tree.node(height, (parent << 1) | 1)));
+            }
+            tree.levels.push(next);
+        }
+        tree
+    }
+
+    fn node(&self, height: usize, position: u64) -> Scalar {
+        self.levels[height].get(&position).copied().unwrap_or(self.empty[height])
+    }
+}
+
+/// Constrain `credential_hash` to be absent from the revocation tree with root
+/// `revocation_root`: the witnessed low leaf is in the tree and skips over the hash
+pub fn non_revocation_gadget<CS: ConstraintSystem<Scalar>>(
+    mut cs: CS,
+    mimc: &MiMC,
+    credential_hash: &AllocatedNum<Scalar>,
+    witness: &NonRevocationWitness,
+    revocation_root: &AllocatedNum<Scalar>,
+) -> Result<(), SynthesisError> {
+    let low = AllocatedNum::alloc(cs.namespace(|| "low value"), || {
+        witness.low_value.ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    let next = AllocatedNum::alloc(cs.namespace(|| "next value"), || {
+        witness.next_value.ok_or(SynthesisError::AssignmentMissing)
+    })?;
+
+    // Canonical decompositions, so the prover cannot compare a value plus the modulus
+    let hash_bits = credential_hash.to_bits_le_strict(cs.namespace(|| "credential hash bits"))?;
+    let low_bits = low.to_bits_le_strict(cs.namespace(|| "low value bits"))?;
+    let next_bits = next.to_bits_le_strict(cs.namespace(|| "next value bits"))?;
+
+    let above_low = less_than(cs.namespace(|| "low < hash"), &low_bits, &hash_bits)?;
+    Boolean::enforce_equal(cs.namespace(|| "hash is above low"), &above_low, &Boolean::constant(true))?;
+
+    // next = 0 marks the leaf of the largest revoked hash, which nothing lies below
+    let below_next = less_than(cs.namespace(|| "hash < next"), &hash_bits, &next_bits)?;
+    cs.enforce(
+        || "hash is below next unless next is zero",
+        |lc| lc + CS::one() - &below_next.lc(CS::one(), Scalar::one()),
+        |lc| lc + next.get_variable(),
+        |lc| lc,
+    );
+
+    let mut node = mimc.hash_gadget(cs.namespace(|| "low leaf"), &[low, next])?;
+    for (i, (sibling, is_right)) in witness.siblings.iter().zip(&witness.is_right).enumerate() {
+        let mut cs = cs.namespace(|| format!("level {}", i));
+
+        let sibling = AllocatedNum::alloc(cs.namespace(|| "sibling"), || {
+            sibling.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let is_right = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "is right"), *is_right)?);
+        let (left, right) = AllocatedNum::conditionally_reverse(cs.namespace(|| "order children"), &node, &sibling, &is_right)?;
+        node = mimc.hash_gadget(cs.namespace(|| "hash"), &[left, right])?;
+    }
+
+    cs.enforce(
+        || "computed root = revocation root",
+        |lc| lc + node.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + revocation_root.get_variable(),
+    );
+    Ok(())
+}
+
+// Whether `a < b`, for little-endian bit decompositions of equal length
+fn less_than<CS: ConstraintSystem<Scalar>>(mut cs: CS, a: &[Boolean], b: &[Boolean]) -> Result<Boolean, SynthesisError> {
+    let mut less = Boolean::constant(false);
+    for (i, (a, b)) in a.iter().zip(b).enumerate() {
+        let mut cs = cs.namespace(|| format!("bit {}", i));
+        // A differing bit outranks all lower ones: then a < b iff b has it set
+        let differs = Boolean::xor(cs.namespace(|| "differs"), a, b)?;
+        less = Boolean::sha256_ch(cs.namespace(|| "decide"), &differs, b, &less)?;
+    }
+    Ok(less)
+}
+
+fn parse_registry(registry_json: &str) -> Result<RevocationRegistry, ZkError> {
+    Ok(serde_json::from_str(registry_json)?)
+}
+
+/// Revoke a credential (JSON) in an issuer's registry (JSON); returns the updated registry JSON
+#[wasm_bindgen]
+pub fn revoke_credential(registry_json: &str, credential_json: &str) -> Result<String, JsValue> {
+    let mut registry = parse_registry(registry_json)?;
+    let credential: Credential = serde_json::from_str(credential_json).map_err(ZkError::from)?;
+    registry.revoke(&credential)?;
+    Ok(serde_json::to_string(&registry).map_err(ZkError::from)?)
+}
+
+/// Hex-encoded root of a revocation registry (JSON), which the issuer publishes for verifiers
+#[wasm_bindgen]
+pub fn compute_revocation_root(registry_json: &str) -> Result<String, JsValue> {
+    Ok(encode_scalar(&parse_registry(registry_json)?.root()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::credential::{
+        build_credential_circuit, compute_holder_commitment, sign_credential, CredentialPresentation, CredentialType,
+    };
+    use crate::crypto::eddsa::SigningKey;
+    use crate::crypto::envelope::{ProofEnvelope, ENVELOPE_VERSION};
+    use crate::crypto::zk_proofs::ProofType;
+    use bellman::{gadgets::test::TestConstraintSystem, Circuit};
+
+    fn credential(id: &str) -> Credential {
+        Credential {
+            id: id.to_string(),
+            issuer: "did:ethr:0x1234".to_string(),
+            subject: "did:ethr:0xabcd".to_string(),
+            type_: CredentialType::Identity,
+            attributes: vec![],
+            issued_at: "2024-01-01T00:00:00Z".to_string(),
+            expires_at: None,
+            revoked: false,
+            holder_commitment: Some(compute_holder_commitment("secret")),
+            issuer_public_key: None,
+            issuer_signature: None,
+            bbs_signature: None,
+        }
+    }
+
+    fn prove_hash_unrevoked(registry: &RevocationRegistry, hash: Scalar, witness: &NonRevocationWitness) -> bool {
+        let mut cs = TestConstraintSystem::new();
+        let hash = AllocatedNum::alloc(cs.namespace(|| "hash"), || Ok(hash)).unwrap();
+        let root = AllocatedNum::alloc(cs.namespace(|| "root"), || Ok(registry.root().unwrap())).unwrap();
+        non_revocation_gadget(cs.namespace(|| "non-revocation"), &MiMC::new(), &hash, witness, &root).unwrap();
+        cs.is_satisfied()
+    }
+
+    fn prove_unrevoked(registry: &RevocationRegistry, credential: &Credential, witness: &NonRevocationWitness) -> bool {
+        prove_hash_unrevoked(registry, credential_hash(credential).unwrap(), witness)
+    }
+
+    #[test]
+    fn unrevoked_credentials_prove_against_the_latest_root() {
+        let mut registry = RevocationRegistry::new();
+        let (alice, bob) = (credential("urn:uuid:alice"), credential("urn:uuid:bob"));
+
+        let path = registry.non_revocation_witness(&alice).unwrap();
+        assert!(prove_unrevoked(&registry, &alice, &path));
+
+        let root = registry.revoke(&bob).unwrap();
+        assert_eq!(root, registry.root().unwrap());
+        assert!(registry.is_revoked(&bob).unwrap());
+        assert!(!registry.is_revoked(&alice).unwrap());
+        assert!(registry.revoke(&bob).is_err());
+
+        // A path against the previous root no longer proves, a fresh one does
+        assert!(!prove_unrevoked(&registry, &alice, &path));
+        let path = registry.non_revocation_witness(&alice).unwrap();
+        assert!(prove_unrevoked(&registry, &alice, &path));
+
+        let restored: RevocationRegistry = serde_json::from_str(&serde_json::to_string(&registry).unwrap()).unwrap();
+        assert_eq!(restored.root().unwrap(), root);
+    }
+
+    #[test]
+    fn revoked_credentials_cannot_prove() {
+        let mut registry = RevocationRegistry::new();
+        let alice = credential("urn:uuid:alice");
+        let before = registry.non_revocation_witness(&alice).unwrap();
+        registry.revoke(&credential("urn:uuid:bob")).unwrap();
+        registry.revoke(&alice).unwrap();
+        registry.revoke(&credential("urn:uuid:carol")).unwrap();
+
+        assert!(registry.non_revocation_witness(&alice).is_err());
+        // The old low leaf skips over the hash under the old root only
+        assert!(!prove_unrevoked(&registry, &alice, &before));
+
+        // No leaf of the latest tree skips over the revoked hash
+        let hash = credential_hash(&alice).unwrap();
+        let (_, values) = registry.tree().unwrap();
+        for low in 0..values.len() {
+            let mut witness = registry.hash_witness(&(values[low] + Scalar::one())).unwrap();
+            witness.low_value = Some(values[low]);
+            witness.next_value = Some(values.get(low + 1).copied().unwrap_or_else(Scalar::zero));
+            assert!(!prove_hash_unrevoked(&registry, hash, &witness));
+        }
+    }
+
+    #[test]
+    fn hashes_sharing_low_bits_with_revoked_ones_still_prove() {
+        let alice = credential("urn:uuid:alice");
+        let hash = credential_hash(&alice).unwrap();
+        // Same low 32 bits (and more), another credential hash
+        let neighbour = hash + Scalar::from(1 << REVOCATION_TREE_DEPTH);
+        assert_eq!(hash.to_bytes()[..4], neighbour.to_bytes()[..4]);
+
+        let mut registry = RevocationRegistry::new();
+        registry.revoke_hash(&neighbour).unwrap();
+        let witness = registry.non_revocation_witness(&alice).unwrap();
+        assert!(prove_unrevoked(&registry, &alice, &witness));
+        assert!(registry.hash_witness(&neighbour).is_err());
+
+        // Below and above the revoked hash alike
+        registry.revoke_hash(&(hash - Scalar::from(1 << REVOCATION_TREE_DEPTH))).unwrap();
+        let witness = registry.non_revocation_witness(&alice).unwrap();
+        assert_eq!(witness.next_value, Some(neighbour));
+        assert!(prove_unrevoked(&registry, &alice, &witness));
+    }
+
+    #[test]
+    fn presentations_do_not_reveal_the_credential_hash() {
+        let mut alice = credential("urn:uuid:alice");
+        alice.expires_at = Some("2099-01-01T00:00:00Z".to_string());
+        let alice = sign_credential(&alice, &SigningKey::from_bytes(&[7; 32]).unwrap()).unwrap();
+        let mut registry = RevocationRegistry::new();
+        registry.revoke(&credential("urn:uuid:bob")).unwrap();
+
+        let now = 1_720_000_000;
+        let (circuit, inputs) = build_credential_circuit(&alice, &registry, "secret", "poll-1", now).unwrap();
+        let mut cs = TestConstraintSystem::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert!(cs.is_satisfied());
+
+        // Only the public values matter here, so the envelope carries no proof
+        let presentation = CredentialPresentation {
+            proof: ProofEnvelope {
+                version: ENVELOPE_VERSION,
+                proof_system: "groth16".to_string(),
+                proof_type: ProofType::CredentialVerification,
+                vk_hash: [0; 32],
+                public_inputs: inputs.clone(),
+                proof: Vec::new(),
+                context: Some("poll-1".to_string()),
+                created_at: now,
+            },
+            issuer_public_key: alice.issuer_public_key.clone().unwrap(),
+            disclosed: vec![],
+            nullifier: encode_scalar(&inputs[inputs.len() - 1]),
+        };
+        assert!(cs.verify(&presentation.public_inputs(&registry.root().unwrap(), "poll-1", now).unwrap()));
+
+        let hash = credential_hash(&alice).unwrap();
+        assert!(!presentation.proof.public_inputs.contains(&hash));
+        assert!(!serde_json::to_string(&presentation).unwrap().contains(&encode_scalar(&hash)));
+    }
+}
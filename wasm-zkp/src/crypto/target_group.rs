@@ -0,0 +1,421 @@
+//! Portable elements of the BLS12-381 target group.
+//!
+//! `bls12_381::Gt` supports the group law but has no byte encoding, and proof
+//! aggregation has to ship target-group elements from prover to verifier. This
+//! module keeps them in the same `Fp12` tower as `bls12_381`
+//! (`Fp2 = Fp[u]/(u² + 1)`, `Fp6 = Fp2[v]/(v³ - (u + 1))`, `Fp12 = Fp6[w]/(w² - v)`)
+//! with just the operations a verifier needs: multiplication, exponentiation by
+//! a scalar, equality and a canonical 576-byte encoding.
+//!
+//! Elements are produced by this module's own optimal ate pairing, which reads
+//! the points through their serialized coordinates, so nothing depends on how
+//! `bls12_381` represents or prints `Gt` internally.
+
+use bls12_381::{G1Affine, G2Affine, Scalar};
+use ff::{Field, PrimeField};
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// BLS12-381 base field
+#[derive(PrimeField)]
+#[PrimeFieldModulus = "4002409555221667393417789825735904156556882819939007885332058136124031650490837864442687629129015664037894272559787"]
+#[PrimeFieldGenerator = "2"]
+#[PrimeFieldReprEndianness = "big"]
+struct Fp([u64; 6]);
+
+const FP_BYTES: usize = 48;
+
+/// Size in bytes of an encoded target-group element
+pub const TARGET_ELEMENT_BYTES: usize = 12 * FP_BYTES;
+
+// |x| for the BLS parameter x = -0xd201000000010000, the Miller loop length
+const BLS_X: u64 = 0xd201_0000_0001_0000;
+
+// (p² + 1)(p⁴ - p² + 1) / r little-endian: the final exponent once the
+// easy p⁶ - 1 part has been applied by conjugation and inversion
+const FINAL_EXPONENT: [u8; 254] = [
+    0x6a, 0x5d, 0x70, 0xc0, 0xcd, 0xe1, 0x39, 0x87, 0x16, 0x1a, 0x38, 0xe0, 0x6d, 0x25, 0xa5, 0x09,
+    0xe2, 0x91, 0xc7, 0x61, 0x0a, 0xf7, 0xf0, 0x9c, 0x6e, 0xf7, 0x03, 0x79, 0x49, 0xc4, 0x09, 0x3a,
+    0x33, 0xf1, 0x90, 0x38, 0x56, 0x71, 0x72, 0x2d, 0x60, 0x77, 0xec, 0x6f, 0xb3, 0x41, 0x47, 0x22,
+    0x40, 0xbd, 0x12, 0x2a, 0xc2, 0x59, 0x82, 0x33, 0xe7, 0x0d, 0x8e, 0x77, 0xd4, 0x1c, 0xee, 0x38,
+    0xb0, 0x20, 0x8a, 0x18, 0x4b, 0xef, 0xb5, 0xc3, 0x7b, 0x4d, 0x76, 0xe2, 0x49, 0x5d, 0x61, 0x1d,
+    0x7d, 0x11, 0x76, 0xd0, 0xdd, 0x01, 0x61, 0x81, 0xfc, 0x3a, 0xbe, 0x7e, 0x1e, 0xc0, 0x07, 0xf0,
+    0xc3, 0x21, 0x50, 0x93, 0x90, 0xbd, 0xd7, 0x27, 0x5f, 0xb1, 0xc0, 0x57, 0xf5, 0xe2, 0xb5, 0xc3,
+    0x84, 0x23, 0xf8, 0xc4, 0x94, 0x6c, 0x88, 0x5e, 0x56, 0x3f, 0xe6, 0x11, 0xdb, 0x95, 0x6a, 0xee,
+    0x6f, 0x4f, 0x9c, 0x4a, 0x51, 0x2f, 0x82, 0x2b, 0xda, 0x73, 0x1b, 0xd2, 0x74, 0xa8, 0xd6, 0x12,
+    0xfb, 0xdf, 0x99, 0xf4, 0x5e, 0x27, 0x04, 0x13, 0x1f, 0x5d, 0xb9, 0xbc, 0xfe, 0x78, 0x78, 0x96,
+    0x22, 0x29, 0x2f, 0x8b, 0x7f, 0x49, 0x44, 0x47, 0x55, 0x18, 0x84, 0xf0, 0x07, 0xe7, 0xa2, 0x85,
+    0xec, 0x2e, 0x80, 0x6c, 0x12, 0x50, 0x0c, 0x9f, 0x89, 0xa4, 0x2f, 0xbd, 0x97, 0xe1, 0x46, 0xfb,
+    0x1a, 0xf6, 0xc5, 0x9b, 0x80, 0xe0, 0x8c, 0x54, 0x8c, 0xaa, 0xbe, 0x73, 0x15, 0xfb, 0x56, 0xcf,
+    0x7c, 0xdf, 0x3b, 0x76, 0xa3, 0x75, 0x73, 0xad, 0xcc, 0xde, 0x9b, 0x17, 0x31, 0x90, 0xec, 0xe0,
+    0xda, 0xc1, 0x48, 0x3c, 0xa8, 0xae, 0x79, 0x65, 0xb3, 0x5b, 0xcf, 0x64, 0xe6, 0x5a, 0xf8, 0xdb,
+    0x66, 0x75, 0xca, 0x55, 0x5c, 0x23, 0x6f, 0x7b, 0x03, 0x75, 0x87, 0x14, 0xb3, 0x28,
+];
+
+impl Fp {
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        let mut repr = FpRepr::default();
+        repr.as_mut().copy_from_slice(bytes);
+        Option::from(Fp::from_repr(repr)).expect("bls12_381 serializes canonical coordinates")
+    }
+
+    // Zero maps to zero, which keeps the tower inversions total
+    fn invert_or_zero(&self) -> Self {
+        Option::from(self.invert()).unwrap_or(Fp::zero())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Fp2 {
+    c0: Fp,
+    c1: Fp,
+}
+
+impl Fp2 {
+    const ZERO: Self = Fp2 { c0: Fp([0; 6]), c1: Fp([0; 6]) };
+
+    fn one() -> Self {
+        Fp2 { c0: Fp::one(), c1: Fp::zero() }
+    }
+
+    // From the `c1 || c0` big-endian layout of serialized G2 coordinates
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        Fp2 { c0: Fp::from_be_bytes(&bytes[FP_BYTES..]), c1: Fp::from_be_bytes(&bytes[..FP_BYTES]) }
+    }
+
+    // Multiply by the cubic non-residue u + 1
+    fn mul_by_nonresidue(self) -> Self {
+        Fp2 { c0: self.c0 - self.c1, c1: self.c0 + self.c1 }
+    }
+
+    fn scale(self, k: Fp) -> Self {
+        Fp2 { c0: self.c0 * k, c1: self.c1 * k }
+    }
+
+    fn double(self) -> Self {
+        self + self
+    }
+
+    fn invert(self) -> Self {
+        let norm = (self.c0.square() + self.c1.square()).invert_or_zero();
+        Fp2 { c0: self.c0 * norm, c1: -self.c1 * norm }
+    }
+}
+
+impl Neg for Fp2 {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Fp2 { c0: -self.c0, c1: -self.c1 }
+    }
+}
+
+impl Add for Fp2 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Fp2 { c0: self.c0 + rhs.c0, c1: self.c1 + rhs.c1 }
+    }
+}
+
+impl Sub for Fp2 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Fp2 { c0: self.c0 - rhs.c0, c1: self.c1 - rhs.c1 }
+    }
+}
+
+impl Mul for Fp2 {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        let t0 = self.c0 * rhs.c0;
+        let t1 = self.c1 * rhs.c1;
+        Fp2 {
+            c0: t0 - t1,
+            c1: (self.c0 + self.c1) * (rhs.c0 + rhs.c1) - t0 - t1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Fp6 {
+    c0: Fp2,
+    c1: Fp2,
+    c2: Fp2,
+}
+
+impl Fp6 {
+    const ZERO: Self = Fp6 { c0: Fp2::ZERO, c1: Fp2::ZERO, c2: Fp2::ZERO };
+
+    // Multiply by the quadratic non-residue v
+    fn mul_by_nonresidue(self) -> Self {
+        Fp6 { c0: self.c2.mul_by_nonresidue(), c1: self.c0, c2: self.c1 }
+    }
+
+    fn invert(self) -> Self {
+        let t0 = self.c0 * self.c0 - (self.c1 * self.c2).mul_by_nonresidue();
+        let t1 = (self.c2 * self.c2).mul_by_nonresidue() - self.c0 * self.c1;
+        let t2 = self.c1 * self.c1 - self.c0 * self.c2;
+        let norm = (self.c0 * t0 + (self.c2 * t1 + self.c1 * t2).mul_by_nonresidue()).invert();
+        Fp6 { c0: t0 * norm, c1: t1 * norm, c2: t2 * norm }
+    }
+}
+
+impl Add for Fp6 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Fp6 { c0: self.c0 + rhs.c0, c1: self.c1 + rhs.c1, c2: self.c2 + rhs.c2 }
+    }
+}
+
+impl Sub for Fp6 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Fp6 { c0: self.c0 - rhs.c0, c1: self.c1 - rhs.c1, c2: self.c2 - rhs.c2 }
+    }
+}
+
+impl Mul for Fp6 {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        let t0 = self.c0 * rhs.c0;
+        let t1 = self.c1 * rhs.c1;
+        let t2 = self.c2 * rhs.c2;
+        Fp6 {
+            c0: ((self.c1 + self.c2) * (rhs.c1 + rhs.c2) - t1 - t2).mul_by_nonresidue() + t0,
+            c1: (self.c0 + self.c1) * (rhs.c0 + rhs.c1) - t0 - t1 + t2.mul_by_nonresidue(),
+            c2: (self.c0 + self.c2) * (rhs.c0 + rhs.c2) - t0 - t2 + t1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Fp12 {
+    c0: Fp6,
+    c1: Fp6,
+}
+
+impl Mul for Fp12 {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        let t0 = self.c0 * rhs.c0;
+        let t1 = self.c1 * rhs.c1;
+        Fp12 {
+            c0: t0 + t1.mul_by_nonresidue(),
+            c1: (self.c0 + self.c1) * (rhs.c0 + rhs.c1) - t0 - t1,
+        }
+    }
+}
+
+impl Fp12 {
+    fn one() -> Self {
+        Fp12 { c0: Fp6 { c0: Fp2::one(), c1: Fp2::ZERO, c2: Fp2::ZERO }, c1: Fp6::ZERO }
+    }
+
+    fn conjugate(self) -> Self {
+        Fp12 { c0: self.c0, c1: Fp6::ZERO - self.c1 }
+    }
+
+    fn invert(self) -> Self {
+        let norm = (self.c0 * self.c0 - (self.c1 * self.c1).mul_by_nonresidue()).invert();
+        Fp12 { c0: self.c0 * norm, c1: Fp6::ZERO - self.c1 * norm }
+    }
+
+    // A line a + b·w⁻³ + c·w⁻¹ scaled by u + 1, using w⁻¹ = w·v²/(u + 1) and
+    // w⁻³ = w·v/(u + 1); subfield factors vanish in the final exponentiation
+    fn line(a: Fp2, b: Fp2, c: Fp2) -> Self {
+        Fp12 {
+            c0: Fp6 { c0: a.mul_by_nonresidue(), c1: Fp2::ZERO, c2: Fp2::ZERO },
+            c1: Fp6 { c0: Fp2::ZERO, c1: b, c2: c },
+        }
+    }
+
+    fn coefficients(&self) -> [Fp; 12] {
+        let mut out = [Fp::zero(); 12];
+        for (i, c6) in [self.c0, self.c1].iter().enumerate() {
+            for (j, c2) in [c6.c0, c6.c1, c6.c2].iter().enumerate() {
+                out[6 * i + 2 * j] = c2.c0;
+                out[6 * i + 2 * j + 1] = c2.c1;
+            }
+        }
+        out
+    }
+
+    fn from_coefficients(c: &[Fp; 12]) -> Self {
+        let fp2 = |i: usize| Fp2 { c0: c[i], c1: c[i + 1] };
+        let fp6 = |i: usize| Fp6 { c0: fp2(i), c1: fp2(i + 2), c2: fp2(i + 4) };
+        Fp12 { c0: fp6(0), c1: fp6(6) }
+    }
+}
+
+// A point of the G2 twist y² = x³ + 4(u + 1) in homogeneous coordinates; the
+// twist maps to the curve by (x, y) ↦ (x·w⁻², y·w⁻³)
+struct TwistPoint {
+    x: Fp2,
+    y: Fp2,
+    z: Fp2,
+}
+
+impl TwistPoint {
+    // Double in place and return the tangent line evaluated at (xp, yp)
+    fn double(&mut self, xp: Fp, yp: Fp) -> Fp12 {
+        let (x, y, z) = (self.x, self.y, self.z);
+        let w = (x * x).double() + x * x;
+        let s = y * z;
+        let b = x * y * s;
+        let h = w * w - b.double().double().double();
+        self.x = (h * s).double();
+        self.y = w * (b.double().double() - h) - (y * y * s * s).double().double().double();
+        self.z = (s * s * s).double().double().double();
+        Fp12::line((s * z).double().scale(yp), w * x - (y * s).double(), -(w * z).scale(xp))
+    }
+
+    // Add the affine point (qx, qy) in place and return the chord evaluated at (xp, yp)
+    fn add(&mut self, qx: Fp2, qy: Fp2, xp: Fp, yp: Fp) -> Fp12 {
+        let (x, y, z) = (self.x, self.y, self.z);
+        let u = qy * z - y;
+        let v = qx * z - x;
+        let vv = v * v;
+        let vvv = v * vv;
+        let r = vv * x;
+        let a = u * u * z - vvv - r.double();
+        self.x = v * a;
+        self.y = u * (r - a) - vvv * y;
+        self.z = vvv * z;
+        Fp12::line(v.scale(yp), u * qx - v * qy, -u.scale(xp))
+    }
+}
+
+/// An element of the BLS12-381 target group, written multiplicatively
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TargetElement(Fp12);
+
+impl TargetElement {
+    pub fn identity() -> Self {
+        let mut one = Fp6::ZERO;
+        one.c0.c0 = Fp::one();
+        TargetElement(Fp12 { c0: one, c1: Fp6::ZERO })
+    }
+
+    /// The optimal ate pairing product `∏ e(g1ᵢ, g2ᵢ)`; pairs with the identity
+    /// contribute nothing
+    pub fn pairing_product(g1: &[G1Affine], g2: &[G2Affine]) -> Self {
+        let pairs: Vec<(Fp, Fp, Fp2, Fp2)> = g1
+            .iter()
+            .zip(g2)
+            .filter(|(p, q)| !bool::from(p.is_identity() | q.is_identity()))
+            .map(|(p, q)| {
+                let (p, q) = (p.to_uncompressed(), q.to_uncompressed());
+                let (px, py) = p.split_at(FP_BYTES);
+                let (qx, qy) = q.split_at(2 * FP_BYTES);
+                (Fp::from_be_bytes(px), Fp::from_be_bytes(py), Fp2::from_be_bytes(qx), Fp2::from_be_bytes(qy))
+            })
+            .collect();
+        let mut points: Vec<TwistPoint> =
+            pairs.iter().map(|&(_, _, x, y)| TwistPoint { x, y, z: Fp2::one() }).collect();
+
+        let mut f = Fp12::one();
+        for bit in (0..BLS_X.ilog2()).rev() {
+            f = f * f;
+            for (&(xp, yp, qx, qy), t) in pairs.iter().zip(points.iter_mut()) {
+                f = f * t.double(xp, yp);
+                if (BLS_X >> bit) & 1 == 1 {
+                    f = f * t.add(qx, qy, xp, yp);
+                }
+            }
+        }
+
+        // x is negative, so the loop result is conjugated; then f^(p⁶ - 1) = f̄ / f
+        // and the rest of the final exponent
+        let f = f.conjugate();
+        TargetElement(f.conjugate() * f.invert()).pow_le_bytes(&FINAL_EXPONENT)
+    }
+
+    /// `self` raised to `exponent`
+    pub fn pow(&self, exponent: &Scalar) -> Self {
+        self.pow_le_bytes(&exponent.to_bytes())
+    }
+
+    fn pow_le_bytes(&self, exponent: &[u8]) -> Self {
+        let mut acc = Self::identity();
+        for byte in exponent.iter().rev() {
+            for bit in (0..8).rev() {
+                acc = acc * acc;
+                if (byte >> bit) & 1 == 1 {
+                    acc = acc * *self;
+                }
+            }
+        }
+        acc
+    }
+
+    /// Whether `self` has the prime order of the pairing groups
+    pub fn is_in_subgroup(&self) -> bool {
+        // self^(r - 1) * self == self^r
+        self.pow(&-Scalar::one()) * *self == Self::identity()
+    }
+
+    pub fn to_bytes(&self) -> [u8; TARGET_ELEMENT_BYTES] {
+        let mut out = [0u8; TARGET_ELEMENT_BYTES];
+        for (chunk, c) in out.chunks_mut(FP_BYTES).zip(self.0.coefficients().iter()) {
+            chunk.copy_from_slice(c.to_repr().as_ref());
+        }
+        out
+    }
+
+    /// Decode a canonical encoding of a target-group element; rejects anything
+    /// outside the prime-order subgroup
+    pub fn from_bytes(bytes: &[u8; TARGET_ELEMENT_BYTES]) -> Option<Self> {
+        let mut coefficients = [Fp::zero(); 12];
+        for (slot, chunk) in coefficients.iter_mut().zip(bytes.chunks(FP_BYTES)) {
+            let mut repr = FpRepr::default();
+            repr.as_mut().copy_from_slice(chunk);
+            *slot = Option::from(Fp::from_repr(repr))?;
+        }
+        let element = TargetElement(Fp12::from_coefficients(&coefficients));
+        element.is_in_subgroup().then_some(element)
+    }
+}
+
+impl Mul for TargetElement {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        TargetElement(self.0 * rhs.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use group::Curve;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn pairing_is_bilinear_and_non_degenerate() {
+        let (g1, g2) = (G1Affine::generator(), G2Affine::generator());
+        let k = Scalar::random(OsRng);
+        let l = Scalar::random(OsRng);
+
+        let e = TargetElement::pairing_product(&[g1], &[g2]);
+        assert_ne!(e, TargetElement::identity());
+        assert!(e.is_in_subgroup());
+        assert_eq!(TargetElement::pairing_product(&[(g1 * k).to_affine()], &[(g2 * l).to_affine()]), e.pow(&(k * l)));
+        assert_eq!(
+            TargetElement::pairing_product(&[(g1 * k).to_affine(), (g1 * l).to_affine()], &[g2, g2]),
+            e.pow(&(k + l))
+        );
+        assert_eq!(TargetElement::pairing_product(&[g1, -g1], &[g2, g2]), TargetElement::identity());
+        assert_eq!(TargetElement::pairing_product(&[G1Affine::identity()], &[g2]), TargetElement::identity());
+    }
+
+    #[test]
+    fn encoding_round_trips_and_rejects_non_members() {
+        let t = TargetElement::pairing_product(&[G1Affine::generator()], &[G2Affine::generator()]).pow(&Scalar::random(OsRng));
+        assert_eq!(TargetElement::from_bytes(&t.to_bytes()), Some(t));
+
+        // 2 ∈ Fp ⊂ Fp12 has order dividing p - 1, so it is not in the subgroup
+        let mut bytes = TargetElement::identity().to_bytes();
+        bytes[FP_BYTES - 1] = 2;
+        assert_eq!(TargetElement::from_bytes(&bytes), None);
+
+        // Non-canonical coefficient
+        assert_eq!(TargetElement::from_bytes(&[0xff; TARGET_ELEMENT_BYTES]), None);
+    }
+}
@@ -1,18 +1,23 @@
-use crate::utils::get_error_message;
+use crate::crypto::envelope::ProofEnvelope;
+use crate::crypto::proof_system::ProofSystem;
+use crate::error::ZkError;
 use bellman::{
-    groth16::{self, create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof},
-    Circuit, ConstraintSystem, SynthesisError,
+    gadgets::test::TestConstraintSystem,
+    groth16::{
+        create_random_proof, generate_random_parameters, verify_proof,
+        Parameters, PreparedVerifyingKey, Proof, VerifyingKey,
+    },
+    Circuit, ConstraintSystem, SynthesisError, VerificationError,
 };
 use bls12_381::{Bls12, Scalar};
-use ff::Field;
 use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
-use std::ops::MulAssign;
+use std::str::FromStr;
 use wasm_bindgen::prelude::*;
 
 /// Types of supported zero-knowledge proofs
 #[wasm_bindgen]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ProofType {
     /// Simple testing ZKP (x^2 = y)
     SquareRoot,
@@ -22,26 +27,73 @@ pub enum ProofType {
     DIDOwnership,
     /// Credential verification without revealing attributes
     CredentialVerification,
+    /// Private numeric attribute compared to a public threshold (e.g. age >= 18)
+    RangeProof,
+    /// Conjunction of predicates over the attributes of a hidden credential
+    Predicates,
+    /// Predicates over several credentials bound to one holder
+    Conjunction,
 }
 
-#[wasm_bindgen]
+impl ProofType {
+    pub const ALL: [ProofType; 7] = [
+        ProofType::SquareRoot,
+        ProofType::SetMembership,
+        ProofType::DIDOwnership,
+        ProofType::CredentialVerification,
+        ProofType::RangeProof,
+        ProofType::Predicates,
+        ProofType::Conjunction,
+    ];
+
+    /// Stable kebab-case name used in files and on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProofType::SquareRoot => "square-root",
+            ProofType::SetMembership => "set-membership",
+            ProofType::DIDOwnership => "did-ownership",
+            ProofType::CredentialVerification => "credential-verification",
+            ProofType::RangeProof => "range-proof",
+            ProofType::Predicates => "predicates",
+            ProofType::Conjunction => "conjunction",
+        }
+    }
+}
+
+impl FromStr for ProofType {
+    type Err = ZkError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "square-root" => Ok(ProofType::SquareRoot),
+            "set-membership" => Ok(ProofType::SetMembership),
+            "did-ownership" => Ok(ProofType::DIDOwnership),
+            "credential-verification" => Ok(ProofType::CredentialVerification),
+            "range-proof" => Ok(ProofType::RangeProof),
+            "predicates" => Ok(ProofType::Predicates),
+            "conjunction" => Ok(ProofType::Conjunction),
+            _ => Err(ZkError::InvalidInput(format!("unknown proof type: {}", name))),
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ProofResult {
     pub success: bool,
     pub message: String,
+    /// The proof and its public inputs; reaches JavaScript through the serialized result
+    #[wasm_bindgen(skip)]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub proof: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub public_inputs: Option<Vec<String>>,
+    pub proof: Option<ProofEnvelope>,
 }
 
 impl ProofResult {
-    pub fn success(message: &str, proof: Option<String>, public_inputs: Option<Vec<String>>) -> Self {
+    pub fn success(message: &str, proof: ProofEnvelope) -> Self {
         Self {
             success: true,
             message: message.to_string(),
-            proof,
-            public_inputs,
+            proof: Some(proof),
         }
     }
 
@@ -50,12 +102,12 @@ impl ProofResult {
             success: false,
             message: message.to_string(),
             proof: None,
-            public_inputs: None,
         }
     }
 }
 
 // Our basic zkSnark circuit for proving we know x such that x^2 = y
+#[derive(Clone)]
 pub struct SquareDemo {
     pub x: Option<Scalar>,
     pub y: Option<Scalar>,
@@ -84,51 +136,130 @@ impl Circuit<Scalar> for SquareDemo {
     }
 }
 
+/// Witness file for the SquareDemo circuit: the private `x`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SquareWitness {
+    pub x: u64,
+}
+
+impl SquareWitness {
+    /// Build the circuit instance and its public input `y = x²`
+    pub fn build_circuit(&self) -> Result<(SquareDemo, Vec<Scalar>), ZkError> {
+        let y = self.x.checked_mul(self.x)
+            .ok_or_else(|| ZkError::InvalidInput("x² does not fit in a u64".to_string()))?;
+
+        let circuit = SquareDemo {
+            x: Some(scalar_from_u64(self.x)),
+            y: Some(scalar_from_u64(y)),
+        };
+        Ok((circuit, vec![scalar_from_u64(y)]))
+    }
+}
+
 // Helper function to convert number to scalar
 pub fn scalar_from_u64(n: u64) -> Scalar {
     let mut scalar = Scalar::zero();
-    scalar.add_assign(&Scalar::from(n));
+    scalar += Scalar::from(n);
     scalar
 }
 
-// Generate parameters and proving/verification keys for the SquareDemo circuit
-pub fn setup_square_circuit(x: u64, y: u64) -> Result<bellman::groth16::Parameters<Bls12>, JsValue> {
-    let rng = &mut OsRng;
+/// Serialize a Groth16 proof to its compressed byte encoding (A, B, C)
+pub fn serialize_proof(proof: &Proof<Bls12>) -> Result<Vec<u8>, ZkError> {
+    let mut bytes = Vec::new();
+    proof.write(&mut bytes)?;
+    Ok(bytes)
+}
 
-    // Create parameters for our circuit
-    let params = {
-        let c = SquareDemo {
-            x: Some(scalar_from_u64(x)),
-            y: Some(scalar_from_u64(y)),
-        };
-        
-        generate_random_parameters::<Bls12, _, _>(c, rng)
-            .map_err(|e| JsValue::from_str(&get_error_message("Parameter generation error", &e)))?
-    };
+/// Deserialize a Groth16 proof previously produced by `serialize_proof`
+pub fn deserialize_proof(bytes: &[u8]) -> Result<Proof<Bls12>, ZkError> {
+    Ok(Proof::read(bytes)?)
+}
+
+/// Serialize proving parameters (which embed the verifying key) to bytes
+pub fn serialize_parameters(params: &Parameters<Bls12>) -> Result<Vec<u8>, ZkError> {
+    let mut bytes = Vec::new();
+    params.write(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Deserialize proving parameters, checking every point is on the curve and in the subgroup
+pub fn deserialize_parameters(bytes: &[u8]) -> Result<Parameters<Bls12>, ZkError> {
+    Ok(Parameters::read(bytes, true)?)
+}
+
+/// Serialize a verifying key to bytes
+pub fn serialize_verifying_key(vk: &VerifyingKey<Bls12>) -> Result<Vec<u8>, ZkError> {
+    let mut bytes = Vec::new();
+    vk.write(&mut bytes)?;
+    Ok(bytes)
+}
 
-    Ok(params)
+/// Deserialize a verifying key previously produced by `serialize_verifying_key`
+pub fn deserialize_verifying_key(bytes: &[u8]) -> Result<VerifyingKey<Bls12>, ZkError> {
+    Ok(VerifyingKey::read(bytes)?)
+}
+
+/// Encode a public input as the hex string of its canonical little-endian bytes
+pub fn encode_scalar(scalar: &Scalar) -> String {
+    hex::encode(scalar.to_bytes())
+}
+
+/// Decode a public input produced by `encode_scalar`
+pub fn decode_scalar(encoded: &str) -> Result<Scalar, ZkError> {
+    let bytes: [u8; 32] = hex::decode(encoded)?
+        .try_into()
+        .map_err(|_| ZkError::Decoding("public input must be 32 bytes".to_string()))?;
+
+    Option::from(Scalar::from_bytes(&bytes))
+        .ok_or_else(|| ZkError::Decoding("public input is not a canonical field element".to_string()))
+}
+
+// Generate random (single-party) parameters for any circuit shape
+pub fn setup_parameters<C: Circuit<Scalar>>(circuit: C) -> Result<Parameters<Bls12>, ZkError> {
+    Ok(generate_random_parameters::<Bls12, _, _>(circuit, &mut OsRng)?)
+}
+
+// Generate a proof for any circuit instance carrying its witness
+pub fn create_circuit_proof<C: Circuit<Scalar>>(params: &Parameters<Bls12>, circuit: C) -> Result<Proof<Bls12>, ZkError> {
+    Ok(create_random_proof(circuit, params, &mut OsRng)?)
+}
+
+// Check a circuit instance is satisfied before spending time on a proof.
+// Returns the name of the first failing constraint.
+pub fn check_satisfied<C: Circuit<Scalar>>(circuit: C) -> Result<(), ZkError> {
+    let mut cs = TestConstraintSystem::new();
+    circuit.synthesize(&mut cs)?;
+
+    match cs.which_is_unsatisfied() {
+        None => Ok(()),
+        Some(name) => Err(ZkError::InvalidInput(format!("witness does not satisfy constraint '{}'", name))),
+    }
+}
+
+// Generate parameters and proving/verification keys for the SquareDemo circuit
+pub fn setup_square_circuit(x: u64, y: u64) -> Result<bellman::groth16::Parameters<Bls12>, ZkError> {
+    // Create parameters for our circuit
+    setup_parameters(SquareDemo {
+        x: Some(scalar_from_u64(x)),
+        y: Some(scalar_from_u64(y)),
+    })
 }
 
 // Generate a ZK proof for the SquareDemo circuit
-pub fn create_square_proof(
-    params: &bellman::groth16::Parameters<Bls12>,
+pub fn create_square_proof<P: ProofSystem>(
+    system: &P,
+    pk: &P::ProvingKey,
     x: u64,
     y: u64,
-) -> Result<(
-    bellman::groth16::Proof<Bls12>,
-    Vec<Scalar>,
-), JsValue> {
-    let rng = &mut OsRng;
-
+) -> Result<(P::Proof, Vec<Scalar>), ZkError> {
     // Create an instance of our circuit with the witness
     let circuit = SquareDemo {
         x: Some(scalar_from_u64(x)), 
         y: Some(scalar_from_u64(y)),
     };
 
-    // Create a proof with our parameters
-    let proof = create_random_proof(circuit, params, rng)
-        .map_err(|e| JsValue::from_str(&get_error_message("Proof generation error", &e)))?;
+    // Create a proof with our proving key
+    let proof = system.prove(pk, circuit)?;
 
     // The public input is our y value
     let inputs = vec![scalar_from_u64(y)];
@@ -137,17 +268,68 @@ pub fn create_square_proof(
 }
 
 // Verify a proof for the SquareDemo circuit
-pub fn verify_square_proof(
-    params: &bellman::groth16::Parameters<Bls12>,
-    proof: &bellman::groth16::Proof<Bls12>,
+pub fn verify_square_proof<P: ProofSystem>(
+    system: &P,
+    pvk: &P::PreparedVerifyingKey,
+    proof: &P::Proof,
+    inputs: &[Scalar],
+) -> Result<bool, ZkError> {
+    system.verify(pvk, proof, inputs)
+}
+
+// Verify a proof against an already prepared verifying key.
+// An invalid proof yields `Ok(false)`; a malformed key or input count is an error.
+pub fn verify_prepared_proof(
+    pvk: &PreparedVerifyingKey<Bls12>,
+    proof: &Proof<Bls12>,
     inputs: &[Scalar],
-) -> Result<bool, JsValue> {
-    // Prepare the verification key
-    let pvk = prepare_verifying_key(&params.vk);
+) -> Result<bool, ZkError> {
+    match verify_proof(pvk, proof, inputs) {
+        Ok(()) => Ok(true),
+        Err(VerificationError::InvalidProof) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::proof_system::Groth16;
+    use bellman::groth16::prepare_verifying_key;
+
+    #[test]
+    fn serialized_square_proof_verifies_after_round_trip() {
+        let params = setup_square_circuit(4, 16).unwrap();
+        let (proof, inputs) = create_square_proof(&Groth16, &params, 5, 25).unwrap();
 
-    // Verify the proof
-    let result = verify_proof(&pvk, proof, inputs)
-        .map_err(|e| JsValue::from_str(&get_error_message("Verification error", &e)))?;
+        let envelope = ProofEnvelope::seal(&Groth16, &params, ProofType::SquareRoot, &proof, &inputs, None).unwrap();
+        let result = ProofResult::success("ok", envelope);
+        let decoded = serde_json::from_str::<ProofResult>(&serde_json::to_string(&result).unwrap()).unwrap().proof.unwrap();
 
-    Ok(result)
+        let pvk = prepare_verifying_key(&params.vk);
+        assert!(verify_prepared_proof(&pvk, &decoded.decode_proof(&Groth16).unwrap(), &decoded.public_inputs).unwrap());
+    }
+
+    #[test]
+    fn exported_keys_verify_proofs_from_another_session() {
+        let params = setup_square_circuit(4, 16).unwrap();
+        let (proof, inputs) = create_square_proof(&Groth16, &params, 3, 9).unwrap();
+
+        let restored = deserialize_parameters(&serialize_parameters(&params).unwrap()).unwrap();
+        let (restored_proof, _) = create_square_proof(&Groth16, &restored, 7, 49).unwrap();
+
+        let vk = deserialize_verifying_key(&serialize_verifying_key(&params.vk).unwrap()).unwrap();
+        let pvk = prepare_verifying_key(&vk);
+        assert!(verify_prepared_proof(&pvk, &proof, &inputs).unwrap());
+        assert!(verify_prepared_proof(&pvk, &restored_proof, &[scalar_from_u64(49)]).unwrap());
+    }
+
+    #[test]
+    fn proof_does_not_verify_against_wrong_input() {
+        let params = setup_square_circuit(4, 16).unwrap();
+        let (proof, _) = create_square_proof(&Groth16, &params, 5, 25).unwrap();
+
+        let pvk = prepare_verifying_key(&params.vk);
+        assert!(!verify_prepared_proof(&pvk, &proof, &[scalar_from_u64(36)]).unwrap());
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,67 @@
+use crate::crypto::ceremony::CeremonyError;
+use bellman::{SynthesisError, VerificationError};
+use std::io;
+use thiserror::Error;
+use wasm_bindgen::prelude::*;
+
+/// Errors returned by the proving APIs.
+///
+/// Native callers (tests, the `zkp` command-line tool) get a regular Rust error;
+/// `#[wasm_bindgen]` exports convert it to a `JsValue` message with `?`.
+#[derive(Debug, Error)]
+pub enum ZkError {
+    #[error("Synthesis error: {0}")]
+    Synthesis(#[from] SynthesisError),
+    #[error("Verification error: {0}")]
+    Verification(#[from] VerificationError),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] io::Error),
+    #[error("Decoding error: {0}")]
+    Decoding(String),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error("Nullifier already used: {0}")]
+    NullifierReused(String),
+    #[error("Proving cancelled")]
+    Cancelled,
+    #[error("Proof envelope mismatch: {0}")]
+    EnvelopeMismatch(String),
+    #[error(transparent)]
+    Ceremony(#[from] CeremonyError),
+}
+
+impl From<serde_json::Error> for ZkError {
+    fn from(error: serde_json::Error) -> Self {
+        ZkError::Decoding(format!("JSON: {}", error))
+    }
+}
+
+impl From<ciborium::de::Error<io::Error>> for ZkError {
+    fn from(error: ciborium::de::Error<io::Error>) -> Self {
+        ZkError::Decoding(format!("CBOR: {}", error))
+    }
+}
+
+impl From<ciborium::ser::Error<io::Error>> for ZkError {
+    fn from(error: ciborium::ser::Error<io::Error>) -> Self {
+        ZkError::Serialization(io::Error::other(format!("CBOR: {}", error)))
+    }
+}
+
+impl From<base64::DecodeError> for ZkError {
+    fn from(error: base64::DecodeError) -> Self {
+        ZkError::Decoding(format!("base64: {}", error))
+    }
+}
+
+impl From<hex::FromHexError> for ZkError {
+    fn from(error: hex::FromHexError) -> Self {
+        ZkError::Decoding(format!("hex: {}", error))
+    }
+}
+
+impl From<ZkError> for JsValue {
+    fn from(error: ZkError) -> Self {
+        JsValue::from_str(&error.to_string())
+    }
+}
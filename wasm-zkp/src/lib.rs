@@ -1,232 +1,850 @@
 // Re-export the modules
 pub mod crypto;
+pub mod error;
 pub mod multi_chain;
 pub mod utils;
 
-use bellman::{
-    groth16::{self, create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof},
-    Circuit, ConstraintSystem, SynthesisError,
+use bls12_381::Scalar;
+use crypto::aggregation::{aggregate_proofs, verify_aggregate_proof, AggregateProof, AggregationSrs, AggregationVerifyingKey};
+use crypto::bbs::{create_bbs_presentation, verify_bbs_presentation, BbsPresentation, BbsPublicKey};
+use crypto::batch::{verify_batch_entries, BatchEntry};
+use crypto::ceremony::{CeremonyError, MPCParameters, PowersOfTau};
+use crypto::conjunction::{create_conjunction_proof, verify_conjunction_proof};
+use crypto::credential::{
+    create_credential_proof, verify_credential_proof, Credential, CredentialPresentation,
 };
-use bls12_381::{Bls12, Scalar};
-use ff::Field;
-use rand::rngs::OsRng;
-use std::ops::MulAssign;
+use crypto::did_resolver::{create_did_ownership_proof, verify_did_ownership_proof, DIDDocument};
+#[cfg(feature = "universal")]
+use crypto::marlin::{CircuitSize, Marlin};
+use crypto::merkle::{
+    create_membership_proof, decode_leaves, member_secret, verify_membership_proof, MerkleTree, MEMBERSHIP_TREE_DEPTH,
+};
+use crypto::eddsa::{SigningKey, VerifyingKey};
+use crypto::envelope::ProofEnvelope;
+use crypto::progress::{settle, CancellationToken, JsProgress, Observed, ProgressObserver};
+use crypto::predicate::{
+    create_predicate_proof, verify_predicate_proof, CredentialPredicates, CredentialRequest, Predicate,
+    PredicatePresentation,
+};
+use crypto::proof_system::{Groth16, ProofSystem};
+#[cfg(feature = "universal")]
+use crypto::registry::circuit_size;
+use crypto::registry::{constraint_count, setup_circuit, verify_ceremony, CircuitId, CircuitRegistry, KeySlot, KeySource};
+use crypto::range_proof::{create_range_proof, verify_range_proof, Comparison, RangePresentation, RangeRequest, SignedValueCommitment};
+use crypto::revocation::RevocationRegistry;
+use crypto::zk_proofs::{
+    create_square_proof, decode_scalar, verify_square_proof, encode_scalar, scalar_from_u64, ProofResult, ProofType,
+};
+use error::ZkError;
+use std::str::FromStr;
+use utils::get_error_message;
 use wasm_bindgen::prelude::*;
-use serde_json::json;
 
 // Setup panic hook for better error reporting
 fn init_panic_hook() {
-    #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
 
-// Our zkSnark circuit for proving we know x such that x^2 = y
-struct SquareDemo {
-    x: Option<Scalar>,
-    y: Option<Scalar>,
+/// A proof system and the registry of keys for it, per circuit
+struct Backend<P: ProofSystem> {
+    system: P,
+    circuits: CircuitRegistry<P>,
 }
 
-impl Circuit<Scalar> for SquareDemo {
-    fn synthesize<CS: ConstraintSystem<Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
-        // Allocate the first value (private)
-        let x = cs.alloc(|| "x", || self.x.ok_or(SynthesisError::AssignmentMissing))?;
-
-        // Allocate the output (public)
-        let y = cs.alloc_input(
-            || "y",
-            || self.y.ok_or(SynthesisError::AssignmentMissing),
-        )?;
-
-        // x^2 = y
-        let mut squared = Scalar::one();
-        squared.mul_assign(&self.x.unwrap());
-        squared.mul_assign(&self.x.unwrap());
-
-        // Enforce that x^2 = y
-        cs.enforce(
-            || "y = x^2",
-            |lc| lc + x,
-            |lc| lc + x,
-            |lc| lc + y,
-        );
+impl<P: ProofSystem> Backend<P> {
+    fn new(system: P) -> Self {
+        Self { system, circuits: CircuitRegistry::default() }
+    }
 
+    fn insert_proving_key(&mut self, circuit: impl Into<CircuitId>, pk: P::ProvingKey) -> Result<(), ZkError> {
+        let slot = KeySlot::with_proving_key(&self.system, pk)?;
+        self.circuits.insert(circuit, slot);
         Ok(())
     }
-}
-
-struct ProofData {
-    proof: Vec<u8>,
-    inputs: Vec<Vec<u8>>,
-}
 
-// Helper function to convert number to scalar
-fn scalar_from_u64(n: u64) -> Scalar {
-    let mut scalar = Scalar::zero();
-    scalar.add_assign(&Scalar::from(n));
-    scalar
-}
+    fn insert_verifying_key(&mut self, circuit: impl Into<CircuitId>, vk: P::VerifyingKey) -> Result<(), ZkError> {
+        let slot = KeySlot::with_verifying_key(&self.system, vk)?;
+        self.circuits.insert(circuit, slot);
+        Ok(())
+    }
 
-// Generate parameters and proving/verification keys
-fn setup_circuit(x: u64, y: u64) -> Result<bellman::groth16::Parameters<Bls12>, JsValue> {
-    let rng = &mut OsRng;
+    /// The proving key for `circuit`, loaded from its registered source on first use
+    fn proving_key(&self, circuit: impl Into<CircuitId>) -> Result<&P::ProvingKey, ZkError> {
+        let circuit = circuit.into();
+        self.circuits.get(&self.system, circuit)?
+            .and_then(|slot| slot.pk.as_ref())
+            .ok_or_else(|| ZkError::InvalidInput(format!("{} parameters not initialized", circuit.name())))
+    }
 
-    // Create parameters for our circuit
-    let params = {
-        let c = SquareDemo {
-            x: Some(scalar_from_u64(x)),
-            y: Some(scalar_from_u64(y)),
-        };
-        
-        generate_random_parameters::<Bls12, _, _>(c, rng)
-            .map_err(|e| JsValue::from_str(&format!("Parameter generation error: {:?}", e)))?
-    };
+    /// The keys for `circuit`, loaded from their registered source on first use
+    fn slot(&self, circuit: impl Into<CircuitId>) -> Result<&KeySlot<P>, ZkError> {
+        let circuit = circuit.into();
+        self.circuits.get(&self.system, circuit)?
+            .ok_or_else(|| ZkError::InvalidInput(format!("{} verifying key not initialized", circuit.name())))
+    }
 
-    Ok(params)
-}
+    fn pvk(&self, circuit: impl Into<CircuitId>) -> Result<&P::PreparedVerifyingKey, ZkError> {
+        Ok(&self.slot(circuit)?.pvk)
+    }
 
-// Generate a ZK proof
-fn create_proof(
-    params: &bellman::groth16::Parameters<Bls12>,
-    x: u64,
-    y: u64,
-) -> Result<(
-    bellman::groth16::Proof<Bls12>,
-    Vec<Scalar>,
-), JsValue> {
-    let rng = &mut OsRng;
-
-    // Create an instance of our circuit with the witness
-    let circuit = SquareDemo {
-        x: Some(scalar_from_u64(x)), 
-        y: Some(scalar_from_u64(y)),
-    };
+    /// The proof system, reporting its runs on `circuit` to `observer`
+    fn observed<'a, O: ProgressObserver + ?Sized>(
+        &'a self,
+        circuit: impl Into<CircuitId>,
+        observer: &'a O,
+    ) -> Result<Observed<'a, P, O>, ZkError> {
+        Ok(Observed::new(&self.system, observer, constraint_count(circuit)?))
+    }
 
-    // Create a proof with our parameters
-    let proof = create_random_proof(circuit, params, rng)
-        .map_err(|e| JsValue::from_str(&format!("Proof generation error: {:?}", e)))?;
+    /// Reject an envelope not holding a proof for `circuit`, under its loaded
+    /// verifying key and made for `context`
+    fn check_envelope(&self, envelope: &ProofEnvelope, circuit: impl Into<CircuitId>, context: Option<&str>) -> Result<(), ZkError> {
+        let circuit = circuit.into();
+        envelope.check(self.system.name(), circuit.proof_type, &self.slot(circuit)?.vk_hash, context)
+    }
 
-    // The public input is our y value
-    let inputs = vec![scalar_from_u64(y)];
+    /// Decode a JSON proof envelope and, once checked as by `check_envelope`, its proof
+    fn open(&self, envelope_json: &str, proof_type: ProofType, context: Option<&str>) -> Result<P::Proof, ZkError> {
+        let envelope = ProofEnvelope::from_json(envelope_json)?;
+        self.check_envelope(&envelope, proof_type, context)?;
+        envelope.decode_proof(&self.system)
+    }
 
-    Ok((proof, inputs))
+    /// Seal a proof for the circuit behind `proof_type` in its envelope
+    fn proof_result(
+        &self,
+        message: &str,
+        proof_type: ProofType,
+        proof: &P::Proof,
+        inputs: &[Scalar],
+        context: Option<&str>,
+    ) -> Result<ProofResult, ZkError> {
+        let pk = self.proving_key(proof_type)?;
+        let envelope = ProofEnvelope::seal(&self.system, pk, proof_type, proof, inputs, context)?;
+        Ok(ProofResult::success(message, envelope))
+    }
 }
 
-// Verify a proof
-fn verify(
-    params: &bellman::groth16::Parameters<Bls12>,
-    proof: &bellman::groth16::Proof<Bls12>,
-    inputs: &[Scalar],
-) -> Result<bool, JsValue> {
-    // Prepare the verification key
-    let pvk = prepare_verifying_key(&params.vk);
-
-    // Verify the proof
-    let result = verify_proof(&pvk, proof, inputs)
-        .map_err(|e| JsValue::from_str(&format!("Verification error: {:?}", e)))?;
+/// The proof system a `ZkProver` proves and verifies with
+enum ProverBackend {
+    Groth16(Backend<Groth16>),
+    #[cfg(feature = "universal")]
+    Universal(Backend<Marlin>),
+}
 
-    Ok(result)
+// Evaluate `$body` with `$backend` bound to the active `Backend`, whatever its proof system
+macro_rules! with_backend {
+    ($prover_backend:expr, $backend:ident => $body:expr) => {
+        match $prover_backend {
+            ProverBackend::Groth16($backend) => $body,
+            #[cfg(feature = "universal")]
+            ProverBackend::Universal($backend) => $body,
+        }
+    };
 }
 
 #[wasm_bindgen]
 pub struct ZkProver {
-    params: Option<bellman::groth16::Parameters<Bls12>>,
+    // Proof system, and its keys per circuit; each circuit has its own shape
+    backend: ProverBackend,
+    // Reference string for proof aggregation, and its verifying key
+    aggregation_srs: Option<AggregationSrs>,
+    aggregation_vk: Option<AggregationVerifyingKey>,
+}
+
+impl Default for ZkProver {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
 impl ZkProver {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
+        Self::with_backend(ProverBackend::Groth16(Backend::new(Groth16)))
+    }
+
+    /// Build a prover on the universal-setup (Marlin) backend from a reference string
+    /// written by `generate_universal_srs`. Keys for each circuit are derived from it
+    /// on the circuit's first use, or ahead of time with `initialize_circuit`; no
+    /// per-circuit ceremony is needed.
+    #[cfg(feature = "universal")]
+    #[wasm_bindgen]
+    pub fn with_universal_srs(srs_bytes: &[u8]) -> Result<ZkProver, JsValue> {
+        let srs = Marlin::deserialize_srs(srs_bytes)?;
+        let backend = Backend { system: Marlin::new(srs), circuits: CircuitRegistry::with_setup() };
+        Ok(Self::with_backend(ProverBackend::Universal(backend)))
+    }
+
+    /// Build a verifier-only instance on the universal-setup backend; load the
+    /// verifying keys of the circuits with `load_circuit_verifying_key`
+    #[cfg(feature = "universal")]
+    #[wasm_bindgen]
+    pub fn universal_verifier() -> ZkProver {
+        Self::with_backend(ProverBackend::Universal(Backend::new(Marlin::default())))
+    }
+
+    /// Name of the proof system behind this instance: `groth16` or `marlin`
+    #[wasm_bindgen]
+    pub fn proof_system(&self) -> String {
+        with_backend!(&self.backend, backend => backend.system.name().to_string())
+    }
+
+    /// Build a prover from parameters previously returned by `export_parameters`
+    #[wasm_bindgen]
+    pub fn from_parameters(params_bytes: &[u8]) -> Result<ZkProver, JsValue> {
+        init_panic_hook();
+        let mut prover = Self::new();
+        prover.load_circuit_parameters(ProofType::SquareRoot, params_bytes, None)?;
+        Ok(prover)
+    }
+
+    /// Build a verifier-only instance from a verifying key; it cannot generate proofs
+    #[wasm_bindgen]
+    pub fn from_verifying_key(vk_bytes: &[u8]) -> Result<ZkProver, JsValue> {
         init_panic_hook();
-        Self { params: None }
+        let mut prover = Self::new();
+        prover.load_circuit_verifying_key(ProofType::SquareRoot, vk_bytes, None)?;
+        Ok(prover)
+    }
+
+    /// Build a Groth16 prover from the output of a phase-2 ceremony (see
+    /// `crypto::ceremony`) for the circuit behind `proof_type`, as
+    /// `load_circuit_ceremony` loads it
+    #[wasm_bindgen]
+    pub fn from_ceremony(
+        proof_type: ProofType,
+        ceremony_bytes: &[u8],
+        powers_bytes: &[u8],
+        credentials: Option<usize>,
+    ) -> Result<ZkProver, JsValue> {
+        init_panic_hook();
+        let mut prover = Self::new();
+        prover.load_circuit_ceremony(proof_type, ceremony_bytes, powers_bytes, credentials)?;
+        Ok(prover)
+    }
+
+    /// Use the keys from a phase-2 ceremony for the circuit behind `proof_type`. The
+    /// transcript is first verified against that circuit and the phase-1
+    /// `powers_bytes` (`PowersOfTau::write` format), as `zkp keygen --ceremony` does.
+    /// Groth16 only.
+    #[wasm_bindgen]
+    pub fn load_circuit_ceremony(
+        &mut self,
+        proof_type: ProofType,
+        ceremony_bytes: &[u8],
+        powers_bytes: &[u8],
+        credentials: Option<usize>,
+    ) -> Result<(), JsValue> {
+        let circuit = CircuitId::new(proof_type, credentials)?;
+        let mpc = MPCParameters::read(ceremony_bytes).map_err(CeremonyError::from)?;
+        let powers = PowersOfTau::read(powers_bytes).map_err(CeremonyError::from)?;
+        verify_ceremony(&mpc, circuit, &powers)?;
+
+        match &mut self.backend {
+            ProverBackend::Groth16(backend) => backend.insert_proving_key(circuit, mpc.params().clone())?,
+            #[cfg(feature = "universal")]
+            ProverBackend::Universal(_) => {
+                return Err(ZkError::InvalidInput("ceremony keys need the Groth16 backend".to_string()).into())
+            }
+        }
+        Ok(())
     }
 
     #[wasm_bindgen]
     pub fn initialize(&mut self) -> Result<(), JsValue> {
-        // Let's use x=4, y=16 for initialization
-        self.params = Some(setup_circuit(4, 16)?);
+        self.initialize_circuit(ProofType::SquareRoot, None)
+    }
+
+    /// Export the proving parameters (including the verifying key) so later sessions can reuse them
+    #[wasm_bindgen]
+    pub fn export_parameters(&self) -> Result<Vec<u8>, JsValue> {
+        self.export_circuit_parameters(ProofType::SquareRoot, None)
+    }
+
+    /// Export only the verifying key, for distribution to verifiers
+    #[wasm_bindgen]
+    pub fn export_verifying_key(&self) -> Result<Vec<u8>, JsValue> {
+        self.export_circuit_verifying_key(ProofType::SquareRoot, None)
+    }
+
+    /// Generate keys for the circuit behind `proof_type`: fresh single-party keys under
+    /// Groth16, or keys derived from the loaded reference string under the universal backend.
+    ///
+    /// Here and in the other key methods, `credentials` picks the conjunction circuit
+    /// for that many credentials (2 to `MAX_CONJUNCTION_CREDENTIALS`, two when omitted);
+    /// each has its own keys. Other proof types take no `credentials`.
+    #[wasm_bindgen]
+    pub fn initialize_circuit(&mut self, proof_type: ProofType, credentials: Option<usize>) -> Result<(), JsValue> {
+        self.initialize_circuit_with(CircuitId::new(proof_type, credentials)?, &())
+    }
+
+    /// `initialize_circuit` as a promise, calling `on_progress(stage, done, total)` as
+    /// it goes and failing with "Proving cancelled" once `cancel` is cancelled (see
+    /// `crypto::progress`). It holds the calling thread until done, so call it from a
+    /// web worker to keep the page responsive.
+    #[wasm_bindgen]
+    pub fn initialize_circuit_async(
+        &mut self,
+        proof_type: ProofType,
+        on_progress: Option<js_sys::Function>,
+        cancel: Option<CancellationToken>,
+        credentials: Option<usize>,
+    ) -> js_sys::Promise {
+        let observer = JsProgress::new(on_progress, cancel);
+        let initialized = CircuitId::new(proof_type, credentials)
+            .map_err(JsValue::from)
+            .and_then(|circuit| self.initialize_circuit_with(circuit, &observer));
+        settle(initialized.map(|()| JsValue::UNDEFINED))
+    }
+
+    /// Load parameters previously returned by `export_circuit_parameters`. They are
+    /// used as given, with nothing to show how they were made, so this is the path for
+    /// single-party keys in development and tests; keys from a phase-2 ceremony are
+    /// loaded with `load_circuit_ceremony`, which verifies the transcript first.
+    #[wasm_bindgen]
+    pub fn load_circuit_parameters(
+        &mut self,
+        proof_type: ProofType,
+        params_bytes: &[u8],
+        credentials: Option<usize>,
+    ) -> Result<(), JsValue> {
+        let circuit = CircuitId::new(proof_type, credentials)?;
+        with_backend!(&mut self.backend, backend => {
+            let pk = backend.system.deserialize_proving_key(params_bytes)?;
+            backend.insert_proving_key(circuit, pk)?;
+        });
+        Ok(())
+    }
+
+    /// Load only a verifying key, for verifiers
+    #[wasm_bindgen]
+    pub fn load_circuit_verifying_key(
+        &mut self,
+        proof_type: ProofType,
+        vk_bytes: &[u8],
+        credentials: Option<usize>,
+    ) -> Result<(), JsValue> {
+        let circuit = CircuitId::new(proof_type, credentials)?;
+        with_backend!(&mut self.backend, backend => {
+            let vk = backend.system.deserialize_verifying_key(vk_bytes)?;
+            backend.insert_verifying_key(circuit, vk)?;
+        });
+        Ok(())
+    }
+
+    /// Register proving parameters (as from `export_circuit_parameters`) for the circuit
+    /// behind `proof_type` without reading them yet: they are deserialized on the
+    /// circuit's first use. Replaces any keys the circuit has, so keys can be swapped
+    /// on a live prover.
+    #[wasm_bindgen]
+    pub fn register_circuit_parameters(
+        &mut self,
+        proof_type: ProofType,
+        params_bytes: Vec<u8>,
+        credentials: Option<usize>,
+    ) -> Result<(), JsValue> {
+        let circuit = CircuitId::new(proof_type, credentials)?;
+        with_backend!(&mut self.backend, backend => {
+            backend.circuits.register(circuit, KeySource::ProvingKey(params_bytes));
+        });
+        Ok(())
+    }
+
+    /// `register_circuit_parameters` for a verifying key, for verifiers
+    #[wasm_bindgen]
+    pub fn register_circuit_verifying_key(
+        &mut self,
+        proof_type: ProofType,
+        vk_bytes: Vec<u8>,
+        credentials: Option<usize>,
+    ) -> Result<(), JsValue> {
+        let circuit = CircuitId::new(proof_type, credentials)?;
+        with_backend!(&mut self.backend, backend => {
+            backend.circuits.register(circuit, KeySource::VerifyingKey(vk_bytes));
+        });
+        Ok(())
+    }
+
+    /// Drop the keys for the circuit behind `proof_type`, loaded or registered
+    #[wasm_bindgen]
+    pub fn unload_circuit(&mut self, proof_type: ProofType, credentials: Option<usize>) -> Result<(), JsValue> {
+        let circuit = CircuitId::new(proof_type, credentials)?;
+        with_backend!(&mut self.backend, backend => backend.circuits.remove(circuit));
         Ok(())
     }
 
+    /// Whether keys for the circuit behind `proof_type` are loaded; registered keys
+    /// count once their first use has read them
+    #[wasm_bindgen]
+    pub fn is_circuit_loaded(&self, proof_type: ProofType, credentials: Option<usize>) -> Result<bool, JsValue> {
+        let circuit = CircuitId::new(proof_type, credentials)?;
+        Ok(with_backend!(&self.backend, backend => backend.circuits.is_loaded(circuit)))
+    }
+
+    #[wasm_bindgen]
+    pub fn export_circuit_parameters(&self, proof_type: ProofType, credentials: Option<usize>) -> Result<Vec<u8>, JsValue> {
+        let circuit = CircuitId::new(proof_type, credentials)?;
+        Ok(with_backend!(&self.backend, backend => {
+            backend.system.serialize_proving_key(backend.proving_key(circuit)?)?
+        }))
+    }
+
+    #[wasm_bindgen]
+    pub fn export_circuit_verifying_key(&self, proof_type: ProofType, credentials: Option<usize>) -> Result<Vec<u8>, JsValue> {
+        let circuit = CircuitId::new(proof_type, credentials)?;
+        Ok(with_backend!(&self.backend, backend => {
+            backend.system.serialize_verifying_key(&backend.slot(circuit)?.vk)?
+        }))
+    }
+
+    /// Verify many proofs for the circuit behind `proof_type` at once. `entries_json` is
+    /// a JSON array of `{ proof, publicInputs }` (proof envelope, hex-encoded inputs the
+    /// verifier expects); returns `{ valid, failed }` with the indices of the entries
+    /// that did not verify. Groth16 only.
+    #[wasm_bindgen]
+    pub fn verify_proofs_batch(&self, proof_type: ProofType, entries_json: &str) -> Result<JsValue, JsValue> {
+        let slot = self.groth16()?.slot(proof_type)?;
+        let entries: Vec<BatchEntry> = serde_json::from_str(entries_json).map_err(ZkError::from)?;
+
+        let result = verify_batch_entries(&slot.vk, &slot.vk_hash, &slot.pvk, &entries);
+
+        serde_wasm_bindgen::to_value(&result)
+            .map_err(|e| JsValue::from_str(&get_error_message("Serialization error", &e)))
+    }
+
+    /// Generate a local aggregation reference string for up to `max_proofs` proofs
+    /// (a power of two). Its secrets are known to this process, so it is for
+    /// development only; production setups load one built from powers of tau.
+    #[wasm_bindgen]
+    pub fn initialize_aggregation(&mut self, max_proofs: usize) -> Result<(), JsValue> {
+        let srs = AggregationSrs::generate_insecure(max_proofs, &mut rand::rngs::OsRng)?;
+        self.aggregation_vk = Some(srs.verifying_key());
+        self.aggregation_srs = Some(srs);
+        Ok(())
+    }
+
+    /// Load an aggregation reference string written by `export_aggregation_srs`
+    #[wasm_bindgen]
+    pub fn load_aggregation_srs(&mut self, srs_bytes: &[u8]) -> Result<(), JsValue> {
+        let srs = AggregationSrs::read(srs_bytes).map_err(ZkError::from)?;
+        self.aggregation_vk = Some(srs.verifying_key());
+        self.aggregation_srs = Some(srs);
+        Ok(())
+    }
+
+    /// Load only the aggregation verifying key, for instances that verify aggregates
+    #[wasm_bindgen]
+    pub fn load_aggregation_verifying_key(&mut self, vk_bytes: &[u8]) -> Result<(), JsValue> {
+        self.aggregation_vk = Some(AggregationVerifyingKey::read(vk_bytes).map_err(ZkError::from)?);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn export_aggregation_srs(&self) -> Result<Vec<u8>, JsValue> {
+        let mut bytes = Vec::new();
+        self.aggregation_srs()?.write(&mut bytes).map_err(ZkError::from)?;
+        Ok(bytes)
+    }
+
+    #[wasm_bindgen]
+    pub fn export_aggregation_verifying_key(&self) -> Result<Vec<u8>, JsValue> {
+        let vk = self.aggregation_vk.as_ref()
+            .ok_or_else(|| ZkError::InvalidInput("no aggregation key loaded".into()))?;
+        let mut bytes = Vec::new();
+        vk.write(&mut bytes).map_err(ZkError::from)?;
+        Ok(bytes)
+    }
+
+    /// Aggregate proofs for the circuit behind `proof_type` into one aggregate, returned
+    /// as a JSON proof envelope of the `groth16-snarkpack` proof system. `entries_json`
+    /// is a JSON array of `{ proof, publicInputs }`, as for `verify_proofs_batch`; the
+    /// verifier needs the inputs in the same order. Groth16 only. Entries whose
+    /// envelope names another key fail the whole aggregate.
+    #[wasm_bindgen]
+    pub fn aggregate_proofs(&self, proof_type: ProofType, entries_json: &str) -> Result<String, JsValue> {
+        let slot = self.groth16()?.slot(proof_type)?;
+        let entries: Vec<BatchEntry> = serde_json::from_str(entries_json).map_err(ZkError::from)?;
+        let proofs = entries.iter()
+            .map(|entry| entry.decode(&slot.vk_hash))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let srs = self.aggregation_srs()?;
+        let aggregate = aggregate_proofs(srs, &slot.vk, &proofs)?;
+        Ok(aggregate.seal(&srs.verifying_key(), &slot.vk, proof_type)?.to_json()?)
+    }
+
+    /// Verify an aggregate envelope from `aggregate_proofs`, rejecting one made under
+    /// another aggregation or verifying key. `public_inputs_json` is a JSON array with
+    /// the hex-encoded public inputs of each aggregated proof, in order.
+    #[wasm_bindgen]
+    pub fn verify_aggregate_proof(&self, proof_type: ProofType, aggregate: &str, public_inputs_json: &str) -> Result<bool, JsValue> {
+        let slot = self.groth16()?.slot(proof_type)?;
+        let agg_vk = self.aggregation_vk.as_ref()
+            .ok_or_else(|| ZkError::InvalidInput("no aggregation key loaded".into()))?;
+        let aggregate = AggregateProof::open(&ProofEnvelope::from_json(aggregate)?, agg_vk, &slot.vk, proof_type)?;
+        let encoded: Vec<Vec<String>> = serde_json::from_str(public_inputs_json).map_err(ZkError::from)?;
+        let public_inputs = encoded.iter()
+            .map(|inputs| inputs.iter().map(|input| decode_scalar(input)).collect::<Result<Vec<_>, _>>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(verify_aggregate_proof(agg_vk, &slot.vk, &public_inputs, &aggregate)?)
+    }
+
+    /// Generate a proof of knowledge of `x` such that `x² = y`.
+    /// Returns a `ProofResult` with the proof envelope.
     #[wasm_bindgen]
     pub fn generate_proof(&self, x: u64) -> Result<JsValue, JsValue> {
-        let params = self.params.as_ref()
-            .ok_or_else(|| JsValue::from_str("Parameters not initialized"))?;
-        
-        let y = x * x; // Calculate the expected result
-        
-        let (proof, inputs) = create_proof(params, x, y)?;
-        
-        // Verify the proof we just created
-        let is_valid = verify(params, &proof, &inputs)?;
-        
-        if !is_valid {
-            return Err(JsValue::from_str("Generated proof failed verification"));
-        }
-        
-        // Return proof data
-        let result = JsValue::from_str(&format!(
-            "{{\"success\":true,\"message\":\"Successfully generated and verified proof for x={}, x²={}\",\"publicInput\":{}}}",
-            x, y, y
-        ));
-        
-        Ok(result)
+        self.generate_proof_with(x, &())
     }
 
+    /// `generate_proof` as a promise, reporting progress and stopping on cancellation
+    /// like `initialize_circuit_async`
     #[wasm_bindgen]
-    pub fn verify_proof(&self, proof_str: &str, public_input: u64) -> Result<bool, JsValue> {
-        // This would typically deserialize a proof and verify it
-        // For now, we'll just check if the public input is a perfect square
-        
-        let sqrt = (public_input as f64).sqrt() as u64;
-        let is_perfect_square = sqrt * sqrt == public_input;
+    pub fn generate_proof_async(
+        &self,
+        x: u64,
+        on_progress: Option<js_sys::Function>,
+        cancel: Option<CancellationToken>,
+    ) -> js_sys::Promise {
+        settle(self.generate_proof_with(x, &JsProgress::new(on_progress, cancel)))
+    }
+
+    /// Verify a proof envelope (JSON) produced by `generate_proof` against the public input `y`
+    #[wasm_bindgen]
+    pub fn verify_proof(&self, proof_json: &str, public_input: u64) -> Result<bool, JsValue> {
+        self.verify_proof_with_inputs(proof_json, vec![encode_scalar(&scalar_from_u64(public_input))])
+    }
+
+    /// Verify a proof envelope (JSON) against hex-encoded public inputs, as recorded in
+    /// the envelope's `publicInputs`
+    #[wasm_bindgen]
+    pub fn verify_proof_with_inputs(&self, proof_json: &str, public_inputs: Vec<String>) -> Result<bool, JsValue> {
+        let inputs = public_inputs.iter()
+            .map(|input| decode_scalar(input))
+            .collect::<Result<Vec<_>, _>>()?;
         
-        Ok(is_perfect_square)
+        Ok(with_backend!(&self.backend, backend => {
+            let proof = backend.open(proof_json, ProofType::SquareRoot, None)?;
+            verify_square_proof(&backend.system, backend.pvk(ProofType::SquareRoot)?, &proof, &inputs)?
+        }))
     }
     
+    /// Prove that the numeric attribute in `signed_commitment` (a `SignedValueCommitment`
+    /// JSON, see `issue_value_commitment`), signed for the holder of `holder_secret`,
+    /// satisfies `value <comparison> threshold` to the verifier identified by `context`,
+    /// where `comparison` is one of `>`, `>=`, `<`, `<=` (e.g. age >= 18). Returns a
+    /// `RangePresentation` as JSON.
+    #[wasm_bindgen]
+    pub fn generate_range_proof(
+        &self,
+        signed_commitment: &str,
+        holder_secret: &str,
+        comparison: &str,
+        threshold: u64,
+        context: &str,
+    ) -> Result<String, JsValue> {
+        self.generate_range_proof_with(signed_commitment, holder_secret, comparison, threshold, context, &())
+    }
+
+    /// `generate_range_proof` as a promise, reporting progress and stopping on cancellation
+    /// like `initialize_circuit_async`
+    #[wasm_bindgen]
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_range_proof_async(
+        &self,
+        signed_commitment: &str,
+        holder_secret: &str,
+        comparison: &str,
+        threshold: u64,
+        context: &str,
+        on_progress: Option<js_sys::Function>,
+        cancel: Option<CancellationToken>,
+    ) -> js_sys::Promise {
+        let observer = JsProgress::new(on_progress, cancel);
+        settle(self.generate_range_proof_with(signed_commitment, holder_secret, comparison, threshold, context, &observer))
+    }
+
+    /// Verify a range presentation made to `context` against `request_json`, a
+    /// `RangeRequest` JSON naming the trusted issuer key, the attribute and the comparison
+    #[wasm_bindgen]
+    pub fn verify_range_proof(&self, presentation_json: &str, request_json: &str, context: &str) -> Result<bool, JsValue> {
+        let presentation: RangePresentation = serde_json::from_str(presentation_json).map_err(ZkError::from)?;
+        let request: RangeRequest = serde_json::from_str(request_json).map_err(ZkError::from)?;
+
+        Ok(with_backend!(&self.backend, backend => {
+            backend.check_envelope(&presentation.proof, ProofType::RangeProof, Some(context))?;
+            let pvk = backend.pvk(ProofType::RangeProof)?;
+            verify_range_proof(&backend.system, pvk, &presentation, &request, context)?
+        }))
+    }
+
+    /// Prove that the holder of `secret` is a member of the set of hex-encoded `leaves`
+    /// (see `compute_member_leaf`) without revealing which member it is. The public
+    /// input is the tree root.
+    #[wasm_bindgen]
+    pub fn generate_membership_proof(&self, leaves: Vec<String>, secret: &str) -> Result<JsValue, JsValue> {
+        self.generate_membership_proof_with(&leaves, secret, &())
+    }
+
+    /// `generate_membership_proof` as a promise, reporting progress and stopping on cancellation
+    /// like `initialize_circuit_async`
+    #[wasm_bindgen]
+    pub fn generate_membership_proof_async(
+        &self,
+        leaves: Vec<String>,
+        secret: &str,
+        on_progress: Option<js_sys::Function>,
+        cancel: Option<CancellationToken>,
+    ) -> js_sys::Promise {
+        settle(self.generate_membership_proof_with(&leaves, secret, &JsProgress::new(on_progress, cancel)))
+    }
+
+    /// Verify a membership proof envelope (JSON) against a hex-encoded root (see `compute_membership_root`)
+    #[wasm_bindgen]
+    pub fn verify_membership_proof(&self, proof_json: &str, root: &str) -> Result<bool, JsValue> {
+        let root = decode_scalar(root)?;
+
+        Ok(with_backend!(&self.backend, backend => {
+            let proof = backend.open(proof_json, ProofType::SetMembership, None)?;
+            verify_membership_proof(&backend.system, backend.pvk(ProofType::SetMembership)?, &proof, &root)?
+        }))
+    }
+
     // New methods for enhanced capabilities
     
+    /// Prove possession of a credential as the holder of `holder_secret` to the verifier
+    /// identified by `context`, disclosing only the attributes marked `reveal`; returns
+    /// the presentation JSON handed to the verifier. `current_time` (Unix seconds) is
+    /// the time the verifier asks the proof to hold at; expired credentials cannot prove,
+    /// nor can credentials revoked in the issuer's registry (JSON, see `revoke_credential`).
     #[wasm_bindgen]
-    pub fn generate_credential_proof(&self, credential_json: &str) -> Result<JsValue, JsValue> {
-        // Parse the credential JSON
-        let credential: crypto::credential::Credential = serde_json::from_str(credential_json)
-            .map_err(|e| JsValue::from_str(&format!("Invalid credential JSON: {:?}", e)))?;
-        
-        // Use a mock holder secret for demonstration
-        let holder_secret = "mock_holder_secret";
-        
-        // Get the list of attributes to reveal (for demo, reveal none)
-        let revealed_attributes: Vec<String> = vec![];
-        
-        // Delegate to the credential module
-        crypto::credential::prepare_credential_proof(&credential, holder_secret, &revealed_attributes)
+    pub fn generate_credential_proof(
+        &self,
+        credential_json: &str,
+        revocation_registry_json: &str,
+        holder_secret: &str,
+        context: &str,
+        current_time: u64,
+    ) -> Result<String, JsValue> {
+        self.generate_credential_proof_with(credential_json, revocation_registry_json, holder_secret, context, current_time, &())
     }
-    
+
+    /// `generate_credential_proof` as a promise, reporting progress and stopping on cancellation
+    /// like `initialize_circuit_async`
     #[wasm_bindgen]
-    pub fn verify_credential_proof(&self, proof_json: &str) -> Result<bool, JsValue> {
-        // In a real implementation, we would parse the proof and extract parameters
-        // For demonstration, use mock values
-        
-        // Delegate to the credential module
-        crypto::credential::verify_credential_proof(
-            proof_json,
-            "mock_credential_hash",
-            "mock_issuer_hash",
-            "mock_attribute_hash"
-        )
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_credential_proof_async(
+        &self,
+        credential_json: &str,
+        revocation_registry_json: &str,
+        holder_secret: &str,
+        context: &str,
+        current_time: u64,
+        on_progress: Option<js_sys::Function>,
+        cancel: Option<CancellationToken>,
+    ) -> js_sys::Promise {
+        let observer = JsProgress::new(on_progress, cancel);
+        settle(self.generate_credential_proof_with(credential_json, revocation_registry_json, holder_secret, context, current_time, &observer))
     }
-    
+
+    /// Verify a credential presentation made to `context` at `current_time` against the
+    /// trusted issuer's hex-encoded public key and latest hex-encoded revocation root.
+    /// The presentation's `nullifier` is the same for every presentation by one holder
+    /// in `context`; relying parties reject repeats.
+    #[wasm_bindgen]
+    pub fn verify_credential_proof(
+        &self,
+        presentation_json: &str,
+        issuer_public_key: &str,
+        revocation_root: &str,
+        context: &str,
+        current_time: u64,
+    ) -> Result<bool, JsValue> {
+        let presentation: CredentialPresentation = serde_json::from_str(presentation_json).map_err(ZkError::from)?;
+        let issuer_public_key = VerifyingKey::from_bytes(&hex::decode(issuer_public_key).map_err(ZkError::from)?)?;
+        let revocation_root = decode_scalar(revocation_root)?;
+
+        Ok(with_backend!(&self.backend, backend => {
+            backend.check_envelope(&presentation.proof, ProofType::CredentialVerification, Some(context))?;
+            let pvk = backend.pvk(ProofType::CredentialVerification)?;
+            verify_credential_proof(&backend.system, pvk, &presentation, &issuer_public_key, &revocation_root, context, current_time)?
+        }))
+    }
+
+    /// Prove that the credential (JSON) held by the holder of `holder_secret` satisfies
+    /// every predicate in `predicates_json` (a JSON array of `{field, operator, value}`),
+    /// revealing nothing else about it, at the verifier's `current_time` (Unix seconds)
+    /// and unrevoked in the issuer's registry (JSON). Returns a `PredicatePresentation` as JSON.
+    #[wasm_bindgen]
+    pub fn generate_predicate_proof(
+        &self,
+        credential_json: &str,
+        revocation_registry_json: &str,
+        holder_secret: &str,
+        predicates_json: &str,
+        context: &str,
+        current_time: u64,
+    ) -> Result<String, JsValue> {
+        let request = (credential_json, revocation_registry_json, predicates_json);
+        self.generate_predicate_proof_with(request, holder_secret, context, current_time, &())
+    }
+
+    /// `generate_predicate_proof` as a promise, reporting progress and stopping on cancellation
+    /// like `initialize_circuit_async`
+    #[wasm_bindgen]
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_predicate_proof_async(
+        &self,
+        credential_json: &str,
+        revocation_registry_json: &str,
+        holder_secret: &str,
+        predicates_json: &str,
+        context: &str,
+        current_time: u64,
+        on_progress: Option<js_sys::Function>,
+        cancel: Option<CancellationToken>,
+    ) -> js_sys::Promise {
+        let request = (credential_json, revocation_registry_json, predicates_json);
+        let observer = JsProgress::new(on_progress, cancel);
+        settle(self.generate_predicate_proof_with(request, holder_secret, context, current_time, &observer))
+    }
+
+    /// Verify a predicate presentation made to `context` at `current_time` against the
+    /// predicates the verifier asked for and the trusted issuer's hex-encoded public key
+    /// and latest hex-encoded revocation root
+    #[wasm_bindgen]
+    pub fn verify_predicate_proof(
+        &self,
+        presentation_json: &str,
+        issuer_public_key: &str,
+        revocation_root: &str,
+        predicates_json: &str,
+        context: &str,
+        current_time: u64,
+    ) -> Result<bool, JsValue> {
+        let presentation: PredicatePresentation = serde_json::from_str(presentation_json).map_err(ZkError::from)?;
+        let predicates: Vec<Predicate> = serde_json::from_str(predicates_json).map_err(ZkError::from)?;
+        let issuer_public_key = VerifyingKey::from_bytes(&hex::decode(issuer_public_key).map_err(ZkError::from)?)?;
+        let request = CredentialRequest::new(&issuer_public_key, &decode_scalar(revocation_root)?, &predicates);
+
+        Ok(with_backend!(&self.backend, backend => {
+            backend.check_envelope(&presentation.proof, ProofType::Predicates, Some(context))?;
+            let pvk = backend.pvk(ProofType::Predicates)?;
+            verify_predicate_proof(&backend.system, pvk, &presentation, &request, context, current_time)?
+        }))
+    }
+
+    /// Prove that several credentials belong to the holder of `holder_secret` and satisfy
+    /// their predicates, without linking them. `credentials_json` is a JSON array of
+    /// `{credential, predicates, revocationRegistry}`, 2 to `MAX_CONJUNCTION_CREDENTIALS` of
+    /// them, proven with the `ProofType.Conjunction` keys for that many credentials; all
+    /// must be unrevoked in their issuer's registry and unexpired at the verifier's
+    /// `current_time` (Unix seconds).
+    #[wasm_bindgen]
+    pub fn generate_conjunction_proof(
+        &self,
+        credentials_json: &str,
+        holder_secret: &str,
+        context: &str,
+        current_time: u64,
+    ) -> Result<String, JsValue> {
+        self.generate_conjunction_proof_with(credentials_json, holder_secret, context, current_time, &())
+    }
+
+    /// `generate_conjunction_proof` as a promise, reporting progress and stopping on cancellation
+    /// like `initialize_circuit_async`
+    #[wasm_bindgen]
+    pub fn generate_conjunction_proof_async(
+        &self,
+        credentials_json: &str,
+        holder_secret: &str,
+        context: &str,
+        current_time: u64,
+        on_progress: Option<js_sys::Function>,
+        cancel: Option<CancellationToken>,
+    ) -> js_sys::Promise {
+        let observer = JsProgress::new(on_progress, cancel);
+        settle(self.generate_conjunction_proof_with(credentials_json, holder_secret, context, current_time, &observer))
+    }
+
+    /// Verify a conjunction presentation made to `context` at `current_time` against
+    /// `requests_json`, a JSON array of `{issuerPublicKey, revocationRoot, predicates}` in
+    /// the order the credentials were proven
+    #[wasm_bindgen]
+    pub fn verify_conjunction_proof(
+        &self,
+        presentation_json: &str,
+        requests_json: &str,
+        context: &str,
+        current_time: u64,
+    ) -> Result<bool, JsValue> {
+        let presentation: PredicatePresentation = serde_json::from_str(presentation_json).map_err(ZkError::from)?;
+        let requests: Vec<CredentialRequest> = serde_json::from_str(requests_json).map_err(ZkError::from)?;
+        let circuit = CircuitId::conjunction(requests.len())?;
+
+        Ok(with_backend!(&self.backend, backend => {
+            backend.check_envelope(&presentation.proof, circuit, Some(context))?;
+            let pvk = backend.pvk(circuit)?;
+            verify_conjunction_proof(&backend.system, pvk, &presentation, &requests, context, current_time)?
+        }))
+    }
+
+    /// Present a BBS+-signed credential (JSON) to the verifier that issued `nonce`,
+    /// disclosing only the attributes marked `reveal`. Needs no proving key: the
+    /// presentation is a proof of knowledge of the issuer's signature, fresh each time,
+    /// in an envelope naming the issuer's hex-encoded BBS+ public key.
+    #[wasm_bindgen]
+    pub fn generate_bbs_presentation(&self, credential_json: &str, issuer_public_key: &str, nonce: &str) -> Result<String, JsValue> {
+        let credential: Credential = serde_json::from_str(credential_json).map_err(ZkError::from)?;
+        let issuer_public_key = BbsPublicKey::from_bytes(&hex::decode(issuer_public_key).map_err(ZkError::from)?)?;
+        let presentation = create_bbs_presentation(&credential, &issuer_public_key, nonce)?;
+        Ok(serde_json::to_string(&presentation).map_err(ZkError::from)?)
+    }
+
+    /// Verify a BBS+ presentation made for `nonce` against the issuer's hex-encoded BBS+ public key
+    #[wasm_bindgen]
+    pub fn verify_bbs_presentation(&self, presentation_json: &str, issuer_public_key: &str, nonce: &str) -> Result<bool, JsValue> {
+        let presentation: BbsPresentation = serde_json::from_str(presentation_json).map_err(ZkError::from)?;
+        let issuer_public_key = BbsPublicKey::from_bytes(&hex::decode(issuer_public_key).map_err(ZkError::from)?)?;
+        Ok(verify_bbs_presentation(&issuer_public_key, &presentation, nonce)?)
+    }
+
+    /// Prove control of `did` with the hex-encoded Jubjub `private_key` (see
+    /// `generate_did_keypair`), answering the verifier's `challenge`
     #[wasm_bindgen]
     pub fn generate_did_proof(&self, did: &str, private_key: &str, challenge: &str) -> Result<JsValue, JsValue> {
-        // Delegate to the did resolver module
-        crypto::did_resolver::generate_did_ownership_proof(did, private_key, challenge)
+        self.generate_did_proof_with(did, private_key, challenge, &())
     }
-    
+
+    /// `generate_did_proof` as a promise, reporting progress and stopping on cancellation
+    /// like `initialize_circuit_async`
     #[wasm_bindgen]
-    pub fn verify_did_proof(&self, did: &str, challenge: &str, proof_str: &str) -> Result<bool, JsValue> {
-        // Delegate to the did resolver module
-        crypto::did_resolver::verify_did_ownership_proof(did, challenge, proof_str)
+    pub fn generate_did_proof_async(
+        &self,
+        did: &str,
+        private_key: &str,
+        challenge: &str,
+        on_progress: Option<js_sys::Function>,
+        cancel: Option<CancellationToken>,
+    ) -> js_sys::Promise {
+        settle(self.generate_did_proof_with(did, private_key, challenge, &JsProgress::new(on_progress, cancel)))
     }
-    
+
+    /// Verify a DID ownership proof envelope (JSON) for `challenge` against the Jubjub
+    /// authentication keys of the resolved DID document (JSON); a document without a
+    /// Jubjub authentication method is an error
+    #[wasm_bindgen]
+    pub fn verify_did_proof(&self, did_document: &str, challenge: &str, proof_json: &str) -> Result<bool, JsValue> {
+        let document: DIDDocument = serde_json::from_str(did_document).map_err(ZkError::from)?;
+
+        Ok(with_backend!(&self.backend, backend => {
+            let proof = backend.open(proof_json, ProofType::DIDOwnership, Some(challenge))?;
+            verify_did_ownership_proof(&backend.system, backend.pvk(ProofType::DIDOwnership)?, &proof, &document, challenge)?
+        }))
+    }
+
     #[wasm_bindgen]
     pub fn resolve_did(&self, did: &str) -> Result<JsValue, JsValue> {
         // Delegate to the did resolver module
@@ -252,6 +870,200 @@ impl ZkProver {
     }
 }
 
+impl ZkProver {
+    fn with_backend(backend: ProverBackend) -> Self {
+        init_panic_hook();
+        Self {
+            backend,
+            aggregation_srs: None,
+            aggregation_vk: None,
+        }
+    }
+
+    /// The Groth16 backend, for the operations only it supports (batching and aggregation)
+    fn groth16(&self) -> Result<&Backend<Groth16>, ZkError> {
+        match &self.backend {
+            ProverBackend::Groth16(backend) => Ok(backend),
+            #[cfg(feature = "universal")]
+            ProverBackend::Universal(_) => Err(ZkError::InvalidInput(
+                "batch verification and aggregation need the Groth16 backend".to_string(),
+            )),
+        }
+    }
+
+    fn initialize_circuit_with<O: ProgressObserver + ?Sized>(&mut self, circuit: CircuitId, observer: &O) -> Result<(), JsValue> {
+        with_backend!(&mut self.backend, backend => {
+            let pk = setup_circuit(&backend.observed(circuit, observer)?, circuit)?;
+            backend.insert_proving_key(circuit, pk)?;
+        });
+        Ok(())
+    }
+
+    fn generate_proof_with<O: ProgressObserver + ?Sized>(&self, x: u64, observer: &O) -> Result<JsValue, JsValue> {
+        let y = x.checked_mul(x)
+            .ok_or_else(|| JsValue::from_str("x² does not fit in a u64"))?;
+        
+        let result = with_backend!(&self.backend, backend => {
+            let pk = backend.proving_key(ProofType::SquareRoot)?;
+            let system = backend.observed(ProofType::SquareRoot, observer)?;
+            let (proof, inputs) = create_square_proof(&system, pk, x, y)?;
+            let message = format!("Successfully generated proof for x² = {}", y);
+            backend.proof_result(&message, ProofType::SquareRoot, &proof, &inputs, None)?
+        });
+        
+        serde_wasm_bindgen::to_value(&result)
+            .map_err(|e| JsValue::from_str(&get_error_message("Serialization error", &e)))
+    }
+
+    fn generate_range_proof_with<O: ProgressObserver + ?Sized>(
+        &self,
+        signed_commitment: &str,
+        holder_secret: &str,
+        comparison: &str,
+        threshold: u64,
+        context: &str,
+        observer: &O,
+    ) -> Result<String, JsValue> {
+        let signed: SignedValueCommitment = serde_json::from_str(signed_commitment).map_err(ZkError::from)?;
+        let comparison = Comparison::from_str(comparison)?;
+
+        let presentation = with_backend!(&self.backend, backend => {
+            let pk = backend.proving_key(ProofType::RangeProof)?;
+            let system = backend.observed(ProofType::RangeProof, observer)?;
+            create_range_proof(&system, pk, &signed, holder_secret, comparison, threshold, context)?
+        });
+        Ok(serde_json::to_string(&presentation).map_err(ZkError::from)?)
+    }
+
+    fn generate_membership_proof_with<O: ProgressObserver + ?Sized>(
+        &self,
+        leaves: &[String],
+        secret: &str,
+        observer: &O,
+    ) -> Result<JsValue, JsValue> {
+        let tree = MerkleTree::new(MEMBERSHIP_TREE_DEPTH, &decode_leaves(leaves)?)?;
+
+        let result = with_backend!(&self.backend, backend => {
+            let pk = backend.proving_key(ProofType::SetMembership)?;
+            let system = backend.observed(ProofType::SetMembership, observer)?;
+            let (proof, inputs) = create_membership_proof(&system, pk, &tree, &member_secret(secret))?;
+            backend.proof_result("Successfully generated set-membership proof", ProofType::SetMembership, &proof, &inputs, None)?
+        });
+
+        serde_wasm_bindgen::to_value(&result)
+            .map_err(|e| JsValue::from_str(&get_error_message("Serialization error", &e)))
+    }
+
+    fn generate_credential_proof_with<O: ProgressObserver + ?Sized>(
+        &self,
+        credential_json: &str,
+        revocation_registry_json: &str,
+        holder_secret: &str,
+        context: &str,
+        current_time: u64,
+        observer: &O,
+    ) -> Result<String, JsValue> {
+        let credential: Credential = serde_json::from_str(credential_json).map_err(ZkError::from)?;
+        let registry: RevocationRegistry = serde_json::from_str(revocation_registry_json).map_err(ZkError::from)?;
+
+        let presentation = with_backend!(&self.backend, backend => {
+            let pk = backend.proving_key(ProofType::CredentialVerification)?;
+            let system = backend.observed(ProofType::CredentialVerification, observer)?;
+            create_credential_proof(&system, pk, &credential, &registry, holder_secret, context, current_time)?
+        });
+        Ok(serde_json::to_string(&presentation).map_err(ZkError::from)?)
+    }
+
+    // `request` is the credential, revocation registry and predicates, all JSON
+    fn generate_predicate_proof_with<O: ProgressObserver + ?Sized>(
+        &self,
+        (credential_json, revocation_registry_json, predicates_json): (&str, &str, &str),
+        holder_secret: &str,
+        context: &str,
+        current_time: u64,
+        observer: &O,
+    ) -> Result<String, JsValue> {
+        let entry = CredentialPredicates {
+            credential: serde_json::from_str(credential_json).map_err(ZkError::from)?,
+            predicates: serde_json::from_str(predicates_json).map_err(ZkError::from)?,
+            revocation_registry: serde_json::from_str(revocation_registry_json).map_err(ZkError::from)?,
+        };
+
+        let presentation = with_backend!(&self.backend, backend => {
+            let pk = backend.proving_key(ProofType::Predicates)?;
+            let system = backend.observed(ProofType::Predicates, observer)?;
+            create_predicate_proof(&system, pk, &entry, holder_secret, context, current_time)?
+        });
+        Ok(serde_json::to_string(&presentation).map_err(ZkError::from)?)
+    }
+
+    fn generate_conjunction_proof_with<O: ProgressObserver + ?Sized>(
+        &self,
+        credentials_json: &str,
+        holder_secret: &str,
+        context: &str,
+        current_time: u64,
+        observer: &O,
+    ) -> Result<String, JsValue> {
+        let credentials: Vec<CredentialPredicates> = serde_json::from_str(credentials_json).map_err(ZkError::from)?;
+        let circuit = CircuitId::conjunction(credentials.len())?;
+
+        let presentation = with_backend!(&self.backend, backend => {
+            let pk = backend.proving_key(circuit)?;
+            let system = backend.observed(circuit, observer)?;
+            create_conjunction_proof(&system, pk, &credentials, holder_secret, context, current_time)?
+        });
+        Ok(serde_json::to_string(&presentation).map_err(ZkError::from)?)
+    }
+
+    fn generate_did_proof_with<O: ProgressObserver + ?Sized>(
+        &self,
+        did: &str,
+        private_key: &str,
+        challenge: &str,
+        observer: &O,
+    ) -> Result<JsValue, JsValue> {
+        let private_key = SigningKey::from_bytes(&hex::decode(private_key).map_err(ZkError::from)?)?;
+
+        let result = with_backend!(&self.backend, backend => {
+            let pk = backend.proving_key(ProofType::DIDOwnership)?;
+            let system = backend.observed(ProofType::DIDOwnership, observer)?;
+            let (proof, inputs) = create_did_ownership_proof(&system, pk, did, &private_key, challenge)?;
+            let message = "Successfully generated DID ownership proof";
+            backend.proof_result(message, ProofType::DIDOwnership, &proof, &inputs, Some(challenge))?
+        });
+
+        serde_wasm_bindgen::to_value(&result)
+            .map_err(|e| JsValue::from_str(&get_error_message("Serialization error", &e)))
+    }
+
+    fn aggregation_srs(&self) -> Result<&AggregationSrs, ZkError> {
+        self.aggregation_srs.as_ref()
+            .ok_or_else(|| ZkError::InvalidInput("no aggregation reference string loaded".into()))
+    }
+}
+
+/// Generate a universal reference string covering every circuit, conjunctions of up to
+/// `MAX_CONJUNCTION_CREDENTIALS` credentials included, for
+/// `ZkProver::with_universal_srs`. Its trapdoor is known to this process, so it is for
+/// development only; production deployments load one from a universal ceremony.
+#[cfg(feature = "universal")]
+#[wasm_bindgen]
+pub fn generate_universal_srs() -> Result<Vec<u8>, JsValue> {
+    let size = CircuitId::all().try_fold(CircuitSize::default(), |size, circuit| {
+        circuit_size(circuit).map(|circuit| size.max(circuit))
+    })?;
+    let srs = Marlin::generate_insecure_srs(size, &mut rand::rngs::OsRng)?;
+    Ok(Marlin::serialize_srs(&srs)?)
+}
+
+/// Start the web workers that run multiexp and FFT in parallel, as
+/// `await initThreadPool(navigator.hardwareConcurrency)` before the first proof. Only the
+/// `threads` build has it; that build needs SharedArrayBuffer, so pages that are not
+/// cross-origin isolated load the single-threaded build instead.
+#[cfg(all(feature = "threads", target_arch = "wasm32"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
 // Initialize the prover
 #[wasm_bindgen]
 pub fn init() -> ZkProver {
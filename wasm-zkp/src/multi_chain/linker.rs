@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::future_to_promise;
 
-#[wasm_bindgen]
+#[wasm_bindgen(getter_with_clone)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct LinkRequest {
     pub source_did: String,
@@ -15,7 +15,7 @@ pub struct LinkRequest {
     pub nonce: String,
 }
 
-#[wasm_bindgen]
+#[wasm_bindgen(getter_with_clone)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct LinkResult {
     pub success: bool,
@@ -35,17 +35,17 @@ pub fn link_identities(
 ) -> js_sys::Promise {
     let source_did_str = source_did.to_string();
     let target_did_str = target_did.to_string();
-    let signature_str = signature.to_string();
-    let nonce_str = nonce.to_string();
+    // Signature and nonce checks are not implemented yet
+    let _ = (signature, nonce);
     
     future_to_promise(async move {
         // Parse the DIDs
-        let (source_did_string, source_chain_type, source_chain_id, source_address) = match parse_multi_chain_did(&source_did_str) {
+        let (source_did_string, _, _, _) = match parse_multi_chain_did(&source_did_str) {
             Ok(result) => result,
             Err(e) => return Err(e),
         };
         
-        let (target_did_string, target_chain_type, target_chain_id, target_address) = match parse_multi_chain_did(&target_did_str) {
+        let (target_did_string, target_chain_type, _, _) = match parse_multi_chain_did(&target_did_str) {
             Ok(result) => result,
             Err(e) => return Err(e),
         };
@@ -90,12 +90,12 @@ pub fn verify_link(
     
     future_to_promise(async move {
         // Parse the DIDs
-        let (source_did_string, source_chain_type, source_chain_id, source_address) = match parse_multi_chain_did(&source_did_str) {
+        let (source_did_string, source_chain_type, source_chain_id, _) = match parse_multi_chain_did(&source_did_str) {
             Ok(result) => result,
             Err(e) => return Err(e),
         };
         
-        let (target_did_string, target_chain_type, target_chain_id, target_address) = match parse_multi_chain_did(&target_did_str) {
+        let (target_did_string, target_chain_type, target_chain_id, _) = match parse_multi_chain_did(&target_did_str) {
             Ok(result) => result,
             Err(e) => return Err(e),
         };
@@ -119,7 +119,7 @@ pub fn verify_link(
         
         js_sys::Reflect::set(&result, &JsValue::from_str("details"), &details)?;
         
-        Ok(result)
+        Ok(result.into())
     })
 }
 
@@ -132,7 +132,7 @@ pub fn unlink_identities(
 ) -> js_sys::Promise {
     let source_did_str = source_did.to_string();
     let target_did_str = target_did.to_string();
-    let signature_str = signature.to_string();
+    let _ = signature;
     
     future_to_promise(async move {
         // Parse the DIDs
@@ -156,6 +156,6 @@ pub fn unlink_identities(
         js_sys::Reflect::set(&result, &JsValue::from_str("sourceDid"), &JsValue::from_str(&source_did_string))?;
         js_sys::Reflect::set(&result, &JsValue::from_str("targetDid"), &JsValue::from_str(&target_did_string))?;
         
-        Ok(result)
+        Ok(result.into())
     })
 } 
\ No newline at end of file
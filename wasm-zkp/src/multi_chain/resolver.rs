@@ -1,10 +1,9 @@
-use crate::utils::get_error_message;
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::future_to_promise;
 
 #[wasm_bindgen]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum ChainType {
     Substrate,
     Ethereum,
@@ -13,7 +12,7 @@ pub enum ChainType {
     Near,
 }
 
-#[wasm_bindgen]
+#[wasm_bindgen(getter_with_clone)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ChainIdentity {
     pub chain_type: ChainType,
@@ -22,8 +21,8 @@ pub struct ChainIdentity {
     pub did: String,
 }
 
-#[wasm_bindgen]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
 pub struct CrossChainIdentity {
     pub id: String,
     pub controller: String,
@@ -83,7 +82,7 @@ pub fn resolve_multi_chain_did(did: &str) -> js_sys::Promise {
     
     future_to_promise(async move {
         // Parse the DID
-        let (did_string, chain_type, chain_id, address) = match parse_multi_chain_did(&did_str) {
+        let (did_string, _, _, _) = match parse_multi_chain_did(&did_str) {
             Ok(result) => result,
             Err(e) => return Err(e),
         };
@@ -91,7 +90,7 @@ pub fn resolve_multi_chain_did(did: &str) -> js_sys::Promise {
         // In a real implementation, we would query multiple chains in parallel
         // For demo, just create a mock CrossChainIdentity
         
-        let identity = CrossChainIdentity::new(did_string.clone());
+        let _identity = CrossChainIdentity::new(did_string.clone());
         
         // Mock linked DIDs on different chains
         let linked_dids = js_sys::Map::new();
@@ -137,7 +136,7 @@ pub fn resolve_multi_chain_did(did: &str) -> js_sys::Promise {
         js_sys::Reflect::set(&result, &JsValue::from_str("created"), &JsValue::from_str("2023-01-01T00:00:00Z"))?;
         js_sys::Reflect::set(&result, &JsValue::from_str("updated"), &JsValue::from_str("2023-01-01T00:00:00Z"))?;
         
-        Ok(result)
+        Ok(result.into())
     })
 }
 
@@ -199,7 +198,7 @@ pub fn search_dids_by_address(
 /// Get all linked identities for a DID
 #[wasm_bindgen]
 pub fn get_linked_identities(did: &str) -> js_sys::Promise {
-    let did_str = did.to_string();
+    let _ = did;
     
     future_to_promise(async move {
         // In a real implementation, we would resolve the DID and find all linked DIDs
@@ -76,4 +76,18 @@ pub fn serialize_to_json(value: &JsValue) -> Result<String, JsValue> {
     
     serde_json::to_string(&parsed)
         .map_err(|e| JsValue::from_str(&get_error_message("JSON serialization error", &e)))
-} 
\ No newline at end of file
+} 
+/// Seconds since the Unix epoch, read from the JavaScript clock under wasm
+pub fn unix_time() -> u64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        (js_sys::Date::now() / 1000.0) as u64
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0)
+    }
+}